@@ -0,0 +1,1174 @@
+use std::{
+    borrow::Cow,
+    io::{self, Read},
+    ops::Range,
+    str,
+};
+
+use crate::{Error, PushKind, ServerError, Value, ValueRef, VerbatimFormat};
+
+const MAX_RECURSE_DEPTH: usize = 100;
+
+fn get_push_kind(kind: String) -> PushKind {
+    match kind.as_str() {
+        "invalidate" => PushKind::Invalidate,
+        "message" => PushKind::Message,
+        "pmessage" => PushKind::PMessage,
+        "smessage" => PushKind::SMessage,
+        "unsubscribe" => PushKind::Unsubscribe,
+        "punsubscribe" => PushKind::PUnsubscribe,
+        "sunsubscribe" => PushKind::SUnsubscribe,
+        "subscribe" => PushKind::Subscribe,
+        "psubscribe" => PushKind::PSubscribe,
+        "ssubscribe" => PushKind::SSubscribe,
+        _ => PushKind::Other(kind),
+    }
+}
+
+/// Why the parser stopped before producing a value.
+enum DecodeError {
+    /// The buffer ends before the value does.
+    Incomplete,
+    /// The reply is malformed.
+    Invalid(&'static str),
+}
+
+fn invalid_reply(description: &'static str) -> DecodeError {
+    DecodeError::Invalid(description)
+}
+
+impl From<DecodeError> for Error {
+    fn from(err: DecodeError) -> Self {
+        match err {
+            DecodeError::Incomplete => Error::unexpected_eof(),
+            DecodeError::Invalid(description) => Error::Invalid(description.to_string()),
+        }
+    }
+}
+
+/// Parses integers that consist of an optional sign and at most 18 digits,
+/// which can't overflow. Other integers are left to `str::parse`.
+fn parse_short_int(line: &[u8]) -> Option<i64> {
+    let (negative, digits) = match line {
+        [b'-', digits @ ..] => (true, digits),
+        digits => (false, digits),
+    };
+    if digits.is_empty() || digits.len() > 18 {
+        return None;
+    }
+    let mut value = 0i64;
+    for digit in digits {
+        if !digit.is_ascii_digit() {
+            return None;
+        }
+        value = value * 10 + i64::from(digit - b'0');
+    }
+    Some(if negative { -value } else { value })
+}
+
+/// The start of a value, as read by [`Cursor::element`].
+enum Element {
+    /// A complete value.
+    Value(Value),
+    /// The header of an aggregate value, with the number of values nested in it.
+    Aggregate(AggregateKind, usize),
+}
+
+#[derive(Clone, Copy)]
+enum AggregateKind {
+    Array,
+    Set,
+    Map,
+    // the attributes are followed by the value they're attached to.
+    Attribute,
+    Push,
+}
+
+/// An aggregate value whose nested values are being decoded.
+struct Aggregate {
+    kind: AggregateKind,
+    length: usize,
+    values: Vec<Value>,
+}
+
+fn into_pairs(values: impl Iterator<Item = Value>) -> Vec<(Value, Value)> {
+    let mut values = values.into_iter();
+    let mut pairs = Vec::with_capacity(values.size_hint().0 / 2);
+    while let (Some(key), Some(value)) = (values.next(), values.next()) {
+        pairs.push((key, value));
+    }
+    pairs
+}
+
+impl Aggregate {
+    fn finish(kind: AggregateKind, mut values: Vec<Value>) -> Result<Value, DecodeError> {
+        Ok(match kind {
+            AggregateKind::Array => Value::Array(values),
+            AggregateKind::Set => Value::Set(values),
+            AggregateKind::Map => Value::Map(into_pairs(values.into_iter())),
+            AggregateKind::Attribute => {
+                let data = values.pop().unwrap_or(Value::Nil);
+                Value::Attribute {
+                    data: Box::new(data),
+                    attributes: into_pairs(values.into_iter()),
+                }
+            }
+            AggregateKind::Push => {
+                let mut values = values.into_iter();
+                let kind = match values.next() {
+                    Some(Value::BulkString(kind)) => {
+                        String::from_utf8(kind).map_err(|_| invalid_reply("Invalid UTF-8"))?
+                    }
+                    Some(Value::SimpleString(kind)) => kind,
+                    _ => return Err(invalid_reply("parse error when decoding push")),
+                };
+                Value::Push {
+                    kind: get_push_kind(kind),
+                    data: values.collect(),
+                }
+            }
+        })
+    }
+}
+
+/// Reads RESP data from a buffer.
+struct Cursor<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Cursor {
+            buffer,
+            position: 0,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self
+            .buffer
+            .get(self.position)
+            .ok_or(DecodeError::Incomplete)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn line(&mut self) -> Result<&'a [u8], DecodeError> {
+        let rest = &self.buffer[self.position..];
+        let mut offset = 0;
+        loop {
+            let cr = rest[offset..]
+                .iter()
+                .position(|b| *b == b'\r')
+                .ok_or(DecodeError::Incomplete)?;
+            let end = offset + cr;
+            match rest.get(end + 1) {
+                Some(b'\n') => {
+                    self.position += end + 2;
+                    return Ok(&rest[..end]);
+                }
+                Some(_) => offset = end + 1,
+                None => return Err(DecodeError::Incomplete),
+            }
+        }
+    }
+
+    fn str_line(&mut self) -> Result<&'a str, DecodeError> {
+        str::from_utf8(self.line()?).map_err(|_| invalid_reply("Invalid UTF-8"))
+    }
+
+    fn int(&mut self) -> Result<i64, DecodeError> {
+        let line = self.line()?;
+        if let Some(int) = parse_short_int(line) {
+            return Ok(int);
+        }
+        str::from_utf8(line)
+            .ok()
+            .and_then(|line| line.trim().parse::<i64>().ok())
+            .ok_or_else(|| invalid_reply("Expected integer, got garbage"))
+    }
+
+    /// Reads a length prefix, with negative lengths denoting nil.
+    fn length(&mut self) -> Result<Option<usize>, DecodeError> {
+        let length = self.int()?;
+        Ok((length >= 0).then_some(length as usize))
+    }
+
+    /// Reads a length prefix that can't denote nil.
+    fn count(&mut self) -> Result<usize, DecodeError> {
+        usize::try_from(self.int()?).map_err(|_| invalid_reply("Negative length"))
+    }
+
+    fn pair_count(&mut self) -> Result<usize, DecodeError> {
+        let length = self.count()?;
+        length
+            .checked_mul(2)
+            .map(|_| length)
+            .ok_or_else(|| invalid_reply("Attribute key-value length is too large"))
+    }
+
+    /// Reads `length` bytes of data followed by CRLF.
+    fn blob(&mut self, length: usize) -> Result<&'a [u8], DecodeError> {
+        let start = self.position;
+        let end = start
+            .checked_add(length)
+            .filter(|end| end.checked_add(2).is_some())
+            .ok_or(DecodeError::Incomplete)?;
+        match self.buffer.get(end..).unwrap_or_default() {
+            [b'\r', b'\n', ..] => {
+                self.position = end + 2;
+                Ok(&self.buffer[start..end])
+            }
+            [] | [b'\r'] => Err(DecodeError::Incomplete),
+            _ => Err(invalid_reply("Expected CRLF after blob")),
+        }
+    }
+
+    fn bulk(&mut self) -> Result<Option<&'a [u8]>, DecodeError> {
+        match self.length()? {
+            Some(length) => self.blob(length).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a blob for a RESP3 type that requires a string.
+    fn blob_string(&mut self) -> Result<Cow<'a, str>, DecodeError> {
+        let length = self.count()?;
+        self.blob(length).map(String::from_utf8_lossy)
+    }
+
+    fn double(&mut self) -> Result<f64, DecodeError> {
+        self.str_line()?
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| invalid_reply("Expected double, got garbage"))
+    }
+
+    fn boolean(&mut self) -> Result<bool, DecodeError> {
+        match self.str_line()? {
+            "t" => Ok(true),
+            "f" => Ok(false),
+            _ => Err(invalid_reply("Expected boolean, got garbage")),
+        }
+    }
+
+    /// Reads a verbatim string, and returns its format and the offset of its text.
+    fn verbatim(&mut self) -> Result<(VerbatimFormat, Cow<'a, str>, usize), DecodeError> {
+        let line = self.blob_string()?;
+        let separator = line
+            .find(':')
+            .ok_or_else(|| invalid_reply("parse error when decoding verbatim string"))?;
+        let format = match &line[..separator] {
+            "txt" => VerbatimFormat::Text,
+            "mkd" => VerbatimFormat::Markdown,
+            x => VerbatimFormat::Unknown(x.to_string()),
+        };
+        Ok((format, line, separator + 1))
+    }
+
+    #[cfg(feature = "num-bigint")]
+    fn big_number(&mut self) -> Result<num_bigint::BigInt, DecodeError> {
+        num_bigint::BigInt::parse_bytes(self.str_line()?.as_bytes(), 10)
+            .ok_or_else(|| invalid_reply("Expected bigint, got garbage"))
+    }
+
+    #[cfg(not(feature = "num-bigint"))]
+    fn big_number(&mut self) -> Result<&'a [u8], DecodeError> {
+        self.str_line().map(str::as_bytes)
+    }
+
+    /// Reads a value, or the header of an aggregate value, whose nested
+    /// values are read separately.
+    fn element(&mut self, depth: usize) -> Result<Element, DecodeError> {
+        let b = self.byte()?;
+        if depth > MAX_RECURSE_DEPTH {
+            return Err(invalid_reply("Maximum recursion depth exceeded"));
+        }
+        let value = match b {
+            b'+' => match self.str_line()? {
+                "OK" => Value::Okay,
+                line => Value::SimpleString(line.to_string()),
+            },
+            b':' => Value::Int(self.int()?),
+            b'$' => match self.bulk()? {
+                Some(bytes) => Value::BulkString(bytes.to_vec()),
+                None => Value::Nil,
+            },
+            b'*' => match self.length()? {
+                Some(length) => return Ok(Element::Aggregate(AggregateKind::Array, length)),
+                None => Value::Nil,
+            },
+            b'%' => {
+                let length = self.pair_count()? * 2;
+                return Ok(Element::Aggregate(AggregateKind::Map, length));
+            }
+            b'|' => {
+                let length = self.pair_count()? * 2 + 1;
+                return Ok(Element::Aggregate(AggregateKind::Attribute, length));
+            }
+            b'~' => match self.length()? {
+                Some(length) => return Ok(Element::Aggregate(AggregateKind::Set, length)),
+                None => Value::Nil,
+            },
+            b'-' => Value::ServerError(ServerError::from_line(self.str_line()?)),
+            b'_' => {
+                self.str_line()?;
+                Value::Nil
+            }
+            b',' => Value::Double(self.double()?),
+            b'#' => Value::Boolean(self.boolean()?),
+            b'!' => Value::ServerError(ServerError::from_line(&self.blob_string()?)),
+            b'=' => {
+                let (format, line, text) = self.verbatim()?;
+                Value::VerbatimString {
+                    format,
+                    text: line[text..].to_string(),
+                }
+            }
+            #[cfg(feature = "num-bigint")]
+            b'(' => Value::BigNumber(self.big_number()?),
+            #[cfg(not(feature = "num-bigint"))]
+            b'(' => Value::BigNumber(self.big_number()?.to_vec()),
+            b'>' => {
+                let length = self.int()?;
+                if length <= 0 {
+                    Value::Push {
+                        kind: PushKind::Other("".to_string()),
+                        data: vec![],
+                    }
+                } else {
+                    return Ok(Element::Aggregate(AggregateKind::Push, length as usize));
+                }
+            }
+            _ => return Err(invalid_reply("Unexpected reply type byte")),
+        };
+        Ok(Element::Value(value))
+    }
+}
+
+/// An incremental RESP decoder.
+///
+/// The buffer that is decoded may end in the middle of a value. The complete
+/// parts of unfinished aggregate values are kept between calls, so that
+/// decoding continues where it stopped once more data is available, instead
+/// of starting over.
+#[derive(Default)]
+pub struct ValueDecoder {
+    // the aggregate values that are being decoded, innermost last.
+    stack: Vec<Aggregate>,
+}
+
+impl ValueDecoder {
+    /// Creates a decoder that starts at the beginning of a value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the next value from `buffer`.
+    ///
+    /// Returns the number of bytes that were consumed, which must be removed
+    /// from the buffer before the next call, and the value if it's complete.
+    ///
+    /// After an error the decoder starts over, at the beginning of a value.
+    pub fn decode(&mut self, buffer: &[u8]) -> Result<(usize, Option<Value>), Error> {
+        let mut cursor = Cursor::new(buffer);
+        loop {
+            let start = cursor.position;
+            let mut value = match cursor.element(self.stack.len() + 1) {
+                Ok(Element::Value(value)) => value,
+                Ok(Element::Aggregate(kind, 0)) => self.finish(kind, Vec::new())?,
+                Ok(Element::Aggregate(kind, length)) => {
+                    // every value takes at least three bytes, so don't trust the length for the allocation.
+                    let capacity = length.min(cursor.remaining() / 3);
+                    self.stack.push(Aggregate {
+                        kind,
+                        length,
+                        values: Vec::with_capacity(capacity),
+                    });
+                    continue;
+                }
+                Err(DecodeError::Incomplete) => return Ok((start, None)),
+                Err(err @ DecodeError::Invalid(_)) => {
+                    self.stack.clear();
+                    return Err(err.into());
+                }
+            };
+
+            loop {
+                let Some(aggregate) = self.stack.last_mut() else {
+                    return Ok((cursor.position, Some(value)));
+                };
+                aggregate.values.push(value);
+                if aggregate.values.len() < aggregate.length {
+                    break;
+                }
+                let aggregate = self.stack.pop().unwrap();
+                value = self.finish(aggregate.kind, aggregate.values)?;
+            }
+        }
+    }
+
+    fn finish(&mut self, kind: AggregateKind, values: Vec<Value>) -> Result<Value, Error> {
+        Aggregate::finish(kind, values).map_err(|err| {
+            self.stack.clear();
+            err.into()
+        })
+    }
+
+    /// Returns true if a value was partially decoded.
+    pub fn in_progress(&self) -> bool {
+        !self.stack.is_empty()
+    }
+}
+
+/// A parser producing values that borrow their strings from a complete
+/// buffer, instead of copying them like the incremental parser does.
+impl<'a> Cursor<'a> {
+    fn values(&mut self, length: usize, depth: usize) -> Result<Vec<ValueRef<'a>>, DecodeError> {
+        let mut values = Vec::with_capacity(length.min(self.remaining() / 3));
+        for _ in 0..length {
+            values.push(self.value_ref(depth + 1)?);
+        }
+        Ok(values)
+    }
+
+    fn pairs(
+        &mut self,
+        length: usize,
+        depth: usize,
+    ) -> Result<Vec<(ValueRef<'a>, ValueRef<'a>)>, DecodeError> {
+        let mut pairs = Vec::with_capacity(length.min(self.remaining() / 6));
+        for _ in 0..length {
+            pairs.push((self.value_ref(depth + 1)?, self.value_ref(depth + 1)?));
+        }
+        Ok(pairs)
+    }
+
+    fn value_ref(&mut self, depth: usize) -> Result<ValueRef<'a>, DecodeError> {
+        let b = self.byte()?;
+        if depth > MAX_RECURSE_DEPTH {
+            return Err(invalid_reply("Maximum recursion depth exceeded"));
+        }
+        Ok(match b {
+            b'+' => match self.str_line()? {
+                "OK" => ValueRef::Okay,
+                line => ValueRef::SimpleString(line),
+            },
+            b':' => ValueRef::Int(self.int()?),
+            b'$' => match self.bulk()? {
+                Some(bytes) => ValueRef::BulkString(bytes),
+                None => ValueRef::Nil,
+            },
+            b'*' => match self.length()? {
+                Some(length) => ValueRef::Array(self.values(length, depth)?),
+                None => ValueRef::Nil,
+            },
+            b'%' => {
+                let length = self.pair_count()?;
+                ValueRef::Map(self.pairs(length, depth)?)
+            }
+            b'|' => {
+                let length = self.pair_count()?;
+                let attributes = self.pairs(length, depth)?;
+                ValueRef::Attribute {
+                    data: Box::new(self.value_ref(depth + 1)?),
+                    attributes,
+                }
+            }
+            b'~' => match self.length()? {
+                Some(length) => ValueRef::Set(self.values(length, depth)?),
+                None => ValueRef::Nil,
+            },
+            b'-' => ValueRef::ServerError(ServerError::from_line(self.str_line()?)),
+            b'_' => {
+                self.str_line()?;
+                ValueRef::Nil
+            }
+            b',' => ValueRef::Double(self.double()?),
+            b'#' => ValueRef::Boolean(self.boolean()?),
+            b'!' => ValueRef::ServerError(ServerError::from_line(&self.blob_string()?)),
+            b'=' => {
+                let (format, line, text) = self.verbatim()?;
+                let text = match line {
+                    Cow::Borrowed(line) => Cow::Borrowed(&line[text..]),
+                    Cow::Owned(mut line) => {
+                        line.drain(..text);
+                        Cow::Owned(line)
+                    }
+                };
+                ValueRef::VerbatimString { format, text }
+            }
+            b'(' => ValueRef::BigNumber(self.big_number()?),
+            b'>' => {
+                let length = self.int()?;
+                if length <= 0 {
+                    ValueRef::Push {
+                        kind: PushKind::Other("".to_string()),
+                        data: vec![],
+                    }
+                } else {
+                    let mut data = self.values(length as usize, depth)?;
+                    let kind = match data.remove(0) {
+                        ValueRef::BulkString(kind) => str::from_utf8(kind)
+                            .map_err(|_| invalid_reply("Invalid UTF-8"))?
+                            .to_string(),
+                        ValueRef::SimpleString(kind) => kind.to_string(),
+                        _ => return Err(invalid_reply("parse error when decoding push")),
+                    };
+                    ValueRef::Push {
+                        kind: get_push_kind(kind),
+                        data,
+                    }
+                }
+            }
+            _ => return Err(invalid_reply("Unexpected reply type byte")),
+        })
+    }
+
+    /// Moves past a value, applying the same validation as `value_ref`
+    /// without building it.
+    fn skip(&mut self, depth: usize) -> Result<(), DecodeError> {
+        let start = self.position;
+        let b = self.byte()?;
+        if depth > MAX_RECURSE_DEPTH {
+            return Err(invalid_reply("Maximum recursion depth exceeded"));
+        }
+        match b {
+            b'+' | b'-' => {
+                self.str_line()?;
+            }
+            b':' => {
+                self.int()?;
+            }
+            b'$' => {
+                self.bulk()?;
+            }
+            b'*' | b'~' => {
+                for _ in 0..self.length()?.unwrap_or(0) {
+                    self.skip(depth + 1)?;
+                }
+            }
+            b'%' => {
+                for _ in 0..self.pair_count()? * 2 {
+                    self.skip(depth + 1)?;
+                }
+            }
+            // the remaining types are either rare or cheap to build.
+            _ => {
+                self.position = start;
+                self.value_ref(depth)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the length of the first value in `bytes`, or `None` if `bytes`
+/// doesn't hold a complete value yet.
+///
+/// The value is validated, but not built, which makes this a cheap way to
+/// split a stream of data into values.
+pub fn reply_len(bytes: &[u8]) -> Result<Option<usize>, Error> {
+    let mut cursor = Cursor::new(bytes);
+    match cursor.skip(1) {
+        Ok(()) => Ok(Some(cursor.position)),
+        Err(DecodeError::Incomplete) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Parses bytes into a value that borrows its strings from `bytes`.
+///
+/// Unlike [`parse_value`], string data isn't copied, which makes this
+/// the cheaper option when `bytes` already holds a complete value.
+pub fn parse_value_ref(bytes: &[u8]) -> Result<ValueRef<'_>, Error> {
+    Ok(Cursor::new(bytes).value_ref(1)?)
+}
+
+// The longest inline command that is accepted, as in the server.
+const MAX_INLINE_LEN: usize = 64 * 1024;
+// The largest argument of a command, as in the server's default configuration.
+const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+// The most arguments that space is reserved for before they're read.
+const MAX_RESERVED_ARGS: usize = 1024;
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n' | b'\x0b' | b'\x0c')
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+/// Reads a quoted argument of an inline command, after the opening quote.
+/// Returns the argument and the number of bytes that were read.
+fn quoted_arg(line: &[u8], quote: u8) -> Result<(Vec<u8>, usize), DecodeError> {
+    let mut arg = Vec::new();
+    let mut position = 0;
+    loop {
+        match (line.get(position), line.get(position + 1)) {
+            (None, _) => return Err(DecodeError::Invalid("Unbalanced quotes in inline command")),
+            (Some(b'\\'), Some(b'x')) if quote == b'"' => {
+                let digits = line
+                    .get(position + 2)
+                    .copied()
+                    .and_then(hex_digit)
+                    .zip(line.get(position + 3).copied().and_then(hex_digit));
+                match digits {
+                    Some((high, low)) => {
+                        arg.push(high << 4 | low);
+                        position += 4;
+                    }
+                    None => {
+                        arg.push(b'x');
+                        position += 2;
+                    }
+                }
+            }
+            (Some(b'\\'), Some(&escaped)) if quote == b'"' => {
+                arg.push(match escaped {
+                    b'n' => b'\n',
+                    b'r' => b'\r',
+                    b't' => b'\t',
+                    b'b' => b'\x08',
+                    b'a' => b'\x07',
+                    escaped => escaped,
+                });
+                position += 2;
+            }
+            (Some(b'\\'), Some(b'\'')) if quote == b'\'' => {
+                arg.push(b'\'');
+                position += 2;
+            }
+            (Some(&byte), next) if byte == quote => {
+                // the closing quote has to end the argument.
+                if next.is_some_and(|next| !is_space(*next)) {
+                    return Err(DecodeError::Invalid(
+                        "Closing quote must be followed by a space in inline command",
+                    ));
+                }
+                return Ok((arg, position + 1));
+            }
+            (Some(&byte), _) => {
+                arg.push(byte);
+                position += 1;
+            }
+        }
+    }
+}
+
+/// Splits an inline command into arguments, separated by spaces.
+///
+/// Arguments can be quoted like in `redis-cli`: double quoted arguments
+/// support escape sequences such as `\n` and `\x00`, and single quoted
+/// arguments only support `\'`.
+fn split_inline(mut line: &[u8]) -> Result<CommandArgs<'_>, DecodeError> {
+    let mut args = Vec::new();
+    loop {
+        let start = line
+            .iter()
+            .position(|byte| !is_space(*byte))
+            .unwrap_or(line.len());
+        line = &line[start..];
+        match line.first() {
+            None => return Ok(args),
+            Some(&quote @ (b'"' | b'\'')) => {
+                let (arg, len) = quoted_arg(&line[1..], quote)?;
+                args.push(Cow::Owned(arg));
+                line = &line[1 + len..];
+            }
+            Some(_) => {
+                let end = line
+                    .iter()
+                    .position(|byte| is_space(*byte))
+                    .unwrap_or(line.len());
+                args.push(Cow::Borrowed(&line[..end]));
+                line = &line[end..];
+            }
+        }
+    }
+}
+
+impl Cursor<'_> {
+    /// Reads a bulk string argument of a command, and returns where its data is.
+    fn command_arg(&mut self) -> Result<Range<usize>, DecodeError> {
+        if self.byte()? != b'$' {
+            return Err(DecodeError::Invalid(
+                "Expected bulk string argument in command",
+            ));
+        }
+        let length = self.int()?;
+        if !(0..=MAX_BULK_LEN as i64).contains(&length) {
+            return Err(DecodeError::Invalid("Invalid argument length in command"));
+        }
+        let start = self.position;
+        self.blob(length as usize)?;
+        Ok(start..start + length as usize)
+    }
+}
+
+/// The arguments of a command, which borrow from the parsed data unless they
+/// had to be unescaped.
+pub type CommandArgs<'a> = Vec<Cow<'a, [u8]>>;
+
+/// Parses the first command in `bytes`, as sent by a client to a server.
+///
+/// Commands are usually sent as arrays of bulk strings, but inline commands,
+/// which are lines of space separated arguments, are supported as well.
+/// Returns the number of bytes that were read and the command's arguments, or
+/// `None` if `bytes` doesn't hold a complete command yet.
+///
+/// Empty commands, such as empty lines, are returned without arguments, and
+/// are ignored by servers.
+///
+/// ```rust
+/// use redis_resp::parse_command;
+///
+/// let (len, args) = parse_command(b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n").unwrap().unwrap();
+/// assert_eq!(len, 22);
+/// assert_eq!(args, [&b"GET"[..], b"key"]);
+///
+/// let (len, args) = parse_command(b"SET key \"two words\"\r\n").unwrap().unwrap();
+/// assert_eq!(len, 21);
+/// assert_eq!(args, [&b"SET"[..], b"key", b"two words"]);
+///
+/// assert_eq!(parse_command(b"*2\r\n$3\r\nGET\r\n").unwrap(), None);
+/// ```
+pub fn parse_command(bytes: &[u8]) -> Result<Option<(usize, CommandArgs<'_>)>, Error> {
+    CommandDecoder::new().decode(bytes)
+}
+
+/// An incremental decoder for the commands that clients send.
+///
+/// Like [`ValueDecoder`], it keeps its progress in an unfinished command
+/// between calls, so that the arguments that were already read aren't parsed
+/// again once more data is available.
+#[derive(Default)]
+pub struct CommandDecoder {
+    // the array command that is being decoded, once its header was read.
+    command: Option<PartialCommand>,
+    // the number of bytes of an unfinished inline command that don't end it.
+    inline_scanned: usize,
+}
+
+struct PartialCommand {
+    count: usize,
+    args: Vec<Range<usize>>,
+    // the end of the last argument that was read.
+    position: usize,
+}
+
+impl CommandDecoder {
+    /// Creates a decoder that starts at the beginning of a command.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the first command in `buffer`, like [`parse_command`].
+    ///
+    /// Until a command is returned, `buffer` must keep starting at that
+    /// command. The number of bytes that were read must be removed from the
+    /// buffer before the next call.
+    ///
+    /// After an error the decoder starts over, at the beginning of a command.
+    pub fn decode<'a>(
+        &mut self,
+        buffer: &'a [u8],
+    ) -> Result<Option<(usize, CommandArgs<'a>)>, Error> {
+        let result = match buffer.first() {
+            None => return Ok(None),
+            Some(b'*') => self.array_command(buffer),
+            Some(_) => self.inline_command(buffer),
+        };
+        match result {
+            Ok(command) => Ok(Some(command)),
+            Err(DecodeError::Incomplete) => Ok(None),
+            Err(err) => {
+                *self = Self::default();
+                Err(err.into())
+            }
+        }
+    }
+
+    fn array_command<'a>(
+        &mut self,
+        buffer: &'a [u8],
+    ) -> Result<(usize, CommandArgs<'a>), DecodeError> {
+        let command = match &mut self.command {
+            Some(command) => command,
+            None => {
+                let mut cursor = Cursor::new(buffer);
+                cursor.position = 1;
+                let count = usize::try_from(cursor.int()?).unwrap_or_default();
+                self.command.insert(PartialCommand {
+                    count,
+                    args: Vec::with_capacity(count.min(MAX_RESERVED_ARGS)),
+                    position: cursor.position,
+                })
+            }
+        };
+        let mut cursor = Cursor::new(buffer);
+        cursor.position = command.position;
+        while command.args.len() < command.count {
+            command.args.push(cursor.command_arg()?);
+            command.position = cursor.position;
+        }
+
+        let command = self.command.take().unwrap();
+        let args = command
+            .args
+            .into_iter()
+            .map(|range| Cow::Borrowed(&buffer[range]))
+            .collect();
+        Ok((command.position, args))
+    }
+
+    fn inline_command<'a>(
+        &mut self,
+        buffer: &'a [u8],
+    ) -> Result<(usize, CommandArgs<'a>), DecodeError> {
+        let scanned = self.inline_scanned.min(buffer.len());
+        let Some(end) = buffer[scanned..].iter().position(|byte| *byte == b'\n') else {
+            if buffer.len() > MAX_INLINE_LEN {
+                return Err(DecodeError::Invalid("Inline command is too long"));
+            }
+            self.inline_scanned = buffer.len();
+            return Err(DecodeError::Incomplete);
+        };
+        self.inline_scanned = 0;
+        let end = scanned + end;
+        let line = &buffer[..end];
+        Ok((
+            end + 1,
+            split_inline(line.strip_suffix(b"\r").unwrap_or(line))?,
+        ))
+    }
+}
+
+// The amount of data that is read from the reader at once.
+const READ_SIZE: usize = 8 * 1024;
+
+/// A parser for values behind a reader.
+pub struct Parser {
+    decoder: ValueDecoder,
+    buffer: Vec<u8>,
+    // the number of bytes at the start of `buffer` that were already decoded.
+    position: usize,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new()
+    }
+}
+
+impl Parser {
+    /// Creates a new parser that parses the data behind the reader.  More
+    /// than one value can be behind the reader in which case the parser can
+    /// be invoked multiple times.  In other words: the stream does not have
+    /// to be terminated.
+    pub fn new() -> Parser {
+        Parser {
+            decoder: ValueDecoder::default(),
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Parses synchronously into a single value from the reader.
+    ///
+    /// Data that was read past the end of the value is kept for the next call.
+    pub fn parse_value<T: Read>(&mut self, mut reader: T) -> Result<Value, Error> {
+        loop {
+            if self.position < self.buffer.len() {
+                let (consumed, value) = self.decoder.decode(&self.buffer[self.position..])?;
+                self.position += consumed;
+                if let Some(value) = value {
+                    return Ok(value);
+                }
+            }
+
+            self.buffer.drain(..self.position);
+            self.position = 0;
+            let len = self.buffer.len();
+            // large values are read in larger parts, as the buffer grows.
+            let size = READ_SIZE.max(self.buffer.capacity() - len);
+            self.buffer.resize(len + size, 0);
+            let result = reader.read(&mut self.buffer[len..]);
+            self.buffer.truncate(len + *result.as_ref().unwrap_or(&0));
+            match result {
+                Ok(0) => return Err(Error::unexpected_eof()),
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// Parses bytes into a value.
+///
+/// Data following the value is ignored, and an incomplete value is an
+/// [`io::ErrorKind::UnexpectedEof`] error.
+pub fn parse_value(bytes: &[u8]) -> Result<Value, Error> {
+    match ValueDecoder::default().decode(bytes)? {
+        (_, Some(value)) => Ok(value),
+        (_, None) => Err(Error::unexpected_eof()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_recursion_depth_set_and_array() {
+        for test_byte in ["*", "~"] {
+            let initial = format!("{test_byte}1\r\n").as_bytes().to_vec();
+            let end = format!("{test_byte}0\r\n").as_bytes().to_vec();
+
+            let mut ba = initial.repeat(MAX_RECURSE_DEPTH - 1).to_vec();
+            ba.extend(end.clone());
+            match parse_value(&ba) {
+                Ok(Value::Array(a)) => assert_eq!(a.len(), 1),
+                Ok(Value::Set(s)) => assert_eq!(s.len(), 1),
+                _ => panic!("Expected valid array or set"),
+            }
+
+            let mut ba = initial.repeat(MAX_RECURSE_DEPTH).to_vec();
+            ba.extend(end);
+            match parse_value(&ba) {
+                Ok(_) => panic!("Expected ParseError"),
+                Err(e) => assert!(matches!(e, Error::Invalid(_))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_recursion_depth_map() {
+        let initial = b"%1\r\n+a\r\n";
+        let end = b"%0\r\n";
+
+        let mut ba = initial.repeat(MAX_RECURSE_DEPTH - 1).to_vec();
+        ba.extend(*end);
+        match parse_value(&ba) {
+            Ok(Value::Map(m)) => assert_eq!(m.len(), 1),
+            Ok(Value::Set(s)) => assert_eq!(s.len(), 1),
+            _ => panic!("Expected valid array or set"),
+        }
+
+        let mut ba = initial.repeat(MAX_RECURSE_DEPTH).to_vec();
+        ba.extend(end);
+        match parse_value(&ba) {
+            Ok(_) => panic!("Expected ParseError"),
+            Err(e) => assert!(matches!(e, Error::Invalid(_))),
+        }
+    }
+
+    #[test]
+    fn decoder_resumes_partial_values() {
+        let reply = b"*3\r\n$5\r\nhello\r\n%1\r\n+key\r\n:-42\r\n~2\r\n#t\r\n,1.5\r\n";
+        let expected = parse_value(reply).unwrap();
+
+        for split in 0..reply.len() {
+            let mut decoder = ValueDecoder::default();
+            let (consumed, value) = decoder.decode(&reply[..split]).unwrap();
+            assert_eq!(value, None);
+
+            let mut buffer = reply[consumed..split].to_vec();
+            buffer.extend_from_slice(&reply[split..]);
+            assert_eq!(
+                decoder.decode(&buffer).unwrap(),
+                (buffer.len(), Some(expected.clone()))
+            );
+        }
+    }
+
+    #[test]
+    fn decoder_returns_values_one_at_a_time() {
+        let mut decoder = ValueDecoder::default();
+        let buffer = b":1\r\n*2\r\n:2\r\n:3\r\n";
+
+        assert_eq!(decoder.decode(buffer).unwrap(), (4, Some(Value::Int(1))));
+        assert_eq!(
+            decoder.decode(&buffer[4..]).unwrap(),
+            (
+                buffer.len() - 4,
+                Some(Value::Array(vec![Value::Int(2), Value::Int(3)]))
+            )
+        );
+        assert_eq!(decoder.decode(b"").unwrap(), (0, None));
+    }
+
+    #[test]
+    fn decoder_recovers_after_invalid_reply() {
+        let mut decoder = ValueDecoder::default();
+        assert_eq!(decoder.decode(b"*2\r\n:1\r\n").unwrap(), (8, None));
+        assert!(decoder.decode(b"?\r\n").is_err());
+        assert_eq!(decoder.decode(b"+OK\r\n").unwrap(), (5, Some(Value::Okay)));
+    }
+
+    #[test]
+    fn negative_lengths_are_rejected_where_nil_is_not_allowed() {
+        for reply in [&b"%-1\r\n"[..], b"|-1\r\n+a\r\n", b"!-1\r\n", b"=-1\r\n"] {
+            assert!(matches!(parse_value(reply), Err(Error::Invalid(_))));
+            assert!(matches!(parse_value_ref(reply), Err(Error::Invalid(_))));
+            assert!(matches!(reply_len(reply), Err(Error::Invalid(_))));
+        }
+        assert_eq!(parse_value(b"*-1\r\n").unwrap(), Value::Nil);
+    }
+
+    #[test]
+    fn parse_integers() {
+        for (reply, expected) in [
+            (&b":0\r\n"[..], 0),
+            (b":-0\r\n", 0),
+            (b":123456789012345678\r\n", 123456789012345678),
+            (b":-9223372036854775808\r\n", i64::MIN),
+            (b":9223372036854775807\r\n", i64::MAX),
+            (b":+5\r\n", 5),
+            (b": 42 \r\n", 42),
+        ] {
+            assert_eq!(parse_value(reply).unwrap(), Value::Int(expected));
+        }
+
+        for reply in [
+            &b":\r\n"[..],
+            b":-\r\n",
+            b":9223372036854775808\r\n",
+            b":1a\r\n",
+        ] {
+            assert!(matches!(parse_value(reply).unwrap_err(), Error::Invalid(_)));
+        }
+    }
+
+    #[test]
+    fn parser_reads_values_across_reads() {
+        struct Chunked<'a>(&'a [u8]);
+
+        impl Read for Chunked<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = buf.len().min(self.0.len()).min(3);
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+
+        let mut reader = Chunked(b"*2\r\n$5\r\nhello\r\n:1\r\n+OK\r\n*1\r\n");
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.parse_value(&mut reader).unwrap(),
+            Value::Array(vec![Value::BulkString(b"hello".to_vec()), Value::Int(1)])
+        );
+        assert_eq!(parser.parse_value(&mut reader).unwrap(), Value::Okay);
+        assert!(matches!(
+            parser.parse_value(&mut reader).unwrap_err(),
+            Error::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof
+        ));
+    }
+
+    fn command(bytes: &[u8]) -> Option<(usize, Vec<Vec<u8>>)> {
+        parse_command(bytes)
+            .unwrap()
+            .map(|(len, args)| (len, args.into_iter().map(Cow::into_owned).collect()))
+    }
+
+    #[test]
+    fn parse_multibulk_commands() {
+        let bytes = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$0\r\n\r\n*1\r\n$4\r\nPING\r\n";
+        let first = 28;
+        for len in 0..first {
+            assert_eq!(command(&bytes[..len]), None, "{len}");
+        }
+        assert_eq!(
+            command(bytes),
+            Some((first, vec![b"SET".to_vec(), b"key".to_vec(), Vec::new()]))
+        );
+        assert_eq!(command(&bytes[first..]), Some((14, vec![b"PING".to_vec()])));
+
+        assert_eq!(command(b"*0\r\n"), Some((4, Vec::new())));
+        assert_eq!(command(b"*-1\r\n"), Some((5, Vec::new())));
+    }
+
+    #[test]
+    fn parse_inline_commands() {
+        assert_eq!(command(b"PING"), None);
+        assert_eq!(command(b"PING\n"), Some((5, vec![b"PING".to_vec()])));
+        assert_eq!(command(b"\r\n"), Some((2, Vec::new())));
+        assert_eq!(
+            command(b"  SET\tkey  value \r\nGET key\r\n"),
+            Some((
+                19,
+                vec![b"SET".to_vec(), b"key".to_vec(), b"value".to_vec()]
+            ))
+        );
+        assert_eq!(
+            command(b"SET \"a \\\"b\\\"\\n\\x41\\xZZ\" 'c\\'d\\n' \"\"\r\n"),
+            Some((
+                37,
+                vec![
+                    b"SET".to_vec(),
+                    b"a \"b\"\nAxZZ".to_vec(),
+                    b"c'd\\n".to_vec(),
+                    Vec::new(),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_invalid_commands() {
+        for bytes in [
+            &b"*1\r\n:1\r\n"[..],
+            b"*1\r\n$-1\r\n",
+            b"*1\r\n$3\r\nGETX\r\n",
+            b"*x\r\n",
+            b"GET \"key\r\n",
+            b"GET 'key'x\r\n",
+        ] {
+            assert!(
+                matches!(parse_command(bytes), Err(Error::Invalid(_))),
+                "{bytes:?}"
+            );
+        }
+
+        let long = vec![b'a'; MAX_INLINE_LEN + 1];
+        assert!(matches!(parse_command(&long), Err(Error::Invalid(_))));
+        assert_eq!(command(&long[..MAX_INLINE_LEN]), None);
+    }
+
+    #[test]
+    fn command_decoder_resumes_partial_commands() {
+        let bytes =
+            b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\nGET key\r\n*1\r\n$4\r\nPING\r\n";
+        let mut decoder = CommandDecoder::new();
+        let mut commands = Vec::new();
+        let mut start = 0;
+        for end in 0..=bytes.len() {
+            if let Some((len, args)) = decoder.decode(&bytes[start..end]).unwrap() {
+                commands.push(args.into_iter().map(Cow::into_owned).collect::<Vec<_>>());
+                start += len;
+            }
+        }
+        assert_eq!(start, bytes.len());
+        assert_eq!(
+            commands,
+            vec![
+                vec![b"SET".to_vec(), b"key".to_vec(), b"value".to_vec()],
+                vec![b"GET".to_vec(), b"key".to_vec()],
+                vec![b"PING".to_vec()],
+            ]
+        );
+
+        // the decoder starts over after an error.
+        assert_eq!(decoder.decode(b"*2\r\n$3\r\nGET\r\n").unwrap(), None);
+        assert!(decoder.decode(b"*2\r\n$3\r\nGET\r\n:1\r\n").is_err());
+        assert!(decoder.decode(b"*1\r\n$4\r\nPING\r\n").unwrap().is_some());
+    }
+}
@@ -0,0 +1,362 @@
+use std::io::{self, Write};
+
+use crate::{ProtocolVersion, Value};
+
+fn countdigits(mut v: usize) -> usize {
+    let mut result = 1;
+    loop {
+        if v < 10 {
+            return result;
+        }
+        if v < 100 {
+            return result + 1;
+        }
+        if v < 1000 {
+            return result + 2;
+        }
+        if v < 10000 {
+            return result + 3;
+        }
+
+        v /= 10000;
+        result += 4;
+    }
+}
+
+#[inline]
+fn bulklen(len: usize) -> usize {
+    1 + countdigits(len) + 2 + len + 2
+}
+
+/// Returns the number of bytes that a command with the given arguments
+/// takes once it's packed.
+pub fn command_len<'a, I>(args: I) -> usize
+where
+    I: ExactSizeIterator<Item = &'a [u8]>,
+{
+    let mut totlen = 1 + countdigits(args.len()) + 2;
+    for arg in args {
+        totlen += bulklen(arg.len());
+    }
+    totlen
+}
+
+/// Writes a command, as an array of bulk strings.
+pub fn write_command<'a, W, I>(writer: &mut W, args: I) -> io::Result<()>
+where
+    W: Write + ?Sized,
+    I: ExactSizeIterator<Item = &'a [u8]>,
+{
+    write_header(writer, b'*', args.len())?;
+    for arg in args {
+        write_bulk(writer, arg)?;
+    }
+    Ok(())
+}
+
+/// Packs a command into a new buffer.
+///
+/// ```rust
+/// let cmd = redis_resp::pack_command(&["SET", "my_key", "42"]);
+/// assert_eq!(cmd, b"*3\r\n$3\r\nSET\r\n$6\r\nmy_key\r\n$2\r\n42\r\n".to_vec());
+/// ```
+pub fn pack_command<A: AsRef<[u8]>>(args: &[A]) -> Vec<u8> {
+    let args = || args.iter().map(AsRef::as_ref);
+    let mut cmd = Vec::with_capacity(command_len(args()));
+    write_command(&mut cmd, args()).unwrap();
+    cmd
+}
+
+fn write_header<W: Write + ?Sized>(writer: &mut W, prefix: u8, len: usize) -> io::Result<()> {
+    writer.write_all(&[prefix])?;
+    writer.write_all(itoa::Buffer::new().format(len).as_bytes())?;
+    writer.write_all(b"\r\n")
+}
+
+fn write_line<W: Write + ?Sized>(writer: &mut W, prefix: u8, line: &[u8]) -> io::Result<()> {
+    writer.write_all(&[prefix])?;
+    writer.write_all(line)?;
+    writer.write_all(b"\r\n")
+}
+
+fn write_blob<W: Write + ?Sized>(writer: &mut W, prefix: u8, data: &[u8]) -> io::Result<()> {
+    write_header(writer, prefix, data.len())?;
+    writer.write_all(data)?;
+    writer.write_all(b"\r\n")
+}
+
+fn write_bulk<W: Write + ?Sized>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    write_blob(writer, b'$', data)
+}
+
+fn has_line_break(line: &str) -> bool {
+    line.contains(['\r', '\n'])
+}
+
+fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+fn big_number_digits(value: &num_bigint::BigInt) -> String {
+    value.to_string()
+}
+
+#[cfg(not(feature = "num-bigint"))]
+fn big_number_digits(value: &[u8]) -> &str {
+    std::str::from_utf8(value).unwrap_or_default()
+}
+
+/// Writes a value in the format of the given protocol version.
+///
+/// Types that RESP2 lacks are written as their closest RESP2 equivalent,
+/// the same way the server does for RESP2 clients: maps and sets are sent
+/// as arrays, doubles, verbatim strings and big numbers as bulk strings,
+/// booleans as integers, and attributes are left out.
+///
+/// Simple strings and errors are single lines, so simple strings that contain
+/// line breaks are written as bulk strings. Errors are written as blob errors
+/// in RESP3, and with the line breaks replaced by spaces in RESP2.
+pub fn write_value<W: Write + ?Sized>(
+    writer: &mut W,
+    value: &Value,
+    protocol: ProtocolVersion,
+) -> io::Result<()> {
+    let resp3 = protocol.supports_resp3();
+    match value {
+        Value::Nil if resp3 => writer.write_all(b"_\r\n"),
+        Value::Nil => writer.write_all(b"$-1\r\n"),
+        Value::Int(value) => {
+            write_line(writer, b':', itoa::Buffer::new().format(*value).as_bytes())
+        }
+        Value::BulkString(data) => write_bulk(writer, data),
+        Value::Array(values) => write_values(writer, b'*', values, protocol),
+        Value::SimpleString(line) if has_line_break(line) => write_bulk(writer, line.as_bytes()),
+        Value::SimpleString(line) => write_line(writer, b'+', line.as_bytes()),
+        Value::Okay => writer.write_all(b"+OK\r\n"),
+        Value::Map(pairs) if resp3 => write_pairs(writer, b'%', pairs, protocol),
+        Value::Map(pairs) => {
+            write_header(writer, b'*', pairs.len() * 2)?;
+            for (key, value) in pairs {
+                write_value(writer, key, protocol)?;
+                write_value(writer, value, protocol)?;
+            }
+            Ok(())
+        }
+        Value::Attribute { data, attributes } => {
+            if resp3 {
+                write_pairs(writer, b'|', attributes, protocol)?;
+            }
+            write_value(writer, data, protocol)
+        }
+        Value::Set(values) if resp3 => write_values(writer, b'~', values, protocol),
+        Value::Set(values) => write_values(writer, b'*', values, protocol),
+        Value::Double(value) if resp3 => write_line(writer, b',', format_double(*value).as_bytes()),
+        Value::Double(value) => write_bulk(writer, format_double(*value).as_bytes()),
+        Value::Boolean(value) if resp3 => {
+            writer.write_all(if *value { b"#t\r\n" } else { b"#f\r\n" })
+        }
+        Value::Boolean(value) => writer.write_all(if *value { b":1\r\n" } else { b":0\r\n" }),
+        Value::VerbatimString { format, text } if resp3 => {
+            write_blob(writer, b'=', format!("{format}:{text}").as_bytes())
+        }
+        Value::VerbatimString { text, .. } => write_bulk(writer, text.as_bytes()),
+        Value::BigNumber(value) if resp3 => {
+            write_line(writer, b'(', big_number_digits(value).as_bytes())
+        }
+        Value::BigNumber(value) => write_bulk(writer, big_number_digits(value).as_bytes()),
+        Value::Push { kind, data } => {
+            write_header(writer, if resp3 { b'>' } else { b'*' }, data.len() + 1)?;
+            write_bulk(writer, kind.to_string().as_bytes())?;
+            for value in data {
+                write_value(writer, value, protocol)?;
+            }
+            Ok(())
+        }
+        Value::ServerError(err) => {
+            let line = match err.details() {
+                Some(details) => format!("{} {details}", err.code()),
+                None => err.code().to_string(),
+            };
+            if !has_line_break(&line) {
+                write_line(writer, b'-', line.as_bytes())
+            } else if resp3 {
+                write_blob(writer, b'!', line.as_bytes())
+            } else {
+                write_line(writer, b'-', line.replace(['\r', '\n'], " ").as_bytes())
+            }
+        }
+    }
+}
+
+fn write_values<W: Write + ?Sized>(
+    writer: &mut W,
+    prefix: u8,
+    values: &[Value],
+    protocol: ProtocolVersion,
+) -> io::Result<()> {
+    write_header(writer, prefix, values.len())?;
+    for value in values {
+        write_value(writer, value, protocol)?;
+    }
+    Ok(())
+}
+
+fn write_pairs<W: Write + ?Sized>(
+    writer: &mut W,
+    prefix: u8,
+    pairs: &[(Value, Value)],
+    protocol: ProtocolVersion,
+) -> io::Result<()> {
+    write_header(writer, prefix, pairs.len())?;
+    for (key, value) in pairs {
+        write_value(writer, key, protocol)?;
+        write_value(writer, value, protocol)?;
+    }
+    Ok(())
+}
+
+/// Encodes a value into a new buffer, see [`write_value`].
+pub fn encode_value(value: &Value, protocol: ProtocolVersion) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, value, protocol).unwrap();
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_value, PushKind, ServerError, ServerErrorKind, VerbatimFormat};
+
+    fn values() -> Vec<Value> {
+        vec![
+            Value::Nil,
+            Value::Int(-42),
+            Value::BulkString(b"binary\r\n\0data".to_vec()),
+            Value::Array(vec![Value::Okay, Value::SimpleString("simple".into())]),
+            Value::Map(vec![(Value::SimpleString("key".into()), Value::Int(1))]),
+            Value::Attribute {
+                data: Box::new(Value::Int(1)),
+                attributes: vec![(Value::SimpleString("ttl".into()), Value::Int(3600))],
+            },
+            Value::Set(vec![Value::Int(1), Value::Int(2)]),
+            Value::Double(1.5),
+            Value::Double(f64::INFINITY),
+            Value::Boolean(true),
+            Value::VerbatimString {
+                format: VerbatimFormat::Text,
+                text: "Some string".into(),
+            },
+            Value::Push {
+                kind: PushKind::Message,
+                data: vec![Value::BulkString(b"channel".to_vec())],
+            },
+            Value::ServerError(ServerError::new(
+                ServerErrorKind::ReadOnly,
+                Some("You can't write against a read only replica."),
+            )),
+            Value::ServerError(ServerError::extension("WRONGTYPE", None)),
+        ]
+    }
+
+    #[test]
+    fn resp3_values_round_trip() {
+        for value in values() {
+            let encoded = encode_value(&value, ProtocolVersion::RESP3);
+            assert_eq!(parse_value(&encoded).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn resp2_values_are_downgraded() {
+        for (value, expected) in [
+            (Value::Nil, &b"$-1\r\n"[..]),
+            (
+                Value::Map(vec![(Value::SimpleString("key".into()), Value::Int(1))]),
+                b"*2\r\n+key\r\n:1\r\n",
+            ),
+            (Value::Set(vec![Value::Int(1)]), b"*1\r\n:1\r\n"),
+            (Value::Double(1.5), b"$3\r\n1.5\r\n"),
+            (Value::Boolean(false), b":0\r\n"),
+            (
+                Value::VerbatimString {
+                    format: VerbatimFormat::Markdown,
+                    text: "# title".into(),
+                },
+                b"$7\r\n# title\r\n",
+            ),
+            (
+                Value::Attribute {
+                    data: Box::new(Value::Int(1)),
+                    attributes: vec![(Value::Int(2), Value::Int(3))],
+                },
+                b":1\r\n",
+            ),
+            (
+                Value::Push {
+                    kind: PushKind::Subscribe,
+                    data: vec![Value::Int(1)],
+                },
+                b"*2\r\n$9\r\nsubscribe\r\n:1\r\n",
+            ),
+        ] {
+            assert_eq!(encode_value(&value, ProtocolVersion::RESP2), expected);
+        }
+    }
+
+    #[test]
+    fn line_breaks_are_not_written_in_lines() {
+        let value = Value::SimpleString("two\r\nlines".into());
+        assert_eq!(
+            encode_value(&value, ProtocolVersion::RESP3),
+            b"$10\r\ntwo\r\nlines\r\n"
+        );
+
+        let value = Value::ServerError(ServerError::new(
+            ServerErrorKind::ResponseError,
+            Some("two\nlines"),
+        ));
+        assert_eq!(
+            encode_value(&value, ProtocolVersion::RESP3),
+            b"!13\r\nERR two\nlines\r\n"
+        );
+        assert_eq!(
+            encode_value(&value, ProtocolVersion::RESP2),
+            b"-ERR two lines\r\n"
+        );
+    }
+
+    #[test]
+    fn special_doubles() {
+        for (value, expected) in [
+            (f64::INFINITY, &b",inf\r\n"[..]),
+            (f64::NEG_INFINITY, b",-inf\r\n"),
+            (f64::NAN, b",nan\r\n"),
+        ] {
+            assert_eq!(
+                encode_value(&Value::Double(value), ProtocolVersion::RESP3),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn commands() {
+        let args: [&[u8]; 3] = [b"SET", b"key", b"\r\n"];
+        let packed = pack_command(&args);
+        assert_eq!(packed, b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$2\r\n\r\n\r\n");
+        assert_eq!(command_len(args.into_iter()), packed.len());
+        assert_eq!(
+            parse_value(&packed).unwrap(),
+            Value::Array(
+                args.iter()
+                    .map(|arg| Value::BulkString(arg.to_vec()))
+                    .collect()
+            )
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use std::{fmt, io};
+
+/// An error that occurred while decoding RESP data.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading the data failed, or the data ended in the middle of a value.
+    ///
+    /// The latter is reported as [`io::ErrorKind::UnexpectedEof`].
+    Io(io::Error),
+    /// The data isn't valid RESP.
+    Invalid(String),
+}
+
+impl Error {
+    pub(crate) fn unexpected_eof() -> Self {
+        Error::Io(io::ErrorKind::UnexpectedEof.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::Invalid(description) => write!(f, "Invalid RESP data - {description}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,67 @@
+//! RESP encoding and decoding, without any I/O.
+//!
+//! This crate holds the protocol layer of the [redis](https://docs.rs/redis)
+//! crate: the [`Value`] type that replies are decoded into, incremental
+//! decoders for RESP2 and RESP3 data, and encoders for commands and values.
+//! It doesn't open connections and doesn't depend on an async runtime, so it
+//! can be used by proxies, fake servers and other tools that speak RESP.
+//!
+//! # Decoding
+//!
+//! [`parse_value`] decodes a complete value from a buffer. When the data
+//! arrives in parts, a [`ValueDecoder`] keeps the partially decoded value
+//! between calls, and a [`Parser`] does the same for data behind a reader:
+//!
+//! ```rust
+//! use redis_resp::{Value, ValueDecoder};
+//!
+//! let mut decoder = ValueDecoder::new();
+//! let (consumed, value) = decoder.decode(b"*2\r\n:1\r\n:").unwrap();
+//! assert_eq!((consumed, value), (8, None));
+//!
+//! let (consumed, value) = decoder.decode(b":2\r\n").unwrap();
+//! assert_eq!(consumed, 4);
+//! assert_eq!(value, Some(Value::Array(vec![Value::Int(1), Value::Int(2)])));
+//! ```
+//!
+//! Servers decode the commands that clients send with [`parse_command`],
+//! which also accepts inline commands, or incrementally with a
+//! [`CommandDecoder`].
+//!
+//! # Encoding
+//!
+//! Commands are sent as arrays of bulk strings, see [`pack_command`] and
+//! [`write_command`]. Replies are written with [`write_value`], in the
+//! format of the protocol version that the client negotiated:
+//!
+//! ```rust
+//! use redis_resp::{encode_value, pack_command, ProtocolVersion, Value};
+//!
+//! assert_eq!(pack_command(&["GET", "key"]), b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n");
+//!
+//! let reply = Value::Map(vec![(Value::SimpleString("key".into()), Value::Int(1))]);
+//! assert_eq!(encode_value(&reply, ProtocolVersion::RESP3), b"%1\r\n+key\r\n:1\r\n");
+//! assert_eq!(encode_value(&reply, ProtocolVersion::RESP2), b"*2\r\n+key\r\n:1\r\n");
+//! ```
+
+#![deny(non_camel_case_types)]
+#![warn(missing_docs)]
+#![cfg_attr(docsrs, warn(rustdoc::broken_intra_doc_links))]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+mod decode;
+mod encode;
+mod error;
+mod server_error;
+mod value;
+mod value_ref;
+
+pub use crate::decode::{
+    parse_command, parse_value, parse_value_ref, reply_len, CommandArgs, CommandDecoder, Parser,
+    ValueDecoder,
+};
+pub use crate::encode::{command_len, encode_value, pack_command, write_command, write_value};
+pub use crate::error::Error;
+pub use crate::server_error::{ServerError, ServerErrorKind};
+pub use crate::value::{MapIter, OwnedMapIter, ProtocolVersion, PushKind, Value, VerbatimFormat};
+pub use crate::value_ref::ValueRef;
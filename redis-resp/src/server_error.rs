@@ -0,0 +1,175 @@
+use arcstr::ArcStr;
+use std::fmt;
+
+/// Kinds of errors returned from the server
+#[derive(PartialEq, Debug, Clone, Copy, Eq)]
+#[non_exhaustive]
+pub enum ServerErrorKind {
+    /// The server generated an invalid response, or returned a general error.
+    ResponseError,
+    /// A script execution was aborted.
+    ExecAbort,
+    /// The server cannot response because it's loading a dump.
+    BusyLoading,
+    /// A script that was requested does not actually exist.
+    NoScript,
+    /// Raised if a key moved to a different node.
+    Moved,
+    /// Raised if a key moved to a different node but we need to ask.
+    Ask,
+    /// Raised if a request needs to be retried.
+    TryAgain,
+    /// Raised if a redis cluster is down.
+    ClusterDown,
+    /// A request spans multiple slots
+    CrossSlot,
+    /// A cluster master is unavailable.
+    MasterDown,
+    /// Attempt to write to a read-only server
+    ReadOnly,
+    /// Attempted to kill a script/function while they werent' executing
+    NotBusy,
+    /// Attempted to unsubscribe on a connection that is not in subscribed mode.
+    NoSub,
+    /// Attempted to use a command without ACL permission.
+    NoPerm,
+}
+
+impl ServerErrorKind {
+    /// The error code that the server sends for this kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ResponseError => "ERR",
+            Self::ExecAbort => "EXECABORT",
+            Self::BusyLoading => "LOADING",
+            Self::NoScript => "NOSCRIPT",
+            Self::Moved => "MOVED",
+            Self::Ask => "ASK",
+            Self::TryAgain => "TRYAGAIN",
+            Self::ClusterDown => "CLUSTERDOWN",
+            Self::CrossSlot => "CROSSSLOT",
+            Self::MasterDown => "MASTERDOWN",
+            Self::ReadOnly => "READONLY",
+            Self::NotBusy => "NOTBUSY",
+            Self::NoSub => "NOSUB",
+            Self::NoPerm => "NOPERM",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Some(match code {
+            "ERR" => Self::ResponseError,
+            "EXECABORT" => Self::ExecAbort,
+            "LOADING" => Self::BusyLoading,
+            "NOSCRIPT" => Self::NoScript,
+            "MOVED" => Self::Moved,
+            "ASK" => Self::Ask,
+            "TRYAGAIN" => Self::TryAgain,
+            "CLUSTERDOWN" => Self::ClusterDown,
+            "CROSSSLOT" => Self::CrossSlot,
+            "MASTERDOWN" => Self::MasterDown,
+            "READONLY" => Self::ReadOnly,
+            "NOTBUSY" => Self::NotBusy,
+            "NOSUB" => Self::NoSub,
+            "NOPERM" => Self::NoPerm,
+            _ => return None,
+        })
+    }
+}
+
+/// An error that was returned from the server
+#[derive(PartialEq, Debug, Clone)]
+pub struct ServerError(Repr);
+
+#[derive(PartialEq, Debug, Clone)]
+enum Repr {
+    Extension {
+        code: ArcStr,
+        detail: Option<ArcStr>,
+    },
+    Known {
+        kind: ServerErrorKind,
+        detail: Option<ArcStr>,
+    },
+}
+
+impl ServerError {
+    /// Creates an error of a known kind.
+    pub fn new(kind: ServerErrorKind, detail: Option<&str>) -> Self {
+        ServerError(Repr::Known {
+            kind,
+            detail: detail.map(ArcStr::from),
+        })
+    }
+
+    /// Creates an error with a code that isn't covered by [`ServerErrorKind`].
+    ///
+    /// The code is used as is, even if it's the code of a known kind.
+    pub fn extension(code: &str, detail: Option<&str>) -> Self {
+        ServerError(Repr::Extension {
+            code: code.into(),
+            detail: detail.map(ArcStr::from),
+        })
+    }
+
+    /// Parses an error line sent by the server, consisting of the error
+    /// code optionally followed by a space and the details.
+    pub(crate) fn from_line(line: &str) -> Self {
+        let mut pieces = line.splitn(2, ' ');
+        let code = pieces.next().unwrap();
+        let detail = pieces.next();
+        match ServerErrorKind::from_code(code) {
+            Some(kind) => ServerError::new(kind, detail),
+            None => ServerError::extension(code, detail),
+        }
+    }
+
+    /// Returns the kind of error. If `None`, try `crate::Self::code` to get the error code.
+    pub fn kind(&self) -> Option<ServerErrorKind> {
+        match &self.0 {
+            Repr::Extension { .. } => None,
+            Repr::Known { kind, .. } => Some(*kind),
+        }
+    }
+
+    /// The error code returned from the server
+    pub fn code(&self) -> &str {
+        match &self.0 {
+            Repr::Extension { code, .. } => code,
+            Repr::Known { kind, .. } => kind.code(),
+        }
+    }
+
+    /// Additional details about the error, if exist
+    pub fn details(&self) -> Option<&str> {
+        match &self.0 {
+            Repr::Extension { detail, .. } => detail.as_ref().map(|str| str.as_str()),
+            Repr::Known { detail, .. } => detail.as_ref().map(|str| str.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Repr::Extension { code, detail } => {
+                fmt::Debug::fmt(&code, f)?;
+                if let Some(detail) = detail {
+                    f.write_str(": ")?;
+                    detail.fmt(f)?;
+                }
+                Ok(())
+            }
+            Repr::Known { kind, detail } => {
+                fmt::Debug::fmt(&kind, f)?;
+                if let Some(detail) = detail {
+                    f.write_str(": ")?;
+                    detail.fmt(f)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
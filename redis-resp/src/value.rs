@@ -0,0 +1,386 @@
+#[cfg(feature = "num-bigint")]
+use num_bigint::BigInt;
+use std::fmt;
+use std::str::from_utf8;
+
+use crate::ServerError;
+
+/// Internal low-level redis value enum.
+#[derive(PartialEq, Clone, Default)]
+#[non_exhaustive]
+pub enum Value {
+    /// A nil response from the server.
+    #[default]
+    Nil,
+    /// An integer response.  Note that there are a few situations
+    /// in which redis actually returns a string for an integer which
+    /// is why this library generally treats integers and strings
+    /// the same for all numeric responses.
+    Int(i64),
+    /// An arbitrary binary data, usually represents a binary-safe string.
+    BulkString(Vec<u8>),
+    /// A response containing an array with more data. This is generally used by redis
+    /// to express nested structures.
+    Array(Vec<Value>),
+    /// A simple string response, without line breaks and not binary safe.
+    SimpleString(String),
+    /// A status response which represents the string "OK".
+    Okay,
+    /// Unordered key,value list from the server. Use `as_map_iter` function.
+    Map(Vec<(Value, Value)>),
+    /// A value with the attributes that the server attached to it. Conversions
+    /// use the data and ignore the attributes, unless the attributes are requested.
+    Attribute {
+        /// Data that attributes belong to.
+        data: Box<Value>,
+        /// Key,Value list of attributes.
+        attributes: Vec<(Value, Value)>,
+    },
+    /// Unordered set value from the server.
+    Set(Vec<Value>),
+    /// A floating number response from the server.
+    Double(f64),
+    /// A boolean response from the server.
+    Boolean(bool),
+    /// First String is format and other is the string
+    VerbatimString {
+        /// Text's format type
+        format: VerbatimFormat,
+        /// Remaining string check format before using!
+        text: String,
+    },
+    #[cfg(feature = "num-bigint")]
+    /// Very large number that out of the range of the signed 64 bit numbers
+    BigNumber(BigInt),
+    #[cfg(not(feature = "num-bigint"))]
+    /// Very large number that out of the range of the signed 64 bit numbers
+    BigNumber(Vec<u8>),
+    /// Push data from the server.
+    Push {
+        /// Push Kind
+        kind: PushKind,
+        /// Remaining data from push message
+        data: Vec<Value>,
+    },
+    /// Represents an error message from the server
+    ServerError(ServerError),
+}
+
+/// `VerbatimString`'s format types defined by spec
+#[derive(PartialEq, Clone, Debug)]
+#[non_exhaustive]
+pub enum VerbatimFormat {
+    /// Unknown type to catch future formats.
+    Unknown(String),
+    /// `mkd` format
+    Markdown,
+    /// `txt` format
+    Text,
+}
+
+/// `Push` type's currently known kinds.
+#[derive(PartialEq, Clone, Debug)]
+#[non_exhaustive]
+pub enum PushKind {
+    /// `Disconnection` is sent from the **library** when connection is closed.
+    Disconnection,
+    /// Other kind to catch future kinds.
+    Other(String),
+    /// `invalidate` is received when a key is changed/deleted.
+    Invalidate,
+    /// `message` is received when pubsub message published by another client.
+    Message,
+    /// `pmessage` is received when pubsub message published by another client and client subscribed to topic via pattern.
+    PMessage,
+    /// `smessage` is received when pubsub message published by another client and client subscribed to it with sharding.
+    SMessage,
+    /// `unsubscribe` is received when client unsubscribed from a channel.
+    Unsubscribe,
+    /// `punsubscribe` is received when client unsubscribed from a pattern.
+    PUnsubscribe,
+    /// `sunsubscribe` is received when client unsubscribed from a shard channel.
+    SUnsubscribe,
+    /// `subscribe` is received when client subscribed to a channel.
+    Subscribe,
+    /// `psubscribe` is received when client subscribed to a pattern.
+    PSubscribe,
+    /// `ssubscribe` is received when client subscribed to a shard channel.
+    SSubscribe,
+}
+
+impl PushKind {
+    /// Returns true if pushes of this kind are also the reply to the command
+    /// that caused them, like the confirmation of a `SUBSCRIBE`.
+    pub fn has_reply(&self) -> bool {
+        matches!(
+            self,
+            &PushKind::Unsubscribe
+                | &PushKind::PUnsubscribe
+                | &PushKind::SUnsubscribe
+                | &PushKind::Subscribe
+                | &PushKind::PSubscribe
+                | &PushKind::SSubscribe
+        )
+    }
+}
+
+impl fmt::Display for VerbatimFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerbatimFormat::Markdown => write!(f, "mkd"),
+            VerbatimFormat::Unknown(val) => write!(f, "{val}"),
+            VerbatimFormat::Text => write!(f, "txt"),
+        }
+    }
+}
+
+impl fmt::Display for PushKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushKind::Other(kind) => write!(f, "{kind}"),
+            PushKind::Invalidate => write!(f, "invalidate"),
+            PushKind::Message => write!(f, "message"),
+            PushKind::PMessage => write!(f, "pmessage"),
+            PushKind::SMessage => write!(f, "smessage"),
+            PushKind::Unsubscribe => write!(f, "unsubscribe"),
+            PushKind::PUnsubscribe => write!(f, "punsubscribe"),
+            PushKind::SUnsubscribe => write!(f, "sunsubscribe"),
+            PushKind::Subscribe => write!(f, "subscribe"),
+            PushKind::PSubscribe => write!(f, "psubscribe"),
+            PushKind::SSubscribe => write!(f, "ssubscribe"),
+            PushKind::Disconnection => write!(f, "disconnection"),
+        }
+    }
+}
+
+/// An iterator over the key-value pairs of a map value, see [`Value::as_map_iter`].
+#[non_exhaustive]
+pub enum MapIter<'a> {
+    /// The pairs of a RESP2 array, which alternates between keys and values.
+    Array(std::slice::Iter<'a, Value>),
+    /// The pairs of a RESP3 map.
+    Map(std::slice::Iter<'a, (Value, Value)>),
+}
+
+impl<'a> Iterator for MapIter<'a> {
+    type Item = (&'a Value, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            MapIter::Array(iter) => Some((iter.next()?, iter.next()?)),
+            MapIter::Map(iter) => {
+                let (k, v) = iter.next()?;
+                Some((k, v))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            MapIter::Array(iter) => iter.size_hint(),
+            MapIter::Map(iter) => iter.size_hint(),
+        }
+    }
+}
+
+/// An owning iterator over the key-value pairs of a map value, see [`Value::into_map_iter`].
+#[non_exhaustive]
+pub enum OwnedMapIter {
+    /// The pairs of a RESP2 array, which alternates between keys and values.
+    Array(std::vec::IntoIter<Value>),
+    /// The pairs of a RESP3 map.
+    Map(std::vec::IntoIter<(Value, Value)>),
+}
+
+impl Iterator for OwnedMapIter {
+    type Item = (Value, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            OwnedMapIter::Array(iter) => Some((iter.next()?, iter.next()?)),
+            OwnedMapIter::Map(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            OwnedMapIter::Array(iter) => {
+                let (low, high) = iter.size_hint();
+                (low / 2, high.map(|h| h / 2))
+            }
+            OwnedMapIter::Map(iter) => iter.size_hint(),
+        }
+    }
+}
+
+/// Values are generally not used directly unless you are using the
+/// more low level functionality in the library.  For the most part
+/// this is hidden with the help of the `FromRedisValue` trait.
+///
+/// While on the redis protocol there is an error type this is already
+/// separated at an early point so the value only holds the remaining
+/// types.
+impl Value {
+    /// Checks if the return value looks like it fulfils the cursor
+    /// protocol.  That means the result is an array item of length
+    /// two with the first one being a cursor and the second an
+    /// array response.
+    pub fn looks_like_cursor(&self) -> bool {
+        match *self {
+            Value::Array(ref items) => {
+                if items.len() != 2 {
+                    return false;
+                }
+                matches!(items[0], Value::BulkString(_)) && matches!(items[1], Value::Array(_))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns an `&[Value]` if `self` is compatible with a sequence type
+    pub fn as_sequence(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(&items[..]),
+            Value::Set(items) => Some(&items[..]),
+            Value::Nil => Some(&[]),
+            _ => None,
+        }
+    }
+
+    /// Returns a `Vec<Value>` if `self` is compatible with a sequence type,
+    /// otherwise returns `Err(self)`.
+    pub fn into_sequence(self) -> Result<Vec<Value>, Value> {
+        match self {
+            Value::Array(items) => Ok(items),
+            Value::Set(items) => Ok(items),
+            Value::Nil => Ok(vec![]),
+            _ => Err(self),
+        }
+    }
+
+    /// Returns an iterator of `(&Value, &Value)` if `self` is compatible with a map type
+    pub fn as_map_iter(&self) -> Option<MapIter<'_>> {
+        match self {
+            Value::Array(items) => {
+                if items.len() % 2 == 0 {
+                    Some(MapIter::Array(items.iter()))
+                } else {
+                    None
+                }
+            }
+            Value::Map(items) => Some(MapIter::Map(items.iter())),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator of `(Value, Value)` if `self` is compatible with a map type.
+    /// If not, returns `Err(self)`.
+    pub fn into_map_iter(self) -> Result<OwnedMapIter, Value> {
+        match self {
+            Value::Array(items) => {
+                if items.len() % 2 == 0 {
+                    Ok(OwnedMapIter::Array(items.into_iter()))
+                } else {
+                    Err(Value::Array(items))
+                }
+            }
+            Value::Map(items) => Ok(OwnedMapIter::Map(items.into_iter())),
+            _ => Err(self),
+        }
+    }
+
+    /// If value contains a server error, return it as an Err. Otherwise wrap the value in Ok.
+    pub fn extract_error(self) -> Result<Self, ServerError> {
+        match self {
+            Self::Array(val) => Ok(Self::Array(Self::extract_error_vec(val)?)),
+            Self::Map(map) => Ok(Self::Map(Self::extract_error_map(map)?)),
+            Self::Attribute { data, attributes } => {
+                let data = Box::new((*data).extract_error()?);
+                let attributes = Self::extract_error_map(attributes)?;
+                Ok(Value::Attribute { data, attributes })
+            }
+            Self::Set(set) => Ok(Self::Set(Self::extract_error_vec(set)?)),
+            Self::Push { kind, data } => Ok(Self::Push {
+                kind,
+                data: Self::extract_error_vec(data)?,
+            }),
+            Value::ServerError(err) => Err(err),
+            _ => Ok(self),
+        }
+    }
+
+    fn extract_error_vec(vec: Vec<Self>) -> Result<Vec<Self>, ServerError> {
+        vec.into_iter().map(Self::extract_error).collect()
+    }
+
+    fn extract_error_map(map: Vec<(Self, Self)>) -> Result<Vec<(Self, Self)>, ServerError> {
+        let mut vec = Vec::with_capacity(map.len());
+        for (key, value) in map.into_iter() {
+            vec.push((key.extract_error()?, value.extract_error()?));
+        }
+        Ok(vec)
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Value::Nil => write!(fmt, "nil"),
+            Value::Int(val) => write!(fmt, "int({val:?})"),
+            Value::BulkString(ref val) => match from_utf8(val) {
+                Ok(x) => write!(fmt, "bulk-string('{x:?}')"),
+                Err(_) => write!(fmt, "binary-data({val:?})"),
+            },
+            Value::Array(ref values) => write!(fmt, "array({values:?})"),
+            Value::Push { ref kind, ref data } => write!(fmt, "push({kind:?}, {data:?})"),
+            Value::Okay => write!(fmt, "ok"),
+            Value::SimpleString(ref s) => write!(fmt, "simple-string({s:?})"),
+            Value::Map(ref values) => write!(fmt, "map({values:?})"),
+            Value::Attribute {
+                ref data,
+                ref attributes,
+            } => write!(fmt, "attribute({data:?}, {attributes:?})"),
+            Value::Set(ref values) => write!(fmt, "set({values:?})"),
+            Value::Double(ref d) => write!(fmt, "double({d:?})"),
+            Value::Boolean(ref b) => write!(fmt, "boolean({b:?})"),
+            Value::VerbatimString {
+                ref format,
+                ref text,
+            } => {
+                write!(fmt, "verbatim-string({format:?},{text:?})")
+            }
+            Value::BigNumber(ref m) => write!(fmt, "big-number({m:?})"),
+            Value::ServerError(ref err) => match err.details() {
+                Some(details) => write!(fmt, "Server error: `{}: {details}`", err.code()),
+                None => write!(fmt, "Server error: `{}`", err.code()),
+            },
+        }
+    }
+}
+
+/// Enum representing the communication protocol with the server.
+///
+/// This enum represents the types of data that the server can send to the client,
+/// and the capabilities that the client can use.
+#[derive(Clone, Eq, PartialEq, Default, Debug, Copy)]
+#[non_exhaustive]
+pub enum ProtocolVersion {
+    /// <https://github.com/redis/redis-specifications/blob/master/protocol/RESP2.md>
+    #[default]
+    RESP2,
+    /// <https://github.com/redis/redis-specifications/blob/master/protocol/RESP3.md>
+    RESP3,
+    /// RESP3 if the server supports it, and RESP2 otherwise.
+    ///
+    /// Clients resolve this to one of the other versions while connecting.
+    /// Where a specific version is required, such as when encoding values,
+    /// it's treated as RESP3.
+    Auto,
+}
+
+impl ProtocolVersion {
+    /// Returns true if the protocol can support RESP3 features.
+    pub fn supports_resp3(&self) -> bool {
+        !matches!(self, ProtocolVersion::RESP2)
+    }
+}
@@ -0,0 +1,109 @@
+use std::borrow::Cow;
+
+#[cfg(feature = "num-bigint")]
+use num_bigint::BigInt;
+
+use crate::{PushKind, ServerError, Value, VerbatimFormat};
+
+/// A redis value that borrows its string data from the buffer it was parsed from.
+///
+/// This mirrors [`Value`], except that bulk strings, simple strings and
+/// verbatim strings are slices of the read buffer instead of owned copies.
+#[derive(PartialEq, Clone, Debug)]
+#[non_exhaustive]
+pub enum ValueRef<'a> {
+    /// A nil response from the server.
+    Nil,
+    /// An integer response.
+    Int(i64),
+    /// An arbitrary binary data, usually represents a binary-safe string.
+    BulkString(&'a [u8]),
+    /// A response containing an array with more data.
+    Array(Vec<ValueRef<'a>>),
+    /// A simple string response, without line breaks and not binary safe.
+    SimpleString(&'a str),
+    /// A status response which represents the string "OK".
+    Okay,
+    /// Unordered key,value list from the server.
+    Map(Vec<(ValueRef<'a>, ValueRef<'a>)>),
+    /// Attribute value from the server.
+    Attribute {
+        /// Data that attributes belong to.
+        data: Box<ValueRef<'a>>,
+        /// Key,Value list of attributes.
+        attributes: Vec<(ValueRef<'a>, ValueRef<'a>)>,
+    },
+    /// Unordered set value from the server.
+    Set(Vec<ValueRef<'a>>),
+    /// A floating number response from the server.
+    Double(f64),
+    /// A boolean response from the server.
+    Boolean(bool),
+    /// A string with a format type.
+    VerbatimString {
+        /// Text's format type
+        format: VerbatimFormat,
+        /// The text. Only owned if the server sent invalid UTF-8.
+        text: Cow<'a, str>,
+    },
+    #[cfg(feature = "num-bigint")]
+    /// Very large number that out of the range of the signed 64 bit numbers
+    BigNumber(BigInt),
+    #[cfg(not(feature = "num-bigint"))]
+    /// Very large number that out of the range of the signed 64 bit numbers
+    BigNumber(&'a [u8]),
+    /// Push data from the server.
+    Push {
+        /// Push Kind
+        kind: PushKind,
+        /// Remaining data from push message
+        data: Vec<ValueRef<'a>>,
+    },
+    /// Represents an error message from the server
+    ServerError(ServerError),
+}
+
+impl ValueRef<'_> {
+    /// Copies the value into an owned [`Value`].
+    pub fn to_value(&self) -> Value {
+        fn values(items: &[ValueRef<'_>]) -> Vec<Value> {
+            items.iter().map(ValueRef::to_value).collect()
+        }
+        fn pairs(items: &[(ValueRef<'_>, ValueRef<'_>)]) -> Vec<(Value, Value)> {
+            items
+                .iter()
+                .map(|(key, value)| (key.to_value(), value.to_value()))
+                .collect()
+        }
+
+        match self {
+            ValueRef::Nil => Value::Nil,
+            ValueRef::Int(val) => Value::Int(*val),
+            ValueRef::BulkString(bytes) => Value::BulkString(bytes.to_vec()),
+            ValueRef::Array(items) => Value::Array(values(items)),
+            ValueRef::SimpleString(s) => Value::SimpleString(s.to_string()),
+            ValueRef::Okay => Value::Okay,
+            ValueRef::Map(items) => Value::Map(pairs(items)),
+            ValueRef::Attribute { data, attributes } => Value::Attribute {
+                data: Box::new(data.to_value()),
+                attributes: pairs(attributes),
+            },
+            ValueRef::Set(items) => Value::Set(values(items)),
+            ValueRef::Double(val) => Value::Double(*val),
+            ValueRef::Boolean(val) => Value::Boolean(*val),
+            ValueRef::VerbatimString { format, text } => Value::VerbatimString {
+                format: format.clone(),
+                text: text.to_string(),
+            },
+            #[cfg(feature = "num-bigint")]
+            ValueRef::BigNumber(val) => Value::BigNumber(val.clone()),
+            #[cfg(not(feature = "num-bigint"))]
+            ValueRef::BigNumber(val) => Value::BigNumber(val.to_vec()),
+            ValueRef::Push { kind, data } => Value::Push {
+                kind: kind.clone(),
+                data: values(data),
+            },
+            ValueRef::ServerError(err) => Value::ServerError(err.clone()),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{self, Read};
+
+use criterion::{criterion_group, criterion_main, Bencher, Criterion, Throughput};
+use redis::Value;
+
+#[path = "../tests/support/mod.rs"]
+mod support;
+
+const ELEMENTS: usize = 10_000;
+
+/// A reader that hands out its data in small chunks, the way replies arrive
+/// over a socket.
+struct ChunkedReader<'a> {
+    data: &'a [u8],
+    chunk_size: usize,
+}
+
+impl Read for ChunkedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.data.len()).min(self.chunk_size);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut input = Vec::new();
+    support::encode_value(value, &mut input).unwrap();
+    assert_eq!(&redis::parse_redis_value(&input).unwrap(), value);
+    input
+}
+
+fn bench_decode_complete(b: &mut Bencher, input: &[u8]) {
+    b.iter(|| redis::parse_redis_value(input).unwrap());
+}
+
+fn bench_decode_chunked(b: &mut Bencher, input: &[u8], chunk_size: usize) {
+    b.iter(|| {
+        let mut reader = ChunkedReader {
+            data: input,
+            chunk_size,
+        };
+        redis::Parser::new().parse_value(&mut reader).unwrap()
+    });
+}
+
+fn bench_parser(c: &mut Criterion) {
+    let inputs = [
+        (
+            "bulk_strings",
+            encode(&Value::Array(
+                (0..ELEMENTS)
+                    .map(|i| Value::BulkString(format!("value:{i}").into_bytes()))
+                    .collect(),
+            )),
+        ),
+        (
+            "integers",
+            encode(&Value::Array(
+                (0..ELEMENTS as i64)
+                    .map(|i| Value::Int(i * 7_919))
+                    .collect(),
+            )),
+        ),
+        (
+            "nested_map",
+            encode(&Value::Map(
+                (0..ELEMENTS / 10)
+                    .map(|i| {
+                        (
+                            Value::SimpleString(format!("key:{i}")),
+                            Value::Array(vec![
+                                Value::Int(i as i64),
+                                Value::Double(i as f64 / 2.0),
+                                Value::Boolean(i % 2 == 0),
+                                Value::Nil,
+                            ]),
+                        )
+                    })
+                    .collect(),
+            )),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("parser");
+    group.throughput(Throughput::Elements(ELEMENTS as u64));
+    for (name, input) in &inputs {
+        group.bench_function(format!("{name}/complete"), |b| {
+            bench_decode_complete(b, input)
+        });
+        group.bench_function(format!("{name}/chunked"), |b| {
+            bench_decode_chunked(b, input, 1_460)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(bench, bench_parser);
+criterion_main!(bench);
@@ -1,24 +1,31 @@
-use super::{AsyncPushSender, HandleContainer, RedisFuture};
+use super::{
+    run_setup_hooks, AsyncConnectionSetup, AsyncPushSender, BulkStringStream, ConnectionLease,
+    HandleContainer, HeartbeatConfig, LeasePool, RedisFuture, SetupFailurePolicy, SetupHook,
+};
 #[cfg(feature = "cache-aio")]
 use crate::caching::CacheManager;
+use crate::circuit_breaker::{circuit_open_error, CircuitBreaker};
 use crate::{
     aio::{ConnectionLike, MultiplexedConnection, Runtime},
     check_resp3,
     client::{DEFAULT_CONNECTION_TIMEOUT, DEFAULT_RESPONSE_TIMEOUT},
     cmd,
-    errors::RedisError,
+    errors::{closed_connection_error, RedisError},
     subscription_tracker::{SubscriptionAction, SubscriptionTracker},
     types::{RedisResult, Value},
-    AsyncConnectionConfig, Client, Cmd, Pipeline, PushInfo, PushKind, ToRedisArgs,
+    AsyncConnectionConfig, CircuitBreakerConfig, CircuitState, Client, Cmd, ConnectionInfo,
+    ErrorKind, ExponentialBackoff, IntoConnectionInfo, Pipeline, ProtocolVersion, PushInfo,
+    PushKind, Reply, RetryContext, RetryPolicy, Role, ServerErrorKind, ToRedisArgs,
 };
 use arc_swap::ArcSwap;
-use backon::{ExponentialBuilder, Retryable};
 use futures_channel::oneshot;
 use futures_util::future::{self, BoxFuture, FutureExt, Shared};
+use futures_util::Stream;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, watch, Mutex};
 
 type OptionalPushSender = Option<Arc<dyn AsyncPushSender>>;
 
@@ -34,6 +41,8 @@ pub struct ConnectionManagerConfig {
     max_delay: Option<Duration>,
     /// number_of_retries times, with an exponentially increasing delay
     number_of_retries: usize,
+    /// if set, replaces the exponential backoff configured by the fields above.
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
     /// The new connection will time out operations after `response_timeout` has passed.
     response_timeout: Option<Duration>,
     /// Each connection attempt to the server will time out after `connection_timeout`.
@@ -42,10 +51,42 @@ pub struct ConnectionManagerConfig {
     push_sender: Option<Arc<dyn AsyncPushSender>>,
     /// if true, the manager should resubscribe automatically to all pubsub channels after reconnect.
     resubscribe_automatically: bool,
+    /// hooks that run on every new connection.
+    setup_hooks: Vec<SetupHook>,
+    /// if set, requests wait for the manager to reconnect instead of failing.
+    reconnect_buffer: Option<ReconnectBufferConfig>,
+    /// if set, requests fail immediately while the server keeps failing.
+    circuit_breaker: Option<CircuitBreakerConfig>,
+    /// if set, the connection is replaced after being open for this long.
+    max_lifetime: Option<Duration>,
+    /// if set, `PING` is sent on the connection after it was idle for this long.
+    idle_ping_interval: Option<Duration>,
+    /// if set, idle connections are pinged, and replaced if the ping fails.
+    heartbeat: Option<HeartbeatConfig>,
+    /// the order in which the endpoints of a manager with several endpoints are tried.
+    endpoint_order: EndpointOrder,
     #[cfg(feature = "cache-aio")]
     pub(crate) cache_config: Option<crate::caching::CacheConfig>,
 }
 
+#[derive(Clone, Copy, Debug)]
+struct ReconnectBufferConfig {
+    max_len: usize,
+    max_wait: Duration,
+}
+
+/// The order in which a [ConnectionManager] with several endpoints tries them when it connects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EndpointOrder {
+    /// The endpoints are tried in the order they were given, so the manager connects to the
+    /// first endpoint that is available.
+    #[default]
+    Priority,
+    /// The endpoints are tried starting with the one after the endpoint of the last connection.
+    RoundRobin,
+}
+
 impl std::fmt::Debug for ConnectionManagerConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let &Self {
@@ -53,10 +94,18 @@ impl std::fmt::Debug for ConnectionManagerConfig {
             min_delay,
             number_of_retries,
             max_delay,
+            retry_policy,
             response_timeout,
             connection_timeout,
             push_sender,
             resubscribe_automatically,
+            setup_hooks,
+            reconnect_buffer,
+            circuit_breaker,
+            max_lifetime,
+            idle_ping_interval,
+            heartbeat,
+            endpoint_order,
             #[cfg(feature = "cache-aio")]
             cache_config,
         } = &self;
@@ -65,6 +114,14 @@ impl std::fmt::Debug for ConnectionManagerConfig {
             .field("min_delay", &min_delay)
             .field("max_delay", &max_delay)
             .field("number_of_retries", &number_of_retries)
+            .field(
+                "retry_policy",
+                if retry_policy.is_some() {
+                    &"set"
+                } else {
+                    &"not set"
+                },
+            )
             .field("response_timeout", &response_timeout)
             .field("connection_timeout", &connection_timeout)
             .field("resubscribe_automatically", &resubscribe_automatically)
@@ -75,7 +132,14 @@ impl std::fmt::Debug for ConnectionManagerConfig {
                 } else {
                     &"not set"
                 },
-            );
+            )
+            .field("setup_hooks", &setup_hooks.len())
+            .field("reconnect_buffer", &reconnect_buffer)
+            .field("circuit_breaker", &circuit_breaker)
+            .field("max_lifetime", &max_lifetime)
+            .field("idle_ping_interval", &idle_ping_interval)
+            .field("heartbeat", &heartbeat)
+            .field("endpoint_order", &endpoint_order);
 
         #[cfg(feature = "cache-aio")]
         str.field("cache_config", &cache_config);
@@ -128,6 +192,26 @@ impl ConnectionManagerConfig {
         self.connection_timeout
     }
 
+    /// Returns the time after which the manager replaces its connection, if set.
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        self.max_lifetime
+    }
+
+    /// Returns the idle time after which the manager pings its connection, if set.
+    pub fn idle_ping_interval(&self) -> Option<Duration> {
+        self.idle_ping_interval
+    }
+
+    /// Returns the configuration of the manager's heartbeat, if set.
+    pub fn heartbeat(&self) -> Option<HeartbeatConfig> {
+        self.heartbeat
+    }
+
+    /// Returns the order in which the endpoints of a manager with several endpoints are tried.
+    pub fn endpoint_order(&self) -> EndpointOrder {
+        self.endpoint_order
+    }
+
     /// Returns `true` if automatic resubscription is enabled after reconnecting.
     pub fn automatic_resubscription(&self) -> bool {
         self.resubscribe_automatically
@@ -164,6 +248,14 @@ impl ConnectionManagerConfig {
         self
     }
 
+    /// Sets the policy that decides whether failed connection attempts are retried, replacing
+    /// the exponential backoff configured by [Self::set_min_delay], [Self::set_max_delay],
+    /// [Self::set_exponent_base] and [Self::set_number_of_retries].
+    pub fn set_retry_policy(mut self, policy: impl RetryPolicy) -> ConnectionManagerConfig {
+        self.retry_policy = Some(Arc::new(policy));
+        self
+    }
+
     /// The new connection will time out operations after `response_timeout` has passed.
     ///
     /// Set `None` if you don't want requests to time out.
@@ -184,6 +276,7 @@ impl ConnectionManagerConfig {
     ///
     /// The sender can be a channel, or an arbitrary function that handles [crate::PushInfo] values.
     /// This will fail client creation if the connection isn't configured for RESP3 communications via the [crate::RedisConnectionInfo::set_protocol] function.
+    /// With [crate::ProtocolVersion::Auto], it fails if the server doesn't support RESP3.
     ///
     /// # Examples
     ///
@@ -216,6 +309,85 @@ impl ConnectionManagerConfig {
         self
     }
 
+    /// Adds a hook that runs on the initial connection and after every reconnect.
+    ///
+    /// See [crate::AsyncConnectionConfig::add_setup_hook] for more details.
+    pub fn add_setup_hook(
+        mut self,
+        hook: impl AsyncConnectionSetup,
+        on_failure: SetupFailurePolicy,
+    ) -> Self {
+        self.setup_hooks.push(SetupHook::new(hook, on_failure));
+        self
+    }
+
+    /// Makes requests wait for the manager to reconnect, instead of failing with the error
+    /// that revealed the disconnection.
+    ///
+    /// At most `max_len` requests wait at the same time, and each of them waits for at most
+    /// `max_wait`, after which it fails with the original error. Requests that were sent on the
+    /// lost connection might have been executed by the server, so they are only sent again on the
+    /// new connection if they are idempotent. Requests that weren't sent yet are always sent on
+    /// the new connection.
+    pub fn set_reconnect_buffer(mut self, max_len: usize, max_wait: Duration) -> Self {
+        self.reconnect_buffer = Some(ReconnectBufferConfig { max_len, max_wait });
+        self
+    }
+
+    /// Enables a circuit breaker for the server, which makes requests fail with
+    /// [crate::ErrorKind::CircuitOpen] while the server keeps failing, instead of waiting
+    /// for it. See [CircuitBreakerConfig] for more details.
+    ///
+    /// A manager with several endpoints has a circuit breaker for each of them, and fails over
+    /// to another endpoint while the circuit of its current endpoint is open.
+    pub fn set_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(config);
+        self
+    }
+
+    /// Replaces the manager's connection after it has been open for `max_lifetime`, for
+    /// servers behind load balancers or proxies that cut connections after a fixed lifetime.
+    ///
+    /// The manager opens the new connection while the old one keeps serving requests, switches
+    /// new requests to the new connection, and then closes the old connection once the requests
+    /// in flight on it were answered, or the response timeout passed. If the new connection
+    /// can't be opened, the old one is kept until the next attempt, after another `max_lifetime`.
+    ///
+    /// By default, the connection is kept open.
+    pub fn set_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// Sends `PING` on the manager's connection whenever no request was sent on it for
+    /// `interval`, so that idle connections aren't cut by load balancers or firewalls.
+    ///
+    /// By default, no pings are sent.
+    pub fn set_idle_ping_interval(mut self, interval: Duration) -> Self {
+        self.idle_ping_interval = Some(interval);
+        self
+    }
+
+    /// Enables a heartbeat, which pings the manager's connection whenever it's idle, and
+    /// reconnects if the ping fails or is answered too slowly. The requests in flight on the
+    /// broken connection fail immediately, instead of waiting for the response timeout.
+    /// See [HeartbeatConfig] for more details.
+    ///
+    /// Unlike [set_idle_ping_interval](Self::set_idle_ping_interval), which only keeps the
+    /// connection active, the heartbeat detects broken connections.
+    pub fn set_heartbeat(mut self, config: HeartbeatConfig) -> Self {
+        self.heartbeat = Some(config);
+        self
+    }
+
+    /// Sets the order in which the endpoints of a manager that was created with
+    /// [ConnectionManager::new_with_endpoints] are tried when it connects. Defaults to
+    /// [EndpointOrder::Priority].
+    pub fn set_endpoint_order(mut self, order: EndpointOrder) -> Self {
+        self.endpoint_order = order;
+        self
+    }
+
     /// Set the cache behavior.
     #[cfg(feature = "cache-aio")]
     pub fn set_cache_config(self, cache_config: crate::caching::CacheConfig) -> Self {
@@ -233,34 +405,256 @@ impl Default for ConnectionManagerConfig {
             min_delay: Self::DEFAULT_CONNECTION_RETRY_MIN_DELAY,
             max_delay: None,
             number_of_retries: Self::DEFAULT_NUMBER_OF_CONNECTION_RETRIES,
+            retry_policy: None,
             response_timeout: DEFAULT_RESPONSE_TIMEOUT,
             connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
             push_sender: None,
             resubscribe_automatically: false,
+            setup_hooks: Vec::new(),
+            reconnect_buffer: None,
+            circuit_breaker: None,
+            max_lifetime: None,
+            idle_ping_interval: None,
+            heartbeat: None,
+            endpoint_order: EndpointOrder::default(),
             #[cfg(feature = "cache-aio")]
             cache_config: None,
         }
     }
 }
 
+/// The state of the connection of a [ConnectionManager].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionState {
+    /// The manager is establishing its initial connection.
+    Connecting,
+    /// The manager has a connection to the server.
+    Connected,
+    /// The connection was lost, and the manager is trying to reconnect.
+    Reconnecting {
+        /// The number of the current reconnection attempt, starting at 1.
+        attempt: usize,
+    },
+    /// The manager ran out of reconnection attempts.
+    ///
+    /// If the last attempt failed with an I/O error, the next request starts reconnecting again.
+    Failed,
+    /// The manager was closed with [ConnectionManager::close], and won't reconnect.
+    Closed,
+}
+
+/// A change in the [ConnectionState] of a [ConnectionManager].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConnectionEvent {
+    /// When the state changed.
+    pub timestamp: SystemTime,
+    /// The new state.
+    pub state: ConnectionState,
+    /// The error that caused the change, if any.
+    pub error: Option<RedisError>,
+}
+
+/// The number of events that a slow [ConnectionManager::events] stream can fall behind by
+/// before it starts skipping events.
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+struct StateTracker {
+    state: watch::Sender<ConnectionState>,
+    events: broadcast::Sender<ConnectionEvent>,
+}
+
+impl StateTracker {
+    fn new() -> Self {
+        Self {
+            state: watch::Sender::new(ConnectionState::Connecting),
+            events: broadcast::Sender::new(EVENT_CHANNEL_CAPACITY),
+        }
+    }
+
+    fn set(&self, state: ConnectionState, error: Option<RedisError>) {
+        // a closed manager stays closed.
+        let changed = self.state.send_if_modified(|current| {
+            if *current == ConnectionState::Closed {
+                return false;
+            }
+            *current = state;
+            true
+        });
+        if !changed {
+            return;
+        }
+        // there's no one to notify if no stream is listening.
+        let _ = self.events.send(ConnectionEvent {
+            timestamp: SystemTime::now(),
+            state,
+            error,
+        });
+    }
+}
+
+/// Tracks the requests that wait for the manager to reconnect.
+struct ReconnectBuffer {
+    config: ReconnectBufferConfig,
+    len: AtomicUsize,
+}
+
+impl ReconnectBuffer {
+    /// Reserves a place for a request, if the buffer isn't full.
+    fn enter(&self) -> Option<BufferSlot<'_>> {
+        self.len
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |len| {
+                (len < self.config.max_len).then_some(len + 1)
+            })
+            .ok()?;
+        Some(BufferSlot {
+            len: &self.len,
+            deadline: Instant::now() + self.config.max_wait,
+        })
+    }
+}
+
+struct BufferSlot<'a> {
+    len: &'a AtomicUsize,
+    deadline: Instant,
+}
+
+impl Drop for BufferSlot<'_> {
+    fn drop(&mut self) {
+        self.len.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
 struct Internals {
     /// Information used for the connection. This is needed to be able to reconnect.
-    client: Client,
+    endpoints: Arc<Endpoints>,
     /// The connection future.
     ///
     /// The `ArcSwap` is required to be able to replace the connection
     /// without making the `ConnectionManager` mutable.
     connection: ArcSwap<SharedRedisFuture<MultiplexedConnection>>,
+    /// The protocol that the first connection negotiated, which decides whether RESP3 features,
+    /// such as subscriptions, are available.
+    protocol: ProtocolVersion,
 
     runtime: Runtime,
-    retry_strategy: ExponentialBuilder,
+    retry_policy: Arc<dyn RetryPolicy>,
     connection_config: AsyncConnectionConfig,
+    /// The pools that connections to each endpoint are leased from, in the order of the
+    /// endpoints.
+    leases: Vec<LeasePool>,
     subscription_tracker: Option<Mutex<SubscriptionTracker>>,
     #[cfg(feature = "cache-aio")]
     cache_manager: Option<CacheManager>,
+    state: StateTracker,
+    reconnect_buffer: Option<ReconnectBuffer>,
     _task_handle: HandleContainer,
 }
 
+/// The servers that the manager connects to. A manager that was created with several endpoints
+/// only connects to the ones that are masters.
+struct Endpoints {
+    clients: Vec<Client>,
+    /// The circuit breakers of the endpoints, in the order of the endpoints, if the manager
+    /// has circuit breakers.
+    circuit_breakers: Option<Vec<std::sync::Mutex<CircuitBreaker>>>,
+    order: EndpointOrder,
+    /// The index of the endpoint of the last connection.
+    active: AtomicUsize,
+}
+
+impl Endpoints {
+    fn active(&self) -> &Client {
+        &self.clients[self.active_index()]
+    }
+
+    fn active_index(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn is_failover(&self) -> bool {
+        self.clients.len() > 1
+    }
+
+    fn acquire_circuit(&self, index: usize) -> RedisResult<()> {
+        match &self.circuit_breakers {
+            Some(breakers) if !breakers[index].lock().unwrap().try_acquire() => Err(
+                circuit_open_error(&self.clients[index].connection_info.addr.to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_circuit<T>(&self, index: usize, result: &RedisResult<T>) {
+        if let Some(breakers) = &self.circuit_breakers {
+            breakers[index].lock().unwrap().record(result);
+        }
+    }
+
+    fn circuit_allows_requests(&self, index: usize) -> bool {
+        self.circuit_breakers
+            .as_ref()
+            .is_none_or(|breakers| breakers[index].lock().unwrap().allows_requests())
+    }
+
+    /// Connects to the first endpoint that is available, in the configured order. Endpoints
+    /// whose circuit is open are tried last.
+    async fn connect(&self, config: &AsyncConnectionConfig) -> RedisResult<MultiplexedConnection> {
+        let len = self.clients.len();
+        let first = match self.order {
+            EndpointOrder::Priority => 0,
+            EndpointOrder::RoundRobin => self.active.load(Ordering::Relaxed) + 1,
+        };
+        let mut indices: Vec<_> = (first..first + len).map(|index| index % len).collect();
+        indices.sort_by_key(|index| !self.circuit_allows_requests(*index));
+        let mut last_error = None;
+        for index in indices {
+            let client = &self.clients[index];
+            let result = async {
+                let mut connection = client
+                    .get_multiplexed_async_connection_with_config(config)
+                    .await?;
+                if self.is_failover() {
+                    check_master(&mut connection, client).await?;
+                }
+                Ok(connection)
+            }
+            .await;
+            self.record_circuit(index, &result);
+            match result {
+                Ok(connection) => {
+                    self.active.store(index, Ordering::Relaxed);
+                    return Ok(connection);
+                }
+                // other errors, such as authentication failures, are configuration errors
+                // rather than failures of the endpoint.
+                Err(err) if !is_failover_error(&err) => return Err(err),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.expect("the manager has at least one endpoint"))
+    }
+}
+
+/// Returns whether a manager with several endpoints fails over to another endpoint after
+/// `err`, which is the case if the endpoint is unreachable, or is no longer a master.
+fn is_failover_error(err: &RedisError) -> bool {
+    err.is_io_error() || err.kind() == ErrorKind::Server(ServerErrorKind::ReadOnly)
+}
+
+async fn check_master(connection: &mut MultiplexedConnection, client: &Client) -> RedisResult<()> {
+    match cmd("ROLE").query_async(connection).await? {
+        Role::Primary { .. } => Ok(()),
+        _ => Err((
+            ErrorKind::Server(ServerErrorKind::ReadOnly),
+            "The endpoint isn't a master",
+            client.connection_info.addr.to_string(),
+        )
+            .into()),
+    }
+}
+
 /// A `ConnectionManager` is a proxy that wraps a [multiplexed
 /// connection][multiplexed-connection] and automatically reconnects to the
 /// server when necessary.
@@ -284,9 +678,21 @@ struct Internals {
 ///   initiated, will have to await the connection future.
 /// - If reconnecting fails, all pending commands will be failed as well. A
 ///   new reconnection attempt will be triggered if the error is an I/O error.
+/// - If the manager was configured with [ConnectionManagerConfig::set_reconnect_buffer],
+///   commands that fail because of the connection loss or a failed reconnection wait for
+///   the next connection instead, and are sent on it if that's safe.
 /// - If the connection manager uses RESP3 connection,it actively listens to updates from the
 ///   server, and so it will cause the manager to reconnect after a disconnection, even if the manager was unused at
 ///   the time of the disconnect.
+/// - The manager's [ConnectionState] can be read with [ConnectionManager::state], and its
+///   changes can be followed with [ConnectionManager::watch_state] or [ConnectionManager::events].
+/// - If the manager was configured with [ConnectionManagerConfig::set_circuit_breaker], commands
+///   fail immediately while the circuit is open. Its state can be read with
+///   [ConnectionManager::circuit_state].
+/// - A manager created with [ConnectionManager::new_with_endpoints] connects to the first
+///   available master among its endpoints, and fails over to another endpoint when its
+///   endpoint becomes unreachable, is demoted to a replica, or its circuit opens. The current
+///   endpoint can be read with [ConnectionManager::active_endpoint].
 ///
 /// [multiplexed-connection]: struct.MultiplexedConnection.html
 #[derive(Clone)]
@@ -295,9 +701,8 @@ pub struct ConnectionManager(Arc<Internals>);
 impl std::fmt::Debug for ConnectionManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ConnectionManager")
-            .field("client", &self.0.client)
-            .field("retry_strategy", &self.0.retry_strategy)
-            .finish()
+            .field("client", self.0.endpoints.active())
+            .finish_non_exhaustive()
     }
 }
 
@@ -309,23 +714,67 @@ macro_rules! reconnect_if_dropped {
     ($self:expr, $result:expr, $current:expr) => {
         if let Err(ref e) = $result {
             if e.is_unrecoverable_error() {
-                Self::reconnect(Arc::downgrade(&$self.0), $current);
+                Self::reconnect(Arc::downgrade(&$self.0), $current, Some(e.clone()));
             }
         }
     };
 }
 
-/// Handle a connection result. If there's an I/O error, reconnect.
-/// Propagate any error.
-macro_rules! reconnect_if_io_error {
-    ($self:expr, $result:expr, $current:expr) => {
-        if let Err(e) = $result {
-            if e.is_io_error() {
-                Self::reconnect(Arc::downgrade(&$self.0), $current);
+/// Send a request on the current connection. If there's an I/O error while
+/// connecting, or the connection was dropped, reconnect.
+///
+/// If the manager buffers requests during reconnection, the request waits for the
+/// new connection and is sent on it, unless it was already sent on the lost
+/// connection and isn't idempotent.
+macro_rules! send_with_reconnect {
+    ($self:expr, $is_idempotent:expr, |$con:ident| $send:expr) => {{
+        let is_idempotent = $is_idempotent;
+        let mut buffer_slot = None;
+        loop {
+            if $self.state() == ConnectionState::Closed {
+                break Err(closed_connection_error());
+            }
+            let endpoints = &$self.0.endpoints;
+            // Clone connection to avoid having to lock the ArcSwap in write mode
+            let guard = $self.0.connection.load();
+            // the endpoint of the current connection, or of the last one while reconnecting.
+            let endpoint = endpoints.active_index();
+            let result = if let Err(err) = endpoints.acquire_circuit(endpoint) {
+                if !endpoints.is_failover() {
+                    break Err(err);
+                }
+                // fails over to another endpoint, unless the manager is already reconnecting.
+                if guard.peek().is_some() {
+                    Self::reconnect(Arc::downgrade(&$self.0), guard, Some(err.clone()));
+                }
+                Err(err)
+            } else {
+                match (**guard).clone().await.map_err(|e| e.clone()) {
+                    Ok(mut $con) => {
+                        let result = $send;
+                        endpoints.record_circuit(endpoint, &result);
+                        reconnect_if_dropped!($self, &result, guard);
+                        match &result {
+                            Err(e) if e.is_unrecoverable_error() && is_idempotent => result,
+                            _ => break result,
+                        }
+                    }
+                    // the failed connection attempts were recorded by the circuit breakers of
+                    // their endpoints.
+                    Err(e) => {
+                        if !is_failover_error(&e) {
+                            break Err(e);
+                        }
+                        Self::reconnect(Arc::downgrade(&$self.0), guard, Some(e.clone()));
+                        Err(e)
+                    }
+                }
+            };
+            if !$self.wait_for_reconnection(&mut buffer_slot).await {
+                break result;
             }
-            return Err(e);
         }
-    };
+    }};
 }
 
 impl ConnectionManager {
@@ -346,33 +795,108 @@ impl ConnectionManager {
     ///
     /// In case of reconnection issues, the manager will retry reconnection
     /// number_of_retries times, with an exponentially increasing delay, calculated as
-    /// min(max_delay, rand(0 .. min_delay * (exponent_base ^ current-try))).
+    /// rand(min_delay ..= min(max_delay, min_delay * (exponent_base ^ current-try))),
+    /// unless a different policy was set with [ConnectionManagerConfig::set_retry_policy].
     ///
     /// The new connection will time out operations after `response_timeout` has passed.
     /// Each connection attempt to the server will time out after `connection_timeout`.
     pub async fn new_with_config(
         client: Client,
         config: ConnectionManagerConfig,
+    ) -> RedisResult<Self> {
+        Self::new_with_clients(vec![client], config).await
+    }
+
+    /// Connect to the first of `endpoints` that is available and is a master, and store the
+    /// connection inside the returned `ConnectionManager`.
+    ///
+    /// The endpoints are tried in the order set with [ConnectionManagerConfig::set_endpoint_order],
+    /// and an endpoint is only used if `ROLE` shows that it's a master. When the manager
+    /// reconnects, or when a request is rejected because the endpoint became a replica, the
+    /// endpoints are tried again in the same order. The endpoint of the current connection is
+    /// returned by [ConnectionManager::active_endpoint]. A reconnection attempt fails only
+    /// after all of the endpoints failed, and is retried according to the manager's retry
+    /// policy.
+    ///
+    /// The endpoints are expected to use the same protocol and database.
+    ///
+    /// ```rust,no_run
+    /// # async fn func() -> redis::RedisResult<()> {
+    /// use redis::aio::{ConnectionManager, ConnectionManagerConfig, EndpointOrder};
+    ///
+    /// let config = ConnectionManagerConfig::new().set_endpoint_order(EndpointOrder::Priority);
+    /// let manager = ConnectionManager::new_with_endpoints(
+    ///     ["redis://primary.example.com/", "redis://standby.example.com/"],
+    ///     config,
+    /// )
+    /// .await?;
+    /// println!("connected to {:?}", manager.active_endpoint().addr());
+    /// # Ok(()) }
+    /// ```
+    pub async fn new_with_endpoints<T: IntoConnectionInfo>(
+        endpoints: impl IntoIterator<Item = T>,
+        config: ConnectionManagerConfig,
+    ) -> RedisResult<Self> {
+        let clients = endpoints
+            .into_iter()
+            .map(Client::open)
+            .collect::<RedisResult<Vec<_>>>()?;
+        if clients.is_empty() {
+            return Err((ErrorKind::InvalidClientConfig, "No endpoints were given").into());
+        }
+        Self::new_with_clients(clients, config).await
+    }
+
+    async fn new_with_clients(
+        clients: Vec<Client>,
+        config: ConnectionManagerConfig,
     ) -> RedisResult<Self> {
         // Create a MultiplexedConnection and wait for it to be established
         let runtime = Runtime::locate();
+        let endpoints = Endpoints {
+            // so that the first connection attempt starts with the first endpoint.
+            active: AtomicUsize::new(clients.len() - 1),
+            circuit_breakers: config.circuit_breaker.map(|config| {
+                clients
+                    .iter()
+                    .map(|_| std::sync::Mutex::new(CircuitBreaker::new(config)))
+                    .collect()
+            }),
+            clients,
+            order: config.endpoint_order,
+        };
+        let client = &endpoints.clients[0];
 
         if config.resubscribe_automatically && config.push_sender.is_none() {
             return Err((crate::ErrorKind::Client, "Cannot set resubscribe_automatically without setting a push sender to receive messages.").into());
         }
 
-        let mut retry_strategy = ExponentialBuilder::default()
-            .with_factor(config.exponent_base)
-            .with_min_delay(config.min_delay)
-            .with_max_times(config.number_of_retries)
-            .with_jitter();
-        if let Some(max_delay) = config.max_delay {
-            retry_strategy = retry_strategy.with_max_delay(max_delay);
-        }
+        let retry_policy = config.retry_policy.unwrap_or_else(|| {
+            let mut backoff = ExponentialBackoff::new()
+                .set_number_of_retries(config.number_of_retries.try_into().unwrap_or(u32::MAX))
+                .set_factor(config.min_delay)
+                .set_min_delay(config.min_delay)
+                .set_exponent_base(config.exponent_base.into());
+            if let Some(max_delay) = config.max_delay {
+                backoff = backoff.set_max_delay(max_delay);
+            }
+            Arc::new(backoff)
+        });
 
+        let lease_config = AsyncConnectionConfig::new()
+            .set_connection_timeout(config.connection_timeout)
+            .set_response_timeout(None)
+            .set_setup_hooks(config.setup_hooks.clone());
+        let leases = endpoints
+            .clients
+            .iter()
+            .map(|client| client.get_lease_pool_with_config(lease_config.clone()))
+            .collect();
         let mut connection_config = AsyncConnectionConfig::new()
             .set_connection_timeout(config.connection_timeout)
-            .set_response_timeout(config.response_timeout);
+            .set_response_timeout(config.response_timeout)
+            .set_idle_ping_interval(config.idle_ping_interval)
+            .set_setup_hooks(config.setup_hooks);
 
         #[cfg(feature = "cache-aio")]
         let cache_manager = config
@@ -401,16 +925,36 @@ impl ConnectionManager {
 
             connection_config =
                 connection_config.set_push_sender_internal(Arc::new(internal_sender));
+            // checked against the negotiated protocol once the manager connected.
+            connection_config.push_sender_allows_resp2 = true;
         } else if client.connection_info.redis.protocol.supports_resp3() {
             let (internal_sender, internal_receiver) = unbounded_channel();
             components_for_reconnection_on_push = Some((internal_receiver, None));
 
             connection_config =
                 connection_config.set_push_sender_internal(Arc::new(internal_sender));
+            // with `ProtocolVersion::Auto`, the connection may fall back to RESP2, where it only
+            // reports disconnects.
+            connection_config.push_sender_allows_resp2 = true;
         }
 
-        let connection =
-            Self::new_connection(&client, retry_strategy, &connection_config, None).await?;
+        let state = StateTracker::new();
+        let connection = Self::new_connection(
+            &endpoints,
+            &*retry_policy,
+            &connection_config,
+            None,
+            |_, _| {},
+        )
+        .await?;
+        let protocol = connection.protocol();
+        if config.push_sender.is_some() {
+            check_resp3!(
+                protocol,
+                "Can only pass push sender to a connection using RESP3"
+            );
+        }
+        state.set(ConnectionState::Connected, None);
         let subscription_tracker = if config.resubscribe_automatically {
             Some(Mutex::new(SubscriptionTracker::default()))
         } else {
@@ -418,14 +962,21 @@ impl ConnectionManager {
         };
 
         let new_self = Self(Arc::new(Internals {
-            client,
+            endpoints: Arc::new(endpoints),
+            protocol,
             connection: ArcSwap::from_pointee(future::ok(connection).boxed().shared()),
             runtime,
-            retry_strategy,
+            retry_policy,
             connection_config,
+            leases,
             subscription_tracker,
             #[cfg(feature = "cache-aio")]
             cache_manager,
+            state,
+            reconnect_buffer: config.reconnect_buffer.map(|config| ReconnectBuffer {
+                config,
+                len: AtomicUsize::new(0),
+            }),
             _task_handle,
         }));
 
@@ -444,72 +995,234 @@ impl ConnectionManager {
                 })?;
         };
 
+        if let Some(max_lifetime) = config.max_lifetime {
+            new_self
+                .0
+                .runtime
+                .spawn(Self::recycle_connections(
+                    Arc::downgrade(&new_self.0),
+                    max_lifetime,
+                ))
+                .detach();
+        }
+        if let Some(heartbeat) = config.heartbeat {
+            new_self
+                .0
+                .runtime
+                .spawn(Self::check_health(Arc::downgrade(&new_self.0), heartbeat))
+                .detach();
+        }
+
         Ok(new_self)
     }
 
     async fn new_connection(
-        client: &Client,
-        exponential_backoff: ExponentialBuilder,
+        endpoints: &Endpoints,
+        retry_policy: &dyn RetryPolicy,
         connection_config: &AsyncConnectionConfig,
         additional_commands: Option<Pipeline>,
+        mut on_failed_attempt: impl FnMut(&RedisError, usize),
     ) -> RedisResult<MultiplexedConnection> {
-        let connection_config = connection_config.clone();
-        let get_conn = || async {
-            client
-                .get_multiplexed_async_connection_with_config(&connection_config)
-                .await
+        let mut failed_attempts = 0;
+        let conn = loop {
+            let err = match endpoints.connect(connection_config).await {
+                Ok(conn) => break conn,
+                Err(err) => err,
+            };
+            failed_attempts += 1;
+            let Some(delay) = retry_policy
+                .decide(&RetryContext::new(&err, failed_attempts))
+                .delay()
+            else {
+                return Err(err);
+            };
+            on_failed_attempt(&err, failed_attempts as usize);
+            Runtime::locate().sleep(delay).await;
         };
-        let mut conn = get_conn
-            .retry(exponential_backoff)
-            .sleep(|duration| async move { Runtime::locate().sleep(duration).await })
-            .await?;
         if let Some(pipeline) = additional_commands {
-            // TODO - should we ignore these failures?
-            let _ = pipeline.exec_async(&mut conn).await;
+            // failing to restore the subscriptions doesn't fail the new connection.
+            let hook = SetupHook::new(pipeline, SetupFailurePolicy::Warn);
+            run_setup_hooks(&conn, &[hook]).await?;
         }
         Ok(conn)
     }
 
+    // The configuration of a new connection, which gets a fresh view of the cache.
+    fn reconnection_config(internals: &Internals) -> AsyncConnectionConfig {
+        #[cfg(not(feature = "cache-aio"))]
+        let connection_config = internals.connection_config.clone();
+        #[cfg(feature = "cache-aio")]
+        let mut connection_config = internals.connection_config.clone();
+        #[cfg(feature = "cache-aio")]
+        if let Some(manager) = internals.cache_manager.as_ref() {
+            let new_cache_manager = manager.clone_and_increase_epoch();
+            connection_config = connection_config.set_cache_manager(new_cache_manager);
+        }
+        connection_config
+    }
+
+    async fn subscription_pipeline(internals: &Internals) -> Option<Pipeline> {
+        match &internals.subscription_tracker {
+            Some(subscription_tracker) => Some(
+                subscription_tracker
+                    .lock()
+                    .await
+                    .get_subscription_pipeline(),
+            ),
+            None => None,
+        }
+    }
+
+    /// Replaces the connection every `max_lifetime`, and closes the replaced connection once
+    /// its requests were answered. Returns once the manager is closed or dropped.
+    async fn recycle_connections(internals: Weak<Internals>, max_lifetime: Duration) {
+        let runtime = Runtime::locate();
+        loop {
+            runtime.sleep(max_lifetime).await;
+            let (current, endpoints, retry_policy, connection_config, subscriptions) = {
+                let Some(internals) = internals.upgrade() else {
+                    return;
+                };
+                if *internals.state.state.borrow() == ConnectionState::Closed {
+                    return;
+                }
+                (
+                    internals.connection.load_full(),
+                    internals.endpoints.clone(),
+                    internals.retry_policy.clone(),
+                    Self::reconnection_config(&internals),
+                    Self::subscription_pipeline(&internals).await,
+                )
+            };
+            // a connection that is being established is new, and a failed one is replaced by
+            // the next request.
+            let Some(Ok(old_connection)) = (*current).clone().now_or_never() else {
+                continue;
+            };
+            // the manager isn't kept alive while connecting, which might take as long as the
+            // retry policy allows, so that it can still be dropped.
+            let Ok(new_connection) = Self::new_connection(
+                &endpoints,
+                &*retry_policy,
+                &connection_config,
+                subscriptions,
+                |_, _| {},
+            )
+            .await
+            else {
+                continue;
+            };
+            let Some(internals) = internals.upgrade() else {
+                let _ = new_connection.close(Duration::ZERO).await;
+                return;
+            };
+            let prev = internals.connection.compare_and_swap(
+                &current,
+                Arc::new(future::ok(new_connection.clone()).boxed().shared()),
+            );
+            // the manager reconnected, or was closed, while the new connection was opened.
+            if !Arc::ptr_eq(&prev, &current)
+                || *internals.state.state.borrow() == ConnectionState::Closed
+            {
+                let _ = new_connection.close(Duration::ZERO).await;
+                if !Arc::ptr_eq(&prev, &current) {
+                    continue;
+                }
+            }
+            // requests without a response timeout get the default one to complete.
+            let drain_timeout = internals
+                .connection_config
+                .response_timeout
+                .or(DEFAULT_RESPONSE_TIMEOUT)
+                .unwrap_or_default();
+            drop(internals);
+            let _ = old_connection.close(drain_timeout).await;
+        }
+    }
+
+    /// Pings the connection whenever it was idle for the heartbeat's interval, and reconnects
+    /// if the ping fails. Returns once the manager is closed or dropped.
+    async fn check_health(internals: Weak<Internals>, config: HeartbeatConfig) {
+        let runtime = Runtime::locate();
+        let mut delay = config.interval();
+        loop {
+            runtime.sleep(delay).await;
+            delay = config.interval();
+            let Some(internals) = internals.upgrade() else {
+                return;
+            };
+            if *internals.state.state.borrow() == ConnectionState::Closed {
+                return;
+            }
+            let current = internals.connection.load();
+            // a connection that is being established is checked once it's ready.
+            let Some(Ok(mut connection)) = (**current).clone().now_or_never() else {
+                continue;
+            };
+            let idle = connection.idle_time();
+            if idle < config.interval() {
+                delay = config.interval() - idle;
+                continue;
+            }
+            if let Err(err) = super::ping(&mut connection, config.timeout()).await {
+                Self::reconnect(Arc::downgrade(&internals), current, Some(err));
+                // the requests in flight on the broken connection fail instead of waiting for
+                // their response timeout.
+                let _ = connection.close(Duration::ZERO).await;
+            }
+        }
+    }
+
     /// Reconnect and overwrite the old connection.
     ///
     /// The `current` guard points to the shared future that was active
-    /// when the connection loss was detected.
+    /// when the connection loss was detected, and `cause` is the error that
+    /// revealed it, if any.
     fn reconnect(
         internals: Weak<Internals>,
         current: arc_swap::Guard<Arc<SharedRedisFuture<MultiplexedConnection>>>,
+        cause: Option<RedisError>,
     ) {
         let Some(internals) = internals.upgrade() else {
             return;
         };
-        let internals_clone = internals.clone();
-        #[cfg(not(feature = "cache-aio"))]
-        let connection_config = internals.connection_config.clone();
-        #[cfg(feature = "cache-aio")]
-        let mut connection_config = internals.connection_config.clone();
-        #[cfg(feature = "cache-aio")]
-        if let Some(manager) = internals.cache_manager.as_ref() {
-            let new_cache_manager = manager.clone_and_increase_epoch();
-            connection_config = connection_config.set_cache_manager(new_cache_manager);
+        if *internals.state.state.borrow() == ConnectionState::Closed {
+            return;
         }
+        let internals_clone = internals.clone();
+        let connection_config = Self::reconnection_config(&internals);
         let new_connection: SharedRedisFuture<MultiplexedConnection> = async move {
-            let additional_commands = match &internals_clone.subscription_tracker {
-                Some(subscription_tracker) => Some(
-                    subscription_tracker
-                        .lock()
-                        .await
-                        .get_subscription_pipeline(),
-                ),
-                None => None,
-            };
+            let additional_commands = Self::subscription_pipeline(&internals_clone).await;
 
-            let con = Self::new_connection(
-                &internals_clone.client,
-                internals_clone.retry_strategy,
+            let result = Self::new_connection(
+                &internals_clone.endpoints,
+                &*internals_clone.retry_policy,
                 &connection_config,
                 additional_commands,
+                |err, failed_attempts| {
+                    internals_clone.state.set(
+                        ConnectionState::Reconnecting {
+                            attempt: failed_attempts + 1,
+                        },
+                        Some(err.clone()),
+                    )
+                },
             )
-            .await?;
-            Ok(con)
+            .await;
+            // the manager might have been closed while reconnecting.
+            if let Ok(connection) = &result {
+                if *internals_clone.state.state.borrow() == ConnectionState::Closed {
+                    let _ = connection.close(Duration::ZERO).await;
+                    return Err(closed_connection_error());
+                }
+            }
+            match &result {
+                Ok(_) => internals_clone.state.set(ConnectionState::Connected, None),
+                Err(err) => internals_clone
+                    .state
+                    .set(ConnectionState::Failed, Some(err.clone())),
+            }
+            result
         }
         .boxed()
         .shared();
@@ -523,6 +1236,9 @@ impl ConnectionManager {
         // If the swap happened...
         if Arc::ptr_eq(&prev, &current) {
             // ...start the connection attempt immediately but do not wait on it.
+            internals
+                .state
+                .set(ConnectionState::Reconnecting { attempt: 1 }, cause);
             internals.runtime.spawn(new_connection.map(|_| ())).detach();
         }
     }
@@ -542,7 +1258,11 @@ impl ConnectionManager {
                 let Some(internals) = this.upgrade() else {
                     return;
                 };
-                Self::reconnect(Arc::downgrade(&internals), internals.connection.load());
+                Self::reconnect(
+                    Arc::downgrade(&internals),
+                    internals.connection.load(),
+                    None,
+                );
             }
             if let Some(sender) = external_sender.as_ref() {
                 let _ = sender.send(push_info);
@@ -550,18 +1270,148 @@ impl ConnectionManager {
         }
     }
 
+    /// Returns the current state of the manager's connection.
+    pub fn state(&self) -> ConnectionState {
+        *self.0.state.state.borrow()
+    }
+
+    /// Returns a receiver that is notified whenever the state of the manager's connection changes.
+    ///
+    /// ```rust,no_run
+    /// # async fn func() -> redis::RedisResult<()> {
+    /// # use redis::aio::ConnectionState;
+    /// let client = redis::Client::open("redis://127.0.0.1/")?;
+    /// let con = client.get_connection_manager().await?;
+    /// let mut state = con.watch_state();
+    /// while state.changed().await.is_ok() {
+    ///     let ready = *state.borrow_and_update() == ConnectionState::Connected;
+    ///     println!("ready: {ready}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.0.state.state.subscribe()
+    }
+
+    /// Returns a stream of the changes in the state of the manager's connection, starting
+    /// from the next change.
+    ///
+    /// A stream that falls behind by more than 64 events skips the oldest ones. The stream
+    /// ends when the manager and all of its clones are dropped.
+    pub fn events(&self) -> impl Stream<Item = ConnectionEvent> + Send + 'static {
+        let receiver = self.0.state.events.subscribe();
+        futures_util::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Returns the state of the circuit breaker of the manager's
+    /// [active endpoint](Self::active_endpoint), or `None` if it wasn't enabled with
+    /// [ConnectionManagerConfig::set_circuit_breaker].
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        let endpoints = &self.0.endpoints;
+        endpoints
+            .circuit_breakers
+            .as_ref()
+            .map(|breakers| breakers[endpoints.active_index()].lock().unwrap().state())
+    }
+
+    /// Closes the manager's connection gracefully, for all of the manager's clones.
+    ///
+    /// The manager stops reconnecting, and new requests fail immediately. The connection is
+    /// closed as described in [MultiplexedConnection::close], and if the manager is reconnecting,
+    /// the new connection is closed once it's established.
+    ///
+    /// This returns an error if requests were still in flight when `drain_timeout` passed.
+    pub async fn close(&self, drain_timeout: Duration) -> RedisResult<()> {
+        self.0.state.set(ConnectionState::Closed, None);
+        let connection = (**self.0.connection.load()).clone();
+        match connection.now_or_never() {
+            Some(Ok(connection)) => connection.close(drain_timeout).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Leases a connection to the manager's server that is used exclusively by the caller, for
+    /// commands that block the connection or change its state, such as `BLPOP`, `WATCH` or
+    /// `MULTI`, which would otherwise affect all the users of the manager.
+    ///
+    /// The connection runs the manager's setup hooks, and has no response timeout. Leased
+    /// connections aren't reconnected by the manager. The idle leased connections are kept
+    /// by the manager and its clones, separately for each endpoint.
+    ///
+    /// See [`ConnectionLease`](crate::aio::ConnectionLease) for more details.
+    pub async fn lease_connection(&self) -> RedisResult<ConnectionLease> {
+        self.0.leases[self.0.endpoints.active_index()].lease().await
+    }
+
+    /// Returns the connection information of the endpoint that the manager is connected to, or
+    /// was last connected to.
+    ///
+    /// Unless the manager was created with [ConnectionManager::new_with_endpoints], this is
+    /// always the endpoint of the manager's client.
+    pub fn active_endpoint(&self) -> &ConnectionInfo {
+        &self.0.endpoints.active().connection_info
+    }
+
+    // A master that was demoted to a replica rejects writes, so a manager with several
+    // endpoints looks for the new master.
+    fn reconnect_if_demoted<'a>(&self, values: impl IntoIterator<Item = &'a Value>) {
+        if !self.0.endpoints.is_failover() {
+            return;
+        }
+        let demoted = values.into_iter().find_map(|value| match value {
+            Value::ServerError(err) if err.kind() == Some(ServerErrorKind::ReadOnly) => {
+                Some(RedisError::from(err.clone()))
+            }
+            _ => None,
+        });
+        if let Some(err) = demoted {
+            Self::reconnect(Arc::downgrade(&self.0), self.0.connection.load(), Some(err));
+        }
+    }
+
     /// Sends an already encoded (packed) command into the TCP socket and
     /// reads the single response from it.
     pub async fn send_packed_command(&mut self, cmd: &Cmd) -> RedisResult<Value> {
-        // Clone connection to avoid having to lock the ArcSwap in write mode
-        let guard = self.0.connection.load();
-        let connection_result = (**guard).clone().await.map_err(|e| e.clone());
-        reconnect_if_io_error!(self, connection_result, guard);
-        let result = connection_result?.send_packed_command(cmd).await;
-        reconnect_if_dropped!(self, &result, guard);
+        let result = send_with_reconnect!(self, cmd.idempotency().is_safe_to_retry(), |con| {
+            con.send_packed_command(cmd).await
+        });
+        if let Ok(value) = &result {
+            self.reconnect_if_demoted([value]);
+        }
         result
     }
 
+    /// Sends an already encoded (packed) command whose reply is a bulk string,
+    /// and returns the bulk string as a stream of chunks instead of buffering it.
+    ///
+    /// See [`MultiplexedConnection::send_packed_command_streamed`] for details.
+    pub async fn send_packed_command_streamed(
+        &mut self,
+        cmd: &Cmd,
+    ) -> RedisResult<Option<BulkStringStream>> {
+        send_with_reconnect!(self, cmd.idempotency().is_safe_to_retry(), |con| {
+            con.send_packed_command_streamed(cmd).await
+        })
+    }
+
+    /// Sends an already encoded (packed) command, and returns its reply without decoding it.
+    ///
+    /// See [`MultiplexedConnection::send_packed_command_reply`] for details.
+    pub async fn send_packed_command_reply(&mut self, cmd: &Cmd) -> RedisResult<Reply> {
+        send_with_reconnect!(self, cmd.idempotency().is_safe_to_retry(), |con| {
+            con.send_packed_command_reply(cmd).await
+        })
+    }
+
     /// Sends multiple already encoded (packed) command into the TCP socket
     /// and reads `count` responses from it.  This is used to implement
     /// pipelining.
@@ -571,17 +1421,34 @@ impl ConnectionManager {
         offset: usize,
         count: usize,
     ) -> RedisResult<Vec<Value>> {
-        // Clone shared connection future to avoid having to lock the ArcSwap in write mode
-        let guard = self.0.connection.load();
-        let connection_result = (**guard).clone().await.map_err(|e| e.clone());
-        reconnect_if_io_error!(self, connection_result, guard);
-        let result = connection_result?
-            .send_packed_commands(cmd, offset, count)
-            .await;
-        reconnect_if_dropped!(self, &result, guard);
+        let result = send_with_reconnect!(self, cmd.is_safe_to_retry(), |con| {
+            con.send_packed_commands(cmd, offset, count).await
+        });
+        if let Ok(values) = &result {
+            self.reconnect_if_demoted(values);
+        }
         result
     }
 
+    /// Waits until the connection that replaces the lost one is ready, and returns whether the
+    /// request should be sent again.
+    async fn wait_for_reconnection<'a>(&'a self, slot: &mut Option<BufferSlot<'a>>) -> bool {
+        let Some(buffer) = &self.0.reconnect_buffer else {
+            return false;
+        };
+        if slot.is_none() {
+            *slot = buffer.enter();
+        }
+        let Some(remaining) = slot
+            .as_ref()
+            .and_then(|slot| slot.deadline.checked_duration_since(Instant::now()))
+        else {
+            return false;
+        };
+        let connection = (**self.0.connection.load()).clone();
+        self.0.runtime.timeout(remaining, connection).await.is_ok()
+    }
+
     async fn update_subscription_tracker(
         &self,
         action: SubscriptionAction,
@@ -618,7 +1485,7 @@ impl ConnectionManager {
     /// # }
     /// ```
     pub async fn subscribe(&mut self, channel_name: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.0.client.connection_info.redis.protocol);
+        check_resp3!(self.0.protocol);
         let mut cmd = cmd("SUBSCRIBE");
         cmd.arg(&channel_name);
         cmd.exec_async(self).await?;
@@ -632,7 +1499,7 @@ impl ConnectionManager {
     ///
     /// This method is only available when the connection is using RESP3 protocol, and will return an error otherwise.
     pub async fn unsubscribe(&mut self, channel_name: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.0.client.connection_info.redis.protocol);
+        check_resp3!(self.0.protocol);
         let mut cmd = cmd("UNSUBSCRIBE");
         cmd.arg(&channel_name);
         cmd.exec_async(self).await?;
@@ -662,7 +1529,7 @@ impl ConnectionManager {
     /// # }
     /// ```
     pub async fn psubscribe(&mut self, channel_pattern: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.0.client.connection_info.redis.protocol);
+        check_resp3!(self.0.protocol);
         let mut cmd = cmd("PSUBSCRIBE");
         cmd.arg(&channel_pattern);
         cmd.exec_async(self).await?;
@@ -675,7 +1542,7 @@ impl ConnectionManager {
     ///
     /// This method is only available when the connection is using RESP3 protocol, and will return an error otherwise.
     pub async fn punsubscribe(&mut self, channel_pattern: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.0.client.connection_info.redis.protocol);
+        check_resp3!(self.0.protocol);
         let mut cmd = cmd("PUNSUBSCRIBE");
         cmd.arg(&channel_pattern);
         cmd.exec_async(self).await?;
@@ -707,6 +1574,6 @@ impl ConnectionLike for ConnectionManager {
     }
 
     fn get_db(&self) -> i64 {
-        self.0.client.connection_info().redis.db
+        self.0.endpoints.active().connection_info().redis.db
     }
 }
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use super::{ConnectionLike, Runtime};
+use crate::{cmd, RedisResult};
+
+/// The configuration of a heartbeat, which detects broken connections without waiting for a
+/// request to fail.
+///
+/// A connection that neither sent a request nor received a reply for `interval` is sent a
+/// `PING`, unless it waits for a blocking command, such as `BLPOP`, which delays the reply to
+/// the `PING`. If the `PING` fails, or isn't answered within `timeout`, the connection is
+/// considered broken: the requests in flight on it fail immediately, and it's replaced by a new
+/// connection. This is independent of the TCP keepalive set in
+/// [TcpSettings](crate::io::tcp::TcpSettings), and also detects servers that are reachable but
+/// unresponsive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    interval: Duration,
+    timeout: Duration,
+}
+
+impl HeartbeatConfig {
+    /// Creates a configuration that pings connections that were idle for `interval`, and
+    /// considers them broken if the `PING` isn't answered within `timeout`.
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        Self { interval, timeout }
+    }
+
+    /// Returns the idle time after which a connection is pinged.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the time a `PING` is given to be answered.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+/// Sends `PING` on the connection, and fails if it isn't answered within `timeout`.
+pub(crate) async fn ping<C>(connection: &mut C, timeout: Duration) -> RedisResult<()>
+where
+    C: ConnectionLike + Send,
+{
+    Runtime::locate()
+        .timeout(timeout, cmd("PING").query_async::<()>(connection))
+        .await?
+}
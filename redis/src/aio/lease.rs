@@ -0,0 +1,352 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use futures_util::future::FutureExt;
+
+use super::{run_setup_hooks, ConnectionLike, MultiplexedConnection, Runtime};
+use crate::{cmd, AsyncConnectionConfig, Client, Cmd, Pipeline, RedisFuture, RedisResult, Value};
+
+/// The maximal number of idle connections that are kept for future leases.
+const MAX_IDLE_CONNECTIONS: usize = 4;
+
+/// A pool of connections that are leased exclusively by their holders.
+///
+/// All the connections of a pool are created with the same configuration, and a connection
+/// returns to the pool it was leased from once its lease is dropped, so that later leases of
+/// the pool can reuse it. The pool's clones share its idle connections, but different pools
+/// never share connections, even if they were created from the same client.
+///
+/// See [ConnectionLease] for more details.
+///
+/// ```rust,no_run
+/// # async fn func() -> redis::RedisResult<()> {
+/// use redis::AsyncTypedCommands;
+///
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let leases = client.get_lease_pool();
+/// let mut lease = leases.lease().await?;
+/// let popped = lease.blpop("queue", 0.0).await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct LeasePool(Arc<LeasePoolInner>);
+
+struct LeasePoolInner {
+    client: Client,
+    config: AsyncConnectionConfig,
+    idle: Mutex<Vec<MultiplexedConnection>>,
+}
+
+impl LeasePool {
+    pub(crate) fn new(client: Client, config: AsyncConnectionConfig) -> Self {
+        Self(Arc::new(LeasePoolInner {
+            client,
+            config,
+            idle: Mutex::new(Vec::new()),
+        }))
+    }
+
+    /// Leases a connection that is used exclusively by the caller, which is taken from the
+    /// pool's idle connections, or created if there's no idle one.
+    ///
+    /// Idle connections are checked with a `PING` before they're leased, and dropped if they
+    /// broke while they were idle.
+    pub async fn lease(&self) -> RedisResult<ConnectionLease> {
+        loop {
+            let idle = self.0.idle.lock().unwrap().pop();
+            let Some(mut connection) = idle else {
+                break;
+            };
+            if cmd("PING").query_async::<()>(&mut connection).await.is_ok() {
+                return Ok(ConnectionLease::new(connection, self.clone()));
+            }
+        }
+        let connection = self
+            .0
+            .client
+            .get_multiplexed_async_connection_with_config(&self.0.config)
+            .await?;
+        Ok(ConnectionLease::new(connection, self.clone()))
+    }
+
+    fn put(&self, connection: MultiplexedConnection) {
+        let mut idle = self.0.idle.lock().unwrap();
+        if idle.len() < MAX_IDLE_CONNECTIONS {
+            idle.push(connection);
+        }
+    }
+
+    /// Resets the state of a connection that the lease holder might have changed, and returns
+    /// the connection to the pool if the reset succeeded.
+    async fn reset_and_put(self, mut connection: MultiplexedConnection, reset: Option<Cmd>) {
+        let result = async {
+            if let Some(reset) = reset {
+                reset.query_async::<()>(&mut connection).await?;
+            }
+            // the setup hooks restore the state that they set up, in case it was changed.
+            run_setup_hooks(&connection, &self.0.config.setup_hooks).await
+        }
+        .await;
+        if result.is_ok() {
+            self.put(connection);
+        }
+    }
+}
+
+impl fmt::Debug for LeasePool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LeasePool")
+            .field("client", &self.0.client)
+            .field("idle", &self.0.idle.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// The state that the commands sent on a leased connection left it in.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct LeaseState {
+    in_transaction: bool,
+    watching: bool,
+    // Set when the connection's state can't be reset without also resetting the state that was
+    // set up when it connected, such as the selected database or the authenticated user.
+    must_close: bool,
+}
+
+impl LeaseState {
+    fn track(&mut self, cmd: &Cmd) {
+        let Some(primary) = cmd.arg_idx(0) else {
+            return;
+        };
+        match crate::commands::command_name(primary, cmd.arg_idx(1)).as_slice() {
+            b"MULTI" => self.in_transaction = true,
+            // both end the transaction, and unwatch all keys.
+            b"EXEC" | b"DISCARD" => {
+                self.in_transaction = false;
+                self.watching = false;
+            }
+            b"WATCH" => self.watching = true,
+            b"UNWATCH" => self.watching = false,
+            b"SUBSCRIBE" | b"PSUBSCRIBE" | b"SSUBSCRIBE" | b"MONITOR" | b"SELECT" | b"HELLO"
+            | b"AUTH" | b"RESET" | b"READONLY" | b"READWRITE" | b"CLIENT TRACKING"
+            | b"CLIENT REPLY" | b"CLIENT SETNAME" | b"CLIENT NO-EVICT" | b"CLIENT NO-TOUCH" => {
+                self.must_close = true
+            }
+            _ => {}
+        }
+    }
+
+    fn track_pipeline(&mut self, pipeline: &Pipeline) {
+        if pipeline.is_transaction() {
+            // the pipeline is wrapped in `MULTI` and `EXEC`, and the `EXEC` unwatches all keys.
+            for cmd in pipeline.cmd_iter() {
+                let mut state = LeaseState::default();
+                state.track(cmd);
+                self.must_close |= state.must_close;
+            }
+            self.watching = false;
+        } else {
+            for cmd in pipeline.cmd_iter() {
+                self.track(cmd);
+            }
+        }
+    }
+
+    /// Returns the command that resets the connection's state, if it needs to be reset.
+    fn reset_command(&self) -> Option<Cmd> {
+        if self.in_transaction {
+            // `DISCARD` also unwatches all keys.
+            Some(cmd("DISCARD"))
+        } else if self.watching {
+            Some(cmd("UNWATCH"))
+        } else {
+            None
+        }
+    }
+}
+
+/// A connection that is used exclusively by its holder.
+///
+/// Commands that block the connection, such as `BLPOP`, or that change its state, such as
+/// `WATCH` or `MULTI`, can't be sent safely on a [MultiplexedConnection] that is shared with
+/// other tasks. A lease provides a dedicated connection for such commands, which is taken from a
+/// small [LeasePool] of idle connections, or created if the pool is empty.
+///
+/// When the lease is dropped, its connection's state is reset in the background, by sending
+/// `DISCARD` if a transaction was started, or `UNWATCH` if keys were watched, and by running the
+/// connection's setup hooks again, and the connection returns to the pool. Connections whose
+/// state can't be reset this way - for example after `SUBSCRIBE`, `SELECT` or `CLIENT TRACKING` -
+/// are closed instead, as are connections that broke, or that were dropped while waiting for a
+/// response. Idle connections are checked with a `PING` before they're leased again.
+///
+/// Leased connections have no response timeout by default, since blocking commands might wait
+/// for a long time. Individual requests can set a timeout with
+/// [Cmd::set_response_timeout](crate::Cmd::set_response_timeout).
+pub struct ConnectionLease {
+    connection: Option<MultiplexedConnection>,
+    pool: LeasePool,
+    runtime: Runtime,
+    state: LeaseState,
+    awaiting_response: bool,
+}
+
+impl ConnectionLease {
+    fn new(connection: MultiplexedConnection, pool: LeasePool) -> Self {
+        Self {
+            connection: Some(connection),
+            pool,
+            runtime: Runtime::locate(),
+            state: LeaseState::default(),
+            awaiting_response: false,
+        }
+    }
+
+    fn connection(&mut self) -> &mut MultiplexedConnection {
+        self.connection
+            .as_mut()
+            .expect("the connection is only taken when the lease is dropped")
+    }
+
+    fn track_result<T>(&mut self, result: &RedisResult<T>) {
+        self.awaiting_response = false;
+        if let Err(err) = result {
+            // a request that timed out might still be running on the server.
+            if err.is_unrecoverable_error() || err.is_timeout() {
+                self.state.must_close = true;
+            }
+        }
+    }
+}
+
+impl ConnectionLike for ConnectionLease {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        async move {
+            self.awaiting_response = true;
+            self.state.track(cmd);
+            let result = self.connection().send_packed_command(cmd).await;
+            self.track_result(&result);
+            result
+        }
+        .boxed()
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        async move {
+            self.awaiting_response = true;
+            self.state.track_pipeline(cmd);
+            let result = self
+                .connection()
+                .send_packed_commands(cmd, offset, count)
+                .await;
+            self.track_result(&result);
+            result
+        }
+        .boxed()
+    }
+
+    fn get_db(&self) -> i64 {
+        self.connection
+            .as_ref()
+            .map_or(0, |connection| connection.get_db())
+    }
+}
+
+impl Drop for ConnectionLease {
+    fn drop(&mut self) {
+        let Some(connection) = self.connection.take() else {
+            return;
+        };
+        // a blocking command might still be running on the server, so the connection can't be
+        // reused.
+        if self.awaiting_response || self.state.must_close {
+            return;
+        }
+        self.runtime
+            .spawn(
+                self.pool
+                    .clone()
+                    .reset_and_put(connection, self.state.reset_command()),
+            )
+            .detach();
+    }
+}
+
+impl fmt::Debug for ConnectionLease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionLease")
+            .field("connection", &self.connection)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracked(commands: &[&[&str]]) -> LeaseState {
+        let mut state = LeaseState::default();
+        for args in commands {
+            state.track(&crate::cmd(args[0]).arg(&args[1..]).clone());
+        }
+        state
+    }
+
+    #[test]
+    fn watch_is_reset_with_unwatch() {
+        let state = tracked(&[&["WATCH", "key"], &["GET", "key"]]);
+        assert!(state.watching && !state.in_transaction && !state.must_close);
+        assert_eq!(
+            state.reset_command().unwrap().get_packed_command(),
+            cmd("UNWATCH").get_packed_command()
+        );
+
+        assert_eq!(
+            tracked(&[&["watch", "key"], &["unwatch"]]),
+            LeaseState::default()
+        );
+    }
+
+    #[test]
+    fn transaction_is_reset_with_discard() {
+        let state = tracked(&[&["WATCH", "key"], &["MULTI"], &["SET", "key", "1"]]);
+        assert!(state.watching && state.in_transaction);
+        assert_eq!(
+            state.reset_command().unwrap().get_packed_command(),
+            cmd("DISCARD").get_packed_command()
+        );
+
+        let state = tracked(&[&["WATCH", "key"], &["MULTI"], &["EXEC"]]);
+        assert_eq!(state, LeaseState::default());
+        assert!(state.reset_command().is_none());
+    }
+
+    #[test]
+    fn connection_state_changes_close_the_connection() {
+        for args in [
+            &["SUBSCRIBE", "channel"][..],
+            &["SELECT", "1"],
+            &["client", "tracking", "on"],
+            &["CLIENT", "SETNAME", "name"],
+        ] {
+            assert!(tracked(&[args]).must_close, "{args:?}");
+        }
+        assert!(!tracked(&[&["CLIENT", "ID"]]).must_close);
+    }
+
+    #[test]
+    fn atomic_pipeline_unwatches_keys() {
+        let mut state = tracked(&[&["WATCH", "key"]]);
+        state.track_pipeline(crate::pipe().atomic().set("key", 1).get("key"));
+        assert_eq!(state, LeaseState::default());
+
+        state.track_pipeline(crate::pipe().cmd("WATCH").arg("key"));
+        assert!(state.watching);
+    }
+}
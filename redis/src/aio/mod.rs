@@ -1,12 +1,12 @@
 //! Adds async IO support to redis.
 use crate::cmd::Cmd;
 use crate::connection::{
-    check_connection_setup, connection_setup_pipeline, AuthResult, ConnectionSetupComponents,
-    RedisConnectionInfo,
+    check_connection_setup, connection_setup_pipeline, negotiated_protocol,
+    should_fall_back_to_resp2, AuthResult, ConnectionSetupComponents, RedisConnectionInfo,
 };
 use crate::io::AsyncDNSResolver;
-use crate::types::{RedisFuture, RedisResult, Value};
-use crate::{errors::closed_connection_error, ErrorKind, PushInfo, RedisError};
+use crate::types::{RedisFuture, RedisResult, ServerInfo, Value};
+use crate::{errors::closed_connection_error, ErrorKind, ProtocolVersion, PushInfo, RedisError};
 use ::tokio::io::{AsyncRead, AsyncWrite};
 use futures_util::{
     future::{Future, FutureExt},
@@ -108,7 +108,7 @@ where
 {
     let count = pipeline.len();
     if count == 0 {
-        return Ok(AuthResult::Succeeded);
+        return Ok(AuthResult::Succeeded(None));
     }
     codec.send(pipeline.get_packed_pipeline()).await?;
 
@@ -121,17 +121,17 @@ where
     check_connection_setup(results, instructions)
 }
 
-pub(super) async fn setup_connection<T>(
+async fn authenticate_and_setup<T>(
     codec: &mut T,
     connection_info: &RedisConnectionInfo,
     #[cfg(feature = "cache-aio")] cache_config: Option<crate::caching::CacheConfig>,
-) -> RedisResult<()>
+) -> RedisResult<Option<ServerInfo>>
 where
     T: Sink<Vec<u8>, Error = RedisError>,
     T: Stream<Item = RedisResult<Value>>,
     T: Unpin + Send + 'static,
 {
-    if execute_connection_pipeline(
+    match execute_connection_pipeline(
         codec,
         connection_setup_pipeline(
             connection_info,
@@ -141,27 +141,79 @@ where
         ),
     )
     .await?
-        == AuthResult::ShouldRetryWithoutUsername
     {
-        execute_connection_pipeline(
-            codec,
-            connection_setup_pipeline(
-                connection_info,
-                false,
-                #[cfg(feature = "cache-aio")]
-                cache_config,
-            ),
-        )
-        .await?;
+        AuthResult::Succeeded(server_info) => Ok(server_info),
+        AuthResult::ShouldRetryWithoutUsername => {
+            execute_connection_pipeline(
+                codec,
+                connection_setup_pipeline(
+                    connection_info,
+                    false,
+                    #[cfg(feature = "cache-aio")]
+                    cache_config,
+                ),
+            )
+            .await?;
+            Ok(None)
+        }
     }
+}
 
-    Ok(())
+/// Sets up the connection, and returns the protocol that it uses and the
+/// server's reply to `HELLO`.
+///
+/// If the connection falls back to RESP2, client-side caching isn't enabled.
+pub(super) async fn setup_connection<T>(
+    codec: &mut T,
+    connection_info: &RedisConnectionInfo,
+    #[cfg(feature = "cache-aio")] cache_config: Option<crate::caching::CacheConfig>,
+) -> RedisResult<(ProtocolVersion, Option<ServerInfo>)>
+where
+    T: Sink<Vec<u8>, Error = RedisError>,
+    T: Stream<Item = RedisResult<Value>>,
+    T: Unpin + Send + 'static,
+{
+    match authenticate_and_setup(
+        codec,
+        connection_info,
+        #[cfg(feature = "cache-aio")]
+        cache_config,
+    )
+    .await
+    {
+        Err(err) if should_fall_back_to_resp2(connection_info, &err) => {
+            let connection_info = connection_info.clone().set_protocol(ProtocolVersion::RESP2);
+            let server_info = authenticate_and_setup(
+                codec,
+                &connection_info,
+                #[cfg(feature = "cache-aio")]
+                None,
+            )
+            .await?;
+            Ok((ProtocolVersion::RESP2, server_info))
+        }
+        result => Ok((negotiated_protocol(connection_info), result?)),
+    }
 }
 
 mod connection;
 pub(crate) use connection::connect_simple;
+#[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
+mod heartbeat;
+#[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
+pub(crate) use heartbeat::ping;
+#[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "connection-manager", feature = "cluster-async")))
+)]
+pub use heartbeat::HeartbeatConfig;
 mod multiplexed_connection;
 pub use multiplexed_connection::*;
+mod lease;
+pub use lease::{ConnectionLease, LeasePool};
+mod multiplexed_pool;
+pub use multiplexed_pool::{MultiplexedPool, MultiplexedPoolConfig};
 #[cfg(feature = "connection-manager")]
 mod connection_manager;
 #[cfg(feature = "connection-manager")]
@@ -229,6 +281,101 @@ where
     }
 }
 
+/// What a connection does when one of its setup hooks fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum SetupFailurePolicy {
+    /// Fail the connection attempt with the hook's error.
+    #[default]
+    Abort,
+    /// Log a warning and use the connection anyway.
+    Warn,
+}
+
+/// A step that runs on every new async connection, after it was authenticated
+/// and selected its database, and before it is returned to the user.
+///
+/// Hooks are registered with [crate::AsyncConnectionConfig::add_setup_hook], and run again
+/// whenever a [ConnectionManager] or a cluster connection reconnects. Commands and pipelines
+/// can be used as hooks directly, as well as closures that receive a clone of the new
+/// connection.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use redis::{AsyncConnectionConfig, aio::SetupFailurePolicy};
+/// let script = redis::Script::new("return redis.call('GET', KEYS[1])");
+/// let config = AsyncConnectionConfig::new()
+///     .add_setup_hook(
+///         redis::cmd("CLIENT").arg("NO-EVICT").arg("ON").clone(),
+///         SetupFailurePolicy::Abort,
+///     )
+///     .add_setup_hook(
+///         move |mut con: redis::aio::MultiplexedConnection| {
+///             let script = script.clone();
+///             async move { script.load_async(&mut con).await.map(|_| ()) }
+///         },
+///         SetupFailurePolicy::Warn,
+///     );
+/// ```
+pub trait AsyncConnectionSetup: Send + Sync + 'static {
+    /// Prepares the new connection.
+    fn setup(&self, con: MultiplexedConnection) -> RedisFuture<'_, ()>;
+}
+
+impl AsyncConnectionSetup for Cmd {
+    fn setup(&self, mut con: MultiplexedConnection) -> RedisFuture<'_, ()> {
+        Box::pin(async move { self.exec_async(&mut con).await })
+    }
+}
+
+impl AsyncConnectionSetup for crate::Pipeline {
+    fn setup(&self, mut con: MultiplexedConnection) -> RedisFuture<'_, ()> {
+        Box::pin(async move { self.exec_async(&mut con).await })
+    }
+}
+
+impl<Func, Fut> AsyncConnectionSetup for Func
+where
+    Func: Fn(MultiplexedConnection) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = RedisResult<()>> + Send + 'static,
+{
+    fn setup(&self, con: MultiplexedConnection) -> RedisFuture<'_, ()> {
+        Box::pin(self(con))
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SetupHook {
+    hook: std::sync::Arc<dyn AsyncConnectionSetup>,
+    on_failure: SetupFailurePolicy,
+}
+
+impl SetupHook {
+    pub(crate) fn new(hook: impl AsyncConnectionSetup, on_failure: SetupFailurePolicy) -> Self {
+        Self {
+            hook: std::sync::Arc::new(hook),
+            on_failure,
+        }
+    }
+}
+
+/// Runs the setup hooks on a new connection, in the order they were registered.
+pub(crate) async fn run_setup_hooks(
+    con: &MultiplexedConnection,
+    hooks: &[SetupHook],
+) -> RedisResult<()> {
+    for SetupHook { hook, on_failure } in hooks {
+        if let Err(err) = hook.setup(con.clone()).await {
+            match on_failure {
+                SetupFailurePolicy::Abort => return Err(err),
+                SetupFailurePolicy::Warn => log::warn!("Connection setup hook failed: {err}"),
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Default DNS resolver which uses the system's DNS resolver.
 #[derive(Clone)]
 pub(crate) struct DefaultAsyncDNSResolver;
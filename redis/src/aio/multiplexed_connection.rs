@@ -4,19 +4,20 @@ use crate::caching::{CacheManager, CacheStatistics, PrepareCacheResult};
 use crate::{
     aio::setup_connection,
     check_resp3, cmd,
-    cmd::Cmd,
-    errors::{closed_connection_error, RedisError},
-    parser::ValueCodec,
-    types::{RedisFuture, RedisResult, Value},
-    AsyncConnectionConfig, ProtocolVersion, PushInfo, RedisConnectionInfo, ServerError,
-    ToRedisArgs,
+    cmd::{Cmd, PackedCommand},
+    errors::{closed_connection_error, ParsingError, RedisError},
+    parser::{NextReply, NextReplyMode, ReplyFrame, StreamingValueCodec, ValueCodec},
+    types::{RedisFuture, RedisResult, ServerInfo, Value},
+    AsyncConnectionConfig, ErrorKind, ProtocolVersion, PushInfo, RedisConnectionInfo, Reply,
+    ServerError, ToRedisArgs,
 };
 use ::tokio::{
-    io::{AsyncRead, AsyncWrite},
-    sync::{mpsc, oneshot},
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::{mpsc, oneshot, Notify, OwnedSemaphorePermit, Semaphore},
 };
+use bytes::{Buf, Bytes};
 use futures_util::{
-    future::{Future, FutureExt},
+    future::{self, Future, FutureExt},
     ready,
     sink::Sink,
     stream::{self, Stream, StreamExt},
@@ -26,14 +27,35 @@ use std::collections::VecDeque;
 use std::fmt;
 use std::fmt::Debug;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::task::{self, Poll};
-use std::time::Duration;
-use tokio_util::codec::Decoder;
+use std::time::{Duration, Instant};
+use tokio_util::{
+    codec::{Decoder, Framed},
+    sync::PollSender,
+};
 
 // Senders which the result of a single request are sent through
 type PipelineOutput = oneshot::Sender<RedisResult<Value>>;
 
+// The number of chunks of a streamed bulk string that are read ahead of its consumer.
+const BULK_STREAM_BUFFER_SIZE: usize = 16;
+
+type BulkStreamChunks = mpsc::Sender<RedisResult<Bytes>>;
+
+// Senders through which the start of a streamed bulk string, or the reply that
+// was received instead of a bulk string, are sent.
+type BulkStreamOutput = oneshot::Sender<RedisResult<BulkStreamHeader>>;
+
+// Senders through which a reply that wasn't decoded is sent.
+type RawReplyOutput = oneshot::Sender<RedisResult<Reply>>;
+
+enum BulkStreamHeader {
+    Started(usize),
+    Value(Value),
+}
+
 enum ErrorOrErrors {
     Errors(Vec<(usize, ServerError)>),
     // only set if we receive a transmission error
@@ -47,6 +69,14 @@ enum ResponseAggregate {
         error_or_errors: ErrorOrErrors,
         expectation: PipelineResponseExpectation,
     },
+    BulkStream {
+        header: Option<BulkStreamOutput>,
+        chunks: PollSender<RedisResult<Bytes>>,
+        started: bool,
+    },
+    Raw {
+        output: Option<RawReplyOutput>,
+    },
 }
 
 // TODO - this is a really bad name.
@@ -61,7 +91,23 @@ struct PipelineResponseExpectation {
 }
 
 impl ResponseAggregate {
-    fn new(expectation: Option<PipelineResponseExpectation>) -> Self {
+    fn new(
+        expectation: Option<PipelineResponseExpectation>,
+        bulk_stream: Option<(BulkStreamOutput, BulkStreamChunks)>,
+        raw: Option<RawReplyOutput>,
+    ) -> Self {
+        if let Some(output) = raw {
+            return ResponseAggregate::Raw {
+                output: Some(output),
+            };
+        }
+        if let Some((header, chunks)) = bulk_stream {
+            return ResponseAggregate::BulkStream {
+                header: Some(header),
+                chunks: PollSender::new(chunks),
+                started: false,
+            };
+        }
         match expectation {
             Some(expectation) => ResponseAggregate::Pipeline {
                 buffer: Vec::new(),
@@ -76,17 +122,366 @@ impl ResponseAggregate {
 struct InFlight {
     output: Option<PipelineOutput>,
     response_aggregate: ResponseAggregate,
+    // released once the response is delivered, so that the request is no longer counted.
+    permit: Option<RequestPermit>,
+}
+
+/// What a request does when its connection reached the limits set with
+/// [AsyncConnectionConfig::set_max_in_flight_requests] or
+/// [AsyncConnectionConfig::set_max_queued_bytes].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum BackpressurePolicy {
+    /// The request waits until earlier requests are answered. The wait counts towards the
+    /// request's response timeout.
+    #[default]
+    Wait,
+    /// The request fails immediately with [ErrorKind::RequestLimitReached].
+    FailFast,
+}
+
+// Counts the requests that a connection has in flight, limits them if the connection was
+// configured to, and tracks whether the connection is closing.
+struct RequestTracker {
+    in_flight: AtomicUsize,
+    queued_bytes: AtomicUsize,
+    // the number of requests in flight that may block on the server, such as `BLPOP`.
+    blocking: AtomicUsize,
+    // when the last request was sent, or answered.
+    last_activity: std::sync::Mutex<Instant>,
+    idle_ping_interval: Option<Duration>,
+    max_lifetime: Option<Duration>,
+    // how long an expired connection waits for the requests in flight.
+    drain_timeout: Option<Duration>,
+    // notified when the last request in flight completes.
+    drained: Notify,
+    closing: AtomicBool,
+    // notified to stop the connection's driver, which closes the socket.
+    closed: Notify,
+    requests: Option<Arc<Semaphore>>,
+    // The semaphore of the queued bytes, and the number of bytes it allows.
+    bytes: Option<(Arc<Semaphore>, u32)>,
+    policy: BackpressurePolicy,
+}
+
+impl RequestTracker {
+    fn new(config: &AsyncConnectionConfig) -> Self {
+        let requests = config
+            .max_in_flight_requests
+            .map(|max| Arc::new(Semaphore::new(max.min(Semaphore::MAX_PERMITS))));
+        let bytes = config.max_queued_bytes.map(|max| {
+            let max = u32::try_from(max).unwrap_or(u32::MAX);
+            (Arc::new(Semaphore::new(max as usize)), max)
+        });
+        Self {
+            in_flight: AtomicUsize::new(0),
+            queued_bytes: AtomicUsize::new(0),
+            blocking: AtomicUsize::new(0),
+            last_activity: std::sync::Mutex::new(Instant::now()),
+            idle_ping_interval: config.idle_ping_interval,
+            max_lifetime: config.max_lifetime,
+            drain_timeout: config.response_timeout,
+            drained: Notify::new(),
+            closing: AtomicBool::new(false),
+            closed: Notify::new(),
+            requests,
+            bytes,
+            policy: config.backpressure_policy,
+        }
+    }
+
+    async fn acquire(self: &Arc<Self>, bytes: usize) -> RedisResult<RequestPermit> {
+        if self.closing.load(Ordering::Acquire) {
+            return Err(closed_connection_error());
+        }
+        let requests = match &self.requests {
+            Some(semaphore) => Some(self.acquire_permits(semaphore, 1).await?),
+            None => None,
+        };
+        let bytes_permit = match &self.bytes {
+            // a request that is larger than the limit takes all of it.
+            Some((semaphore, max)) => Some(
+                self.acquire_permits(
+                    semaphore,
+                    u32::try_from(bytes).unwrap_or(u32::MAX).min(*max),
+                )
+                .await?,
+            ),
+            None => None,
+        };
+        Ok(self.permit(bytes, requests, bytes_permit))
+    }
+
+    fn permit(
+        self: &Arc<Self>,
+        bytes: usize,
+        requests: Option<OwnedSemaphorePermit>,
+        bytes_permit: Option<OwnedSemaphorePermit>,
+    ) -> RequestPermit {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        self.queued_bytes.fetch_add(bytes, Ordering::Relaxed);
+        *self.last_activity.lock().unwrap() = Instant::now();
+        RequestPermit {
+            tracker: self.clone(),
+            bytes,
+            _requests: requests,
+            _bytes: bytes_permit,
+        }
+    }
+
+    async fn wait_for_drain(&self) {
+        loop {
+            // created before the check, so that it can't miss the last request completing.
+            let drained = self.drained.notified();
+            if self.in_flight.load(Ordering::Acquire) == 0 {
+                return;
+            }
+            drained.await;
+        }
+    }
+
+    // Sends `PING` whenever no request was sent or answered for the idle ping interval, and
+    // returns once all the clones of the connection were dropped. Connections that wait for a
+    // blocking command aren't pinged, since the `PING` would wait behind the command.
+    async fn ping_when_idle(self: Arc<Self>, sender: mpsc::WeakSender<PipelineMessage>) {
+        let Some(interval) = self.idle_ping_interval else {
+            return future::pending().await;
+        };
+        let runtime = Runtime::locate();
+        loop {
+            let idle = self.last_activity.lock().unwrap().elapsed();
+            if idle < interval {
+                runtime.sleep(interval - idle).await;
+                continue;
+            }
+            if self.blocking.load(Ordering::Acquire) > 0 {
+                runtime.sleep(interval).await;
+                continue;
+            }
+            let Some(sender) = sender.upgrade() else {
+                return;
+            };
+            let mut pipeline = Pipeline {
+                sender,
+                tracker: self.clone(),
+            };
+            let ping = cmd("PING").get_packed_command_shared();
+            // a broken connection is noticed by the next request.
+            if pipeline.send_recv(ping, None, None, true).await.is_err() {
+                runtime.sleep(interval).await;
+            }
+        }
+    }
+
+    // Once the connection's lifetime passes, tells the push sender that the connection
+    // disconnected, and closes it like `MultiplexedConnection::close`. Returns once the requests
+    // in flight were answered and `QUIT` was sent, or the drain timeout passed.
+    async fn expire(
+        self: Arc<Self>,
+        sender: mpsc::WeakSender<PipelineMessage>,
+        push_sender: Option<Arc<dyn AsyncPushSender>>,
+    ) {
+        let Some(max_lifetime) = self.max_lifetime else {
+            return future::pending().await;
+        };
+        Runtime::locate().sleep(max_lifetime).await;
+        send_disconnect(&push_sender);
+        let Some(sender) = sender.upgrade() else {
+            return;
+        };
+        let mut pipeline = Pipeline {
+            sender,
+            tracker: self.clone(),
+        };
+        let _ = pipeline.drain_and_quit(self.drain_timeout).await;
+    }
+
+    async fn acquire_permits(
+        &self,
+        semaphore: &Arc<Semaphore>,
+        permits: u32,
+    ) -> RedisResult<OwnedSemaphorePermit> {
+        match self.policy {
+            BackpressurePolicy::Wait => semaphore
+                .clone()
+                .acquire_many_owned(permits)
+                .await
+                .map_err(|_| closed_connection_error()),
+            BackpressurePolicy::FailFast => semaphore
+                .clone()
+                .try_acquire_many_owned(permits)
+                .map_err(|_| {
+                    RedisError::from((
+                        ErrorKind::RequestLimitReached,
+                        "The connection has too many requests in flight",
+                    ))
+                }),
+        }
+    }
+}
+
+// Held by a request from before it's queued until it's answered, or dropped.
+struct RequestPermit {
+    tracker: Arc<RequestTracker>,
+    bytes: usize,
+    _requests: Option<OwnedSemaphorePermit>,
+    _bytes: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        self.tracker
+            .queued_bytes
+            .fetch_sub(self.bytes, Ordering::Relaxed);
+        *self.tracker.last_activity.lock().unwrap() = Instant::now();
+        if self.tracker.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.tracker.drained.notify_waiters();
+        }
+    }
+}
+
+// Counts a blocking request as in flight on the connection while it's held.
+struct BlockingRequest(Option<Arc<RequestTracker>>);
+
+impl BlockingRequest {
+    fn start(tracker: &Arc<RequestTracker>, blocking: bool) -> Self {
+        if !blocking {
+            return Self(None);
+        }
+        tracker.blocking.fetch_add(1, Ordering::Relaxed);
+        Self(Some(tracker.clone()))
+    }
+}
+
+impl Drop for BlockingRequest {
+    fn drop(&mut self) {
+        if let Some(tracker) = &self.0 {
+            tracker.blocking.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
 }
 
 // A single message sent through the pipeline
 struct PipelineMessage {
-    input: Vec<u8>,
+    input: PackedCommand,
     // If `output` is None, then the caller doesn't expect to receive an answer.
     output: Option<PipelineOutput>,
     // If `None`, this is a single request, not a pipeline of multiple requests.
     // If `Some`, the first value is the number of responses to skip,
     // the second is the number of responses to keep, and the third is whether the pipeline is a transaction.
     expectation: Option<PipelineResponseExpectation>,
+    // If `Some`, the reply is expected to be a bulk string, which is streamed through these channels.
+    bulk_stream: Option<(BulkStreamOutput, BulkStreamChunks)>,
+    // If `Some`, the reply isn't decoded, and is sent through this channel.
+    raw: Option<RawReplyOutput>,
+    permit: RequestPermit,
+}
+
+pin_project! {
+    // Wraps the framed connection, and writes packed commands that reference shared arguments
+    // directly to the socket with vectored writes, instead of copying them into the write buffer.
+    struct VectoredFramed<C> {
+        #[pin]
+        framed: Framed<C, StreamingValueCodec>,
+        // A packed command with shared arguments, and the number of its bytes that were written.
+        pending: Option<(PackedCommand, usize)>,
+    }
+}
+
+impl<C> VectoredFramed<C>
+where
+    C: AsyncWrite,
+{
+    fn new(framed: Framed<C, StreamingValueCodec>) -> Self {
+        VectoredFramed {
+            framed,
+            pending: None,
+        }
+    }
+
+    fn poll_write_pending(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), RedisError>> {
+        let mut this = self.project();
+        let Some((packed, written)) = this.pending else {
+            return Poll::Ready(Ok(()));
+        };
+        // Commands that were sent before must be written first.
+        ready!(this.framed.as_mut().poll_flush(cx))?;
+        loop {
+            let slices = packed.io_slices(*written);
+            if slices.is_empty() {
+                *this.pending = None;
+                return Poll::Ready(Ok(()));
+            }
+            let count = ready!(this
+                .framed
+                .as_mut()
+                .get_pin_mut()
+                .poll_write_vectored(cx, &slices))?;
+            if count == 0 {
+                return Poll::Ready(Err(
+                    std::io::Error::from(std::io::ErrorKind::WriteZero).into()
+                ));
+            }
+            *written += count;
+        }
+    }
+}
+
+impl<C> Sink<PackedCommand> for VectoredFramed<C>
+where
+    C: AsyncWrite,
+{
+    type Error = RedisError;
+
+    fn poll_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_write_pending(cx))?;
+        self.project().framed.poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: PackedCommand) -> Result<(), Self::Error> {
+        let this = self.project();
+        match item.try_into_vec() {
+            Ok(item) => this.framed.start_send(item),
+            // `poll_ready` made sure that there's no other pending command.
+            Err(item) => {
+                *this.pending = Some((item, 0));
+                Ok(())
+            }
+        }
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_write_pending(cx))?;
+        self.project().framed.poll_flush(cx)
+    }
+
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), Self::Error>> {
+        ready!(self.as_mut().poll_write_pending(cx))?;
+        self.project().framed.poll_close(cx)
+    }
+}
+
+impl<C> Stream for VectoredFramed<C>
+where
+    C: AsyncRead,
+{
+    type Item = RedisResult<ReplyFrame>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Option<Self::Item>> {
+        self.project().framed.poll_next(cx)
+    }
 }
 
 /// Wrapper around a `Stream + Sink` where each item sent through the `Sink` results in one or more
@@ -96,6 +491,7 @@ struct PipelineMessage {
 #[derive(Clone)]
 struct Pipeline {
     sender: mpsc::Sender<PipelineMessage>,
+    tracker: Arc<RequestTracker>,
 }
 
 impl Debug for Pipeline {
@@ -112,6 +508,8 @@ pin_project! {
         in_flight: VecDeque<InFlight>,
         error: Option<RedisError>,
         push_sender: Option<Arc<dyn AsyncPushSender>>,
+        next_reply: Arc<NextReplyMode>,
+        tracker: Arc<RequestTracker>,
         cache_manager: Option<CacheManager>,
     }
 }
@@ -124,6 +522,8 @@ pin_project! {
         in_flight: VecDeque<InFlight>,
         error: Option<RedisError>,
         push_sender: Option<Arc<dyn AsyncPushSender>>,
+        next_reply: Arc<NextReplyMode>,
+        tracker: Arc<RequestTracker>,
     }
 }
 
@@ -137,23 +537,74 @@ pub(crate) fn send_disconnect(push_sender: &Option<Arc<dyn AsyncPushSender>>) {
     send_push(push_sender, PushInfo::disconnect());
 }
 
+// Passes a part of a streamed bulk string to the request at the front of the queue.
+fn send_bulk_frame(in_flight: &mut VecDeque<InFlight>, frame: ReplyFrame) {
+    if frame == ReplyFrame::BulkEnd {
+        // dropping the chunks sender ends the stream.
+        in_flight.pop_front();
+        return;
+    }
+    let Some(InFlight {
+        response_aggregate:
+            ResponseAggregate::BulkStream {
+                header,
+                chunks,
+                started,
+            },
+        ..
+    }) = in_flight.front_mut()
+    else {
+        return;
+    };
+    match frame {
+        ReplyFrame::BulkStart(length) => {
+            *started = true;
+            if let Some(header) = header.take() {
+                _ = header.send(Ok(BulkStreamHeader::Started(length)));
+            }
+        }
+        // `poll_read` reserved capacity for the chunk before reading it.
+        ReplyFrame::BulkChunk(chunk) => _ = chunks.send_item(Ok(chunk)),
+        ReplyFrame::Value(_) | ReplyFrame::Raw(_) | ReplyFrame::BulkEnd => {}
+    }
+}
+
+// Passes a reply that wasn't decoded to the request at the front of the queue, which asked for it.
+fn send_raw_reply(in_flight: &mut VecDeque<InFlight>, reply: Reply) {
+    let Some(mut entry) = in_flight.pop_front() else {
+        return;
+    };
+    entry.permit = None;
+    if let ResponseAggregate::Raw { output } = &mut entry.response_aggregate {
+        if let Some(output) = output.take() {
+            _ = output.send(Ok(reply));
+        }
+    }
+}
+
 impl<T> PipelineSink<T>
 where
-    T: Stream<Item = RedisResult<Value>> + 'static,
+    T: Stream<Item = RedisResult<ReplyFrame>> + 'static,
 {
     fn new(
         sink_stream: T,
         push_sender: Option<Arc<dyn AsyncPushSender>>,
+        next_reply: Arc<NextReplyMode>,
+        tracker: Arc<RequestTracker>,
         #[cfg(feature = "cache-aio")] cache_manager: Option<CacheManager>,
     ) -> Self
     where
-        T: Sink<Vec<u8>, Error = RedisError> + Stream<Item = RedisResult<Value>> + 'static,
+        T: Sink<PackedCommand, Error = RedisError>
+            + Stream<Item = RedisResult<ReplyFrame>>
+            + 'static,
     {
         PipelineSink {
             sink_stream,
             in_flight: VecDeque::new(),
             error: None,
             push_sender,
+            next_reply,
+            tracker,
             #[cfg(feature = "cache-aio")]
             cache_manager,
         }
@@ -162,6 +613,32 @@ where
     // Read messages from the stream and send them back to the caller
     fn poll_read(mut self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Result<(), ()>> {
         loop {
+            let self_ = self.as_mut().project();
+            let next_reply = match self_.in_flight.front_mut() {
+                Some(InFlight {
+                    response_aggregate:
+                        ResponseAggregate::BulkStream {
+                            chunks, started, ..
+                        },
+                    ..
+                }) => {
+                    if *started {
+                        // Don't read further into a streamed bulk string than its consumer can take.
+                        // If the consumer was dropped, the rest of the string is read and discarded.
+                        _ = ready!(chunks.poll_reserve(cx));
+                        NextReply::Value
+                    } else {
+                        NextReply::Streamed
+                    }
+                }
+                Some(InFlight {
+                    response_aggregate: ResponseAggregate::Raw { .. },
+                    ..
+                }) => NextReply::Raw,
+                _ => NextReply::Value,
+            };
+            self_.next_reply.set(next_reply);
+
             let item = ready!(self.as_mut().project().sink_stream.poll_next(cx));
             let item = match item {
                 Some(result) => result,
@@ -173,14 +650,29 @@ where
             self.as_mut().send_result(item);
             if is_unrecoverable {
                 let self_ = self.project();
-                send_disconnect(self_.push_sender);
+                // a connection that is being closed is expected to disconnect.
+                if !self_.tracker.closing.load(Ordering::Acquire) {
+                    send_disconnect(self_.push_sender);
+                }
                 return Poll::Ready(Err(()));
             }
         }
     }
 
-    fn send_result(self: Pin<&mut Self>, result: RedisResult<Value>) {
+    fn send_result(self: Pin<&mut Self>, result: RedisResult<ReplyFrame>) {
         let self_ = self.project();
+        let result = match result {
+            Ok(ReplyFrame::Value(value)) => Ok(value),
+            Ok(ReplyFrame::Raw(reply)) => {
+                send_raw_reply(self_.in_flight, reply);
+                return;
+            }
+            Ok(frame) => {
+                send_bulk_frame(self_.in_flight, frame);
+                return;
+            }
+            Err(err) => Err(err),
+        };
         let result = match result {
             // If this push message isn't a reply, we'll pass it as-is to the push manager and stop iterating
             Ok(Value::Push { kind, data }) if !kind.has_reply() => {
@@ -213,10 +705,34 @@ where
 
         match &mut entry.response_aggregate {
             ResponseAggregate::SingleCommand => {
+                entry.permit = None;
                 if let Some(output) = entry.output.take() {
                     _ = output.send(result);
                 }
             }
+            ResponseAggregate::BulkStream {
+                header,
+                chunks,
+                started,
+            } => {
+                if !*started {
+                    if let Some(header) = header.take() {
+                        _ = header.send(result.map(BulkStreamHeader::Value));
+                    }
+                } else if let (Err(err), Some(chunks)) = (result, chunks.get_ref()) {
+                    // the stream was cut short by an error.
+                    _ = chunks.try_send(Err(err));
+                }
+            }
+            ResponseAggregate::Raw { output } => {
+                entry.permit = None;
+                if let Some(output) = output.take() {
+                    // replies to raw requests are only decoded if they failed.
+                    _ = output.send(result.and_then(|_| {
+                        Err(ParsingError::from("Expected an undecoded reply".to_string()).into())
+                    }));
+                }
+            }
             ResponseAggregate::Pipeline {
                 buffer,
                 error_or_errors,
@@ -281,6 +797,7 @@ where
                 // `Err` means that the receiver was dropped in which case it does not
                 // care about the output and we can continue by just dropping the value
                 // and sender
+                entry.permit = None;
                 if let Some(output) = entry.output.take() {
                     _ = output.send(response);
                 }
@@ -291,7 +808,7 @@ where
 
 impl<T> Sink<PipelineMessage> for PipelineSink<T>
 where
-    T: Sink<Vec<u8>, Error = RedisError> + Stream<Item = RedisResult<Value>> + 'static,
+    T: Sink<PackedCommand, Error = RedisError> + Stream<Item = RedisResult<ReplyFrame>> + 'static,
 {
     type Error = ();
 
@@ -315,6 +832,9 @@ where
             input,
             mut output,
             expectation,
+            bulk_stream,
+            raw,
+            permit,
         }: PipelineMessage,
     ) -> Result<(), Self::Error> {
         // If initially a receiver was created, but then dropped, there is nothing to receive our output we do not need to send the message as it is
@@ -335,10 +855,11 @@ where
 
         match self_.sink_stream.start_send(input) {
             Ok(()) => {
-                let response_aggregate = ResponseAggregate::new(expectation);
+                let response_aggregate = ResponseAggregate::new(expectation, bulk_stream, raw);
                 let entry = InFlight {
                     output,
                     response_aggregate,
+                    permit: Some(permit),
                 };
 
                 self_.in_flight.push_back(entry);
@@ -388,32 +909,98 @@ impl Pipeline {
     fn new<T>(
         sink_stream: T,
         push_sender: Option<Arc<dyn AsyncPushSender>>,
+        next_reply: Arc<NextReplyMode>,
+        tracker: RequestTracker,
         #[cfg(feature = "cache-aio")] cache_manager: Option<CacheManager>,
     ) -> (Self, impl Future<Output = ()>)
     where
-        T: Sink<Vec<u8>, Error = RedisError>,
-        T: Stream<Item = RedisResult<Value>>,
+        T: Sink<PackedCommand, Error = RedisError>,
+        T: Stream<Item = RedisResult<ReplyFrame>>,
         T: Unpin + Send + 'static,
     {
         const BUFFER_SIZE: usize = 50;
         let (sender, mut receiver) = mpsc::channel(BUFFER_SIZE);
 
+        let tracker = Arc::new(tracker);
+        let expiry_push_sender = push_sender.clone();
         let sink = PipelineSink::new(
             sink_stream,
             push_sender,
+            next_reply,
+            tracker.clone(),
             #[cfg(feature = "cache-aio")]
             cache_manager,
         );
-        let f = stream::poll_fn(move |cx| receiver.poll_recv(cx))
+        let maintained = tracker.clone();
+        let weak_sender = sender.downgrade();
+        let forward = stream::poll_fn(move |cx| receiver.poll_recv(cx))
             .map(Ok)
-            .forward(sink)
-            .map(|_| ());
-        (Pipeline { sender }, f)
+            .forward(sink);
+        let stop = async move {
+            let closed = maintained.closed.notified();
+            let expired = maintained
+                .clone()
+                .expire(weak_sender.clone(), expiry_push_sender);
+            let ping = maintained.clone().ping_when_idle(weak_sender);
+            futures_util::pin_mut!(closed, expired, ping);
+            future::select(closed, future::select(expired, ping)).await;
+        };
+        // Stopping the driver drops the socket, which closes it.
+        let f = future::select(Box::pin(forward), Box::pin(stop)).map(|_| ());
+        (Pipeline { sender, tracker }, f)
+    }
+
+    // Rejects new requests, gives the requests in flight up to `drain_timeout` to be answered,
+    // and sends `QUIT` if they were. Returns an error if requests were still in flight when
+    // `drain_timeout` passed.
+    async fn drain_and_quit(&mut self, drain_timeout: Option<Duration>) -> RedisResult<()> {
+        self.tracker.closing.store(true, Ordering::Release);
+        let drained = match drain_timeout {
+            Some(timeout) => {
+                Runtime::locate()
+                    .timeout(timeout, self.tracker.wait_for_drain())
+                    .await
+            }
+            None => {
+                self.tracker.wait_for_drain().await;
+                Ok(())
+            }
+        };
+        if drained.is_ok() {
+            // the server might not reply to `QUIT` before closing the connection.
+            let _ = self.send_quit(drain_timeout).await;
+        }
+        Ok(drained?)
+    }
+
+    // Sends `QUIT` even though the connection is closing, and regardless of its limits.
+    async fn send_quit(&mut self, timeout: Option<Duration>) -> RedisResult<()> {
+        let (sender, receiver) = oneshot::channel();
+        let input = cmd("QUIT").get_packed_command_shared();
+        let permit = self.tracker.permit(input.byte_len(), None, None);
+        let request = async {
+            self.sender
+                .send(PipelineMessage {
+                    input,
+                    expectation: None,
+                    output: Some(sender),
+                    bulk_stream: None,
+                    raw: None,
+                    permit,
+                })
+                .await
+                .map_err(|_| None)?;
+            receiver
+                .await
+                .map_err(|_| None)
+                .and_then(|res| res.map_err(Some))
+        };
+        with_timeout(timeout, request).await.map(|_| ())
     }
 
     async fn send_recv(
         &mut self,
-        input: Vec<u8>,
+        input: PackedCommand,
         // If `None`, this is a single request, not a pipeline of multiple requests.
         // If `Some`, the value inside defines how the response should look like
         expectation: Option<PipelineResponseExpectation>,
@@ -421,12 +1008,16 @@ impl Pipeline {
         skip_response: bool,
     ) -> Result<Value, RedisError> {
         let request = async {
+            let permit = self.tracker.acquire(input.byte_len()).await.map_err(Some)?;
             if skip_response {
                 self.sender
                     .send(PipelineMessage {
                         input,
                         expectation,
                         output: None,
+                        bulk_stream: None,
+                        raw: None,
+                        permit,
                     })
                     .await
                     .map_err(|_| None)?;
@@ -441,6 +1032,9 @@ impl Pipeline {
                     input,
                     expectation,
                     output: Some(sender),
+                    bulk_stream: None,
+                    raw: None,
+                    permit,
                 })
                 .await
                 .map_err(|_| None)?;
@@ -452,14 +1046,190 @@ impl Pipeline {
             .and_then(|res| res.map_err(Some))
         };
 
-        match timeout {
-            Some(timeout) => match Runtime::locate().timeout(timeout, request).await {
-                Ok(res) => res,
-                Err(elapsed) => Err(Some(elapsed.into())),
-            },
-            None => request.await,
+        with_timeout(timeout, request).await
+    }
+
+    async fn send_recv_bulk_stream(
+        &mut self,
+        input: PackedCommand,
+        timeout: Option<Duration>,
+    ) -> RedisResult<Option<BulkStringStream>> {
+        let (header_sender, header_receiver) = oneshot::channel();
+        let (chunks_sender, chunks_receiver) = mpsc::channel(BULK_STREAM_BUFFER_SIZE);
+        let request = async {
+            let permit = self.tracker.acquire(input.byte_len()).await.map_err(Some)?;
+            self.sender
+                .send(PipelineMessage {
+                    input,
+                    expectation: None,
+                    output: None,
+                    bulk_stream: Some((header_sender, chunks_sender)),
+                    raw: None,
+                    permit,
+                })
+                .await
+                .map_err(|_| None)?;
+
+            header_receiver
+                .await
+                .map_err(|_| None)
+                .and_then(|res| res.map_err(Some))
+        };
+
+        match with_timeout(timeout, request).await? {
+            BulkStreamHeader::Started(length) => {
+                Ok(Some(BulkStringStream::new(chunks_receiver, length)))
+            }
+            BulkStreamHeader::Value(Value::Nil) => Ok(None),
+            BulkStreamHeader::Value(Value::ServerError(err)) => Err(err.into()),
+            BulkStreamHeader::Value(value) => {
+                crate::errors::invalid_type_error!(value, "Response type not a bulk string.")
+            }
+        }
+    }
+
+    async fn send_recv_raw(
+        &mut self,
+        input: PackedCommand,
+        timeout: Option<Duration>,
+    ) -> RedisResult<Reply> {
+        let (sender, receiver) = oneshot::channel();
+        let request = async {
+            let permit = self.tracker.acquire(input.byte_len()).await.map_err(Some)?;
+            self.sender
+                .send(PipelineMessage {
+                    input,
+                    expectation: None,
+                    output: None,
+                    bulk_stream: None,
+                    raw: Some(sender),
+                    permit,
+                })
+                .await
+                .map_err(|_| None)?;
+
+            receiver
+                .await
+                .map_err(|_| None)
+                .and_then(|res| res.map_err(Some))
+        };
+
+        with_timeout(timeout, request).await
+    }
+}
+
+// `None` errors mean that the stream part failed for one reason or another.
+async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    request: impl Future<Output = Result<T, Option<RedisError>>>,
+) -> RedisResult<T> {
+    match timeout {
+        Some(timeout) => match Runtime::locate().timeout(timeout, request).await {
+            Ok(res) => res,
+            Err(elapsed) => Err(Some(elapsed.into())),
+        },
+        None => request.await,
+    }
+    .map_err(|err| err.unwrap_or_else(closed_connection_error))
+}
+
+/// A bulk string reply that is read from the connection in chunks, as the
+/// chunks are consumed.
+///
+/// The stream can be consumed either as a [`Stream`] of [`Bytes`] chunks, or
+/// through [`AsyncRead`]. If it ends before the full length of the string was
+/// received, it returns an error.
+///
+/// While the stream is alive, replies to later requests on the same connection
+/// can't be read, so the stream should be consumed or dropped promptly.
+pub struct BulkStringStream {
+    chunks: mpsc::Receiver<RedisResult<Bytes>>,
+    length: usize,
+    received: usize,
+    // the unread rest of the last chunk, when read through `AsyncRead`.
+    current: Bytes,
+    done: bool,
+}
+
+impl BulkStringStream {
+    fn new(chunks: mpsc::Receiver<RedisResult<Bytes>>, length: usize) -> Self {
+        BulkStringStream {
+            chunks,
+            length,
+            received: 0,
+            current: Bytes::new(),
+            done: false,
+        }
+    }
+
+    /// The total length of the bulk string, as announced by the server.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Returns `true` if the bulk string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+impl Debug for BulkStringStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BulkStringStream")
+            .field("length", &self.length)
+            .field("received", &self.received)
+            .finish()
+    }
+}
+
+impl Stream for BulkStringStream {
+    type Item = RedisResult<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+        if !self.current.is_empty() {
+            return Poll::Ready(Some(Ok(std::mem::take(&mut self.current))));
+        }
+        if self.done {
+            return Poll::Ready(None);
+        }
+        let item = match ready!(self.chunks.poll_recv(cx)) {
+            Some(Ok(chunk)) => {
+                self.received += chunk.len();
+                Ok(chunk)
+            }
+            Some(Err(err)) => {
+                self.done = true;
+                Err(err)
+            }
+            None => {
+                self.done = true;
+                if self.received == self.length {
+                    return Poll::Ready(None);
+                }
+                Err(closed_connection_error())
+            }
+        };
+        Poll::Ready(Some(item))
+    }
+}
+
+impl AsyncRead for BulkStringStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        while self.current.is_empty() {
+            match ready!(self.as_mut().poll_next(cx)) {
+                Some(Ok(chunk)) => self.current = chunk,
+                Some(Err(err)) => return Poll::Ready(Err(std::io::Error::other(err))),
+                None => return Poll::Ready(Ok(())),
+            }
         }
-        .map_err(|err| err.unwrap_or_else(closed_connection_error))
+        let len = self.current.len().min(buf.remaining());
+        buf.put_slice(&self.current[..len]);
+        self.current.advance(len);
+        Poll::Ready(Ok(()))
     }
 }
 
@@ -481,6 +1251,7 @@ pub struct MultiplexedConnection {
     db: i64,
     response_timeout: Option<Duration>,
     protocol: ProtocolVersion,
+    server_info: Option<Arc<ServerInfo>>,
     // This handle ensures that once all the clones of the connection will be dropped, the underlying task will stop.
     // This handle is only set for connection whose task was spawned by the crate, not for users who spawned their own
     // task.
@@ -513,6 +1284,9 @@ impl MultiplexedConnection {
 
     /// Constructs a new `MultiplexedConnection` out of a `AsyncRead + AsyncWrite` object
     /// , a `RedisConnectionInfo` and a `AsyncConnectionConfig`.
+    ///
+    /// The config's setup hooks aren't run, since the connection can't send requests before
+    /// the returned driver is spawned. Send the setup commands once the driver is running instead.
     pub async fn new_with_config<C>(
         connection_info: &RedisConnectionInfo,
         stream: C,
@@ -522,6 +1296,7 @@ impl MultiplexedConnection {
         C: Unpin + AsyncRead + AsyncWrite + Send + 'static,
     {
         let mut codec = ValueCodec::default().framed(stream);
+        let tracker = RequestTracker::new(&config);
         if config.push_sender.is_some() {
             check_resp3!(
                 connection_info.protocol,
@@ -553,23 +1328,32 @@ impl MultiplexedConnection {
             })
             .transpose()?;
 
-        setup_connection(
+        let (protocol, server_info) = setup_connection(
             &mut codec,
             connection_info,
             #[cfg(feature = "cache-aio")]
             cache_config,
         )
         .await?;
-        if config.push_sender.is_some() {
+        if config.push_sender.is_some() && !config.push_sender_allows_resp2 {
             check_resp3!(
-                connection_info.protocol,
+                protocol,
                 "Can only pass push sender to a connection using RESP3"
             );
         }
+        // client-side caching requires RESP3, so it's disabled if the connection fell back to RESP2.
+        #[cfg(feature = "cache-aio")]
+        let cache_manager_opt = cache_manager_opt.filter(|_| protocol.supports_resp3());
 
+        let next_reply = Arc::new(NextReplyMode::default());
+        let codec = VectoredFramed::new(
+            codec.map_codec(|codec| StreamingValueCodec::new(codec, next_reply.clone())),
+        );
         let (pipeline, driver) = Pipeline::new(
             codec,
             config.push_sender,
+            next_reply,
+            tracker,
             #[cfg(feature = "cache-aio")]
             cache_manager_opt.clone(),
         );
@@ -577,7 +1361,8 @@ impl MultiplexedConnection {
             pipeline,
             db: connection_info.db,
             response_timeout: config.response_timeout,
-            protocol: connection_info.protocol,
+            protocol,
+            server_info: server_info.map(Arc::new),
             _task_handle: None,
             #[cfg(feature = "cache-aio")]
             cache_manager: cache_manager_opt,
@@ -597,6 +1382,61 @@ impl MultiplexedConnection {
         self.response_timeout = Some(timeout);
     }
 
+    /// Returns the number of requests that the connection has in flight - sent or waiting to be
+    /// sent, and not yet answered - across all of its clones.
+    pub fn queue_depth(&self) -> usize {
+        self.pipeline.tracker.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total size, in bytes, of the requests that the connection has in flight.
+    pub fn queued_bytes(&self) -> usize {
+        self.pipeline.tracker.queued_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Returns the time since the connection last sent a request or received a reply, or zero
+    /// while a blocking command, such as `BLPOP`, is in flight, since the server doesn't reply to
+    /// other requests before it's answered.
+    #[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
+    pub(crate) fn idle_time(&self) -> Duration {
+        let tracker = &self.pipeline.tracker;
+        if tracker.blocking.load(Ordering::Relaxed) > 0 {
+            return Duration::ZERO;
+        }
+        tracker.last_activity.lock().unwrap().elapsed()
+    }
+
+    /// Closes the connection gracefully, for all of its clones.
+    ///
+    /// New requests fail immediately, and the requests that are already in flight are given up
+    /// to `drain_timeout` to be answered. If they were, `QUIT` is sent, so that the server closes
+    /// the connection as well. Then the socket is closed, and requests that are still in flight
+    /// fail.
+    ///
+    /// This returns an error if requests were still in flight when `drain_timeout` passed.
+    pub async fn close(&self, drain_timeout: Duration) -> RedisResult<()> {
+        let result = self
+            .pipeline
+            .clone()
+            .drain_and_quit(Some(drain_timeout))
+            .await;
+        self.pipeline.tracker.closed.notify_one();
+        result
+    }
+
+    /// Returns the protocol version that the connection uses.
+    ///
+    /// If the connection was configured with [ProtocolVersion::Auto], this is
+    /// the version that was negotiated with the server.
+    pub fn protocol(&self) -> ProtocolVersion {
+        self.protocol
+    }
+
+    /// Returns the information that the server sent in reply to `HELLO`, or
+    /// `None` if the connection uses RESP2.
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_deref()
+    }
+
     /// Sends an already encoded (packed) command into the TCP socket and
     /// reads the single response from it.
     pub async fn send_packed_command(&mut self, cmd: &Cmd) -> RedisResult<Value> {
@@ -611,14 +1451,14 @@ impl MultiplexedConnection {
                     let result = self
                         .pipeline
                         .send_recv(
-                            pipeline.get_packed_pipeline(),
+                            pipeline.get_packed_pipeline().into(),
                             Some(PipelineResponseExpectation {
                                 skipped_response_count: 0,
                                 expected_response_count: pipeline.commands.len(),
                                 is_transaction: false,
                                 seen_responses: 0,
                             }),
-                            self.response_timeout,
+                            cmd.response_timeout(self.response_timeout),
                             cmd.is_no_response(),
                         )
                         .await?;
@@ -628,16 +1468,81 @@ impl MultiplexedConnection {
                 _ => (),
             }
         }
+        let _blocking = BlockingRequest::start(
+            &self.pipeline.tracker,
+            cmd.block_time() != Some(Duration::ZERO),
+        );
         self.pipeline
             .send_recv(
-                cmd.get_packed_command(),
+                cmd.get_packed_command_shared(),
                 None,
-                self.response_timeout,
+                cmd.response_timeout(self.response_timeout),
                 cmd.is_no_response(),
             )
             .await
     }
 
+    /// Sends an already encoded (packed) command whose reply is a bulk string,
+    /// and returns the bulk string as a stream of chunks instead of buffering it.
+    ///
+    /// Chunks are read from the socket as the stream is consumed, which allows
+    /// passing on large values, such as the replies to `GET` or `DUMP`, without
+    /// holding them in memory. Returns `None` if the reply is nil, and an error
+    /// if the reply is an error or of another type.
+    ///
+    /// The response timeout applies until the bulk string starts.
+    ///
+    /// ```rust,no_run
+    /// # async fn func() -> redis::RedisResult<()> {
+    /// use futures::StreamExt;
+    ///
+    /// # let client = redis::Client::open("redis://127.0.0.1/").unwrap();
+    /// let mut con = client.get_multiplexed_async_connection().await?;
+    /// if let Some(mut value) = con.send_packed_command_streamed(redis::cmd("GET").arg("blob")).await? {
+    ///     while let Some(chunk) = value.next().await {
+    ///         let chunk: bytes::Bytes = chunk?;
+    ///         // pass the chunk on
+    ///     }
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub async fn send_packed_command_streamed(
+        &mut self,
+        cmd: &Cmd,
+    ) -> RedisResult<Option<BulkStringStream>> {
+        self.pipeline
+            .send_recv_bulk_stream(
+                cmd.get_packed_command_shared(),
+                cmd.response_timeout(self.response_timeout),
+            )
+            .await
+    }
+
+    /// Sends an already encoded (packed) command, and returns its reply without decoding it.
+    ///
+    /// The reply's bytes are split off the connection's read buffer without being copied, so
+    /// that large replies can be parsed into borrowed types such as `&str`, `&[u8]` or
+    /// [`Bytes`], see [`Reply::parse`]. Server errors are returned when the reply is parsed.
+    ///
+    /// Client-side caching doesn't apply to these requests.
+    ///
+    /// ```rust,no_run
+    /// # async fn func() -> redis::RedisResult<()> {
+    /// # let client = redis::Client::open("redis://127.0.0.1/").unwrap();
+    /// let mut con = client.get_multiplexed_async_connection().await?;
+    /// let reply = con.send_packed_command_reply(redis::cmd("MGET").arg(&["a", "b"])).await?;
+    /// let values: Vec<Option<&str>> = reply.parse()?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn send_packed_command_reply(&mut self, cmd: &Cmd) -> RedisResult<Reply> {
+        self.pipeline
+            .send_recv_raw(
+                cmd.get_packed_command_shared(),
+                cmd.response_timeout(self.response_timeout),
+            )
+            .await
+    }
+
     /// Sends multiple already encoded (packed) command into the TCP socket
     /// and reads `count` responses from it.  This is used to implement
     /// pipelining.
@@ -654,31 +1559,36 @@ impl MultiplexedConnection {
             let result = self
                 .pipeline
                 .send_recv(
-                    pipeline.get_packed_pipeline(),
+                    pipeline.get_packed_pipeline().into(),
                     Some(PipelineResponseExpectation {
                         skipped_response_count,
                         expected_response_count,
                         is_transaction: cacheable_pipeline.transaction_mode,
                         seen_responses: 0,
                     }),
-                    self.response_timeout,
+                    cmd.response_timeout(self.response_timeout),
                     false,
                 )
                 .await?;
 
             return cacheable_pipeline.resolve(cache_manager, result);
         }
+        let _blocking = BlockingRequest::start(
+            &self.pipeline.tracker,
+            cmd.cmd_iter()
+                .any(|cmd| cmd.block_time() != Some(Duration::ZERO)),
+        );
         let value = self
             .pipeline
             .send_recv(
-                cmd.get_packed_pipeline(),
+                cmd.get_packed_pipeline_shared(),
                 Some(PipelineResponseExpectation {
                     skipped_response_count: offset,
                     expected_response_count: count,
                     is_transaction: cmd.is_transaction(),
                     seen_responses: 0,
                 }),
-                self.response_timeout,
+                cmd.response_timeout(self.response_timeout),
                 false,
             )
             .await?;
@@ -0,0 +1,353 @@
+use std::{
+    fmt,
+    future::Future,
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+use futures_util::future::{self, FutureExt};
+
+use super::{ConnectionLike, HandleContainer, MultiplexedConnection, Runtime};
+use crate::{
+    AsyncConnectionConfig, Client, Cmd, Pipeline, RedisError, RedisFuture, RedisResult, Value,
+};
+
+/// The configuration of a [MultiplexedPool].
+#[derive(Clone)]
+pub struct MultiplexedPoolConfig {
+    size: usize,
+    connection_config: AsyncConnectionConfig,
+    replacement_delay: Duration,
+}
+
+impl MultiplexedPoolConfig {
+    /// Creates a configuration of a pool of 4 connections.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of connections in the pool. The pool has at least one connection.
+    pub fn set_size(mut self, size: usize) -> Self {
+        self.size = size.max(1);
+        self
+    }
+
+    /// Sets the configuration of each connection in the pool.
+    pub fn set_connection_config(mut self, connection_config: AsyncConnectionConfig) -> Self {
+        self.connection_config = connection_config;
+        self
+    }
+
+    /// Sets the delay between failed attempts to replace a broken connection.
+    pub fn set_replacement_delay(mut self, replacement_delay: Duration) -> Self {
+        self.replacement_delay = replacement_delay;
+        self
+    }
+
+    /// Returns the number of connections in the pool.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the delay between failed attempts to replace a broken connection.
+    pub fn replacement_delay(&self) -> Duration {
+        self.replacement_delay
+    }
+}
+
+impl Default for MultiplexedPoolConfig {
+    fn default() -> Self {
+        Self {
+            size: 4,
+            connection_config: AsyncConnectionConfig::new(),
+            replacement_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+/// A pool of [MultiplexedConnection]s to a single server.
+///
+/// Each request is sent on the connection with the fewest requests in flight, so that traffic
+/// is spread over several sockets instead of saturating one. Unlike pools that check out
+/// connections exclusively, such as [bb8](https://docs.rs/bb8), requests don't wait for each
+/// other, since every connection is multiplexed.
+///
+/// When a request fails because its connection broke, the connection is replaced in the
+/// background, and until then requests are sent on the other connections. Requests fail only
+/// if none of the connections is available.
+///
+/// The pool is cheap to clone, and clones share the same connections. It can also be used as the
+/// connection to each node of a cluster, by creating the cluster connection with
+/// `ClusterClient::get_async_generic_connection::<MultiplexedPool>`, in which case each node gets
+/// a pool of the default size.
+///
+/// Since consecutive requests might be sent on different connections, commands that change the
+/// state of a connection, such as `WATCH`, `MULTI` or `SUBSCRIBE`, shouldn't be sent through the
+/// pool. Transactions sent as an atomic [Pipeline] are safe to use.
+///
+/// ```rust,no_run
+/// # async fn func() -> redis::RedisResult<()> {
+/// use redis::AsyncTypedCommands;
+///
+/// let client = redis::Client::open("redis://127.0.0.1/")?;
+/// let config = redis::aio::MultiplexedPoolConfig::new().set_size(8);
+/// let mut pool = client.get_multiplexed_pool_with_config(config).await?;
+/// pool.set("key", "value").await?;
+/// # Ok(()) }
+/// ```
+#[derive(Clone)]
+pub struct MultiplexedPool(Arc<PoolInner>);
+
+struct PoolInner {
+    client: Client,
+    config: MultiplexedPoolConfig,
+    runtime: Runtime,
+    members: Vec<Member>,
+    // The member that the search for the least loaded one starts from, so that ties are spread.
+    next: AtomicUsize,
+}
+
+struct Member {
+    state: Mutex<MemberState>,
+    in_flight: AtomicUsize,
+}
+
+struct MemberState {
+    // `None` while the connection is being replaced.
+    connection: Option<MultiplexedConnection>,
+    // Incremented whenever the connection is replaced, so that failures of requests that were
+    // sent on an earlier connection don't break its replacement.
+    generation: u64,
+    replacement: Option<HandleContainer>,
+}
+
+/// Decrements the number of requests in flight on a member when the request completes or is
+/// dropped.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn new(in_flight: &'a AtomicUsize) -> Self {
+        in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(in_flight)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl MultiplexedPool {
+    /// Connects the pool's connections to the server.
+    ///
+    /// This fails only if none of the connections could be established. Connections that
+    /// failed are retried in the background.
+    pub async fn new(client: Client, config: MultiplexedPoolConfig) -> RedisResult<Self> {
+        let runtime = Runtime::locate();
+        let results = future::join_all((0..config.size).map(|_| {
+            client.get_multiplexed_async_connection_with_config(&config.connection_config)
+        }))
+        .await;
+        if !results.iter().any(Result::is_ok) {
+            return Err(results
+                .into_iter()
+                .find_map(Result::err)
+                .unwrap_or_else(no_connection_error));
+        }
+
+        let members = results
+            .into_iter()
+            .map(|result| Member {
+                state: Mutex::new(MemberState {
+                    connection: result.ok(),
+                    generation: 0,
+                    replacement: None,
+                }),
+                in_flight: AtomicUsize::new(0),
+            })
+            .collect();
+        let inner = Arc::new(PoolInner {
+            client,
+            config,
+            runtime,
+            members,
+            next: AtomicUsize::new(0),
+        });
+        for (index, member) in inner.members.iter().enumerate() {
+            let mut state = member.state.lock().unwrap();
+            if state.connection.is_none() {
+                state.replacement = Some(inner.spawn_replacement(index));
+            }
+        }
+        Ok(Self(inner))
+    }
+
+    /// Returns the number of connections in the pool.
+    pub fn size(&self) -> usize {
+        self.0.members.len()
+    }
+
+    /// Returns the number of connections that are currently available, and not being replaced.
+    pub fn available_connections(&self) -> usize {
+        self.0
+            .members
+            .iter()
+            .filter(|member| member.state.lock().unwrap().connection.is_some())
+            .count()
+    }
+
+    /// Closes the pool's connections gracefully, for all of the pool's clones.
+    ///
+    /// New requests fail immediately, connections that are being replaced aren't replaced, and
+    /// each connection is closed as described in [MultiplexedConnection::close].
+    ///
+    /// This returns an error if requests were still in flight when `drain_timeout` passed.
+    pub async fn close(&self, drain_timeout: Duration) -> RedisResult<()> {
+        let connections: Vec<_> = self
+            .0
+            .members
+            .iter()
+            .filter_map(|member| {
+                let mut state = member.state.lock().unwrap();
+                state.replacement = None;
+                state.connection.take()
+            })
+            .collect();
+        future::join_all(
+            connections
+                .iter()
+                .map(|connection| connection.close(drain_timeout)),
+        )
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    async fn send<T, F, Fut>(&self, send: F) -> RedisResult<T>
+    where
+        F: FnOnce(MultiplexedConnection) -> Fut,
+        Fut: Future<Output = RedisResult<T>>,
+    {
+        let Some((index, generation, connection)) = self.0.least_loaded() else {
+            return Err(no_connection_error());
+        };
+        let member = &self.0.members[index];
+        let _in_flight = InFlight::new(&member.in_flight);
+        let result = send(connection).await;
+        if let Err(err) = &result {
+            if err.is_unrecoverable_error() {
+                self.0.replace(index, generation);
+            }
+        }
+        result
+    }
+}
+
+impl PoolInner {
+    fn least_loaded(&self) -> Option<(usize, u64, MultiplexedConnection)> {
+        let count = self.members.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let index = (0..count)
+            .map(|offset| (start + offset) % count)
+            .filter(|&index| {
+                self.members[index]
+                    .state
+                    .lock()
+                    .unwrap()
+                    .connection
+                    .is_some()
+            })
+            .min_by_key(|&index| self.members[index].in_flight.load(Ordering::Relaxed))?;
+        let state = self.members[index].state.lock().unwrap();
+        Some((index, state.generation, state.connection.clone()?))
+    }
+
+    /// Replaces the member's connection, unless it was already replaced since `generation`.
+    fn replace(self: &Arc<Self>, index: usize, generation: u64) {
+        let mut state = self.members[index].state.lock().unwrap();
+        if state.generation != generation || state.connection.is_none() {
+            return;
+        }
+        state.connection = None;
+        state.replacement = Some(self.spawn_replacement(index));
+    }
+
+    fn spawn_replacement(self: &Arc<Self>, index: usize) -> HandleContainer {
+        HandleContainer::new(
+            self.runtime
+                .spawn(Self::connect_member(Arc::downgrade(self), index)),
+        )
+    }
+
+    async fn connect_member(pool: Weak<Self>, index: usize) {
+        loop {
+            // the pool isn't kept alive while connecting, so that dropping it stops the task.
+            let Some((client, connection_config)) = pool
+                .upgrade()
+                .map(|pool| (pool.client.clone(), pool.config.connection_config.clone()))
+            else {
+                return;
+            };
+            let result = client
+                .get_multiplexed_async_connection_with_config(&connection_config)
+                .await;
+            let Some(pool) = pool.upgrade() else {
+                return;
+            };
+            if let Ok(connection) = result {
+                let mut state = pool.members[index].state.lock().unwrap();
+                state.connection = Some(connection);
+                state.generation += 1;
+                return;
+            }
+            let (runtime, delay) = (pool.runtime, pool.config.replacement_delay);
+            drop(pool);
+            runtime.sleep(delay).await;
+        }
+    }
+}
+
+fn no_connection_error() -> RedisError {
+    io::Error::new(
+        io::ErrorKind::NotConnected,
+        "None of the connections in the pool is available",
+    )
+    .into()
+}
+
+impl ConnectionLike for MultiplexedPool {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        self.send(move |mut connection| async move { connection.send_packed_command(cmd).await })
+            .boxed()
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        self.send(move |mut connection| async move {
+            connection.send_packed_commands(cmd, offset, count).await
+        })
+        .boxed()
+    }
+
+    fn get_db(&self) -> i64 {
+        self.0.client.get_connection_info().redis.db
+    }
+}
+
+impl fmt::Debug for MultiplexedPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiplexedPool")
+            .field("size", &self.size())
+            .field("available_connections", &self.available_connections())
+            .finish_non_exhaustive()
+    }
+}
@@ -30,7 +30,6 @@ pub(crate) enum TaskHandle {
 }
 
 impl TaskHandle {
-    #[cfg(feature = "connection-manager")]
     pub(crate) fn detach(self) {
         match self {
             #[cfg(feature = "smol-comp")]
@@ -163,7 +162,6 @@ impl Runtime {
         }
     }
 
-    #[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
     pub(crate) async fn sleep(&self, duration: Duration) {
         match self {
             #[cfg(feature = "tokio-comp")]
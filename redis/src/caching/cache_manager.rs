@@ -223,6 +223,10 @@ impl CacheManager {
             None => return PrepareCacheResult::NotCacheable,
         };
 
+        // The command's buffer doesn't contain its shared arguments, so it can't identify the command.
+        if cmd.has_shared_args() {
+            return PrepareCacheResult::NotCacheable;
+        }
         let cmd_key = cmd.data.as_slice();
 
         if let Some(value) = self.get(redis_key, cmd_key) {
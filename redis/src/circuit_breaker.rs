@@ -0,0 +1,227 @@
+//! Circuit breakers, which stop sending requests to an endpoint that keeps failing.
+use std::time::{Duration, Instant};
+
+use crate::{ErrorKind, RedisError, RedisResult};
+
+/// The configuration of a circuit breaker.
+///
+/// A circuit breaker counts consecutive failures of an endpoint - I/O errors, timeouts and
+/// disconnections, but not errors returned by the server. After `failure_threshold` consecutive
+/// failures the circuit opens, and requests to the endpoint fail immediately with
+/// [ErrorKind::CircuitOpen] instead of waiting for a response. Once `cool_down` passes, the
+/// circuit is half-open, and a single request is sent as a probe. If it succeeds the circuit
+/// closes, and if it fails the circuit opens again for another `cool_down`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    failure_threshold: u32,
+    cool_down: Duration,
+}
+
+impl CircuitBreakerConfig {
+    /// Creates a configuration that opens the circuit after `failure_threshold` consecutive
+    /// failures, and half-opens it after `cool_down`.
+    pub fn new(failure_threshold: u32, cool_down: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cool_down,
+        }
+    }
+
+    /// Returns the number of consecutive failures that opens the circuit.
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    /// Returns the duration the circuit stays open before a probe request is allowed.
+    pub fn cool_down(&self) -> Duration {
+        self.cool_down
+    }
+}
+
+/// The state of a circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CircuitState {
+    /// Requests are sent to the endpoint.
+    Closed,
+    /// Requests to the endpoint fail immediately.
+    Open,
+    /// The cool-down passed, and a probe request decides whether the circuit closes.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BreakerState {
+    Closed {
+        consecutive_failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A probe was sent, and no other request is sent until it completes, or until
+    /// `probe_deadline`, in case the probe was dropped.
+    HalfOpen {
+        probe_deadline: Instant,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: BreakerState,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: BreakerState::Closed {
+                consecutive_failures: 0,
+            },
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        match self.state {
+            BreakerState::Closed { .. } => CircuitState::Closed,
+            BreakerState::Open { until } if until > Instant::now() => CircuitState::Open,
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Returns whether [Self::try_acquire] would allow a request.
+    pub(crate) fn allows_requests(&self) -> bool {
+        match self.state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } => until <= Instant::now(),
+            BreakerState::HalfOpen { probe_deadline } => probe_deadline <= Instant::now(),
+        }
+    }
+
+    /// Returns whether a request may be sent, and if it's sent as a probe, marks it as such.
+    pub(crate) fn try_acquire(&mut self) -> bool {
+        if !self.allows_requests() {
+            return false;
+        }
+        if !matches!(self.state, BreakerState::Closed { .. }) {
+            self.state = BreakerState::HalfOpen {
+                probe_deadline: Instant::now() + self.config.cool_down,
+            };
+        }
+        true
+    }
+
+    /// Records the result of a request that was allowed by [Self::try_acquire].
+    pub(crate) fn record<T>(&mut self, result: &RedisResult<T>) {
+        let failed = matches!(result, Err(err) if is_endpoint_failure(err));
+        self.state = match (self.state, failed) {
+            (BreakerState::Closed { .. }, false) | (BreakerState::HalfOpen { .. }, false) => {
+                BreakerState::Closed {
+                    consecutive_failures: 0,
+                }
+            }
+            (
+                BreakerState::Closed {
+                    consecutive_failures,
+                },
+                true,
+            ) => {
+                let consecutive_failures = consecutive_failures + 1;
+                if consecutive_failures >= self.config.failure_threshold {
+                    self.open()
+                } else {
+                    BreakerState::Closed {
+                        consecutive_failures,
+                    }
+                }
+            }
+            (BreakerState::HalfOpen { .. }, true) => self.open(),
+            // results of requests that were sent before the circuit opened don't change it.
+            (state @ BreakerState::Open { .. }, _) => state,
+        };
+    }
+
+    fn open(&self) -> BreakerState {
+        BreakerState::Open {
+            until: Instant::now() + self.config.cool_down,
+        }
+    }
+}
+
+fn is_endpoint_failure(err: &RedisError) -> bool {
+    err.is_io_error() || err.is_unrecoverable_error()
+}
+
+pub(crate) fn circuit_open_error(endpoint: &str) -> RedisError {
+    RedisError::from((
+        ErrorKind::CircuitOpen,
+        "Circuit breaker is open",
+        endpoint.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn io_error() -> RedisResult<()> {
+        Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into())
+    }
+
+    fn command_error() -> RedisResult<()> {
+        Err((ErrorKind::Client, "").into())
+    }
+
+    #[test]
+    fn circuit_opens_after_consecutive_failures() {
+        let mut breaker =
+            CircuitBreaker::new(CircuitBreakerConfig::new(2, Duration::from_secs(60)));
+
+        assert!(breaker.try_acquire());
+        breaker.record(&io_error());
+        assert!(breaker.try_acquire());
+        breaker.record(&Ok(()));
+        assert!(breaker.try_acquire());
+        breaker.record(&io_error());
+        assert!(breaker.try_acquire());
+        breaker.record(&command_error());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        assert!(breaker.try_acquire());
+        breaker.record(&io_error());
+        assert!(breaker.try_acquire());
+        breaker.record(&io_error());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.try_acquire());
+    }
+
+    #[test]
+    fn circuit_half_opens_after_cool_down() {
+        let mut breaker = CircuitBreaker::new(CircuitBreakerConfig::new(1, Duration::ZERO));
+
+        assert!(breaker.try_acquire());
+        breaker.record(&io_error());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        assert!(breaker.try_acquire());
+        breaker.record(&io_error());
+        assert!(breaker.try_acquire());
+        breaker.record(&Ok(()));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn half_open_circuit_allows_a_single_probe() {
+        let mut breaker =
+            CircuitBreaker::new(CircuitBreakerConfig::new(1, Duration::from_secs(60)));
+        breaker.state = BreakerState::Open {
+            until: Instant::now(),
+        };
+
+        assert!(breaker.try_acquire());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(!breaker.try_acquire());
+        breaker.record(&io_error());
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+}
@@ -188,9 +188,18 @@ pub struct AsyncConnectionConfig {
     /// Maximum time to wait for a connection to be established
     pub(crate) connection_timeout: Option<Duration>,
     pub(crate) push_sender: Option<std::sync::Arc<dyn AsyncPushSender>>,
+    // whether the push sender may be used by a connection that negotiated RESP2, where it only
+    // receives disconnect notifications, instead of failing the connection.
+    pub(crate) push_sender_allows_resp2: bool,
     #[cfg(feature = "cache-aio")]
     pub(crate) cache: Option<Cache>,
     pub(crate) dns_resolver: Option<std::sync::Arc<dyn AsyncDNSResolver>>,
+    pub(crate) setup_hooks: Vec<crate::aio::SetupHook>,
+    pub(crate) max_in_flight_requests: Option<usize>,
+    pub(crate) max_queued_bytes: Option<usize>,
+    pub(crate) backpressure_policy: crate::aio::BackpressurePolicy,
+    pub(crate) idle_ping_interval: Option<Duration>,
+    pub(crate) max_lifetime: Option<Duration>,
 }
 
 #[cfg(feature = "aio")]
@@ -200,9 +209,16 @@ impl Default for AsyncConnectionConfig {
             response_timeout: DEFAULT_RESPONSE_TIMEOUT,
             connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
             push_sender: Default::default(),
+            push_sender_allows_resp2: false,
             #[cfg(feature = "cache-aio")]
             cache: Default::default(),
             dns_resolver: Default::default(),
+            setup_hooks: Default::default(),
+            max_in_flight_requests: None,
+            max_queued_bytes: None,
+            backpressure_policy: Default::default(),
+            idle_ping_interval: None,
+            max_lifetime: None,
         }
     }
 }
@@ -224,16 +240,74 @@ impl AsyncConnectionConfig {
 
     /// The new connection will time out operations after `response_timeout` has passed.
     ///
-    /// Set `None` if you don't want requests to time out.
+    /// Set `None` if you don't want requests to time out. Individual requests can override the
+    /// timeout with [Cmd::set_response_timeout](crate::Cmd::set_response_timeout) and
+    /// [Cmd::set_response_deadline](crate::Cmd::set_response_deadline).
     pub fn set_response_timeout(mut self, response_timeout: Option<Duration>) -> Self {
         self.response_timeout = response_timeout;
         self
     }
 
+    /// Limits the number of requests that the connection has in flight - sent or waiting to be
+    /// sent, and not yet answered.
+    ///
+    /// Requests beyond the limit wait, or fail, according to the
+    /// [BackpressurePolicy](crate::aio::BackpressurePolicy). Set `None`, which is the default, if
+    /// the number of requests in flight shouldn't be limited.
+    pub fn set_max_in_flight_requests(mut self, max_in_flight_requests: Option<usize>) -> Self {
+        self.max_in_flight_requests = max_in_flight_requests.map(|max| max.max(1));
+        self
+    }
+
+    /// Limits the total size, in bytes, of the requests that the connection has in flight.
+    ///
+    /// A single request that is larger than the limit is sent when no other request is in
+    /// flight. Requests beyond the limit wait, or fail, according to the
+    /// [BackpressurePolicy](crate::aio::BackpressurePolicy). Set `None`, which is the default, if
+    /// the size of requests in flight shouldn't be limited.
+    pub fn set_max_queued_bytes(mut self, max_queued_bytes: Option<usize>) -> Self {
+        self.max_queued_bytes = max_queued_bytes.map(|max| max.max(1));
+        self
+    }
+
+    /// Sets what requests do when the connection reached the limits set with
+    /// [Self::set_max_in_flight_requests] or [Self::set_max_queued_bytes].
+    pub fn set_backpressure_policy(
+        mut self,
+        backpressure_policy: crate::aio::BackpressurePolicy,
+    ) -> Self {
+        self.backpressure_policy = backpressure_policy;
+        self
+    }
+
+    /// Sends `PING` on the connection whenever no request was sent on it for `interval`, so
+    /// that idle connections aren't cut by load balancers or firewalls.
+    ///
+    /// Set `None`, which is the default, to not send pings.
+    pub fn set_idle_ping_interval(mut self, interval: Option<Duration>) -> Self {
+        self.idle_ping_interval = interval;
+        self
+    }
+
+    /// Closes the connection gracefully once it has been open for `max_lifetime`.
+    ///
+    /// Once the lifetime passes, the push sender is told that the connection disconnected, new
+    /// requests fail, and the requests in flight are given up to the response timeout to be
+    /// answered. Then the connection is closed like
+    /// [MultiplexedConnection::close](crate::aio::MultiplexedConnection::close). A
+    /// [ConnectionManager](crate::aio::ConnectionManager) should be configured with
+    /// `ConnectionManagerConfig::set_max_lifetime` instead, so that it replaces its connection
+    /// before closing it. Set `None`, which is the default, to keep the connection open.
+    pub fn set_max_lifetime(mut self, max_lifetime: Option<Duration>) -> Self {
+        self.max_lifetime = max_lifetime;
+        self
+    }
+
     /// Sets sender sender for push values.
     ///
     /// The sender can be a channel, or an arbitrary function that handles [crate::PushInfo] values.
     /// This will fail client creation if the connection isn't configured for RESP3 communications via the [crate::RedisConnectionInfo::set_protocol] function.
+    /// With [crate::ProtocolVersion::Auto], it fails if the server doesn't support RESP3.
     ///
     /// # Examples
     ///
@@ -297,6 +371,33 @@ impl AsyncConnectionConfig {
         self.dns_resolver = Some(dns_resolver);
         self
     }
+
+    /// Adds a hook that runs on the new connection before it is returned.
+    ///
+    /// Hooks run in the order they were added, and run again on every reconnect of a
+    /// [crate::aio::ConnectionManager] or a cluster connection that uses this config.
+    /// `on_failure` decides whether a failing hook fails the connection attempt.
+    /// See [crate::aio::AsyncConnectionSetup] for more details.
+    ///
+    /// Hooks only run on connections that are created by a [Client]. They don't run on a
+    /// connection that is created over an existing stream with
+    /// [crate::aio::MultiplexedConnection::new_with_config], since its driver isn't running
+    /// before the caller spawns it.
+    pub fn add_setup_hook(
+        mut self,
+        hook: impl crate::aio::AsyncConnectionSetup,
+        on_failure: crate::aio::SetupFailurePolicy,
+    ) -> Self {
+        self.setup_hooks
+            .push(crate::aio::SetupHook::new(hook, on_failure));
+        self
+    }
+
+    #[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
+    pub(crate) fn set_setup_hooks(mut self, setup_hooks: Vec<crate::aio::SetupHook>) -> Self {
+        self.setup_hooks = setup_hooks;
+        self
+    }
 }
 
 /// To enable async support you need to chose one of the supported runtimes and active its
@@ -339,6 +440,53 @@ impl Client {
         }
     }
 
+    /// Returns a pool of multiplexed connections from the client, with the default
+    /// configuration.
+    ///
+    /// See [`MultiplexedPool`](crate::aio::MultiplexedPool) for more details.
+    #[cfg(feature = "aio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "aio")))]
+    pub async fn get_multiplexed_pool(&self) -> RedisResult<crate::aio::MultiplexedPool> {
+        self.get_multiplexed_pool_with_config(crate::aio::MultiplexedPoolConfig::new())
+            .await
+    }
+
+    /// Returns a pool of multiplexed connections from the client.
+    ///
+    /// See [`MultiplexedPool`](crate::aio::MultiplexedPool) for more details.
+    #[cfg(feature = "aio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "aio")))]
+    pub async fn get_multiplexed_pool_with_config(
+        &self,
+        config: crate::aio::MultiplexedPoolConfig,
+    ) -> RedisResult<crate::aio::MultiplexedPool> {
+        crate::aio::MultiplexedPool::new(self.clone(), config).await
+    }
+
+    /// Returns a new pool of connections that are leased exclusively by their holders, for
+    /// commands that block the connection or change its state, such as `BLPOP`, `WATCH` or
+    /// `MULTI`. The connections have no response timeout.
+    ///
+    /// See [`LeasePool`](crate::aio::LeasePool) for more details.
+    #[cfg(feature = "aio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "aio")))]
+    pub fn get_lease_pool(&self) -> crate::aio::LeasePool {
+        self.get_lease_pool_with_config(AsyncConnectionConfig::new().set_response_timeout(None))
+    }
+
+    /// Returns a new pool of leased connections, which are created with the given
+    /// configuration.
+    ///
+    /// See [`LeasePool`](crate::aio::LeasePool) for more details.
+    #[cfg(feature = "aio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "aio")))]
+    pub fn get_lease_pool_with_config(
+        &self,
+        config: AsyncConnectionConfig,
+    ) -> crate::aio::LeasePool {
+        crate::aio::LeasePool::new(self.clone(), config)
+    }
+
     /// Returns an async [`ConnectionManager`][connection-manager] from the client.
     ///
     /// The connection manager wraps a
@@ -427,6 +575,7 @@ impl Client {
             .await?;
         let handle = T::spawn(driver);
         connection.set_task_handle(handle);
+        crate::aio::run_setup_hooks(&connection, &config.setup_hooks).await?;
         Ok(connection)
     }
 
@@ -0,0 +1,139 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+/// The number of recent latencies the hedging delay is computed from.
+const LATENCY_WINDOW: usize = 256;
+
+/// Configuration for hedged reads.
+///
+/// When hedged reads are enabled, a read-only command that is routed to a node of its slot is
+/// also sent to another node of the slot - a different replica, or the primary - if the first
+/// node didn't reply within a delay, and the first successful reply is used. The delay is the
+/// given percentile of the latencies of recent hedgeable reads, clamped between a minimal and
+/// a maximal delay. Until enough latencies were measured, the maximal delay is used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HedgedReadsConfig {
+    percentile: f64,
+    min_delay: Duration,
+    max_delay: Duration,
+}
+
+impl HedgedReadsConfig {
+    const DEFAULT_MIN_DELAY: Duration = Duration::from_millis(1);
+    const DEFAULT_MAX_DELAY: Duration = Duration::from_millis(100);
+
+    /// Creates a configuration that hedges reads that take longer than the given percentile of
+    /// recent read latencies, where `percentile` is between 0 and 100.
+    ///
+    /// The delay is at least 1 millisecond and at most 100 milliseconds, unless set otherwise.
+    pub fn new(percentile: f64) -> Self {
+        Self {
+            percentile: percentile.clamp(0.0, 100.0),
+            min_delay: Self::DEFAULT_MIN_DELAY,
+            max_delay: Self::DEFAULT_MAX_DELAY,
+        }
+    }
+
+    /// Sets the minimal delay before a read is hedged.
+    pub fn set_min_delay(mut self, min_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self
+    }
+
+    /// Sets the maximal delay before a read is hedged.
+    pub fn set_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Returns the percentile of recent read latencies after which a read is hedged.
+    pub fn percentile(&self) -> f64 {
+        self.percentile
+    }
+
+    /// Returns the minimal delay before a read is hedged.
+    pub fn min_delay(&self) -> Duration {
+        self.min_delay
+    }
+
+    /// Returns the maximal delay before a read is hedged.
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+}
+
+/// Tracks the latencies of recent hedgeable reads, and computes the hedging delay from them.
+pub(super) struct LatencyTracker {
+    config: HedgedReadsConfig,
+    latencies: Mutex<VecDeque<Duration>>,
+}
+
+impl LatencyTracker {
+    pub(super) fn new(config: HedgedReadsConfig) -> Self {
+        Self {
+            config,
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+        }
+    }
+
+    pub(super) fn record(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    pub(super) fn hedge_delay(&self) -> Duration {
+        let mut latencies: Vec<_> = {
+            let latencies = self.latencies.lock().unwrap();
+            // A handful of samples doesn't say much about the tail of the distribution.
+            if latencies.len() < LATENCY_WINDOW / 8 {
+                return self.config.max_delay;
+            }
+            latencies.iter().copied().collect()
+        };
+        latencies.sort_unstable();
+        let index = ((latencies.len() - 1) as f64 * self.config.percentile / 100.0).round();
+        latencies[index as usize].clamp(self.config.min_delay, self.config.max_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hedge_delay_is_max_delay_until_enough_latencies_are_recorded() {
+        let tracker = LatencyTracker::new(HedgedReadsConfig::new(90.0));
+        for _ in 0..LATENCY_WINDOW / 8 - 1 {
+            tracker.record(Duration::from_millis(5));
+        }
+        assert_eq!(tracker.hedge_delay(), HedgedReadsConfig::DEFAULT_MAX_DELAY);
+
+        tracker.record(Duration::from_millis(5));
+        assert_eq!(tracker.hedge_delay(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn hedge_delay_is_clamped_percentile_of_recent_latencies() {
+        let tracker = LatencyTracker::new(
+            HedgedReadsConfig::new(90.0)
+                .set_min_delay(Duration::from_millis(3))
+                .set_max_delay(Duration::from_millis(50)),
+        );
+        for millis in 1..=100 {
+            tracker.record(Duration::from_millis(millis));
+        }
+        assert_eq!(tracker.hedge_delay(), Duration::from_millis(50));
+
+        for _ in 0..LATENCY_WINDOW {
+            tracker.record(Duration::from_millis(1));
+        }
+        assert_eq!(tracker.hedge_delay(), Duration::from_millis(3));
+
+        for millis in 1..=LATENCY_WINDOW as u64 {
+            tracker.record(Duration::from_millis(millis % 20));
+        }
+        assert_eq!(tracker.hedge_delay(), Duration::from_millis(17));
+    }
+}
@@ -97,21 +97,31 @@ use std::{
     io, mem,
     ops::Deref,
     pin::Pin,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
     task::{self, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+mod hedging;
 mod request;
 mod routing;
 use crate::{
-    aio::{ConnectionLike, HandleContainer, MultiplexedConnection, Runtime},
+    aio::{
+        ConnectionLease, ConnectionLike, HandleContainer, HeartbeatConfig, LeasePool,
+        MultiplexedConnection, MultiplexedPool, MultiplexedPoolConfig, Runtime, SetupFailurePolicy,
+        SetupHook,
+    },
     check_resp3,
+    circuit_breaker::{circuit_open_error, CircuitBreaker},
     cluster_handling::{
         client::ClusterParams,
         get_connection_info,
         routing::{
-            MultipleNodeRoutingInfo, Redirect, ResponsePolicy, RoutingInfo, SingleNodeRoutingInfo,
+            MultipleNodeRoutingInfo, Redirect, ResponsePolicy, Route, RoutingInfo,
+            SingleNodeRoutingInfo, SlotAddr,
         },
         slot_cmd,
         slot_map::{Slot, SlotMap},
@@ -120,8 +130,8 @@ use crate::{
     cmd,
     errors::closed_connection_error,
     subscription_tracker::SubscriptionTracker,
-    AsyncConnectionConfig, Cmd, ConnectionInfo, ErrorKind, IntoConnectionInfo, RedisError,
-    RedisFuture, RedisResult, ToRedisArgs, Value,
+    AsyncConnectionConfig, CircuitBreakerConfig, CircuitState, Client, Cmd, ConnectionInfo,
+    ErrorKind, IntoConnectionInfo, RedisError, RedisFuture, RedisResult, ToRedisArgs, Value,
 };
 
 #[cfg(feature = "cache-aio")]
@@ -129,24 +139,35 @@ use crate::caching::{CacheManager, CacheStatistics};
 use crate::ProtocolVersion;
 use arcstr::ArcStr;
 use futures_util::{
-    future::{self, BoxFuture, FutureExt},
+    future::{self, BoxFuture, Either, FutureExt},
     ready,
     sink::Sink,
     stream::{self, Stream, StreamExt},
 };
+use hedging::LatencyTracker;
 use log::{debug, trace, warn};
 use rand::{rng, seq::IteratorRandom};
 use request::{CmdArg, PendingRequest, Request, RequestState, Retry};
 use routing::{route_for_pipeline, InternalRoutingInfo, InternalSingleNodeRouting};
-use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::sync::{mpsc, oneshot, Notify, RwLock};
+
+pub use hedging::HedgedReadsConfig;
 
 struct ClientSideState {
     protocol: ProtocolVersion,
-    _task_handle: HandleContainer,
+    // the protocol that the connections negotiated, if `protocol` is `Auto`.
+    negotiated_protocol: OnceLock<ProtocolVersion>,
+    // taken when the connection is closed, which stops the task.
+    task_handle: Mutex<Option<HandleContainer>>,
+    closing: AtomicBool,
+    in_flight: AtomicUsize,
+    // notified when the last request in flight completes.
+    drained: Notify,
     response_timeout: Option<Duration>,
     runtime: Runtime,
     #[cfg(feature = "cache-aio")]
     cache_manager: Option<CacheManager>,
+    circuit_breakers: Option<Arc<CircuitBreakers>>,
 }
 
 /// This represents an async Redis Cluster connection.
@@ -157,6 +178,7 @@ struct ClientSideState {
 pub struct ClusterConnection<C = MultiplexedConnection> {
     state: Arc<ClientSideState>,
     sender: mpsc::Sender<Message<C>>,
+    core: Core<C>,
 }
 
 impl<C> ClusterConnection<C>
@@ -191,31 +213,50 @@ where
         #[cfg(feature = "cache-aio")]
         let cache_manager = cluster_params.cache_manager.clone();
         let runtime = Runtime::locate();
-        let mut inner = ClusterConnInner::new(initial_nodes, cluster_params);
+        let circuit_breakers = cluster_params
+            .circuit_breaker
+            .map(|config| Arc::new(CircuitBreakers::new(config)));
+        let heartbeat = cluster_params.heartbeat;
+        let mut inner =
+            ClusterConnInner::new(initial_nodes, cluster_params, circuit_breakers.clone());
+        let core = inner.inner.clone();
 
         let (connect_sender, connect_receiver) = oneshot::channel::<RedisResult<()>>();
         let (sender, mut receiver) = mpsc::channel::<Message<_>>(100);
+        let health_checked = core.clone();
         let stream = async move {
             let connect_result = inner.wait_for_initial_connection().await;
             let _ = connect_sender.send(connect_result);
 
-            let _ = stream::poll_fn(move |cx| receiver.poll_recv(cx))
+            let forward = stream::poll_fn(move |cx| receiver.poll_recv(cx))
                 .map(Ok)
-                .forward(inner)
-                .await;
+                .forward(inner);
+            match heartbeat {
+                Some(heartbeat) => {
+                    let health_check = health_checked.check_health(heartbeat);
+                    future::select(Box::pin(forward), Box::pin(health_check)).await;
+                }
+                None => _ = forward.await,
+            }
         };
-        let _task_handle = HandleContainer::new(runtime.spawn(stream));
+        let task_handle = HandleContainer::new(runtime.spawn(stream));
 
         (
             ClusterConnection {
                 sender,
+                core,
                 state: Arc::new(ClientSideState {
                     protocol,
-                    _task_handle,
+                    negotiated_protocol: OnceLock::new(),
+                    task_handle: Mutex::new(Some(task_handle)),
+                    closing: AtomicBool::new(false),
+                    in_flight: AtomicUsize::new(0),
+                    drained: Notify::new(),
                     response_timeout,
                     runtime,
                     #[cfg(feature = "cache-aio")]
                     cache_manager,
+                    circuit_breakers,
                 }),
             },
             connect_receiver,
@@ -225,6 +266,8 @@ where
     /// Send a command to the given `routing`, and aggregate the response according to `response_policy`.
     pub async fn route_command(&mut self, cmd: Cmd, routing: RoutingInfo) -> RedisResult<Value> {
         trace!("send_packed_command");
+        let _in_flight = InFlightRequest::start(&self.state)?;
+        let response_timeout = cmd.response_timeout(self.state.response_timeout);
         let (sender, receiver) = oneshot::channel();
         let request = async {
             self.sender
@@ -257,12 +300,84 @@ where
                 })
         };
 
-        match self.state.response_timeout {
+        match response_timeout {
             Some(duration) => self.state.runtime.timeout(duration, request).await?,
             None => request.await,
         }
     }
 
+    /// Closes the cluster connection gracefully, for all of its clones.
+    ///
+    /// New requests fail immediately, and the requests that are already in flight are given up
+    /// to `drain_timeout` to complete. Then the connection stops refreshing the cluster's
+    /// topology, and the connection to each node is closed with [Connect::close].
+    ///
+    /// This returns an error if requests were still in flight when `drain_timeout` passed.
+    pub async fn close(&self, drain_timeout: Duration) -> RedisResult<()> {
+        self.state.closing.store(true, Ordering::Release);
+        let drained = self
+            .state
+            .runtime
+            .timeout(drain_timeout, self.state.wait_for_drain())
+            .await;
+        // stops the background task first, so that it can't reconnect to the nodes, or add
+        // connections while they're closed.
+        self.state.task_handle.lock().unwrap().take();
+        let connections: Vec<_> = self
+            .core
+            .conn_lock
+            .write()
+            .await
+            .0
+            .drain()
+            .map(|(_, connection)| connection)
+            .collect();
+        let drain_timeout = if drained.is_ok() {
+            drain_timeout
+        } else {
+            Duration::ZERO
+        };
+        future::join_all(
+            connections
+                .iter()
+                .map(|connection| connection.close(drain_timeout)),
+        )
+        .await;
+        Ok(drained?)
+    }
+
+    /// Leases a connection to the node of the given `routing` that is used exclusively by the
+    /// caller, for commands that block the connection or change its state, such as `BLPOP`,
+    /// `WATCH` or `MULTI`, which would otherwise affect all the requests sent to the node.
+    ///
+    /// The connection isn't redirected if the slot moves to a different node. Each node has its
+    /// own pool of idle leased connections.
+    ///
+    /// See [`ConnectionLease`](crate::aio::ConnectionLease) for more details.
+    ///
+    /// ```rust,no_run
+    /// # async fn func() -> redis::RedisResult<()> {
+    /// use redis::cluster_routing::SingleNodeRoutingInfo;
+    /// use redis::AsyncTypedCommands;
+    ///
+    /// let client = redis::cluster::ClusterClient::new(vec!["redis://127.0.0.1:6379/"])?;
+    /// let connection = client.get_async_connection().await?;
+    /// let mut lease = connection
+    ///     .lease_connection(SingleNodeRoutingInfo::ByAddress {
+    ///         host: "127.0.0.1".to_string(),
+    ///         port: 6379,
+    ///     })
+    ///     .await?;
+    /// let popped = lease.blpop("queue", 0.0).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn lease_connection(
+        &self,
+        routing: SingleNodeRoutingInfo,
+    ) -> RedisResult<ConnectionLease> {
+        self.core.lease_connection(routing).await
+    }
+
     /// Send commands in `pipeline` to the given `route`. If `route` is [None], it will be sent to a random node.
     pub async fn route_pipeline(
         &mut self,
@@ -271,6 +386,8 @@ where
         count: usize,
         route: SingleNodeRoutingInfo,
     ) -> RedisResult<Vec<Value>> {
+        let _in_flight = InFlightRequest::start(&self.state)?;
+        let response_timeout = pipeline.response_timeout(self.state.response_timeout);
         let (sender, receiver) = oneshot::channel();
 
         let request = async {
@@ -295,12 +412,28 @@ where
                 })
         };
 
-        match self.state.response_timeout {
+        match response_timeout {
             Some(duration) => self.state.runtime.timeout(duration, request).await?,
             None => request.await,
         }
     }
 
+    /// Returns the protocol version of the connections, which is negotiated with the nodes if
+    /// the cluster is configured with [ProtocolVersion::Auto].
+    async fn protocol(&self) -> ProtocolVersion {
+        if self.state.protocol != ProtocolVersion::Auto {
+            return self.state.protocol;
+        }
+        if let Some(protocol) = self.state.negotiated_protocol.get() {
+            return *protocol;
+        }
+        let connections = &self.core.conn_lock.read().await.0;
+        match connections.values().find_map(C::protocol) {
+            Some(protocol) => *self.state.negotiated_protocol.get_or_init(|| protocol),
+            None => self.state.protocol,
+        }
+    }
+
     /// Subscribes to a new channel(s).
     ///
     /// Updates from the sender will be sent on the push sender that was passed to the manager.
@@ -323,7 +456,7 @@ where
     /// # Ok(()) }
     /// ```
     pub async fn subscribe(&mut self, channel_name: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.state.protocol);
+        check_resp3!(self.protocol().await);
         let mut cmd = cmd("SUBSCRIBE");
         cmd.arg(channel_name);
         cmd.exec_async(self).await?;
@@ -334,7 +467,7 @@ where
     ///
     /// This method is only available when the connection is using RESP3 protocol, and will return an error otherwise.
     pub async fn unsubscribe(&mut self, channel_name: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.state.protocol);
+        check_resp3!(self.protocol().await);
         let mut cmd = cmd("UNSUBSCRIBE");
         cmd.arg(channel_name);
         cmd.exec_async(self).await?;
@@ -365,7 +498,7 @@ where
     /// # }
     /// ```
     pub async fn psubscribe(&mut self, channel_pattern: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.state.protocol);
+        check_resp3!(self.protocol().await);
         let mut cmd = cmd("PSUBSCRIBE");
         cmd.arg(channel_pattern);
         cmd.exec_async(self).await?;
@@ -376,7 +509,7 @@ where
     ///
     /// This method is only available when the connection is using RESP3 protocol, and will return an error otherwise.
     pub async fn punsubscribe(&mut self, channel_pattern: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.state.protocol);
+        check_resp3!(self.protocol().await);
         let mut cmd = cmd("PUNSUBSCRIBE");
         cmd.arg(channel_pattern);
         cmd.exec_async(self).await?;
@@ -405,7 +538,7 @@ where
     /// # Ok(()) }
     /// ```
     pub async fn ssubscribe(&mut self, channel_name: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.state.protocol);
+        check_resp3!(self.protocol().await);
         let mut cmd = cmd("SSUBSCRIBE");
         cmd.arg(channel_name);
         cmd.exec_async(self).await?;
@@ -416,7 +549,7 @@ where
     ///
     /// This method is only available when the connection is using RESP3 protocol, and will return an error otherwise.
     pub async fn sunsubscribe(&mut self, channel_name: impl ToRedisArgs) -> RedisResult<()> {
-        check_resp3!(self.state.protocol);
+        check_resp3!(self.protocol().await);
         let mut cmd = cmd("SUNSUBSCRIBE");
         cmd.arg(channel_name);
         cmd.exec_async(self).await?;
@@ -428,10 +561,117 @@ where
     pub fn get_cache_statistics(&self) -> Option<CacheStatistics> {
         self.state.cache_manager.as_ref().map(|cm| cm.statistics())
     }
+
+    /// Returns the state of the circuit breaker of every node that received requests, by the
+    /// node's address.
+    ///
+    /// Returns `None` if circuit breakers weren't enabled with
+    /// [crate::cluster::ClusterClientBuilder::circuit_breaker].
+    pub fn circuit_states(&self) -> Option<HashMap<String, CircuitState>> {
+        self.state
+            .circuit_breakers
+            .as_ref()
+            .map(|breakers| breakers.states())
+    }
 }
 
 type ConnectionMap<C> = HashMap<ArcStr, C>;
 
+/// The circuit breakers of the cluster's nodes, by address.
+struct CircuitBreakers {
+    config: CircuitBreakerConfig,
+    breakers: Mutex<HashMap<ArcStr, CircuitBreaker>>,
+}
+
+impl CircuitBreakers {
+    fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn allows_requests(&self, addr: &ArcStr) -> bool {
+        self.breakers
+            .lock()
+            .unwrap()
+            .get(addr)
+            .is_none_or(|breaker| breaker.allows_requests())
+    }
+
+    fn acquire(&self, addr: &ArcStr) -> RedisResult<()> {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers
+            .entry(addr.clone())
+            .or_insert_with(|| CircuitBreaker::new(self.config));
+        if breaker.try_acquire() {
+            Ok(())
+        } else {
+            Err(circuit_open_error(addr))
+        }
+    }
+
+    fn record<T>(&self, addr: &ArcStr, result: &RedisResult<T>) {
+        self.breakers
+            .lock()
+            .unwrap()
+            .entry(addr.clone())
+            .or_insert_with(|| CircuitBreaker::new(self.config))
+            .record(result);
+    }
+
+    /// Drops the breakers of the nodes that aren't in `nodes`, which must be sorted.
+    fn retain(&self, nodes: &[ArcStr]) {
+        self.breakers
+            .lock()
+            .unwrap()
+            .retain(|addr, _| nodes.binary_search(addr).is_ok());
+    }
+
+    fn states(&self) -> HashMap<String, CircuitState> {
+        self.breakers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(addr, breaker)| (addr.to_string(), breaker.state()))
+            .collect()
+    }
+}
+
+impl ClientSideState {
+    async fn wait_for_drain(&self) {
+        loop {
+            // created before the check, so that it can't miss the last request completing.
+            let drained = self.drained.notified();
+            if self.in_flight.load(Ordering::Acquire) == 0 {
+                return;
+            }
+            drained.await;
+        }
+    }
+}
+
+/// Counts a request as in flight until it's dropped.
+struct InFlightRequest(Arc<ClientSideState>);
+
+impl InFlightRequest {
+    fn start(state: &Arc<ClientSideState>) -> RedisResult<Self> {
+        if state.closing.load(Ordering::Acquire) {
+            return Err(closed_connection_error());
+        }
+        state.in_flight.fetch_add(1, Ordering::AcqRel);
+        Ok(Self(state.clone()))
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.0.drained.notify_waiters();
+        }
+    }
+}
+
 /// This is the internal representation of an async Redis Cluster connection. It stores the
 /// underlying connections maintained for each node in the cluster, as well
 /// as common parameters for connecting to nodes and executing commands.
@@ -441,6 +681,10 @@ struct InnerCore<C> {
     pending_requests: Mutex<Vec<PendingRequest<C>>>,
     initial_nodes: Vec<ConnectionInfo>,
     subscription_tracker: Option<Mutex<SubscriptionTracker>>,
+    circuit_breakers: Option<Arc<CircuitBreakers>>,
+    latency_tracker: Option<LatencyTracker>,
+    // the pools that connections to each node are leased from.
+    lease_pools: Mutex<HashMap<ArcStr, LeasePool>>,
 }
 
 /// This is a clonable wrapper.
@@ -692,21 +936,32 @@ where
             }
         };
 
+        if let InternalSingleNodeRouting::SpecificNode(route) = &route {
+            if let Some(latency_tracker) = &self.latency_tracker {
+                if route.slot_addr() == SlotAddr::ReplicaOptional {
+                    let delay = latency_tracker.hedge_delay();
+                    return self.try_hedged_cmd_request(cmd, *route, delay).await;
+                }
+            }
+        }
+
         match self.get_connection(route).await {
-            Ok((addr, mut conn)) => (
-                addr.into(),
-                conn.req_packed_command(&cmd)
-                    .await
-                    .inspect(|res| {
-                        if !matches!(res, Value::ServerError(_)) {
-                            if let Some(tracker) = &self.subscription_tracker {
-                                let mut tracker = tracker.lock().unwrap();
-                                tracker.update_with_cmd(cmd.as_ref());
+            Ok((addr, conn)) => {
+                let (addr, result) = self.send_cmd_to_node(addr, conn, &cmd).await;
+                (
+                    addr.into(),
+                    result
+                        .inspect(|res| {
+                            if !matches!(res, Value::ServerError(_)) {
+                                if let Some(tracker) = &self.subscription_tracker {
+                                    let mut tracker = tracker.lock().unwrap();
+                                    tracker.update_with_cmd(cmd.as_ref());
+                                }
                             }
-                        }
-                    })
-                    .map(Response::Single),
-            ),
+                        })
+                        .map(Response::Single),
+                )
+            }
             Err(err) => (OperationTarget::NotFound, Err(err)),
         }
     }
@@ -720,26 +975,136 @@ where
     ) -> OperationResult {
         let conn = self.get_connection(route);
         match conn.await {
-            Ok((addr, mut conn)) => (
-                OperationTarget::Node { address: addr },
-                conn.req_packed_commands(&pipeline, offset, count)
-                    .await
-                    .inspect(|res| {
-                        for (index, cmd) in pipeline.cmd_iter().enumerate() {
-                            if !matches!(res[index], Value::ServerError(_)) {
-                                if let Some(tracker) = &self.subscription_tracker {
-                                    let mut tracker = tracker.lock().unwrap();
-                                    tracker.update_with_cmd(cmd);
+            Ok((addr, mut conn)) => {
+                if let Err(err) = self.acquire_circuit(&addr) {
+                    return (addr.into(), Err(err));
+                }
+                let result = conn.req_packed_commands(&pipeline, offset, count).await;
+                self.record_circuit(&addr, &result);
+                (
+                    OperationTarget::Node { address: addr },
+                    result
+                        .inspect(|res| {
+                            for (index, cmd) in pipeline.cmd_iter().enumerate() {
+                                if !matches!(res[index], Value::ServerError(_)) {
+                                    if let Some(tracker) = &self.subscription_tracker {
+                                        let mut tracker = tracker.lock().unwrap();
+                                        tracker.update_with_cmd(cmd);
+                                    }
                                 }
                             }
-                        }
-                    })
-                    .map(Response::Multiple),
-            ),
+                        })
+                        .map(Response::Multiple),
+                )
+            }
             Err(err) => (OperationTarget::NotFound, Err(err)),
         }
     }
 
+    /// Sends a read-only command to the node that was chosen for its route, and if that node
+    /// doesn't reply within the hedging delay, to another node of the slot as well. The first
+    /// successful reply wins.
+    ///
+    /// The latency of the first node is recorded even if the hedged request wins, so that slow
+    /// nodes keep raising the hedging delay: in that case the first request keeps running in
+    /// the background until it's answered.
+    async fn try_hedged_cmd_request(
+        &self,
+        cmd: Arc<Cmd>,
+        route: Route,
+        delay: Duration,
+    ) -> OperationResult {
+        let (addr, conn) = match self
+            .get_connection(InternalSingleNodeRouting::SpecificNode(route))
+            .await
+        {
+            Ok(tuple) => tuple,
+            Err(err) => return (OperationTarget::NotFound, Err(err)),
+        };
+        let first = {
+            let core = self.clone();
+            let cmd = cmd.clone();
+            let addr = addr.clone();
+            let start = Instant::now();
+            async move {
+                let (addr, result) = core.send_cmd_to_node(addr, conn, &cmd).await;
+                if let (Some(latency_tracker), Ok(_)) = (&core.latency_tracker, &result) {
+                    latency_tracker.record(start.elapsed());
+                }
+                (addr, result)
+            }
+            .boxed()
+        };
+        let (addr, result) = match future::select(first, boxed_sleep(delay)).await {
+            Either::Left((response, _)) => response,
+            Either::Right(((), first)) => match self.hedge_connection(&route, &addr).await {
+                Some((hedge_addr, hedge_conn)) => {
+                    trace!("hedging request to {addr} with {hedge_addr}");
+                    let hedge = self.send_cmd_to_node(hedge_addr, hedge_conn, &cmd).boxed();
+                    match future::select(first, hedge).await {
+                        Either::Left(((addr, Ok(value)), _)) => (addr, Ok(value)),
+                        Either::Right(((addr, Ok(value)), first)) => {
+                            Runtime::locate().spawn(first.map(drop)).detach();
+                            (addr, Ok(value))
+                        }
+                        Either::Left((_, other)) | Either::Right((_, other)) => other.await,
+                    }
+                }
+                None => first.await,
+            },
+        };
+        (addr.into(), result.map(Response::Single))
+    }
+
+    /// Returns a connection to a random node of the route's slot, other than `first_addr`.
+    async fn hedge_connection(&self, route: &Route, first_addr: &ArcStr) -> Option<(ArcStr, C)> {
+        let addr = {
+            let read_guard = self.conn_lock.read().await;
+            read_guard
+                .1
+                .slot_addrs_for_route(route)?
+                .into_iter()
+                .filter(|addr| {
+                    *addr != first_addr
+                        && self
+                            .circuit_breakers
+                            .as_ref()
+                            .is_none_or(|breakers| breakers.allows_requests(addr))
+                })
+                .choose(&mut rng())?
+                .clone()
+        };
+        let conn = self.connection_for_address(&addr).await.ok()?;
+        Some((addr, conn))
+    }
+
+    async fn send_cmd_to_node(
+        &self,
+        addr: ArcStr,
+        mut conn: C,
+        cmd: &Cmd,
+    ) -> (ArcStr, RedisResult<Value>) {
+        if let Err(err) = self.acquire_circuit(&addr) {
+            return (addr, Err(err));
+        }
+        let result = conn.req_packed_command(cmd).await;
+        self.record_circuit(&addr, &result);
+        (addr, result)
+    }
+
+    fn acquire_circuit(&self, addr: &ArcStr) -> RedisResult<()> {
+        match &self.circuit_breakers {
+            Some(breakers) => breakers.acquire(addr),
+            None => Ok(()),
+        }
+    }
+
+    fn record_circuit<T>(&self, addr: &ArcStr, result: &RedisResult<T>) {
+        if let Some(breakers) = &self.circuit_breakers {
+            breakers.record(addr, result);
+        }
+    }
+
     async fn try_request(self, cmd: CmdArg<C>) -> OperationResult {
         match cmd {
             CmdArg::Cmd { cmd, routing } => self.try_cmd_request(cmd, routing).await,
@@ -764,7 +1129,25 @@ where
         let conn = match route {
             InternalSingleNodeRouting::Random => None,
             InternalSingleNodeRouting::SpecificNode(route) => {
-                read_guard.1.slot_addr_for_route(&route).cloned()
+                let slot_map = &read_guard.1;
+                match (&self.circuit_breakers, slot_map.slot_addr_for_route(&route)) {
+                    // Commands that can be served by replicas avoid nodes whose circuit is open,
+                    // if the connections to replicas allow reads.
+                    (Some(breakers), Some(addr))
+                        if self.cluster_params.read_from_replicas
+                            && route.slot_addr() != SlotAddr::Master
+                            && !breakers.allows_requests(addr) =>
+                    {
+                        slot_map
+                            .slot_addrs_for_route(&route)
+                            .into_iter()
+                            .flatten()
+                            .find(|addr| breakers.allows_requests(addr))
+                            .or(Some(addr))
+                            .cloned()
+                    }
+                    (_, addr) => addr.cloned(),
+                }
             }
             InternalSingleNodeRouting::Connection { identifier, conn } => {
                 return Ok((identifier, conn));
@@ -795,11 +1178,14 @@ where
 
         let addr_conn_option = match conn {
             Some((addr, Some(conn))) => Some((addr, conn)),
-            Some((addr, None)) => self
-                .connect_check_and_add(&addr)
-                .await
-                .ok()
-                .map(|conn| (addr, conn)),
+            Some((addr, None)) => {
+                let result = self.connect_check_and_add(&addr).await;
+                // only failures count, since connecting doesn't prove that requests succeed.
+                if result.is_err() {
+                    self.record_circuit(&addr, &result);
+                }
+                result.ok().map(|conn| (addr, conn))
+            }
             None => None,
         };
 
@@ -827,23 +1213,97 @@ where
             Redirect::Moved(addr) => addr,
             Redirect::Ask(addr) => addr,
         };
-        let read_guard = self.conn_lock.read().await;
-        let conn = read_guard.0.get(&addr).cloned();
-        drop(read_guard);
-        let mut conn = match conn {
-            Some(conn) => conn,
-            None => self.connect_check_and_add(&addr).await?,
-        };
+        let mut conn = self.connection_for_address(&addr).await?;
         if asking {
             let _ = conn
                 .req_packed_command(&crate::cmd::cmd("ASKING"))
                 .await
-                .and_then(|value| value.extract_error());
+                .and_then(crate::types::extract_error);
         }
 
         Ok((addr, conn))
     }
 
+    async fn connection_for_address(&self, addr: &ArcStr) -> RedisResult<C> {
+        let conn = self.conn_lock.read().await.0.get(addr).cloned();
+        match conn {
+            Some(conn) => Ok(conn),
+            None => self.connect_check_and_add(addr).await,
+        }
+    }
+
+    async fn lease_connection(
+        &self,
+        routing: SingleNodeRoutingInfo,
+    ) -> RedisResult<ConnectionLease> {
+        let addr = {
+            let read_guard = self.conn_lock.read().await;
+            match InternalSingleNodeRouting::<C>::from(routing) {
+                InternalSingleNodeRouting::SpecificNode(route) => {
+                    read_guard.1.slot_addr_for_route(&route).cloned()
+                }
+                InternalSingleNodeRouting::ByAddress(addr) => Some(addr),
+                _ => read_guard.0.keys().choose(&mut rng()).cloned(),
+            }
+        }
+        .ok_or_else(|| {
+            RedisError::from((ErrorKind::ClusterConnectionNotFound, "No connections found"))
+        })?;
+        let pool = {
+            let mut lease_pools = self.lease_pools.lock().unwrap();
+            match lease_pools.get(&addr) {
+                Some(pool) => pool.clone(),
+                None => {
+                    let client = Client::open(get_connection_info(&addr, &self.cluster_params)?)?;
+                    let pool = client.get_lease_pool_with_config(
+                        node_connection_config(&self.cluster_params, true)
+                            .set_response_timeout(None),
+                    );
+                    lease_pools.insert(addr, pool.clone());
+                    pool
+                }
+            }
+        };
+        pool.lease().await
+    }
+
+    /// Pings the connections to the nodes that were idle for the heartbeat's interval, and
+    /// replaces the connections whose ping failed.
+    async fn check_health(self, config: HeartbeatConfig) {
+        let runtime = Runtime::locate();
+        loop {
+            runtime.sleep(config.interval()).await;
+            let idle_connections: Vec<_> = self
+                .conn_lock
+                .read()
+                .await
+                .0
+                .iter()
+                .filter(|(_, conn)| {
+                    conn.idle_time()
+                        .is_none_or(|idle| idle >= config.interval())
+                })
+                .map(|(addr, conn)| (addr.clone(), conn.clone()))
+                .collect();
+            let failed = future::join_all(idle_connections.into_iter().map(
+                |(addr, mut conn)| async move {
+                    let result = crate::aio::ping(&mut conn, config.timeout()).await;
+                    result.err().map(|err| (addr, conn, err))
+                },
+            ))
+            .await;
+            for (addr, conn, err) in failed.into_iter().flatten() {
+                warn!("Health check of node {addr} failed: {err}");
+                // the requests in flight on the broken connection fail, and are retried.
+                let _ = conn.close(Duration::ZERO).await;
+                if self.connect_check_and_add(&addr).await.is_err() {
+                    // the next request to the node reconnects.
+                    self.conn_lock.write().await.0.remove(&addr);
+                }
+            }
+        }
+    }
+
     async fn connect_check_and_add(&self, addr: &ArcStr) -> RedisResult<C> {
         match connect_and_check::<C>(addr, &self.cluster_params).await {
             Ok(conn) => {
@@ -869,7 +1329,7 @@ where
                 let value = conn
                     .req_packed_command(&slot_cmd())
                     .await
-                    .and_then(|value| value.extract_error())?;
+                    .and_then(crate::types::extract_error)?;
                 let v: Vec<Slot> = parse_slots(value, addr.rsplit_once(':').unwrap().0)?;
                 build_slot_map(slots, v)
             }
@@ -883,6 +1343,9 @@ where
         let mut nodes = slots.values().flatten().cloned().collect::<Vec<_>>();
         nodes.sort_unstable();
         nodes.dedup();
+        if let Some(breakers) = &self.circuit_breakers {
+            breakers.retain(&nodes);
+        }
         self.refresh_connections_locked(connections, nodes).await;
 
         Ok(())
@@ -1019,12 +1482,21 @@ impl<C> ClusterConnInner<C>
 where
     C: ConnectionLike + Connect + Clone + Send + Sync + 'static,
 {
-    fn new(initial_nodes: &[ConnectionInfo], cluster_params: ClusterParams) -> Self {
+    fn new(
+        initial_nodes: &[ConnectionInfo],
+        cluster_params: ClusterParams,
+        circuit_breakers: Option<Arc<CircuitBreakers>>,
+    ) -> Self {
         let subscription_tracker = if cluster_params.async_push_sender.is_some() {
             Some(Mutex::new(SubscriptionTracker::default()))
         } else {
             None
         };
+        // Reads are only hedged against replicas if replicas are allowed to serve them.
+        let latency_tracker = cluster_params
+            .hedged_reads
+            .filter(|_| cluster_params.read_from_replicas)
+            .map(LatencyTracker::new);
         let inner = Arc::new(InnerCore {
             conn_lock: RwLock::new((
                 Default::default(),
@@ -1034,6 +1506,9 @@ where
             pending_requests: Mutex::new(Vec::new()),
             initial_nodes: initial_nodes.to_vec(),
             subscription_tracker,
+            circuit_breakers,
+            latency_tracker,
+            lease_pools: Mutex::new(HashMap::new()),
         });
         let core = Core(inner);
         let mut inner = ClusterConnInner {
@@ -1430,10 +1905,42 @@ where
 /// Implements the process of connecting to a Redis server
 /// and obtaining a connection handle.
 pub trait Connect: Sized {
+    /// Whether the connection consists of several connections to the node, which are opened
+    /// by the connection itself. Commands that prepare each of them, such as `READONLY`, are
+    /// then sent by [setup hooks](crate::aio::SetupHook).
+    ///
+    /// By default, this is `false`.
+    const POOLED: bool = false;
+
     /// Connect to a node, returning handle for command execution.
     fn connect_with_config<'a, T>(info: T, config: AsyncConnectionConfig) -> RedisFuture<'a, Self>
     where
         T: IntoConnectionInfo + Send + 'a;
+
+    /// Closes the connection gracefully, giving the requests in flight up to `drain_timeout`
+    /// to complete.
+    ///
+    /// By default, this does nothing, and the connection is closed once it's dropped.
+    fn close(&self, drain_timeout: Duration) -> RedisFuture<'_, ()> {
+        let _ = drain_timeout;
+        future::ok(()).boxed()
+    }
+
+    /// Returns the time since the connection was last active, which the cluster's heartbeat
+    /// uses to only ping idle connections. Connections that wait for a blocking command, such
+    /// as `BLPOP`, should return zero, since a `PING` isn't answered before the command.
+    ///
+    /// By default, this returns `None`, and the connection is pinged on every heartbeat.
+    fn idle_time(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns the protocol version that the connection negotiated with the node.
+    ///
+    /// By default, this returns `None`, and the configured protocol version is assumed.
+    fn protocol(&self) -> Option<ProtocolVersion> {
+        None
+    }
 }
 
 impl Connect for MultiplexedConnection {
@@ -1450,6 +1957,61 @@ impl Connect for MultiplexedConnection {
         }
         .boxed()
     }
+
+    fn close(&self, drain_timeout: Duration) -> RedisFuture<'_, ()> {
+        MultiplexedConnection::close(self, drain_timeout).boxed()
+    }
+
+    fn idle_time(&self) -> Option<Duration> {
+        Some(MultiplexedConnection::idle_time(self))
+    }
+
+    fn protocol(&self) -> Option<ProtocolVersion> {
+        Some(MultiplexedConnection::protocol(self))
+    }
+}
+
+impl Connect for MultiplexedPool {
+    const POOLED: bool = true;
+
+    fn connect_with_config<'a, T>(info: T, config: AsyncConnectionConfig) -> RedisFuture<'a, Self>
+    where
+        T: IntoConnectionInfo + Send + 'a,
+    {
+        async move {
+            let connection_info = info.into_connection_info()?;
+            let client = crate::Client::open(connection_info)?;
+            MultiplexedPool::new(
+                client,
+                MultiplexedPoolConfig::new().set_connection_config(config),
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn close(&self, drain_timeout: Duration) -> RedisFuture<'_, ()> {
+        MultiplexedPool::close(self, drain_timeout).boxed()
+    }
+}
+
+/// Returns the configuration that every connection to a node is created with.
+///
+/// If `readonly_hook` is set, READONLY is sent by a setup hook to every connection that's
+/// opened with the configuration, rather than once after connecting.
+fn node_connection_config(params: &ClusterParams, readonly_hook: bool) -> AsyncConnectionConfig {
+    let mut config =
+        AsyncConnectionConfig::default().set_connection_timeout(Some(params.connection_timeout));
+    if let Some(resolver) = &params.async_dns_resolver {
+        config = config.set_dns_resolver_internal(resolver.clone());
+    }
+    let mut setup_hooks = Vec::with_capacity(params.setup_hooks.len() + 1);
+    if params.read_from_replicas && readonly_hook {
+        // If READONLY is sent to primary nodes, it will have no effect
+        setup_hooks.push(SetupHook::new(cmd("READONLY"), SetupFailurePolicy::Abort));
+    }
+    setup_hooks.extend(params.setup_hooks.iter().cloned());
+    config.set_setup_hooks(setup_hooks)
 }
 
 async fn connect_and_check<C>(node: &str, params: &ClusterParams) -> RedisResult<C>
@@ -1457,16 +2019,14 @@ where
     C: ConnectionLike + Connect + Send + 'static,
 {
     let info = get_connection_info(node, params)?;
+    // the connections of a pool are opened in the background, so each needs READONLY sent on
+    // its own.
     let mut config =
-        AsyncConnectionConfig::default().set_connection_timeout(Some(params.connection_timeout));
-    config = config.set_response_timeout(params.response_timeout);
+        node_connection_config(params, C::POOLED).set_response_timeout(params.response_timeout);
 
     if let Some(push_sender) = &params.async_push_sender {
         config = config.set_push_sender_internal(push_sender.clone());
     }
-    if let Some(resolver) = &params.async_dns_resolver {
-        config = config.set_dns_resolver_internal(resolver.clone());
-    }
     #[cfg(feature = "cache-aio")]
     if let Some(cache_manager) = &params.cache_manager {
         config = config.set_cache_manager(cache_manager.clone_and_increase_epoch());
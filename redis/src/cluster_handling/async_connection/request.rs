@@ -6,10 +6,10 @@ use std::{
     time::Duration,
 };
 
-use crate::errors::RetryMethod;
+use crate::errors::{is_error_that_requires_action, ErrorKind, RetryMethod};
 use crate::{
     cluster_async::OperationTarget, cluster_handling::client::RetryParams,
-    cluster_routing::Redirect, Cmd, RedisResult,
+    cluster_routing::Redirect, Cmd, RedisResult, RetryContext, RetryRequest,
 };
 
 use futures_util::{future::BoxFuture, ready};
@@ -77,6 +77,20 @@ impl<C> CmdArg<C> {
         }
     }
 
+    fn is_safe_to_retry(&self) -> bool {
+        match self {
+            CmdArg::Cmd { cmd, .. } => cmd.idempotency().is_safe_to_retry(),
+            CmdArg::Pipeline { pipeline, .. } => pipeline.is_safe_to_retry(),
+        }
+    }
+
+    fn retry_request(&self) -> RetryRequest<'_> {
+        match self {
+            CmdArg::Cmd { cmd, .. } => RetryRequest::Cmd(cmd),
+            CmdArg::Pipeline { pipeline, .. } => RetryRequest::Pipeline(pipeline),
+        }
+    }
+
     fn reset_routing(&mut self) {
         let fix_route = |route: &mut InternalSingleNodeRouting<C>| {
             match route {
@@ -169,13 +183,13 @@ fn choose_response<C>(
     let err = match result {
         Ok(item) => {
             if let Some(error) = match &item {
-                Response::Single(value) if value.is_error_that_requires_action() => {
-                    Some(value.clone().extract_error().unwrap_err())
+                Response::Single(value) if is_error_that_requires_action(value) => {
+                    Some(value.clone().extract_error().unwrap_err().into())
                 }
                 Response::Multiple(values) => values
                     .iter()
-                    .position(|value| value.is_error_that_requires_action())
-                    .map(|position| values[position].clone().extract_error().unwrap_err()),
+                    .position(is_error_that_requires_action)
+                    .map(|position| values[position].clone().extract_error().unwrap_err().into()),
                 _ => None,
             } {
                 error
@@ -188,26 +202,34 @@ fn choose_response<C>(
         Err(err) => err,
     };
 
-    let has_retries_remaining = request.retry < retry_params.number_of_retries;
+    // If the error happened after the request was sent, the server might have executed it,
+    // so it can only be sent again if that's safe.
+    let might_have_executed = matches!(err.kind(), ErrorKind::Io | ErrorKind::Parse);
+
+    request.retry = request.retry.saturating_add(1);
 
     macro_rules! retry_or_send {
-        ($retry_func: expr) => {
-            if has_retries_remaining {
-                Some($retry_func(request))
-            } else {
-                let _ = request.sender.send(Err(err));
+        ($retry_func: expr) => {{
+            let delay = if might_have_executed && !request.cmd.is_safe_to_retry() {
                 None
+            } else {
+                let context = RetryContext::new(&err, request.retry)
+                    .with_request(request.cmd.retry_request());
+                retry_params.decide(&context).delay()
+            };
+            match delay {
+                Some(sleep_duration) => Some($retry_func(request, sleep_duration)),
+                None => {
+                    let _ = request.sender.send(Err(err));
+                    None
+                }
             }
-        };
+        }};
     }
 
-    request.retry = request.retry.saturating_add(1);
-
-    let sleep_duration = retry_params.wait_time_for_retry(request.retry);
-
     match (target, err.retry_method()) {
         (_, RetryMethod::ReconnectFromInitialConnections) => {
-            let retry = retry_or_send!(|mut request: PendingRequest<C>| {
+            let retry = retry_or_send!(|mut request: PendingRequest<C>, _| {
                 request.cmd.reset_routing();
                 Retry::MoveToPending { request }
             });
@@ -215,7 +237,7 @@ fn choose_response<C>(
         }
 
         (OperationTarget::Node { address }, RetryMethod::Reconnect) => (
-            retry_or_send!(|mut request: PendingRequest<C>| {
+            retry_or_send!(|mut request: PendingRequest<C>, _| {
                 request.cmd.reset_routing();
                 Retry::MoveToPending { request }
             }),
@@ -228,7 +250,7 @@ fn choose_response<C>(
             (None, PollFlushAction::None)
         }
         (OperationTarget::NotFound, _) => {
-            let retry = retry_or_send!(|mut request: PendingRequest<C>| {
+            let retry = retry_or_send!(|mut request: PendingRequest<C>, sleep_duration| {
                 request.cmd.reset_routing();
                 Retry::AfterSleep {
                     request,
@@ -239,7 +261,7 @@ fn choose_response<C>(
         }
 
         (_, RetryMethod::AskRedirect) => {
-            let retry = retry_or_send!(|mut request: PendingRequest<C>| {
+            let retry = retry_or_send!(|mut request: PendingRequest<C>, _| {
                 request.cmd.set_redirect(
                     err.redirect_node()
                         .map(|(node, _slot)| Redirect::Ask(node.into())),
@@ -250,7 +272,7 @@ fn choose_response<C>(
         }
 
         (_, RetryMethod::MovedRedirect) => {
-            let retry = retry_or_send!(|mut request: PendingRequest<C>| {
+            let retry = retry_or_send!(|mut request: PendingRequest<C>, _| {
                 request.cmd.set_redirect(
                     err.redirect_node()
                         .map(|(node, _slot)| Redirect::Moved(node.into())),
@@ -261,7 +283,7 @@ fn choose_response<C>(
         }
 
         (_, RetryMethod::WaitAndRetry) => (
-            retry_or_send!(|request: PendingRequest<C>| {
+            retry_or_send!(|request: PendingRequest<C>, sleep_duration| {
                 Retry::AfterSleep {
                     sleep_duration,
                     request,
@@ -276,7 +298,7 @@ fn choose_response<C>(
         }
 
         (_, RetryMethod::RetryImmediately) => (
-            retry_or_send!(|request: PendingRequest<C>| { Retry::MoveToPending { request } }),
+            retry_or_send!(|request: PendingRequest<C>, _| { Retry::MoveToPending { request } }),
             PollFlushAction::None,
         ),
     }
@@ -386,6 +408,7 @@ mod tests {
             .unwrap()
             .extract_error()
             .unwrap_err()
+            .into()
     }
 
     #[test]
@@ -411,7 +434,7 @@ mod tests {
         assert_eq!(next, PollFlushAction::None);
 
         // try the same, without remaining retries
-        let (request, mut receiver) = request_and_receiver(retry_params.number_of_retries);
+        let (request, mut receiver) = request_and_receiver(retry_params.number_of_retries());
         let result = (
             OperationTarget::Node {
                 address: ADDRESS.into(),
@@ -451,7 +474,7 @@ mod tests {
         assert_eq!(next, PollFlushAction::RebuildSlots);
 
         // try the same, without remaining retries
-        let (request, mut receiver) = request_and_receiver(retry_params.number_of_retries);
+        let (request, mut receiver) = request_and_receiver(retry_params.number_of_retries());
         let result = (
             OperationTarget::Node {
                 address: ADDRESS.into(),
@@ -497,7 +520,7 @@ mod tests {
         assert_eq!(next, PollFlushAction::RebuildSlots);
 
         // try the same, without remaining retries
-        let (request, mut receiver) = request_and_receiver(retry_params.number_of_retries);
+        let (request, mut receiver) = request_and_receiver(retry_params.number_of_retries());
         let result = (
             OperationTarget::Node {
                 address: ADDRESS.into(),
@@ -559,4 +582,33 @@ mod tests {
         };
         assert_eq!(next, PollFlushAction::ReconnectFromInitialConnections);
     }
+
+    #[test]
+    fn should_retry_after_io_error_only_if_command_is_safe_to_retry() {
+        let err = || RedisError::from(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        let retry_params = RetryParams::default();
+
+        for (command, should_retry) in [("GET", true), ("SET", true), ("INCR", false)] {
+            let (sender, mut receiver) = oneshot::channel();
+            let request = PendingRequest::<usize> {
+                retry: 0,
+                sender: ResultExpectation::External(sender),
+                cmd: super::CmdArg::Cmd {
+                    cmd: Arc::new(crate::cmd(command).arg("foo").clone()),
+                    routing: routing::InternalSingleNodeRouting::Random.into(),
+                },
+            };
+            let result = (
+                OperationTarget::Node {
+                    address: ADDRESS.into(),
+                },
+                Err(err()),
+            );
+            let (retry, next) = choose_response(result, request, &retry_params);
+
+            assert_eq!(retry.is_some(), should_retry, "{command}");
+            assert_eq!(receiver.try_recv().is_ok(), !should_retry, "{command}");
+            assert_eq!(next, PollFlushAction::None);
+        }
+    }
 }
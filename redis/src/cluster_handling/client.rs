@@ -1,18 +1,22 @@
 #[cfg(feature = "cluster-async")]
-use crate::aio::AsyncPushSender;
+use crate::aio::{
+    AsyncConnectionSetup, AsyncPushSender, HeartbeatConfig, SetupFailurePolicy, SetupHook,
+};
 #[cfg(all(feature = "cache-aio", feature = "cluster-async"))]
 use crate::caching::{CacheConfig, CacheManager};
 use crate::client::DEFAULT_CONNECTION_TIMEOUT;
+#[cfg(feature = "cluster-async")]
+use crate::cluster_async::HedgedReadsConfig;
 use crate::connection::{ConnectionAddr, ConnectionInfo, IntoConnectionInfo};
 use crate::errors::{ErrorKind, RedisError};
 use crate::io::tcp::TcpSettings;
 #[cfg(feature = "cluster-async")]
 use crate::io::AsyncDNSResolver;
 use crate::types::{ProtocolVersion, RedisResult};
-use crate::{cluster, TlsMode};
-use arcstr::ArcStr;
-use rand::Rng;
 #[cfg(feature = "cluster-async")]
+use crate::CircuitBreakerConfig;
+use crate::{cluster, ExponentialBackoff, RetryContext, RetryDecision, RetryPolicy, TlsMode};
+use arcstr::ArcStr;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -46,44 +50,56 @@ struct BuilderParams {
     pub(crate) tcp_settings: TcpSettings,
     #[cfg(feature = "cluster-async")]
     async_dns_resolver: Option<Arc<dyn AsyncDNSResolver>>,
+    #[cfg(feature = "cluster-async")]
+    setup_hooks: Vec<SetupHook>,
+    #[cfg(feature = "cluster-async")]
+    circuit_breaker: Option<CircuitBreakerConfig>,
+    #[cfg(feature = "cluster-async")]
+    hedged_reads: Option<HedgedReadsConfig>,
+    #[cfg(feature = "cluster-async")]
+    heartbeat: Option<HeartbeatConfig>,
     #[cfg(feature = "cache-aio")]
     cache_config: Option<CacheConfig>,
 }
 
 #[derive(Clone)]
 pub(crate) struct RetryParams {
-    pub(crate) number_of_retries: u32,
-    max_wait_time: u64,
-    min_wait_time: u64,
-    exponent_base: u64,
-    factor: u64,
+    /// The default policy, configured by the builder's retry settings.
+    backoff: ExponentialBackoff,
+    /// A user provided policy, which replaces the default one.
+    policy: Option<Arc<dyn RetryPolicy>>,
 }
 
 impl Default for RetryParams {
     fn default() -> Self {
         const DEFAULT_RETRIES: u32 = 16;
-        const DEFAULT_MAX_RETRY_WAIT_TIME: u64 = 655360;
-        const DEFAULT_MIN_RETRY_WAIT_TIME: u64 = 1280;
-        const DEFAULT_EXPONENT_BASE: u64 = 2;
-        const DEFAULT_FACTOR: u64 = 10;
+        const DEFAULT_MAX_RETRY_WAIT_TIME: Duration = Duration::from_millis(655360);
+        const DEFAULT_MIN_RETRY_WAIT_TIME: Duration = Duration::from_millis(1280);
+        const DEFAULT_EXPONENT_BASE: f64 = 2.0;
+        const DEFAULT_FACTOR: Duration = Duration::from_millis(10);
         Self {
-            number_of_retries: DEFAULT_RETRIES,
-            max_wait_time: DEFAULT_MAX_RETRY_WAIT_TIME,
-            min_wait_time: DEFAULT_MIN_RETRY_WAIT_TIME,
-            exponent_base: DEFAULT_EXPONENT_BASE,
-            factor: DEFAULT_FACTOR,
+            backoff: ExponentialBackoff::new()
+                .set_number_of_retries(DEFAULT_RETRIES)
+                .set_max_delay(DEFAULT_MAX_RETRY_WAIT_TIME)
+                .set_min_delay(DEFAULT_MIN_RETRY_WAIT_TIME)
+                .set_exponent_base(DEFAULT_EXPONENT_BASE)
+                .set_factor(DEFAULT_FACTOR),
+            policy: None,
         }
     }
 }
 
 impl RetryParams {
-    pub(crate) fn wait_time_for_retry(&self, retry: u32) -> Duration {
-        let base_wait = self.exponent_base.pow(retry) * self.factor;
-        let clamped_wait = base_wait
-            .min(self.max_wait_time)
-            .max(self.min_wait_time + 1);
-        let jittered_wait = rand::rng().random_range(self.min_wait_time..clamped_wait);
-        Duration::from_millis(jittered_wait)
+    pub(crate) fn decide(&self, context: &RetryContext<'_>) -> RetryDecision {
+        match &self.policy {
+            Some(policy) => policy.decide(context),
+            None => self.backoff.decide(context),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn number_of_retries(&self) -> u32 {
+        self.backoff.number_of_retries()
     }
 }
 
@@ -107,6 +123,14 @@ pub(crate) struct ClusterParams {
     pub(crate) tcp_settings: TcpSettings,
     #[cfg(feature = "cluster-async")]
     pub(crate) async_dns_resolver: Option<Arc<dyn AsyncDNSResolver>>,
+    #[cfg(feature = "cluster-async")]
+    pub(crate) setup_hooks: Vec<SetupHook>,
+    #[cfg(feature = "cluster-async")]
+    pub(crate) circuit_breaker: Option<CircuitBreakerConfig>,
+    #[cfg(feature = "cluster-async")]
+    pub(crate) hedged_reads: Option<HedgedReadsConfig>,
+    #[cfg(feature = "cluster-async")]
+    pub(crate) heartbeat: Option<HeartbeatConfig>,
     #[cfg(all(feature = "cache-aio", feature = "cluster-async"))]
     pub(crate) cache_manager: Option<CacheManager>,
 }
@@ -161,6 +185,14 @@ impl ClusterParams {
             tcp_settings: value.tcp_settings,
             #[cfg(feature = "cluster-async")]
             async_dns_resolver: value.async_dns_resolver,
+            #[cfg(feature = "cluster-async")]
+            setup_hooks: value.setup_hooks,
+            #[cfg(feature = "cluster-async")]
+            circuit_breaker: value.circuit_breaker,
+            #[cfg(feature = "cluster-async")]
+            hedged_reads: value.hedged_reads,
+            #[cfg(feature = "cluster-async")]
+            heartbeat: value.heartbeat,
             #[cfg(all(feature = "cache-aio", feature = "cluster-async"))]
             cache_manager,
         })
@@ -318,27 +350,44 @@ impl ClusterClientBuilder {
 
     /// Sets number of retries for the new ClusterClient.
     pub fn retries(mut self, retries: u32) -> ClusterClientBuilder {
-        self.builder_params.retries_configuration.number_of_retries = retries;
+        let backoff = &mut self.builder_params.retries_configuration.backoff;
+        *backoff = backoff.set_number_of_retries(retries);
         self
     }
 
     /// Sets maximal wait time in millisceonds between retries for the new ClusterClient.
     pub fn max_retry_wait(mut self, max_wait: u64) -> ClusterClientBuilder {
-        self.builder_params.retries_configuration.max_wait_time = max_wait;
+        let backoff = &mut self.builder_params.retries_configuration.backoff;
+        *backoff = backoff.set_max_delay(Duration::from_millis(max_wait));
         self
     }
 
     /// Sets minimal wait time in millisceonds between retries for the new ClusterClient.
     pub fn min_retry_wait(mut self, min_wait: u64) -> ClusterClientBuilder {
-        self.builder_params.retries_configuration.min_wait_time = min_wait;
+        let backoff = &mut self.builder_params.retries_configuration.backoff;
+        *backoff = backoff.set_min_delay(Duration::from_millis(min_wait));
         self
     }
 
     /// Sets the factor and exponent base for the retry wait time.
     /// The formula for the wait is rand(min_wait_retry .. min(max_retry_wait , factor * exponent_base ^ retry))ms.
     pub fn retry_wait_formula(mut self, factor: u64, exponent_base: u64) -> ClusterClientBuilder {
-        self.builder_params.retries_configuration.factor = factor;
-        self.builder_params.retries_configuration.exponent_base = exponent_base;
+        let backoff = &mut self.builder_params.retries_configuration.backoff;
+        *backoff = backoff
+            .set_factor(Duration::from_millis(factor))
+            .set_exponent_base(exponent_base as f64);
+        self
+    }
+
+    /// Sets the policy that decides whether failed requests are retried, replacing the policy
+    /// configured by [Self::retries], [Self::max_retry_wait], [Self::min_retry_wait] and
+    /// [Self::retry_wait_formula].
+    ///
+    /// The policy is consulted for errors that can be retried. Redirections are followed
+    /// immediately, and the delay returned by the policy is only waited for errors that require
+    /// waiting before a retry, such as `TRYAGAIN` or `CLUSTERDOWN`.
+    pub fn retry_policy(mut self, policy: impl RetryPolicy) -> ClusterClientBuilder {
+        self.builder_params.retries_configuration.policy = Some(Arc::new(policy));
         self
     }
 
@@ -477,6 +526,60 @@ impl ClusterClientBuilder {
         self
     }
 
+    /// Adds a hook that runs on every new connection to a cluster node, including the
+    /// connections that replace disconnected ones.
+    ///
+    /// See [crate::AsyncConnectionConfig::add_setup_hook] for more details.
+    #[cfg(feature = "cluster-async")]
+    pub fn add_setup_hook(
+        mut self,
+        hook: impl AsyncConnectionSetup,
+        on_failure: SetupFailurePolicy,
+    ) -> ClusterClientBuilder {
+        self.builder_params
+            .setup_hooks
+            .push(SetupHook::new(hook, on_failure));
+        self
+    }
+
+    /// Enables a circuit breaker for each node of the cluster.
+    ///
+    /// While a node's circuit is open, requests to it fail with [ErrorKind::CircuitOpen].
+    /// With [ClusterClientBuilder::read_from_replicas], read-only commands are sent to another
+    /// node that serves the same slot instead, if one is available. Failed attempts to connect
+    /// to a node count as failures as well. See [CircuitBreakerConfig] for more details.
+    ///
+    /// The states of the circuits can be read with
+    /// [crate::cluster_async::ClusterConnection::circuit_states].
+    #[cfg(feature = "cluster-async")]
+    pub fn circuit_breaker(mut self, config: CircuitBreakerConfig) -> ClusterClientBuilder {
+        self.builder_params.circuit_breaker = Some(config);
+        self
+    }
+
+    /// Enables hedged reads.
+    ///
+    /// When a read-only command that was sent to a node didn't get a reply within the delay
+    /// computed from `config`, it's also sent to another node that serves the same slot, and the
+    /// first successful reply is returned. Since the other node might be a replica, hedged reads
+    /// only apply to connections that [read from replicas](Self::read_from_replicas).
+    /// See [HedgedReadsConfig] for more details.
+    #[cfg(feature = "cluster-async")]
+    pub fn hedged_reads(mut self, config: HedgedReadsConfig) -> ClusterClientBuilder {
+        self.builder_params.hedged_reads = Some(config);
+        self
+    }
+
+    /// Enables a heartbeat, which pings the connection to each node whenever it's idle, and
+    /// replaces the connection if the ping fails or is answered too slowly. The requests in
+    /// flight on the broken connection fail immediately, and are retried like other requests
+    /// that failed on a broken connection. See [HeartbeatConfig] for more details.
+    #[cfg(feature = "cluster-async")]
+    pub fn heartbeat(mut self, config: HeartbeatConfig) -> ClusterClientBuilder {
+        self.builder_params.heartbeat = Some(config);
+        self
+    }
+
     /// Sets cache config for [`crate::cluster_async::ClusterConnection`], check CacheConfig for more details.
     #[cfg(all(feature = "cache-aio", feature = "cluster-async"))]
     pub fn cache_config(mut self, cache_config: CacheConfig) -> Self {
@@ -3,7 +3,7 @@ use rand::Rng;
 
 use crate::cluster_handling::slot_map::SLOT_SIZE;
 use crate::cmd::{Arg, Cmd};
-use crate::commands::is_readonly_cmd;
+use crate::commands::{command_name, is_readonly_cmd};
 use crate::types::Value;
 use crate::{ErrorKind, RedisError, RedisResult};
 use std::borrow::Cow;
@@ -284,9 +284,9 @@ pub(crate) fn combine_array_results(values: Vec<Value>) -> RedisResult<Value> {
 
 // An iterator that yields `Cow<[usize]>` representing grouped result indices according to a specified argument pattern.
 // This type is used to combine multi-slot array responses.
-type MultiSlotResIdxIter<'a> = std::iter::Map<
-    std::slice::Iter<'a, (Route, Vec<usize>)>,
-    fn(&'a (Route, Vec<usize>)) -> Cow<'a, [usize]>,
+type MultiSlotResIdxIter<'a, K> = std::iter::Map<
+    std::slice::Iter<'a, (K, Vec<usize>)>,
+    fn(&'a (K, Vec<usize>)) -> Cow<'a, [usize]>,
 >;
 
 /// Generates an iterator that yields a vector of result indices for each slot within the final merged results array for a multi-slot command response.
@@ -309,10 +309,10 @@ type MultiSlotResIdxIter<'a> = std::iter::Map<
 /// - Therefore, the iterator generated by this function would yield grouped result indices as follows:
 ///   - Slot "foo" is mapped to `[0, 2]` in the final result order.
 ///   - Slot "foo2" is mapped to `[1]`.
-fn calculate_multi_slot_result_indices<'a>(
-    route_arg_indices: &'a [(Route, Vec<usize>)],
+fn calculate_multi_slot_result_indices<'a, K>(
+    route_arg_indices: &'a [(K, Vec<usize>)],
     args_pattern: &MultiSlotArgPattern,
-) -> RedisResult<MultiSlotResIdxIter<'a>> {
+) -> RedisResult<MultiSlotResIdxIter<'a, K>> {
     let check_indices_input = |step_count: usize| {
         for (_, indices) in route_arg_indices {
             if indices.len() % step_count != 0 {
@@ -384,7 +384,8 @@ fn calculate_multi_slot_result_indices<'a>(
 /// * `route_arg_indices` - A reference to a vector of tuples, where each tuple represents a route and a vector of
 ///   argument indices associated with that route. The route indicates the slot, while the indices vector
 ///   specifies the positions of arguments relevant to this slot. This is used to construct `sorting_order`,
-///   which guides the placement of results in the final array.
+///   which guides the placement of results in the final array. Only the indices are inspected, so callers
+///   that group arguments by something other than a `Route` (e.g. by node address) can reuse this function.
 ///
 /// * `args_pattern` - Specifies the argument pattern (e.g., `KeysOnly`, `KeyValuePairs`, ...).
 ///   The pattern defines how the argument indices are grouped for each slot and determines
@@ -393,9 +394,9 @@ fn calculate_multi_slot_result_indices<'a>(
 /// # Returns
 ///
 /// Returns a `RedisResult<Value>` containing the final ordered array (`Value::Array`) of combined results.
-pub(crate) fn combine_and_sort_array_results(
+pub(crate) fn combine_and_sort_array_results<K>(
     values: Vec<Value>,
-    route_arg_indices: &[(Route, Vec<usize>)],
+    route_arg_indices: &[(K, Vec<usize>)],
     args_pattern: &MultiSlotArgPattern,
 ) -> RedisResult<Value> {
     let result_indices = calculate_multi_slot_result_indices(route_arg_indices, args_pattern)?;
@@ -871,28 +872,7 @@ pub(crate) trait Routable {
     /// Convenience function to return ascii uppercase version of the
     /// the first argument (i.e., the command).
     fn command(&self) -> Option<Vec<u8>> {
-        let primary_command = self.arg_idx(0).map(|x| x.to_ascii_uppercase())?;
-        let mut primary_command = match primary_command.as_slice() {
-            b"XGROUP" | b"OBJECT" | b"SLOWLOG" | b"FUNCTION" | b"MODULE" | b"COMMAND"
-            | b"PUBSUB" | b"CONFIG" | b"MEMORY" | b"XINFO" | b"CLIENT" | b"ACL" | b"SCRIPT"
-            | b"CLUSTER" | b"LATENCY" => primary_command,
-            _ => {
-                return Some(primary_command);
-            }
-        };
-
-        Some(match self.arg_idx(1) {
-            Some(secondary_command) => {
-                let previous_len = primary_command.len();
-                primary_command.reserve(secondary_command.len() + 1);
-                primary_command.extend(b" ");
-                primary_command.extend(secondary_command);
-                let current_len = primary_command.len();
-                primary_command[previous_len + 1..current_len].make_ascii_uppercase();
-                primary_command
-            }
-            None => primary_command,
-        })
+        Some(command_name(self.arg_idx(0)?, self.arg_idx(1)))
     }
 
     /// Returns a reference to the data for the argument at `idx`.
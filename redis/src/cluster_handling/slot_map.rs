@@ -54,17 +54,19 @@ impl SlotMap {
     }
 
     pub fn slot_addr_for_route(&self, route: &Route) -> Option<&ArcStr> {
+        self.slot_addrs_for_route(route)
+            .map(|addrs| addrs.slot_addr(&route.slot_addr(), self.read_from_replica))
+    }
+
+    /// Returns the addresses of all the nodes that serve the route's slot.
+    pub fn slot_addrs_for_route(&self, route: &Route) -> Option<&SlotAddrs> {
         let slot = route.slot();
         self.slots
             .range(slot..)
             .next()
             .and_then(|(end, slot_value)| {
                 if slot <= *end && slot_value.start <= slot {
-                    Some(
-                        slot_value
-                            .addrs
-                            .slot_addr(&route.slot_addr(), self.read_from_replica),
-                    )
+                    Some(&slot_value.addrs)
                 } else {
                     None
                 }
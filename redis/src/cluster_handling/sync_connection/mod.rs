@@ -84,9 +84,9 @@ use crate::cmd::{cmd, Cmd};
 use crate::connection::{connect, Connection, ConnectionInfo, ConnectionLike};
 use crate::errors::{ErrorKind, RedisError, RetryMethod};
 use crate::parser::parse_redis_value;
-use crate::types::{HashMap, RedisResult, Value};
-use crate::IntoConnectionInfo;
+use crate::types::{extract_error, extract_error_vec, HashMap, RedisResult, Value};
 pub use crate::TlsMode; // Pub for backwards compatibility
+use crate::{IntoConnectionInfo, RetryContext, RetryRequest};
 use arcstr::ArcStr;
 use pipeline::UNROUTABLE_ERROR;
 use rand::{rng, seq::IteratorRandom, Rng};
@@ -112,15 +112,15 @@ impl<'a> Input<'a> {
         match self {
             Input::Slice { cmd, routable: _ } => connection
                 .req_packed_command(cmd)
-                .and_then(|value| value.extract_error())
+                .and_then(extract_error)
                 .map(Output::Single),
             Input::Cmd(cmd) => connection
                 .req_command(cmd)
-                .and_then(|value| value.extract_error())
+                .and_then(extract_error)
                 .map(Output::Single),
             Input::Commands { cmd, offset, count } => connection
                 .req_packed_commands(cmd, *offset, *count)
-                .and_then(Value::extract_error_vec)
+                .and_then(extract_error_vec)
                 .map(Output::Multi),
         }
     }
@@ -827,7 +827,7 @@ where
                         // actually want to execute.
                         conn = conn.and_then(|conn| {
                             conn.req_packed_command(&b"*1\r\n$6\r\nASKING\r\n"[..])
-                                .and_then(|value| value.extract_error())?;
+                                .and_then(extract_error)?;
                             Ok(conn)
                         });
                     }
@@ -870,10 +870,16 @@ where
                         self.refresh_slots()?;
                     }
 
-                    if retries == self.cluster_params.retry_params.number_of_retries {
-                        return Err(err);
-                    }
                     retries += 1;
+                    let mut context = RetryContext::new(&err, retries);
+                    if let Input::Cmd(cmd) = input {
+                        context = context.with_request(RetryRequest::Cmd(cmd));
+                    }
+                    let Some(sleep_time) =
+                        self.cluster_params.retry_params.decide(&context).delay()
+                    else {
+                        return Err(err);
+                    };
 
                     match err.retry_method() {
                         RetryMethod::AskRedirect => {
@@ -891,10 +897,6 @@ where
                         }
                         RetryMethod::WaitAndRetry => {
                             // Sleep and retry.
-                            let sleep_time = self
-                                .cluster_params
-                                .retry_params
-                                .wait_time_for_retry(retries);
                             thread::sleep(sleep_time);
                         }
                         RetryMethod::Reconnect => {
@@ -6,9 +6,8 @@ use futures_util::{
 };
 #[cfg(feature = "aio")]
 use std::pin::Pin;
-#[cfg(feature = "cache-aio")]
-use std::time::Duration;
-use std::{fmt, io, io::Write};
+use std::time::{Duration, Instant};
+use std::{fmt, io, io::Write, sync::Arc};
 
 use crate::pipeline::Pipeline;
 use crate::types::{from_redis_value, FromRedisValue, RedisResult, RedisWrite, ToRedisArgs};
@@ -24,6 +23,162 @@ pub enum Arg<D> {
     Cursor,
 }
 
+// Shared arguments at least this large are held by reference instead of being copied into the
+// command's buffer. Smaller ones aren't worth a separate write.
+const MIN_SHARED_ARG_LEN: usize = 4 * 1024;
+
+// An argument whose bytes are owned by the caller, and are only referenced by the command.
+#[derive(Clone)]
+enum SharedArg {
+    Arc(Arc<[u8]>),
+    #[cfg(feature = "bytes")]
+    Bytes(bytes::Bytes),
+}
+
+impl std::ops::Deref for SharedArg {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            SharedArg::Arc(arg) => arg,
+            #[cfg(feature = "bytes")]
+            SharedArg::Bytes(arg) => arg,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum CmdArg {
+    // Contains the offset that marks the end of the argument in `Cmd::data`
+    Simple(usize),
+    Shared(SharedArg),
+    Cursor,
+}
+
+/// A packed command or pipeline, which references the shared arguments of its commands
+/// instead of copying them, so that they can be written to the socket with vectored I/O.
+#[derive(Clone, Default)]
+pub(crate) struct PackedCommand {
+    data: Vec<u8>,
+    // The shared arguments, and the offsets in `data` at which they should be written.
+    shared: Vec<(usize, SharedArg)>,
+}
+
+impl PackedCommand {
+    pub(crate) fn push_cmd(&mut self, cmd: &Cmd) {
+        let mut buf = ::itoa::Buffer::new();
+        let cursor = cmd.cursor.unwrap_or(0);
+
+        self.data.push(b'*');
+        self.data
+            .extend_from_slice(buf.format(cmd.args.len()).as_bytes());
+        self.data.extend_from_slice(b"\r\n");
+
+        let mut cursor_bytes = itoa::Buffer::new();
+        let mut prev = 0;
+        for arg in &cmd.args {
+            let bytes = match arg {
+                CmdArg::Simple(end) => {
+                    let bytes = &cmd.data[prev..*end];
+                    prev = *end;
+                    bytes
+                }
+                CmdArg::Shared(arg) => arg,
+                CmdArg::Cursor => cursor_bytes.format(cursor).as_bytes(),
+            };
+
+            self.data.push(b'$');
+            self.data
+                .extend_from_slice(buf.format(bytes.len()).as_bytes());
+            self.data.extend_from_slice(b"\r\n");
+            if let CmdArg::Shared(arg) = arg {
+                self.shared.push((self.data.len(), arg.clone()));
+            } else {
+                self.data.extend_from_slice(bytes);
+            }
+            self.data.extend_from_slice(b"\r\n");
+        }
+    }
+
+    /// Returns the number of bytes that the packed command is written as.
+    #[cfg(feature = "aio")]
+    pub(crate) fn byte_len(&self) -> usize {
+        self.data.len() + self.shared.iter().map(|(_, arg)| arg.len()).sum::<usize>()
+    }
+
+    /// Returns the packed command as a contiguous buffer, if it has no shared arguments.
+    #[cfg(feature = "aio")]
+    pub(crate) fn try_into_vec(self) -> Result<Vec<u8>, Self> {
+        if self.shared.is_empty() {
+            Ok(self.data)
+        } else {
+            Err(self)
+        }
+    }
+
+    // Iterates over the parts of the packed command, in the order in which they should be written.
+    fn segments(&self) -> impl Iterator<Item = &[u8]> {
+        let mut prev = 0;
+        self.shared
+            .iter()
+            .flat_map(move |(offset, arg)| {
+                let segment = &self.data[prev..*offset];
+                prev = *offset;
+                [segment, &**arg]
+            })
+            .chain(std::iter::once(
+                &self.data[self.shared.last().map_or(0, |(offset, _)| *offset)..],
+            ))
+            .filter(|segment| !segment.is_empty())
+    }
+
+    /// Returns the parts of the packed command that remain after the first `written` bytes,
+    /// for use with vectored writes.
+    pub(crate) fn io_slices(&self, mut written: usize) -> Vec<io::IoSlice<'_>> {
+        let mut slices = Vec::with_capacity(self.shared.len() * 2 + 1);
+        for segment in self.segments() {
+            if written >= segment.len() {
+                written -= segment.len();
+                continue;
+            }
+            slices.push(io::IoSlice::new(&segment[written..]));
+            written = 0;
+        }
+        slices
+    }
+}
+
+impl From<Vec<u8>> for PackedCommand {
+    fn from(data: Vec<u8>) -> Self {
+        PackedCommand {
+            data,
+            shared: Vec::new(),
+        }
+    }
+}
+
+/// Writes all of `bufs` to `writer`, using vectored writes where the writer supports them.
+pub(crate) fn write_all_vectored(
+    writer: &mut (impl ?Sized + Write),
+    mut bufs: &mut [io::IoSlice<'_>],
+) -> io::Result<()> {
+    io::IoSlice::advance_slices(&mut bufs, 0);
+    while !bufs.is_empty() {
+        match writer.write_vectored(bufs) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ));
+            }
+            Ok(n) => io::IoSlice::advance_slices(&mut bufs, n),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
 /// CommandCacheConfig is used to define caching behaviour of individual commands.
 /// # Example
 /// ```rust
@@ -78,15 +233,73 @@ impl Default for CommandCacheConfig {
 #[derive(Clone)]
 pub struct Cmd {
     pub(crate) data: Vec<u8>,
-    // Arg::Simple contains the offset that marks the end of the argument
-    args: Vec<Arg<usize>>,
+    args: Vec<CmdArg>,
     cursor: Option<u64>,
     // If it's true command's response won't be read from socket. Useful for Pub/Sub.
     no_response: bool,
+    // Overrides the built-in classification of the command.
+    idempotency: Option<CommandIdempotency>,
+    // Overrides the response timeout of the connection.
+    response_timeout: Option<ResponseTimeout>,
     #[cfg(feature = "cache-aio")]
     cache: Option<CommandCacheConfig>,
 }
 
+/// Overrides the response timeout of the connection for a single request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResponseTimeout {
+    /// Wait for the given duration, or indefinitely if `None`.
+    After(Option<Duration>),
+    /// Wait until the given instant.
+    Deadline(Instant),
+}
+
+impl ResponseTimeout {
+    /// Returns the response timeout of a request that can block on the server for
+    /// `block_time`, or indefinitely if it's `None`, given the request's override and the
+    /// timeout of the connection.
+    #[cfg(feature = "aio")]
+    pub(crate) fn resolve(
+        response_timeout: Option<ResponseTimeout>,
+        connection_timeout: Option<Duration>,
+        block_time: Option<Duration>,
+    ) -> Option<Duration> {
+        let timeout = match response_timeout {
+            None => connection_timeout,
+            Some(ResponseTimeout::After(timeout)) => timeout,
+            Some(ResponseTimeout::Deadline(deadline)) => {
+                Some(deadline.saturating_duration_since(Instant::now()))
+            }
+        }?;
+        Some(timeout.saturating_add(block_time?))
+    }
+}
+
+/// Describes whether a command can be sent again when it's unknown whether the server
+/// executed it, for example after the connection was lost while waiting for its reply.
+///
+/// Connections that retry requests automatically only retry commands that are
+/// [safe to retry](CommandIdempotency::is_safe_to_retry) after such errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CommandIdempotency {
+    /// The command doesn't change any data.
+    ReadOnly,
+    /// Executing the command more than once has the same effect on the data as executing it
+    /// once, such as `SET` or `DEL`.
+    IdempotentWrite,
+    /// Executing the command more than once might have a different effect than executing it
+    /// once, such as `INCR` or `LPUSH`.
+    NonIdempotent,
+}
+
+impl CommandIdempotency {
+    /// Returns true if the command can be sent again when it's unknown whether it was executed.
+    pub fn is_safe_to_retry(self) -> bool {
+        !matches!(self, CommandIdempotency::NonIdempotent)
+    }
+}
+
 /// Represents a redis iterator.
 pub struct Iter<'a, T: FromRedisValue> {
     iter: CheckedIter<'a, T>,
@@ -252,62 +465,35 @@ impl<'a, T: FromRedisValue + Unpin + Send + 'a> Stream for AsyncIter<'a, T> {
     }
 }
 
-fn countdigits(mut v: usize) -> usize {
-    let mut result = 1;
-    loop {
-        if v < 10 {
-            return result;
-        }
-        if v < 100 {
-            return result + 1;
-        }
-        if v < 1000 {
-            return result + 2;
-        }
-        if v < 10000 {
-            return result + 3;
-        }
-
-        v /= 10000;
-        result += 4;
-    }
-}
-
-#[inline]
-fn bulklen(len: usize) -> usize {
-    1 + countdigits(len) + 2 + len + 2
+// Replaces the cursor placeholder with the cursor's formatted value.
+fn with_cursor<'a: 'b, 'b, I>(
+    args: I,
+    cursor: &'b [u8],
+) -> impl ExactSizeIterator<Item = &'b [u8]> + use<'a, 'b, I>
+where
+    I: ExactSizeIterator<Item = Arg<&'a [u8]>>,
+{
+    args.map(move |arg| match arg {
+        Arg::Cursor => cursor,
+        Arg::Simple(val) => val,
+    })
 }
 
 fn args_len<'a, I>(args: I, cursor: u64) -> usize
 where
-    I: IntoIterator<Item = Arg<&'a [u8]>> + ExactSizeIterator,
+    I: ExactSizeIterator<Item = Arg<&'a [u8]>>,
 {
-    let mut totlen = 1 + countdigits(args.len()) + 2;
-    for item in args {
-        totlen += bulklen(match item {
-            Arg::Cursor => countdigits(cursor as usize),
-            Arg::Simple(val) => val.len(),
-        });
-    }
-    totlen
+    let mut cursor_bytes = itoa::Buffer::new();
+    redis_resp::command_len(with_cursor(args, cursor_bytes.format(cursor).as_bytes()))
 }
 
 pub(crate) fn cmd_len(cmd: &Cmd) -> usize {
     args_len(cmd.args_iter(), cmd.cursor.unwrap_or(0))
 }
 
-fn encode_command<'a, I>(args: I, cursor: u64) -> Vec<u8>
-where
-    I: IntoIterator<Item = Arg<&'a [u8]>> + Clone + ExactSizeIterator,
-{
-    let mut cmd = Vec::new();
-    write_command_to_vec(&mut cmd, args, cursor);
-    cmd
-}
-
 fn write_command_to_vec<'a, I>(cmd: &mut Vec<u8>, args: I, cursor: u64)
 where
-    I: IntoIterator<Item = Arg<&'a [u8]>> + Clone + ExactSizeIterator,
+    I: ExactSizeIterator<Item = Arg<&'a [u8]>> + Clone,
 {
     let totlen = args_len(args.clone(), cursor);
 
@@ -318,49 +504,47 @@ where
 
 fn write_command<'a, I>(cmd: &mut (impl ?Sized + Write), args: I, cursor: u64) -> io::Result<()>
 where
-    I: IntoIterator<Item = Arg<&'a [u8]>> + Clone + ExactSizeIterator,
+    I: ExactSizeIterator<Item = Arg<&'a [u8]>>,
 {
-    let mut buf = ::itoa::Buffer::new();
-
-    cmd.write_all(b"*")?;
-    let s = buf.format(args.len());
-    cmd.write_all(s.as_bytes())?;
-    cmd.write_all(b"\r\n")?;
-
     let mut cursor_bytes = itoa::Buffer::new();
-    for item in args {
-        let bytes = match item {
-            Arg::Cursor => cursor_bytes.format(cursor).as_bytes(),
-            Arg::Simple(val) => val,
-        };
-
-        cmd.write_all(b"$")?;
-        let s = buf.format(bytes.len());
-        cmd.write_all(s.as_bytes())?;
-        cmd.write_all(b"\r\n")?;
-
-        cmd.write_all(bytes)?;
-        cmd.write_all(b"\r\n")?;
-    }
-    Ok(())
+    redis_resp::write_command(
+        cmd,
+        with_cursor(args, cursor_bytes.format(cursor).as_bytes()),
+    )
 }
 
 impl RedisWrite for Cmd {
     fn write_arg(&mut self, arg: &[u8]) {
         self.data.extend_from_slice(arg);
-        self.args.push(Arg::Simple(self.data.len()));
+        self.args.push(CmdArg::Simple(self.data.len()));
     }
 
     fn write_arg_fmt(&mut self, arg: impl fmt::Display) {
         write!(self.data, "{arg}").unwrap();
-        self.args.push(Arg::Simple(self.data.len()));
+        self.args.push(CmdArg::Simple(self.data.len()));
+    }
+
+    fn write_shared_arg(&mut self, arg: &Arc<[u8]>) {
+        if arg.len() < MIN_SHARED_ARG_LEN {
+            return self.write_arg(arg);
+        }
+        self.args.push(CmdArg::Shared(SharedArg::Arc(arg.clone())));
+    }
+
+    #[cfg(feature = "bytes")]
+    fn write_bytes_arg(&mut self, arg: &bytes::Bytes) {
+        if arg.len() < MIN_SHARED_ARG_LEN {
+            return self.write_arg(arg);
+        }
+        self.args
+            .push(CmdArg::Shared(SharedArg::Bytes(arg.clone())));
     }
 
     fn writer_for_next_arg(&mut self) -> impl Write + '_ {
         struct CmdBufferedArgGuard<'a>(&'a mut Cmd);
         impl Drop for CmdBufferedArgGuard<'_> {
             fn drop(&mut self) {
-                self.0.args.push(Arg::Simple(self.0.data.len()));
+                self.0.args.push(CmdArg::Simple(self.0.data.len()));
             }
         }
         impl Write for CmdBufferedArgGuard<'_> {
@@ -394,7 +578,7 @@ impl RedisWrite for Cmd {
         struct CmdBufferedArgGuard<'a>(&'a mut Cmd);
         impl Drop for CmdBufferedArgGuard<'_> {
             fn drop(&mut self) {
-                self.0.args.push(Arg::Simple(self.0.data.len()));
+                self.0.args.push(CmdArg::Simple(self.0.data.len()));
             }
         }
         unsafe impl bytes::BufMut for CmdBufferedArgGuard<'_> {
@@ -472,6 +656,8 @@ impl Cmd {
             args: vec![],
             cursor: None,
             no_response: false,
+            idempotency: None,
+            response_timeout: None,
             #[cfg(feature = "cache-aio")]
             cache: None,
         }
@@ -484,6 +670,8 @@ impl Cmd {
             args: Vec::with_capacity(arg_count),
             cursor: None,
             no_response: false,
+            idempotency: None,
+            response_timeout: None,
             #[cfg(feature = "cache-aio")]
             cache: None,
         }
@@ -519,6 +707,8 @@ impl Cmd {
         self.args.clear();
         self.cursor = None;
         self.no_response = false;
+        self.idempotency = None;
+        self.response_timeout = None;
         #[cfg(feature = "cache-aio")]
         {
             self.cache = None;
@@ -571,7 +761,7 @@ impl Cmd {
     #[inline]
     pub fn cursor_arg(&mut self, cursor: u64) -> &mut Cmd {
         self.cursor = Some(cursor);
-        self.args.push(Arg::Cursor);
+        self.args.push(CmdArg::Cursor);
         self
     }
 
@@ -603,6 +793,18 @@ impl Cmd {
         write_command(cmd, self.args_iter(), self.cursor.unwrap_or(0)).unwrap()
     }
 
+    /// Returns true if some of the command's arguments are held by reference, see [`RedisWrite::write_shared_arg`].
+    pub(crate) fn has_shared_args(&self) -> bool {
+        self.args.iter().any(|arg| matches!(arg, CmdArg::Shared(_)))
+    }
+
+    /// Packs the command without copying its shared arguments.
+    pub(crate) fn get_packed_command_shared(&self) -> PackedCommand {
+        let mut packed = PackedCommand::default();
+        packed.push_cmd(self);
+        packed
+    }
+
     /// Returns true if the command is in scan mode.
     #[inline]
     pub fn in_scan_mode(&self) -> bool {
@@ -737,35 +939,31 @@ impl Cmd {
     pub fn args_iter(&self) -> impl Clone + ExactSizeIterator<Item = Arg<&[u8]>> {
         let mut prev = 0;
         self.args.iter().map(move |arg| match *arg {
-            Arg::Simple(i) => {
+            CmdArg::Simple(i) => {
                 let arg = Arg::Simple(&self.data[prev..i]);
                 prev = i;
                 arg
             }
-
-            Arg::Cursor => Arg::Cursor,
+            CmdArg::Shared(ref arg) => Arg::Simple(&**arg),
+            CmdArg::Cursor => Arg::Cursor,
         })
     }
 
     // Get a reference to the argument at `idx`
-    #[cfg(any(feature = "cluster", feature = "cache-aio"))]
     pub(crate) fn arg_idx(&self, idx: usize) -> Option<&[u8]> {
-        if idx >= self.args.len() {
-            return None;
-        }
-
-        let start = if idx == 0 {
-            0
-        } else {
-            match self.args[idx - 1] {
-                Arg::Simple(n) => n,
-                _ => 0,
-            }
-        };
-        let end = match self.args[idx] {
-            Arg::Simple(n) => n,
-            _ => 0,
+        let end = match self.args.get(idx)? {
+            CmdArg::Simple(n) => *n,
+            CmdArg::Shared(arg) => return Some(arg),
+            CmdArg::Cursor => 0,
         };
+        let start = self.args[..idx]
+            .iter()
+            .rev()
+            .find_map(|arg| match arg {
+                CmdArg::Simple(n) => Some(*n),
+                _ => None,
+            })
+            .unwrap_or(0);
         if start == 0 && end == 0 {
             return None;
         }
@@ -788,6 +986,137 @@ impl Cmd {
         self.no_response
     }
 
+    /// Overrides the built-in classification of the command's idempotency.
+    ///
+    /// This is useful for commands that the library doesn't know, such as module commands
+    /// or scripts, and for commands whose effect depends on their arguments.
+    ///
+    /// ```rust
+    /// # use redis::CommandIdempotency;
+    /// let mut cmd = redis::cmd("EVALSHA");
+    /// cmd.arg("a42059b356c875f0717db19a51f6aaca9ae659ea").arg(0);
+    /// assert_eq!(cmd.idempotency(), CommandIdempotency::NonIdempotent);
+    /// cmd.set_idempotency(CommandIdempotency::ReadOnly);
+    /// assert_eq!(cmd.idempotency(), CommandIdempotency::ReadOnly);
+    /// ```
+    #[inline]
+    pub fn set_idempotency(&mut self, idempotency: CommandIdempotency) -> &mut Cmd {
+        self.idempotency = Some(idempotency);
+        self
+    }
+
+    /// Returns whether the command can be sent again when it's unknown whether the server
+    /// executed it.
+    ///
+    /// Unless it was overridden with [Cmd::set_idempotency], this is based on the command's
+    /// name and arguments. Commands that the library doesn't know are
+    /// [CommandIdempotency::NonIdempotent].
+    pub fn idempotency(&self) -> CommandIdempotency {
+        if let Some(idempotency) = self.idempotency {
+            return idempotency;
+        }
+        let Some(primary) = self.arg_idx(0) else {
+            return CommandIdempotency::NonIdempotent;
+        };
+        match crate::commands::command_name(primary, self.arg_idx(1)).as_slice() {
+            name if crate::commands::is_readonly_cmd(name) => CommandIdempotency::ReadOnly,
+            // `ZADD ... INCR` increments the member's score instead of setting it.
+            b"ZADD" if self.has_option(2, &[b"INCR"]) => CommandIdempotency::NonIdempotent,
+            // conditional `SET`s and `SET ... GET` reply differently to a retry.
+            b"SET" if self.has_option(3, &[b"NX", b"XX", b"GET"]) => {
+                CommandIdempotency::NonIdempotent
+            }
+            name if crate::commands::is_idempotent_write_cmd(name) => {
+                CommandIdempotency::IdempotentWrite
+            }
+            _ => CommandIdempotency::NonIdempotent,
+        }
+    }
+
+    // Returns true if one of the arguments after the first `skip` ones is one of `options`.
+    fn has_option(&self, skip: usize, options: &[&[u8]]) -> bool {
+        self.args_iter().skip(skip).any(|arg| {
+            matches!(arg, Arg::Simple(arg) if options.iter().any(|option| arg.eq_ignore_ascii_case(option)))
+        })
+    }
+
+    /// Sets how long async connections wait for the response to this command, overriding
+    /// the response timeout of the connection. `None` waits indefinitely.
+    ///
+    /// Blocking commands, such as `BLPOP` or `XREAD` with `BLOCK`, get the time they may block
+    /// on the server added to the timeout. Commands that may block indefinitely, such as
+    /// `BLPOP` with a timeout of 0, wait indefinitely.
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// let mut cmd = redis::cmd("BLPOP");
+    /// // waits for up to 5 seconds for the reply.
+    /// cmd.arg("list").arg(4).set_response_timeout(Some(Duration::from_secs(1)));
+    /// ```
+    #[inline]
+    pub fn set_response_timeout(&mut self, timeout: Option<Duration>) -> &mut Cmd {
+        self.response_timeout = Some(ResponseTimeout::After(timeout));
+        self
+    }
+
+    /// Sets the instant by which async connections stop waiting for the response to this
+    /// command, overriding the response timeout of the connection.
+    ///
+    /// Like with [Cmd::set_response_timeout], the time blocking commands may block on the
+    /// server is added to the deadline.
+    #[inline]
+    pub fn set_response_deadline(&mut self, deadline: Instant) -> &mut Cmd {
+        self.response_timeout = Some(ResponseTimeout::Deadline(deadline));
+        self
+    }
+
+    /// Returns how long to wait for the response to this command on a connection whose
+    /// response timeout is `connection_timeout`.
+    #[cfg(feature = "aio")]
+    pub(crate) fn response_timeout(
+        &self,
+        connection_timeout: Option<Duration>,
+    ) -> Option<Duration> {
+        ResponseTimeout::resolve(self.response_timeout, connection_timeout, self.block_time())
+    }
+
+    /// Returns how long the command may block on the server, zero if it doesn't block, or
+    /// `None` if it may block indefinitely.
+    #[cfg(feature = "aio")]
+    pub(crate) fn block_time(&self) -> Option<Duration> {
+        let Some(primary) = self.arg_idx(0) else {
+            return Some(Duration::ZERO);
+        };
+        let last_arg = || match self.args_iter().last() {
+            Some(Arg::Simple(arg)) => Some(arg),
+            _ => None,
+        };
+        let block_time = match primary.to_ascii_uppercase().as_slice() {
+            b"BLPOP" | b"BRPOP" | b"BRPOPLPUSH" | b"BLMOVE" | b"BZPOPMIN" | b"BZPOPMAX" => {
+                last_arg().and_then(parse_seconds)
+            }
+            b"BLMPOP" | b"BZMPOP" => self.arg_idx(1).and_then(parse_seconds),
+            b"WAIT" | b"WAITAOF" => last_arg().and_then(parse_millis),
+            b"XREAD" | b"XREADGROUP" => {
+                // the options precede the stream names, which might be named `BLOCK` too.
+                let mut options = self.args_iter().skip(1).map_while(|arg| match arg {
+                    Arg::Simple(arg) if !arg.eq_ignore_ascii_case(b"STREAMS") => Some(arg),
+                    _ => None,
+                });
+                options
+                    .find(|arg| arg.eq_ignore_ascii_case(b"BLOCK"))
+                    .and_then(|_| options.next())
+                    .and_then(parse_millis)
+            }
+            _ => None,
+        };
+        // a timeout of 0 blocks until the command can be served.
+        match block_time {
+            Some(Duration::ZERO) => None,
+            block_time => Some(block_time.unwrap_or_default()),
+        }
+    }
+
     /// Changes caching behaviour for this specific command.
     #[cfg(feature = "cache-aio")]
     #[cfg_attr(docsrs, doc(cfg(feature = "cache-aio")))]
@@ -803,6 +1132,18 @@ impl Cmd {
     }
 }
 
+#[cfg(feature = "aio")]
+fn parse_seconds(arg: &[u8]) -> Option<Duration> {
+    let seconds = std::str::from_utf8(arg).ok()?.parse::<f64>().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(feature = "aio")]
+fn parse_millis(arg: &[u8]) -> Option<Duration> {
+    let millis = std::str::from_utf8(arg).ok()?.parse::<u64>().ok()?;
+    Some(Duration::from_millis(millis))
+}
+
 /// Shortcut function to creating a command with a single argument.
 ///
 /// The first argument of a redis command is always the name of the command
@@ -837,7 +1178,7 @@ pub fn cmd(name: &str) -> Cmd {
 /// assert_eq!(cmd, b"*3\r\n$3\r\nSET\r\n$6\r\nmy_key\r\n$2\r\n42\r\n".to_vec());
 /// ```
 pub fn pack_command(args: &[Vec<u8>]) -> Vec<u8> {
-    encode_command(args.iter().map(|x| Arg::Simple(&x[..])), 0)
+    redis_resp::pack_command(args)
 }
 
 /// Shortcut for creating a new pipeline.
@@ -1089,4 +1430,326 @@ mod tests {
         assert_eq!(c.arg_idx(3), None);
         assert_eq!(c.arg_idx(4), None);
     }
+
+    fn concat_io_slices(packed: &PackedCommand, written: usize) -> Vec<u8> {
+        packed
+            .io_slices(written)
+            .iter()
+            .flat_map(|slice| slice.iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn test_cmd_shared_arg_is_packed_like_copied_arg() {
+        let value: Arc<[u8]> = vec![b'x'; MIN_SHARED_ARG_LEN].into();
+        let mut shared = cmd("SET");
+        shared.arg("key").arg(&value).arg("EX").cursor_arg(10);
+        let mut copied = cmd("SET");
+        copied.arg("key").arg(&*value).arg("EX").cursor_arg(10);
+
+        assert!(shared.has_shared_args());
+        assert!(!copied.has_shared_args());
+        assert_eq!(shared.data.len(), copied.data.len() - value.len());
+        assert_practical_equivalent(shared.clone(), copied.clone());
+
+        let packed = shared.get_packed_command_shared();
+        assert_eq!(packed.shared.len(), 1);
+        assert_eq!(packed.io_slices(0).len(), 3);
+        let expected = copied.get_packed_command();
+        for written in [0, 1, 20, 30, 1000, expected.len() - 1, expected.len()] {
+            assert_eq!(concat_io_slices(&packed, written), &expected[written..]);
+        }
+    }
+
+    #[test]
+    fn test_cmd_small_shared_arg_is_copied() {
+        let value: Arc<[u8]> = vec![b'x'; MIN_SHARED_ARG_LEN - 1].into();
+        let mut c = cmd("SET");
+        c.arg("key").arg(&value);
+
+        assert!(!c.has_shared_args());
+        assert_eq!(c.get_packed_command_shared().io_slices(0).len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "bytes")]
+    fn test_cmd_shared_bytes_args() {
+        let first = bytes::Bytes::from(vec![b'a'; MIN_SHARED_ARG_LEN]);
+        let second = bytes::Bytes::from(vec![b'b'; MIN_SHARED_ARG_LEN * 2]);
+        let mut shared = cmd("MSET");
+        shared
+            .arg("first")
+            .arg(&first)
+            .arg("second")
+            .arg(&second)
+            .arg("third")
+            .arg(bytes::Bytes::from_static(b"small"));
+        let mut copied = cmd("MSET");
+        copied
+            .arg("first")
+            .arg(&first[..])
+            .arg("second")
+            .arg(&second[..])
+            .arg("third")
+            .arg("small");
+
+        assert_practical_equivalent(shared.clone(), copied.clone());
+        let packed = shared.get_packed_command_shared();
+        let slices = packed.io_slices(0);
+        assert_eq!(slices.len(), 5);
+        // the shared arguments are written from their own buffers.
+        assert_eq!(slices[1].as_ptr(), first.as_ptr());
+        assert_eq!(slices[3].as_ptr(), second.as_ptr());
+        assert_eq!(concat_io_slices(&packed, 0), copied.get_packed_command());
+    }
+
+    #[test]
+    #[cfg(feature = "aio")]
+    fn test_pipeline_shared_args() {
+        let value: Arc<[u8]> = vec![b'x'; MIN_SHARED_ARG_LEN].into();
+        let mut pipeline = pipe();
+        pipeline
+            .atomic()
+            .set("first", &value)
+            .get("first")
+            .set("second", &value);
+
+        assert_eq!(
+            concat_io_slices(&pipeline.get_packed_pipeline_shared(), 0),
+            pipeline.get_packed_pipeline()
+        );
+    }
+
+    #[test]
+    fn test_write_all_vectored() {
+        // Writes up to 3 bytes of the first non-empty buffer at a time.
+        struct ShortWriter(Vec<u8>);
+        impl Write for ShortWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let len = buf.len().min(3);
+                self.0.extend_from_slice(&buf[..len]);
+                Ok(len)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut writer = ShortWriter(Vec::new());
+        let bufs = [&b"hello"[..], b"", b" ", b"world"];
+        let mut slices: Vec<_> = bufs.iter().map(|buf| io::IoSlice::new(buf)).collect();
+        write_all_vectored(&mut writer, &mut slices).unwrap();
+        assert_eq!(writer.0, b"hello world");
+    }
+
+    #[test]
+    fn test_cmd_arg_idx_with_shared_args() {
+        let value: Arc<[u8]> = vec![b'x'; MIN_SHARED_ARG_LEN].into();
+        let mut c = cmd("SET");
+        c.arg("foo").arg(&value).arg("EX").arg(10);
+
+        assert_eq!(c.arg_idx(1), Some(&b"foo"[..]));
+        assert_eq!(c.arg_idx(2), Some(&value[..]));
+        assert_eq!(c.arg_idx(3), Some(&b"EX"[..]));
+        assert_eq!(c.arg_idx(4), Some(&b"10"[..]));
+        assert_eq!(c.arg_idx(5), None);
+    }
+
+    #[test]
+    fn test_cmd_idempotency() {
+        assert_eq!(
+            cmd("GET").arg("foo").idempotency(),
+            CommandIdempotency::ReadOnly
+        );
+        assert_eq!(
+            cmd("client").arg("getname").idempotency(),
+            CommandIdempotency::ReadOnly
+        );
+        assert_eq!(
+            cmd("set").arg("foo").arg("bar").idempotency(),
+            CommandIdempotency::IdempotentWrite
+        );
+        assert_eq!(
+            cmd("ZADD").arg("foo").arg(1).arg("bar").idempotency(),
+            CommandIdempotency::IdempotentWrite
+        );
+        assert_eq!(
+            cmd("ZADD")
+                .arg("foo")
+                .arg("incr")
+                .arg(1)
+                .arg("bar")
+                .idempotency(),
+            CommandIdempotency::NonIdempotent
+        );
+        assert_eq!(
+            cmd("INCR").arg("foo").idempotency(),
+            CommandIdempotency::NonIdempotent
+        );
+        for name in ["SETNX", "HSETNX", "MSETNX"] {
+            assert_eq!(
+                cmd(name).arg("foo").arg("bar").idempotency(),
+                CommandIdempotency::NonIdempotent,
+                "{name}"
+            );
+        }
+        assert_eq!(
+            cmd("SET")
+                .arg("foo")
+                .arg("bar")
+                .arg("EX")
+                .arg(10)
+                .idempotency(),
+            CommandIdempotency::IdempotentWrite
+        );
+        // a value that looks like an option doesn't make the command conditional.
+        assert_eq!(
+            cmd("SET").arg("nx").arg("get").idempotency(),
+            CommandIdempotency::IdempotentWrite
+        );
+        for options in [
+            &["NX"][..],
+            &["xx"],
+            &["GET"],
+            &["KEEPTTL", "NX"],
+            &["XX", "GET", "EX", "10"],
+        ] {
+            assert_eq!(
+                cmd("SET").arg("foo").arg("bar").arg(options).idempotency(),
+                CommandIdempotency::NonIdempotent,
+                "{options:?}"
+            );
+        }
+        assert_eq!(
+            cmd("SET")
+                .arg("foo")
+                .arg("bar")
+                .arg(crate::SetOptions::default().conditional_set(crate::ExistenceCheck::NX))
+                .idempotency(),
+            CommandIdempotency::NonIdempotent
+        );
+        assert_eq!(
+            cmd("MY.MODULE.COMMAND").idempotency(),
+            CommandIdempotency::NonIdempotent
+        );
+        assert_eq!(Cmd::new().idempotency(), CommandIdempotency::NonIdempotent);
+
+        let mut c = cmd("INCR");
+        c.arg("foo")
+            .set_idempotency(CommandIdempotency::IdempotentWrite);
+        assert_eq!(c.idempotency(), CommandIdempotency::IdempotentWrite);
+        c.clear();
+        c.arg("INCR").arg("foo");
+        assert_eq!(c.idempotency(), CommandIdempotency::NonIdempotent);
+    }
+
+    #[test]
+    #[cfg(feature = "aio")]
+    fn test_cmd_block_time() {
+        assert_eq!(cmd("GET").arg("foo").block_time(), Some(Duration::ZERO));
+        assert_eq!(
+            cmd("blpop").arg("foo").arg("bar").arg(1.5).block_time(),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(cmd("BLPOP").arg("foo").arg(0).block_time(), None);
+        assert_eq!(
+            cmd("BZMPOP")
+                .arg(2)
+                .arg(1)
+                .arg("foo")
+                .arg("MIN")
+                .block_time(),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            cmd("WAIT").arg(1).arg(300).block_time(),
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(cmd("WAIT").arg(1).arg(0).block_time(), None);
+        assert_eq!(
+            cmd("XREAD")
+                .arg("COUNT")
+                .arg(2)
+                .arg("BLOCK")
+                .arg(100)
+                .arg("STREAMS")
+                .arg("foo")
+                .arg("$")
+                .block_time(),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            cmd("XREAD")
+                .arg("BLOCK")
+                .arg(0)
+                .arg("STREAMS")
+                .arg("foo")
+                .arg("$")
+                .block_time(),
+            None
+        );
+        assert_eq!(
+            cmd("XREAD")
+                .arg("STREAMS")
+                .arg("block")
+                .arg("100")
+                .block_time(),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "aio")]
+    fn test_cmd_response_timeout() {
+        let connection_timeout = Some(Duration::from_secs(1));
+        assert_eq!(
+            cmd("GET").arg("foo").response_timeout(connection_timeout),
+            connection_timeout
+        );
+        assert_eq!(cmd("GET").arg("foo").response_timeout(None), None);
+        assert_eq!(
+            cmd("BLPOP")
+                .arg("foo")
+                .arg(2)
+                .response_timeout(connection_timeout),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            cmd("BLPOP")
+                .arg("foo")
+                .arg(0)
+                .response_timeout(connection_timeout),
+            None
+        );
+        assert_eq!(
+            cmd("GET")
+                .arg("foo")
+                .set_response_timeout(None)
+                .response_timeout(connection_timeout),
+            None
+        );
+        assert_eq!(
+            cmd("BLPOP")
+                .arg("foo")
+                .arg(2)
+                .set_response_timeout(Some(Duration::from_millis(10)))
+                .response_timeout(connection_timeout),
+            Some(Duration::from_millis(2010))
+        );
+
+        let timeout = cmd("GET")
+            .arg("foo")
+            .set_response_deadline(Instant::now() + Duration::from_secs(10))
+            .response_timeout(connection_timeout)
+            .unwrap();
+        assert!(timeout > Duration::from_secs(9) && timeout <= Duration::from_secs(10));
+        assert_eq!(
+            cmd("GET")
+                .arg("foo")
+                .set_response_deadline(Instant::now())
+                .response_timeout(connection_timeout),
+            Some(Duration::ZERO)
+        );
+    }
 }
@@ -1,7 +1,7 @@
 //! Defines types to use with the ACL commands.
 
 use crate::errors::ParsingError;
-use crate::types::{FromRedisValue, RedisWrite, ToRedisArgs, Value};
+use crate::types::{get_owned_inner_value, FromRedisValue, RedisWrite, ToRedisArgs, Value};
 
 macro_rules! not_convertible_error {
     ($v:expr, $det:expr) => {
@@ -140,6 +140,7 @@ pub struct AclInfo {
 
 impl FromRedisValue for AclInfo {
     fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        let v = get_owned_inner_value(v);
         let mut it = v
             .as_sequence()
             .ok_or_else(|| not_convertible_error!(v, ""))?
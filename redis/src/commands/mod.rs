@@ -45,7 +45,41 @@ use crate::RedisConnectionInfo;
 #[cfg_attr(docsrs, doc(cfg(feature = "vector-sets")))]
 pub mod vector_sets;
 
-#[cfg(any(feature = "cluster", feature = "cache-aio"))]
+/// Returns the uppercase name of a command, including its subcommand for
+/// container commands such as `CLIENT` or `CONFIG`.
+pub(crate) fn command_name(primary: &[u8], secondary: Option<&[u8]>) -> Vec<u8> {
+    let mut command_name = primary.to_ascii_uppercase();
+    if !matches!(
+        command_name.as_slice(),
+        b"XGROUP"
+            | b"OBJECT"
+            | b"SLOWLOG"
+            | b"FUNCTION"
+            | b"MODULE"
+            | b"COMMAND"
+            | b"PUBSUB"
+            | b"CONFIG"
+            | b"MEMORY"
+            | b"XINFO"
+            | b"CLIENT"
+            | b"ACL"
+            | b"SCRIPT"
+            | b"CLUSTER"
+            | b"LATENCY"
+    ) {
+        return command_name;
+    }
+
+    if let Some(secondary) = secondary {
+        let previous_len = command_name.len();
+        command_name.reserve(secondary.len() + 1);
+        command_name.extend(b" ");
+        command_name.extend(secondary);
+        command_name[previous_len + 1..].make_ascii_uppercase();
+    }
+    command_name
+}
+
 pub(crate) fn is_readonly_cmd(cmd: &[u8]) -> bool {
     matches!(
         cmd,
@@ -280,6 +314,46 @@ pub(crate) fn is_readonly_cmd(cmd: &[u8]) -> bool {
     )
 }
 
+/// Returns true if executing the command more than once has the same effect
+/// on the data as executing it once.
+///
+/// Conditional writes such as `SETNX` aren't listed, since their reply to a
+/// retry differs from the reply to the first attempt.
+pub(crate) fn is_idempotent_write_cmd(cmd: &[u8]) -> bool {
+    matches!(
+        cmd,
+        b"DEL"
+            | b"EXPIREAT"
+            | b"FLUSHALL"
+            | b"FLUSHDB"
+            | b"GEOADD"
+            | b"HDEL"
+            | b"HMSET"
+            | b"HSET"
+            | b"LSET"
+            | b"MSET"
+            | b"PERSIST"
+            | b"PEXPIREAT"
+            | b"PFADD"
+            | b"PSETEX"
+            | b"SADD"
+            | b"SET"
+            | b"SETBIT"
+            | b"SETEX"
+            | b"SETRANGE"
+            | b"SREM"
+            | b"UNLINK"
+            | b"XACK"
+            | b"XDEL"
+            | b"XGROUP DESTROY"
+            | b"XGROUP SETID"
+            | b"ZADD"
+            | b"ZREM"
+            | b"ZREMRANGEBYLEX"
+            | b"ZREMRANGEBYSCORE"
+    )
+}
+
 // Note - Brackets are needed around return types for purposes of macro branching.
 implement_commands! {
     'a
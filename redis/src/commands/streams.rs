@@ -3,7 +3,7 @@
 #[cfg(feature = "streams")]
 use crate::{
     errors::{invalid_type_error, ParsingError},
-    types::HashMap,
+    types::{get_owned_inner_value, HashMap},
     FromRedisValue, RedisWrite, ToRedisArgs, Value,
 };
 use crate::{from_redis_value, from_redis_value_ref, types::ToSingleRedisArg};
@@ -730,6 +730,7 @@ type SACRows = Vec<HashMap<String, HashMap<String, Value>>>;
 
 impl FromRedisValue for StreamAutoClaimReply {
     fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        let v = get_owned_inner_value(v);
         let Value::Array(mut items) = v else {
             invalid_type_error!("Not a array response", v);
         };
@@ -892,6 +893,7 @@ impl FromRedisValue for StreamPendingReply {
 
 impl FromRedisValue for StreamPendingCountReply {
     fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        let v = get_owned_inner_value(v);
         let mut reply = StreamPendingCountReply::default();
         match v {
             Value::Array(outer_tuple) => {
@@ -1060,6 +1062,7 @@ pub enum XDelExStatusCode {
 #[cfg(feature = "streams")]
 impl FromRedisValue for XDelExStatusCode {
     fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        let v = get_owned_inner_value(v);
         match v {
             Value::Int(code) => match code {
                 -1 => Ok(XDelExStatusCode::IdNotFound),
@@ -1089,6 +1092,7 @@ pub enum XAckDelStatusCode {
 #[cfg(feature = "streams")]
 impl FromRedisValue for XAckDelStatusCode {
     fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        let v = get_owned_inner_value(v);
         match v {
             Value::Int(code) => match code {
                 -1 => Ok(XAckDelStatusCode::IdNotFound),
@@ -8,14 +8,14 @@ use std::path::PathBuf;
 use std::str::{from_utf8, FromStr};
 use std::time::{Duration, Instant};
 
-use crate::cmd::{cmd, pipe, Cmd};
+use crate::cmd::{cmd, pipe, write_all_vectored, Cmd};
 use crate::errors::{ErrorKind, RedisError, ServerError, ServerErrorKind};
 use crate::io::tcp::{stream_with_settings, TcpSettings};
 use crate::parser::Parser;
 use crate::pipeline::Pipeline;
 use crate::types::{
-    from_redis_value_ref, FromRedisValue, HashMap, PushKind, RedisResult, SyncPushSender,
-    ToRedisArgs, Value,
+    from_redis_value_ref, FromRedisValue, HashMap, PushKind, RedisResult, ServerInfo,
+    SyncPushSender, ToRedisArgs, Value,
 };
 use crate::{check_resp3, from_redis_value, ProtocolVersion};
 
@@ -394,6 +394,7 @@ impl IntoConnectionInfo for ConnectionAddr {
 /// - Enabling TLS: `rediss://127.0.0.1:6379`
 /// - Enabling Insecure TLS: `rediss://127.0.0.1:6379/#insecure`
 /// - Enabling RESP3: `redis://127.0.0.1:6379/?protocol=resp3`
+/// - Using RESP3 if the server supports it: `redis://127.0.0.1:6379/?protocol=auto`
 impl IntoConnectionInfo for &str {
     fn into_connection_info(self) -> RedisResult<ConnectionInfo> {
         match parse_redis_url(self) {
@@ -425,6 +426,7 @@ where
 /// - Enabling TLS: `rediss://127.0.0.1:6379`
 /// - Enabling Insecure TLS: `rediss://127.0.0.1:6379/#insecure`
 /// - Enabling RESP3: `redis://127.0.0.1:6379/?protocol=resp3`
+/// - Using RESP3 if the server supports it: `redis://127.0.0.1:6379/?protocol=auto`
 impl IntoConnectionInfo for String {
     fn into_connection_info(self) -> RedisResult<ConnectionInfo> {
         match parse_redis_url(&self) {
@@ -441,6 +443,8 @@ fn parse_protocol(query: &HashMap<Cow<str>, Cow<str>>) -> RedisResult<ProtocolVe
                 ProtocolVersion::RESP2
             } else if protocol == "3" || protocol == "resp3" {
                 ProtocolVersion::RESP3
+            } else if protocol == "auto" {
+                ProtocolVersion::Auto
             } else {
                 fail!((
                     ErrorKind::InvalidClientConfig,
@@ -777,6 +781,9 @@ pub struct Connection {
     // Field indicating which protocol to use for server communications.
     protocol: ProtocolVersion,
 
+    // The server's reply to `HELLO`, if the connection uses RESP3.
+    server_info: Option<ServerInfo>,
+
     /// This is used to manage Push messages in RESP3 mode.
     push_sender: Option<SyncPushSender>,
 
@@ -988,58 +995,42 @@ impl ActualConnection {
     }
 
     pub fn send_bytes(&mut self, bytes: &[u8]) -> RedisResult<Value> {
-        match *self {
+        self.write_with(|writer| writer.write_all(bytes))
+    }
+
+    pub fn send_vectored(&mut self, bufs: &mut [io::IoSlice<'_>]) -> RedisResult<Value> {
+        self.write_with(|writer| write_all_vectored(writer, bufs))
+    }
+
+    fn write_with(
+        &mut self,
+        write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> RedisResult<Value> {
+        let (writer, open): (&mut dyn Write, _) = match *self {
             ActualConnection::Tcp(ref mut connection) => {
-                let res = connection.reader.write_all(bytes).map_err(RedisError::from);
-                match res {
-                    Err(e) => {
-                        if e.is_unrecoverable_error() {
-                            connection.open = false;
-                        }
-                        Err(e)
-                    }
-                    Ok(_) => Ok(Value::Okay),
-                }
+                (&mut connection.reader, &mut connection.open)
             }
             #[cfg(all(feature = "tls-native-tls", not(feature = "tls-rustls")))]
             ActualConnection::TcpNativeTls(ref mut connection) => {
-                let res = connection.reader.write_all(bytes).map_err(RedisError::from);
-                match res {
-                    Err(e) => {
-                        if e.is_unrecoverable_error() {
-                            connection.open = false;
-                        }
-                        Err(e)
-                    }
-                    Ok(_) => Ok(Value::Okay),
-                }
+                (&mut connection.reader, &mut connection.open)
             }
             #[cfg(feature = "tls-rustls")]
             ActualConnection::TcpRustls(ref mut connection) => {
-                let res = connection.reader.write_all(bytes).map_err(RedisError::from);
-                match res {
-                    Err(e) => {
-                        if e.is_unrecoverable_error() {
-                            connection.open = false;
-                        }
-                        Err(e)
-                    }
-                    Ok(_) => Ok(Value::Okay),
-                }
+                (&mut connection.reader, &mut connection.open)
             }
             #[cfg(unix)]
             ActualConnection::Unix(ref mut connection) => {
-                let result = connection.sock.write_all(bytes).map_err(RedisError::from);
-                match result {
-                    Err(e) => {
-                        if e.is_unrecoverable_error() {
-                            connection.open = false;
-                        }
-                        Err(e)
-                    }
-                    Ok(_) => Ok(Value::Okay),
+                (&mut connection.sock, &mut connection.open)
+            }
+        };
+        match write(writer).map_err(RedisError::from) {
+            Err(e) => {
+                if e.is_unrecoverable_error() {
+                    *open = false;
                 }
+                Err(e)
             }
+            Ok(_) => Ok(Value::Okay),
         }
     }
 
@@ -1348,23 +1339,24 @@ pub(crate) fn connection_setup_pipeline(
     )
 }
 
-fn check_resp3_auth(result: &Value) -> RedisResult<()> {
+fn check_resp3_auth(result: &Value) -> RedisResult<Option<ServerInfo>> {
     if let Value::ServerError(err) = result {
         return Err(get_resp3_hello_command_error(err.clone().into()));
     }
-    Ok(())
+    // some proxies accept `HELLO` without replying with the server's information.
+    Ok(ServerInfo::from_redis_value_ref(result).ok())
 }
 
 #[derive(PartialEq)]
 pub(crate) enum AuthResult {
-    Succeeded,
+    Succeeded(Option<ServerInfo>),
     ShouldRetryWithoutUsername,
 }
 
 fn check_resp2_auth(result: &Value) -> RedisResult<AuthResult> {
     let err = match result {
         Value::Okay => {
-            return Ok(AuthResult::Succeeded);
+            return Ok(AuthResult::Succeeded(None));
         }
         Value::ServerError(err) => err,
         _ => {
@@ -1436,11 +1428,12 @@ pub(crate) fn check_connection_setup(
     // can't have both values set
     assert!(!(resp2_auth_cmd_idx.is_some() && resp3_auth_cmd_idx.is_some()));
 
+    let mut server_info = None;
     if let Some(index) = resp3_auth_cmd_idx {
         let Some(value) = results.get(index) else {
             return Err((ErrorKind::Client, "Missing RESP3 auth response").into());
         };
-        check_resp3_auth(value)?;
+        server_info = check_resp3_auth(value)?;
     } else if let Some(index) = resp2_auth_cmd_idx {
         let Some(value) = results.get(index) else {
             return Err((ErrorKind::Client, "Missing RESP2 auth response").into());
@@ -1465,7 +1458,7 @@ pub(crate) fn check_connection_setup(
         check_caching(value)?;
     }
 
-    Ok(AuthResult::Succeeded)
+    Ok(AuthResult::Succeeded(server_info))
 }
 
 fn execute_connection_pipeline(
@@ -1473,13 +1466,43 @@ fn execute_connection_pipeline(
     (pipeline, instructions): (crate::Pipeline, ConnectionSetupComponents),
 ) -> RedisResult<AuthResult> {
     if pipeline.is_empty() {
-        return Ok(AuthResult::Succeeded);
+        return Ok(AuthResult::Succeeded(None));
     }
     let results = rv.req_packed_commands(&pipeline.get_packed_pipeline(), 0, pipeline.len())?;
 
     check_connection_setup(results, instructions)
 }
 
+fn authenticate_and_setup(
+    rv: &mut Connection,
+    connection_info: &RedisConnectionInfo,
+    #[cfg(feature = "cache-aio")] cache_config: Option<crate::caching::CacheConfig>,
+) -> RedisResult<Option<ServerInfo>> {
+    match execute_connection_pipeline(
+        rv,
+        connection_setup_pipeline(
+            connection_info,
+            true,
+            #[cfg(feature = "cache-aio")]
+            cache_config,
+        ),
+    )? {
+        AuthResult::Succeeded(server_info) => Ok(server_info),
+        AuthResult::ShouldRetryWithoutUsername => {
+            execute_connection_pipeline(
+                rv,
+                connection_setup_pipeline(
+                    connection_info,
+                    false,
+                    #[cfg(feature = "cache-aio")]
+                    cache_config,
+                ),
+            )?;
+            Ok(None)
+        }
+    }
+}
+
 fn setup_connection(
     con: ActualConnection,
     connection_info: &RedisConnectionInfo,
@@ -1491,34 +1514,53 @@ fn setup_connection(
         db: connection_info.db,
         pubsub: false,
         protocol: connection_info.protocol,
+        server_info: None,
         push_sender: None,
         messages_to_skip: 0,
     };
 
-    if execute_connection_pipeline(
+    let (protocol, server_info) = match authenticate_and_setup(
         &mut rv,
-        connection_setup_pipeline(
-            connection_info,
-            true,
-            #[cfg(feature = "cache-aio")]
-            cache_config,
-        ),
-    )? == AuthResult::ShouldRetryWithoutUsername
-    {
-        execute_connection_pipeline(
-            &mut rv,
-            connection_setup_pipeline(
-                connection_info,
-                false,
+        connection_info,
+        #[cfg(feature = "cache-aio")]
+        cache_config,
+    ) {
+        Err(err) if should_fall_back_to_resp2(connection_info, &err) => {
+            let connection_info = connection_info.clone().set_protocol(ProtocolVersion::RESP2);
+            let server_info = authenticate_and_setup(
+                &mut rv,
+                &connection_info,
                 #[cfg(feature = "cache-aio")]
-                cache_config,
-            ),
-        )?;
-    }
+                None,
+            )?;
+            (ProtocolVersion::RESP2, server_info)
+        }
+        result => (negotiated_protocol(connection_info), result?),
+    };
+    rv.protocol = protocol;
+    rv.server_info = server_info;
 
     Ok(rv)
 }
 
+/// Returns true if the connection should be set up again with RESP2, after
+/// setting it up with RESP3 failed with `err`.
+pub(crate) fn should_fall_back_to_resp2(
+    connection_info: &RedisConnectionInfo,
+    err: &RedisError,
+) -> bool {
+    connection_info.protocol == ProtocolVersion::Auto && err.kind() == ErrorKind::RESP3NotSupported
+}
+
+/// Returns the protocol that a connection uses after it was set up without
+/// falling back to RESP2.
+pub(crate) fn negotiated_protocol(connection_info: &RedisConnectionInfo) -> ProtocolVersion {
+    match connection_info.protocol {
+        ProtocolVersion::Auto => ProtocolVersion::RESP3,
+        protocol => protocol,
+    }
+}
+
 /// Implements the "stateless" part of the connection interface that is used by the
 /// different objects in redis-rs.
 ///
@@ -1832,8 +1874,32 @@ impl Connection {
         self.push_sender = Some(sender);
     }
 
+    /// Returns the protocol version that the connection uses.
+    ///
+    /// If the connection was configured with [ProtocolVersion::Auto], this is
+    /// the version that was negotiated with the server.
+    pub fn protocol(&self) -> ProtocolVersion {
+        self.protocol
+    }
+
+    /// Returns the information that the server sent in reply to `HELLO`, or
+    /// `None` if the connection uses RESP2.
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
+
     fn send_bytes(&mut self, bytes: &[u8]) -> RedisResult<Value> {
         let result = self.con.send_bytes(bytes);
+        self.check_send_result(result)
+    }
+
+    // Sends a packed command whose shared arguments are written straight from their buffers.
+    fn send_vectored(&mut self, bufs: &mut [io::IoSlice<'_>]) -> RedisResult<Value> {
+        let result = self.con.send_vectored(bufs);
+        self.check_send_result(result)
+    }
+
+    fn check_send_result(&mut self, result: RedisResult<Value>) -> RedisResult<Value> {
         if self.protocol.supports_resp3() {
             if let Err(e) = &result {
                 if e.is_connection_dropped() {
@@ -1892,12 +1958,16 @@ impl Connection {
 impl ConnectionLike for Connection {
     /// Sends a [Cmd] into the TCP socket and reads a single response from it.
     fn req_command(&mut self, cmd: &Cmd) -> RedisResult<Value> {
-        let pcmd = cmd.get_packed_command();
         if self.pubsub {
             self.exit_pubsub()?;
         }
 
-        self.send_bytes(&pcmd)?;
+        if cmd.has_shared_args() {
+            let pcmd = cmd.get_packed_command_shared();
+            self.send_vectored(&mut pcmd.io_slices(0))?;
+        } else {
+            self.send_bytes(&cmd.get_packed_command())?;
+        }
         if cmd.is_no_response() {
             return Ok(Value::Nil);
         }
@@ -2383,6 +2453,13 @@ pub fn no_sub_err_is_pub_sub_state_cleared(
 
 /// Common logic for checking real cause of hello3 command error
 pub fn get_resp3_hello_command_error(err: RedisError) -> RedisError {
+    if err.code() == Some("NOPROTO") {
+        return (
+            ErrorKind::RESP3NotSupported,
+            "Redis Server doesn't support RESP3",
+        )
+            .into();
+    }
     if let Some(detail) = err.detail() {
         if detail.starts_with("unknown command `HELLO`") {
             return (
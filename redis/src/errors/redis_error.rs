@@ -47,6 +47,13 @@ pub enum ErrorKind {
     /// Redis Servers prior to v6.0.0 doesn't support RESP3.
     /// Try disabling resp3 option
     RESP3NotSupported,
+
+    /// The request wasn't sent, because the circuit breaker of its target is open.
+    CircuitOpen,
+
+    /// The request wasn't sent, because the connection has as many requests in flight, or as
+    /// many bytes queued, as it's configured to allow.
+    RequestLimitReached,
 }
 
 /// Represents a redis error.
@@ -305,11 +312,14 @@ impl RedisError {
             #[cfg(feature = "json")]
             ErrorKind::Serialize => "serializing",
             ErrorKind::RESP3NotSupported => "resp3 is not supported by server",
+            ErrorKind::CircuitOpen => "circuit breaker is open",
+            ErrorKind::RequestLimitReached => "request limit reached",
             ErrorKind::Parse => "parse error",
             ErrorKind::Server(ServerErrorKind::NoSub) => {
                 "Server declined unsubscribe related command in non-subscribed mode"
             }
             ErrorKind::Server(ServerErrorKind::NoPerm) => "",
+            ErrorKind::Server(_) => "server error",
         }
     }
 
@@ -415,7 +425,7 @@ impl RedisError {
     /// than just the error kind on when to retry.
     pub fn retry_method(&self) -> RetryMethod {
         match self.kind() {
-            ErrorKind::Server(server_error) => server_error.retry_method(),
+            ErrorKind::Server(server_error) => super::retry_method(server_error),
 
             ErrorKind::MasterNameNotFoundBySentinel => RetryMethod::WaitAndRetry,
             ErrorKind::NoValidReplicasFoundBySentinel => RetryMethod::WaitAndRetry,
@@ -428,6 +438,8 @@ impl RedisError {
             #[cfg(feature = "json")]
             ErrorKind::Serialize => RetryMethod::NoRetry,
             ErrorKind::RESP3NotSupported => RetryMethod::NoRetry,
+            ErrorKind::CircuitOpen => RetryMethod::NoRetry,
+            ErrorKind::RequestLimitReached => RetryMethod::NoRetry,
 
             ErrorKind::Parse => RetryMethod::Reconnect,
             ErrorKind::AuthenticationFailed => RetryMethod::Reconnect,
@@ -494,10 +506,7 @@ impl RedisError {
 /// A `RedisError` with the `Extension` kind.
 pub fn make_extension_error(code: String, detail: Option<String>) -> RedisError {
     RedisError {
-        repr: ErrorRepr::Server(ServerError(crate::errors::Repr::Extension {
-            code: code.into(),
-            detail: detail.map(|detail| detail.into()),
-        })),
+        repr: ErrorRepr::Server(ServerError::extension(&code, detail.as_deref())),
     }
 }
 
@@ -547,6 +556,16 @@ impl From<ParsingError> for RedisError {
     }
 }
 
+impl From<redis_resp::Error> for RedisError {
+    fn from(err: redis_resp::Error) -> Self {
+        match err {
+            redis_resp::Error::Io(err) => err.into(),
+            redis_resp::Error::Invalid(description) => ParsingError::from(description).into(),
+            err => ParsingError::from(err.to_string()).into(),
+        }
+    }
+}
+
 impl TryFrom<RedisError> for ServerError {
     type Error = RedisError;
 
@@ -560,14 +579,15 @@ impl TryFrom<RedisError> for ServerError {
 
 #[cfg(test)]
 mod tests {
-    use crate::parse_redis_value;
+    use crate::{parse_redis_value, RedisError};
 
     #[test]
     fn test_redirect_node() {
-        let err = parse_redis_value(b"-ASK 123 foobar:6380\r\n")
+        let err: RedisError = parse_redis_value(b"-ASK 123 foobar:6380\r\n")
             .unwrap()
             .extract_error()
-            .unwrap_err();
+            .unwrap_err()
+            .into();
         let node = err.redirect_node();
 
         assert_eq!(node, Some(("foobar:6380", 123)));
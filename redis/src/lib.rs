@@ -83,6 +83,8 @@
 //! cheap to clone and can be used safely concurrently from multiple threads, so a single connection can be easily
 //! reused. For automatic reconnections consider using `ConnectionManager` with the `connection-manager` feature.
 //! Async cluster connections also don't require pooling and are thread-safe and reusable.
+//! If a single socket becomes the bottleneck, `aio::MultiplexedPool` spreads requests over several
+//! multiplexed connections.
 //!
 //! ## Optional Features
 //!
@@ -103,7 +105,10 @@
 //! * `connection-manager`: enables support for automatic reconnection (optional)
 //! * `rust_decimal`, `bigdecimal`, `num-bigint`: enables type conversions to large number representation from different crates (optional)
 //! * `uuid`: enables type conversion to UUID (optional)
+//! * `bytes`: enables conversion to `bytes::Bytes` and borrowed reply decoding with `Reply`/`ValueRef` (optional, enabled by the async features)
 //! * `sentinel`: enables high-level interfaces for communication with Redis sentinels (optional)
+//! * `sharded-aio`: enables an async client that shards keys across standalone servers using consistent hashing (optional)
+//! * `replicated-aio`: enables an async client that sends reads to the replicas of a standalone primary (optional)
 //! * `json`: enables high-level interfaces for communication with the JSON module (optional)
 //! * `cache-aio`: enables **experimental** client side caching for MultiplexedConnection, ConnectionManager and async ClusterConnection (optional)
 //! * `disable-client-setinfo`: disables the `CLIENT SETINFO` handshake during connection initialization
@@ -637,7 +642,7 @@ pub use crate::client::AsyncConnectionConfig;
 pub use crate::client::Client;
 #[cfg(feature = "cache-aio")]
 pub use crate::cmd::CommandCacheConfig;
-pub use crate::cmd::{cmd, pack_command, pipe, Arg, Cmd, Iter};
+pub use crate::cmd::{cmd, pack_command, pipe, Arg, Cmd, CommandIdempotency, Iter};
 pub use crate::commands::{
     Commands, ControlFlow, CopyOptions, Direction, FlushAllOptions, FlushDbOptions,
     HashFieldExpirationOptions, LposOptions, PubSubCommands, ScanOptions, SetOptions,
@@ -674,8 +679,10 @@ pub use crate::types::{
     ExpireOption,
     Role,
     ReplicaInfo,
+    ServerInfo,
     IntegerReplyOrNoOp,
 	ValueType,
+    WithAttributes,
     RedisResult,
     RedisWrite,
     ToRedisArgs,
@@ -688,6 +695,13 @@ pub use crate::types::{
     ProtocolVersion,
     PushInfo,
 };
+#[cfg(feature = "bytes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+pub use crate::{
+    parser::parse_redis_value_ref,
+    value_ref::{FromRedisValueRef, Reply, ValueRef},
+};
+
 pub use crate::errors::{
     make_extension_error, ErrorKind, ParsingError, RedisError, RetryMethod, ServerError,
     ServerErrorKind,
@@ -697,7 +711,8 @@ pub use crate::errors::{
 #[cfg_attr(docsrs, doc(cfg(feature = "aio")))]
 pub use crate::{
     cmd::AsyncIter, commands::AsyncCommands, commands::AsyncTypedCommands,
-    parser::parse_redis_value_async, types::RedisFuture,
+    parser::parse_redis_value_async, parser::AsyncValueDecoder, parser::ReplyCodec,
+    parser::ServerCodec, types::RedisFuture,
 };
 
 mod macros;
@@ -730,6 +745,40 @@ pub use commands::geo;
 #[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
 mod subscription_tracker;
 
+#[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
+mod circuit_breaker;
+
+#[cfg(any(feature = "connection-manager", feature = "cluster-async"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "connection-manager", feature = "cluster-async")))
+)]
+pub use crate::circuit_breaker::{CircuitBreakerConfig, CircuitState};
+
+#[cfg(any(
+    feature = "connection-manager",
+    feature = "cluster",
+    feature = "sentinel"
+))]
+mod retry_policy;
+
+#[cfg(any(
+    feature = "connection-manager",
+    feature = "cluster",
+    feature = "sentinel"
+))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        feature = "connection-manager",
+        feature = "cluster",
+        feature = "sentinel"
+    )))
+)]
+pub use crate::retry_policy::{
+    ExponentialBackoff, RetryContext, RetryDecision, RetryPolicy, RetryRequest,
+};
+
 #[cfg(feature = "cluster")]
 mod cluster_handling;
 
@@ -762,6 +811,14 @@ pub use cluster_handling::async_connection as cluster_async;
 #[cfg_attr(docsrs, doc(cfg(feature = "sentinel")))]
 pub mod sentinel;
 
+#[cfg(feature = "sharded-aio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sharded-aio")))]
+pub mod sharded;
+
+#[cfg(feature = "replicated-aio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "replicated-aio")))]
+pub mod replicated;
+
 #[cfg(feature = "tls-rustls")]
 mod tls;
 
@@ -783,6 +840,8 @@ pub mod io;
 mod parser;
 mod script;
 mod types;
+#[cfg(feature = "bytes")]
+mod value_ref;
 
 macro_rules! check_resp3 {
     ($protocol: expr) => {
@@ -1,385 +1,64 @@
-use std::{
-    io::{self, Read},
-    str,
-};
-
-use crate::errors::{ParsingError, RedisError, Repr, ServerError, ServerErrorKind};
-use crate::types::{PushKind, RedisResult, Value, VerbatimFormat};
-
-use combine::{
-    any,
-    error::StreamError,
-    opaque,
-    parser::{
-        byte::{crlf, take_until_bytes},
-        combinator::{any_send_sync_partial_state, AnySendSyncPartialState},
-        range::{recognize, take},
-    },
-    stream::{
-        decoder::{self, Decoder},
-        PointerOffset, RangeStream, StreamErrorFor,
-    },
-    unexpected_any, ParseError, Parser as _,
-};
-
-const MAX_RECURSE_DEPTH: usize = 100;
-
-fn err_parser(line: &str) -> ServerError {
-    let mut pieces = line.splitn(2, ' ');
-    let kind = match pieces.next().unwrap() {
-        "ERR" => ServerErrorKind::ResponseError,
-        "EXECABORT" => ServerErrorKind::ExecAbort,
-        "LOADING" => ServerErrorKind::BusyLoading,
-        "NOSCRIPT" => ServerErrorKind::NoScript,
-        "MOVED" => ServerErrorKind::Moved,
-        "ASK" => ServerErrorKind::Ask,
-        "TRYAGAIN" => ServerErrorKind::TryAgain,
-        "CLUSTERDOWN" => ServerErrorKind::ClusterDown,
-        "CROSSSLOT" => ServerErrorKind::CrossSlot,
-        "MASTERDOWN" => ServerErrorKind::MasterDown,
-        "READONLY" => ServerErrorKind::ReadOnly,
-        "NOTBUSY" => ServerErrorKind::NotBusy,
-        "NOSUB" => ServerErrorKind::NoSub,
-        "NOPERM" => ServerErrorKind::NoPerm,
-        code => {
-            return ServerError(Repr::Extension {
-                code: code.into(),
-                detail: pieces.next().map(|str| str.into()),
-            })
-        }
-    };
-    let detail = pieces.next().map(|str| str.into());
-    ServerError(Repr::Known { kind, detail })
-}
+use std::io::Read;
 
-pub fn get_push_kind(kind: String) -> PushKind {
-    match kind.as_str() {
-        "invalidate" => PushKind::Invalidate,
-        "message" => PushKind::Message,
-        "pmessage" => PushKind::PMessage,
-        "smessage" => PushKind::SMessage,
-        "unsubscribe" => PushKind::Unsubscribe,
-        "punsubscribe" => PushKind::PUnsubscribe,
-        "sunsubscribe" => PushKind::SUnsubscribe,
-        "subscribe" => PushKind::Subscribe,
-        "psubscribe" => PushKind::PSubscribe,
-        "ssubscribe" => PushKind::SSubscribe,
-        _ => PushKind::Other(kind),
-    }
-}
+use crate::types::{RedisResult, Value};
+#[cfg(feature = "bytes")]
+use crate::value_ref::ValueRef;
+#[cfg(feature = "aio")]
+use redis_resp::ValueDecoder;
 
-fn value<'a, I>(
-    count: Option<usize>,
-) -> impl combine::Parser<I, Output = Value, PartialState = AnySendSyncPartialState>
-where
-    I: RangeStream<Token = u8, Range = &'a [u8]>,
-    I::Error: combine::ParseError<u8, &'a [u8], I::Position>,
-{
-    let count = count.unwrap_or(1);
-
-    opaque!(any_send_sync_partial_state(
-        any()
-            .then_partial(move |&mut b| {
-                if count > MAX_RECURSE_DEPTH {
-                    combine::unexpected_any("Maximum recursion depth exceeded").left()
-                } else {
-                    combine::value(b).right()
-                }
-            })
-            .then_partial(move |&mut b| {
-                let line = || {
-                    recognize(take_until_bytes(&b"\r\n"[..]).with(take(2).map(|_| ()))).and_then(
-                        |line: &[u8]| {
-                            str::from_utf8(&line[..line.len() - 2])
-                                .map_err(StreamErrorFor::<I>::other)
-                        },
-                    )
-                };
-
-                let simple_string = || {
-                    line().map(|line| {
-                        if line == "OK" {
-                            Value::Okay
-                        } else {
-                            Value::SimpleString(line.into())
-                        }
-                    })
-                };
-
-                let int = || {
-                    line().and_then(|line| {
-                        line.trim().parse::<i64>().map_err(|_| {
-                            StreamErrorFor::<I>::message_static_message(
-                                "Expected integer, got garbage",
-                            )
-                        })
-                    })
-                };
-
-                let bulk_string = || {
-                    int().then_partial(move |size| {
-                        if *size < 0 {
-                            combine::produce(|| Value::Nil).left()
-                        } else {
-                            take(*size as usize)
-                                .map(|bs: &[u8]| Value::BulkString(bs.to_vec()))
-                                .skip(crlf())
-                                .right()
-                        }
-                    })
-                };
-                let blob = || {
-                    int().then_partial(move |size| {
-                        take(*size as usize)
-                            .map(|bs: &[u8]| String::from_utf8_lossy(bs).to_string())
-                            .skip(crlf())
-                    })
-                };
-
-                let array = || {
-                    int().then_partial(move |&mut length| {
-                        if length < 0 {
-                            combine::produce(|| Value::Nil).left()
-                        } else {
-                            let length = length as usize;
-                            combine::count_min_max(length, length, value(Some(count + 1)))
-                                .map(Value::Array)
-                                .right()
-                        }
-                    })
-                };
-
-                let error = || line().map(err_parser);
-                let map = || {
-                    int().then_partial(move |&mut kv_length| {
-                        match (kv_length as usize).checked_mul(2) {
-                            Some(length) => {
-                                combine::count_min_max(length, length, value(Some(count + 1)))
-                                    .map(move |result: Vec<Value>| {
-                                        let mut it = result.into_iter();
-                                        let mut x = vec![];
-                                        for _ in 0..kv_length {
-                                            if let (Some(k), Some(v)) = (it.next(), it.next()) {
-                                                x.push((k, v))
-                                            }
-                                        }
-                                        Value::Map(x)
-                                    })
-                                    .left()
-                            }
-                            None => {
-                                unexpected_any("Attribute key-value length is too large").right()
-                            }
-                        }
-                    })
-                };
-                let attribute = || {
-                    int().then_partial(move |&mut kv_length| {
-                        match (kv_length as usize).checked_mul(2) {
-                            Some(length) => {
-                                // + 1 is for data!
-                                let length = length + 1;
-                                combine::count_min_max(length, length, value(Some(count + 1)))
-                                    .map(move |result: Vec<Value>| {
-                                        let mut it = result.into_iter();
-                                        let mut attributes = vec![];
-                                        for _ in 0..kv_length {
-                                            if let (Some(k), Some(v)) = (it.next(), it.next()) {
-                                                attributes.push((k, v))
-                                            }
-                                        }
-                                        Value::Attribute {
-                                            data: Box::new(it.next().unwrap()),
-                                            attributes,
-                                        }
-                                    })
-                                    .left()
-                            }
-                            None => {
-                                unexpected_any("Attribute key-value length is too large").right()
-                            }
-                        }
-                    })
-                };
-                let set = || {
-                    int().then_partial(move |&mut length| {
-                        if length < 0 {
-                            combine::produce(|| Value::Nil).left()
-                        } else {
-                            let length = length as usize;
-                            combine::count_min_max(length, length, value(Some(count + 1)))
-                                .map(Value::Set)
-                                .right()
-                        }
-                    })
-                };
-                let push = || {
-                    int().then_partial(move |&mut length| {
-                        if length <= 0 {
-                            combine::produce(|| Value::Push {
-                                kind: PushKind::Other("".to_string()),
-                                data: vec![],
-                            })
-                            .left()
-                        } else {
-                            let length = length as usize;
-                            combine::count_min_max(length, length, value(Some(count + 1)))
-                                .and_then(|result: Vec<Value>| {
-                                    let mut it = result.into_iter();
-                                    let first = it.next().unwrap_or(Value::Nil);
-                                    if let Value::BulkString(kind) = first {
-                                        let push_kind = String::from_utf8(kind)
-                                            .map_err(StreamErrorFor::<I>::other)?;
-                                        Ok(Value::Push {
-                                            kind: get_push_kind(push_kind),
-                                            data: it.collect(),
-                                        })
-                                    } else if let Value::SimpleString(kind) = first {
-                                        Ok(Value::Push {
-                                            kind: get_push_kind(kind),
-                                            data: it.collect(),
-                                        })
-                                    } else {
-                                        Err(StreamErrorFor::<I>::message_static_message(
-                                            "parse error when decoding push",
-                                        ))
-                                    }
-                                })
-                                .right()
-                        }
-                    })
-                };
-                let null = || line().map(|_| Value::Nil);
-                let double = || {
-                    line().and_then(|line| {
-                        line.trim()
-                            .parse::<f64>()
-                            .map_err(StreamErrorFor::<I>::other)
-                    })
-                };
-                let boolean = || {
-                    line().and_then(|line: &str| match line {
-                        "t" => Ok(true),
-                        "f" => Ok(false),
-                        _ => Err(StreamErrorFor::<I>::message_static_message(
-                            "Expected boolean, got garbage",
-                        )),
-                    })
-                };
-                let blob_error = || blob().map(|line| err_parser(&line));
-                let verbatim = || {
-                    blob().and_then(|line| {
-                        if let Some((format, text)) = line.split_once(':') {
-                            let format = match format {
-                                "txt" => VerbatimFormat::Text,
-                                "mkd" => VerbatimFormat::Markdown,
-                                x => VerbatimFormat::Unknown(x.to_string()),
-                            };
-                            Ok(Value::VerbatimString {
-                                format,
-                                text: text.to_string(),
-                            })
-                        } else {
-                            Err(StreamErrorFor::<I>::message_static_message(
-                                "parse error when decoding verbatim string",
-                            ))
-                        }
-                    })
-                };
-                let big_number = || {
-                    line().and_then(|line| {
-                        #[cfg(not(feature = "num-bigint"))]
-                        return Ok::<_, StreamErrorFor<I>>(Value::BigNumber(
-                            line.as_bytes().to_vec(),
-                        ));
-                        #[cfg(feature = "num-bigint")]
-                        num_bigint::BigInt::parse_bytes(line.as_bytes(), 10)
-                            .ok_or_else(|| {
-                                StreamErrorFor::<I>::message_static_message(
-                                    "Expected bigint, got garbage",
-                                )
-                            })
-                            .map(Value::BigNumber)
-                    })
-                };
-                combine::dispatch!(b;
-                    b'+' => simple_string(),
-                    b':' => int().map(Value::Int),
-                    b'$' => bulk_string(),
-                    b'*' => array(),
-                    b'%' => map(),
-                    b'|' => attribute(),
-                    b'~' => set(),
-                    b'-' => error().map(Value::ServerError),
-                    b'_' => null(),
-                    b',' => double().map(Value::Double),
-                    b'#' => boolean().map(Value::Boolean),
-                    b'!' => blob_error().map(Value::ServerError),
-                    b'=' => verbatim(),
-                    b'(' => big_number(),
-                    b'>' => push(),
-                    b => combine::unexpected_any(combine::error::Token(b))
-                )
-            })
-    ))
+/// Returns the length of the first reply in `bytes`, or `None` if `bytes`
+/// doesn't hold a complete reply yet.
+#[cfg(feature = "bytes")]
+pub(crate) fn reply_len(bytes: &[u8]) -> RedisResult<Option<usize>> {
+    Ok(redis_resp::reply_len(bytes)?)
 }
 
-// a macro is needed because of lifetime shenanigans with `decoder`.
-macro_rules! to_redis_err {
-    ($err: expr, $decoder: expr) => {
-        match $err {
-            decoder::Error::Io { error, .. } => error.into(),
-            decoder::Error::Parse(err) => {
-                if err.is_unexpected_end_of_input() {
-                    RedisError::from(io::Error::from(io::ErrorKind::UnexpectedEof))
-                } else {
-                    let err = err
-                        .map_range(|range| format!("{range:?}"))
-                        .map_position(|pos| pos.translate_position($decoder.buffer()))
-                        .to_string();
-                    RedisError::from(ParsingError::from(err))
-                }
-            }
-        }
-    };
+/// Parses bytes into a redis value that borrows its strings from `bytes`.
+///
+/// Unlike [`parse_redis_value`], string data isn't copied, which makes this
+/// the cheaper option when `bytes` already holds a complete reply.
+#[cfg(feature = "bytes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+pub fn parse_redis_value_ref(bytes: &[u8]) -> RedisResult<ValueRef<'_>> {
+    Ok(redis_resp::parse_value_ref(bytes)?)
 }
 
 #[cfg(feature = "aio")]
 mod aio_support {
     use super::*;
 
-    use bytes::{Buf, BytesMut};
+    use crate::cmd::Cmd;
+    use crate::errors::{ParsingError, RedisError};
+    use crate::types::ProtocolVersion;
+    use crate::value_ref::Reply;
+    use bytes::{Buf, BufMut, Bytes, BytesMut};
+    use std::sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    };
+    use std::{io, str};
     use tokio::io::AsyncRead;
     use tokio_util::codec::{Decoder, Encoder};
 
     #[derive(Default)]
     pub struct ValueCodec {
-        state: AnySendSyncPartialState,
+        decoder: ValueDecoder,
     }
 
     impl ValueCodec {
         fn decode_stream(&mut self, bytes: &mut BytesMut, eof: bool) -> RedisResult<Option<Value>> {
-            let (opt, removed_len) = {
-                let buffer = &bytes[..];
-                let mut stream =
-                    combine::easy::Stream(combine::stream::MaybePartialStream(buffer, !eof));
-                match combine::stream::decode_tokio(value(None), &mut stream, &mut self.state) {
-                    Ok(x) => x,
-                    Err(err) => {
-                        let err = err
-                            .map_position(|pos| pos.translate_position(buffer))
-                            .map_range(|range| format!("{range:?}"))
-                            .to_string();
-                        return Err(RedisError::from(ParsingError::from(err)));
-                    }
-                }
-            };
-
-            bytes.advance(removed_len);
-            match opt {
-                Some(result) => Ok(Some(result)),
-                None => Ok(None),
+            let (consumed, value) = self.decoder.decode(bytes)?;
+            bytes.advance(consumed);
+            if value.is_none() && eof && (!bytes.is_empty() || self.decoder.in_progress()) {
+                self.decoder = ValueDecoder::default();
+                fail!(ParsingError::from("Unexpected end of input".to_string()));
             }
+            Ok(value)
+        }
+
+        /// Returns true if a value was partially decoded.
+        pub(crate) fn in_progress(&self) -> bool {
+            self.decoder.in_progress()
         }
     }
 
@@ -404,20 +83,335 @@ mod aio_support {
         }
     }
 
+    /// How [`StreamingValueCodec`] decodes the next reply, as requested by the
+    /// request that the reply answers.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum NextReply {
+        /// The reply is decoded into a [`Value`].
+        Value = 0,
+        /// If the reply is a bulk string, it's streamed in chunks.
+        Streamed = 1,
+        /// The reply is split off the read buffer as a [`Reply`], without being decoded.
+        Raw = 2,
+    }
+
+    /// The [`NextReply`] mode that is shared between a connection's codec and its driver.
+    #[derive(Debug, Default)]
+    pub(crate) struct NextReplyMode(AtomicU8);
+
+    impl NextReplyMode {
+        pub(crate) fn set(&self, mode: NextReply) {
+            self.0.store(mode as u8, Ordering::Relaxed);
+        }
+
+        pub(crate) fn get(&self) -> NextReply {
+            match self.0.load(Ordering::Relaxed) {
+                1 => NextReply::Streamed,
+                2 => NextReply::Raw,
+                _ => NextReply::Value,
+            }
+        }
+    }
+
+    /// A frame decoded by [`StreamingValueCodec`].
+    #[derive(Debug, PartialEq)]
+    pub(crate) enum ReplyFrame {
+        /// A complete value.
+        Value(Value),
+        /// A complete reply that wasn't decoded.
+        Raw(Reply),
+        /// The header of a streamed bulk string, with the string's length.
+        BulkStart(usize),
+        /// The next part of a streamed bulk string.
+        BulkChunk(Bytes),
+        /// The end of a streamed bulk string.
+        BulkEnd,
+    }
+
+    /// Wraps a [`ValueCodec`], streaming the next reply's data in chunks instead
+    /// of buffering it, if that reply is a bulk string and the next reply is
+    /// [`NextReply::Streamed`], or splitting the next reply off with a
+    /// [`ReplyCodec`] if the next reply is [`NextReply::Raw`].
+    pub(crate) struct StreamingValueCodec {
+        inner: ValueCodec,
+        replies: ReplyCodec,
+        next_reply: Arc<NextReplyMode>,
+        // the number of bytes left in the bulk string that is currently streamed.
+        streaming: Option<usize>,
+    }
+
+    impl StreamingValueCodec {
+        pub(crate) fn new(inner: ValueCodec, next_reply: Arc<NextReplyMode>) -> Self {
+            StreamingValueCodec {
+                inner,
+                replies: ReplyCodec::default(),
+                next_reply,
+                streaming: None,
+            }
+        }
+
+        fn decode_chunk(
+            &mut self,
+            bytes: &mut BytesMut,
+            remaining: usize,
+        ) -> RedisResult<Option<ReplyFrame>> {
+            if remaining > 0 {
+                if bytes.is_empty() {
+                    return Ok(None);
+                }
+                let len = remaining.min(bytes.len());
+                self.streaming = Some(remaining - len);
+                return Ok(Some(ReplyFrame::BulkChunk(bytes.split_to(len).freeze())));
+            }
+            if bytes.len() < 2 {
+                return Ok(None);
+            }
+            if &bytes[..2] != b"\r\n" {
+                fail!(ParsingError::from(
+                    "Expected CRLF after bulk string".to_string()
+                ));
+            }
+            bytes.advance(2);
+            self.streaming = None;
+            Ok(Some(ReplyFrame::BulkEnd))
+        }
+
+        /// Returns the length of the bulk string header at the start of the
+        /// buffer and the string's length, if the header is complete.
+        fn bulk_header(bytes: &[u8]) -> RedisResult<Option<(usize, i64)>> {
+            let Some(end) = bytes.windows(2).position(|window| window == b"\r\n") else {
+                return Ok(None);
+            };
+            let length = str::from_utf8(&bytes[1..end])
+                .ok()
+                .and_then(|length| length.trim().parse::<i64>().ok())
+                .ok_or_else(|| ParsingError::from("Expected integer, got garbage".to_string()))?;
+            Ok(Some((end + 2, length)))
+        }
+
+        fn decode_frame(
+            &mut self,
+            bytes: &mut BytesMut,
+            eof: bool,
+        ) -> RedisResult<Option<ReplyFrame>> {
+            if let Some(remaining) = self.streaming {
+                return self.decode_chunk(bytes, remaining);
+            }
+            let next_reply = match self.inner.in_progress() {
+                true => NextReply::Value,
+                false => self.next_reply.get(),
+            };
+            // pushes aren't replies to the request, and are always decoded.
+            if next_reply == NextReply::Raw && !bytes.is_empty() && bytes[0] != b'>' {
+                match self.replies.decode(bytes)? {
+                    Some(reply) => return Ok(Some(ReplyFrame::Raw(reply))),
+                    // an incomplete reply is reported by the inner codec.
+                    None if eof => {}
+                    None => return Ok(None),
+                }
+            }
+            if next_reply == NextReply::Streamed && bytes.first() == Some(&b'$') {
+                match Self::bulk_header(bytes)? {
+                    Some((header_len, length)) if length >= 0 => {
+                        bytes.advance(header_len);
+                        self.streaming = Some(length as usize);
+                        return Ok(Some(ReplyFrame::BulkStart(length as usize)));
+                    }
+                    // nil is decoded by the inner codec as usual.
+                    Some(_) => {}
+                    None if eof => {}
+                    None => return Ok(None),
+                }
+            }
+
+            let value = self.inner.decode_stream(bytes, eof)?;
+            Ok(value.map(ReplyFrame::Value))
+        }
+    }
+
+    impl Encoder<Vec<u8>> for StreamingValueCodec {
+        type Error = RedisError;
+        fn encode(&mut self, item: Vec<u8>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+            self.inner.encode(item, dst)
+        }
+    }
+
+    impl Decoder for StreamingValueCodec {
+        type Item = ReplyFrame;
+        type Error = RedisError;
+
+        fn decode(&mut self, bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            self.decode_frame(bytes, false)
+        }
+
+        fn decode_eof(&mut self, bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            self.decode_frame(bytes, true)
+        }
+    }
+
+    /// A codec that splits complete replies off the read buffer as [`Reply`]s.
+    ///
+    /// The reply's bytes are handed over without being copied, and are only
+    /// decoded when the reply is parsed, see [`FromRedisValueRef`](crate::FromRedisValueRef).
+    /// Multiplexed connections use it for the replies to
+    /// [`send_packed_command_reply`](crate::aio::MultiplexedConnection::send_packed_command_reply).
+    #[derive(Default)]
+    pub struct ReplyCodec {
+        _private: (),
+    }
+
+    impl Encoder<Vec<u8>> for ReplyCodec {
+        type Error = RedisError;
+        fn encode(&mut self, item: Vec<u8>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+            dst.extend_from_slice(item.as_ref());
+            Ok(())
+        }
+    }
+
+    impl Decoder for ReplyCodec {
+        type Item = Reply;
+        type Error = RedisError;
+
+        fn decode(&mut self, bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            Ok(reply_len(bytes)?.map(|len| Reply::new(bytes.split_to(len).freeze())))
+        }
+    }
+
+    /// A codec for the server side of a connection, for writing proxies and
+    /// fake servers.
+    ///
+    /// Commands that clients send, either as arrays of bulk strings or as inline
+    /// commands, are decoded into [`Cmd`]s, and empty commands are skipped.
+    /// Replies are encoded in the format of the codec's protocol version, so
+    /// RESP3 types are downgraded to their RESP2 counterparts until the client
+    /// switches to RESP3, see [`ServerCodec::set_protocol`].
+    ///
+    /// ```rust,no_run
+    /// # async fn run<S>(stream: S) -> redis::RedisResult<()>
+    /// # where S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin {
+    /// use futures::{SinkExt, StreamExt};
+    /// use redis::{Arg, ServerCodec, ServerError, Value};
+    /// use tokio_util::codec::Framed;
+    ///
+    /// let mut framed = Framed::new(stream, ServerCodec::default());
+    /// while let Some(cmd) = framed.next().await {
+    ///     let reply = match cmd?.args_iter().next() {
+    ///         Some(Arg::Simple(name)) if name.eq_ignore_ascii_case(b"PING") => {
+    ///             Value::SimpleString("PONG".into())
+    ///         }
+    ///         _ => Value::ServerError(ServerError::extension("ERR", Some("unknown command"))),
+    ///     };
+    ///     framed.send(reply).await?;
+    /// }
+    /// # Ok(()) }
+    /// ```
+    #[derive(Default)]
+    pub struct ServerCodec {
+        protocol: ProtocolVersion,
+        decoder: redis_resp::CommandDecoder,
+    }
+
+    impl ServerCodec {
+        /// Creates a codec that encodes replies for the given protocol version.
+        pub fn new(protocol: ProtocolVersion) -> Self {
+            ServerCodec {
+                protocol,
+                decoder: Default::default(),
+            }
+        }
+
+        /// Returns the protocol version that replies are encoded for.
+        pub fn protocol(&self) -> ProtocolVersion {
+            self.protocol
+        }
+
+        /// Sets the protocol version that replies are encoded for, for example
+        /// after the client sent `HELLO 3`.
+        pub fn set_protocol(&mut self, protocol: ProtocolVersion) {
+            self.protocol = protocol;
+        }
+    }
+
+    impl Encoder<Value> for ServerCodec {
+        type Error = RedisError;
+        fn encode(&mut self, item: Value, dst: &mut BytesMut) -> Result<(), Self::Error> {
+            self.encode(&item, dst)
+        }
+    }
+
+    impl Encoder<&Value> for ServerCodec {
+        type Error = RedisError;
+        fn encode(&mut self, item: &Value, dst: &mut BytesMut) -> Result<(), Self::Error> {
+            redis_resp::write_value(&mut dst.writer(), item, self.protocol)?;
+            Ok(())
+        }
+    }
+
+    impl Decoder for ServerCodec {
+        type Item = Cmd;
+        type Error = RedisError;
+
+        fn decode(&mut self, bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+            while let Some((len, args)) = self.decoder.decode(bytes)? {
+                let cmd = (!args.is_empty()).then(|| {
+                    let mut cmd =
+                        Cmd::with_capacity(args.len(), args.iter().map(|arg| arg.len()).sum());
+                    for arg in &args {
+                        cmd.arg(&arg[..]);
+                    }
+                    cmd
+                });
+                bytes.advance(len);
+                if cmd.is_some() {
+                    return Ok(cmd);
+                }
+            }
+            Ok(None)
+        }
+    }
+
+    /// The state that [`parse_redis_value_async`] keeps between calls.
+    #[derive(Default)]
+    pub struct AsyncValueDecoder {
+        codec: ValueCodec,
+        buffer: BytesMut,
+    }
+
+    impl AsyncValueDecoder {
+        /// Creates a decoder with an empty buffer.
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
     /// Parses a redis value asynchronously.
+    ///
+    /// `decoder` buffers the data that was read, so that data following the
+    /// value is kept for the next call, and keeps the parts of the value that
+    /// were already parsed, so that only new data is parsed after each read.
     pub async fn parse_redis_value_async<R>(
-        decoder: &mut combine::stream::Decoder<AnySendSyncPartialState, PointerOffset<[u8]>>,
+        decoder: &mut AsyncValueDecoder,
         read: &mut R,
     ) -> RedisResult<Value>
     where
         R: AsyncRead + std::marker::Unpin,
     {
-        let result = combine::decode_tokio!(*decoder, *read, value(None), |input, _| {
-            combine::stream::easy::Stream::from(input)
-        });
-        match result {
-            Err(err) => Err(to_redis_err!(err, decoder)),
-            Ok(result) => Ok(result),
+        loop {
+            if let Some(value) = decoder.codec.decode_stream(&mut decoder.buffer, false)? {
+                return Ok(value);
+            }
+            decoder.buffer.reserve(8 * 1024);
+            let read = std::future::poll_fn(|cx| {
+                tokio_util::io::poll_read_buf(
+                    std::pin::Pin::new(&mut *read),
+                    cx,
+                    &mut decoder.buffer,
+                )
+            })
+            .await?;
+            if read == 0 {
+                fail!(io::Error::from(io::ErrorKind::UnexpectedEof));
+            }
         }
     }
 }
@@ -427,14 +421,9 @@ mod aio_support {
 pub use self::aio_support::*;
 
 /// The internal redis response parser.
+#[derive(Default)]
 pub struct Parser {
-    decoder: Decoder<AnySendSyncPartialState, PointerOffset<[u8]>>,
-}
-
-impl Default for Parser {
-    fn default() -> Self {
-        Parser::new()
-    }
+    inner: redis_resp::Parser,
 }
 
 /// The parser can be used to parse redis responses into values.  Generally
@@ -447,23 +436,14 @@ impl Parser {
     /// be invoked multiple times.  In other words: the stream does not have
     /// to be terminated.
     pub fn new() -> Parser {
-        Parser {
-            decoder: Decoder::new(),
-        }
+        Parser::default()
     }
 
     // public api
 
     /// Parses synchronously into a single value from the reader.
-    pub fn parse_value<T: Read>(&mut self, mut reader: T) -> RedisResult<Value> {
-        let mut decoder = &mut self.decoder;
-        let result = combine::decode!(decoder, reader, value(None), |input, _| {
-            combine::stream::easy::Stream::from(input)
-        });
-        match result {
-            Err(err) => Err(to_redis_err!(err, decoder)),
-            Ok(result) => Ok(result),
-        }
+    pub fn parse_value<T: Read>(&mut self, reader: T) -> RedisResult<Value> {
+        Ok(self.inner.parse_value(reader)?)
     }
 }
 
@@ -472,14 +452,18 @@ impl Parser {
 /// This is the most straightforward way to parse something into a low
 /// level redis value instead of having to use a whole parser.
 pub fn parse_redis_value(bytes: &[u8]) -> RedisResult<Value> {
-    let mut parser = Parser::new();
-    parser.parse_value(bytes)
+    Ok(redis_resp::parse_value(bytes)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(any(feature = "aio", feature = "bytes"))]
     use crate::errors::ErrorKind;
+    use crate::errors::{ServerError, ServerErrorKind};
+    #[cfg(feature = "aio")]
+    use crate::types::ProtocolVersion;
+    use crate::types::PushKind;
 
     #[cfg(feature = "aio")]
     #[test]
@@ -510,10 +494,10 @@ mod tests {
             result,
             Value::Array(vec![
                 Value::Okay,
-                Value::ServerError(ServerError(Repr::Known {
-                    kind: ServerErrorKind::BusyLoading,
-                    detail: Some(arcstr::literal!("server is loading"))
-                })),
+                Value::ServerError(ServerError::new(
+                    ServerErrorKind::BusyLoading,
+                    Some("server is loading")
+                )),
                 Value::Okay
             ])
         );
@@ -524,6 +508,267 @@ mod tests {
         assert_eq!(result, Value::Okay);
     }
 
+    #[cfg(feature = "bytes")]
+    const REPLIES: &[&[u8]] = &[
+        b"+OK\r\n",
+        b"+GET 123\r\n",
+        b":-42\r\n",
+        b"$5\r\nhe\r\no\r\n",
+        b"$0\r\n\r\n",
+        b"$-1\r\n",
+        b"*-1\r\n",
+        b"*3\r\n+OK\r\n-LOADING server is loading\r\n+OK\r\n",
+        b"%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n",
+        b"|1\r\n+key-popularity\r\n,0.1923\r\n$3\r\nfoo\r\n",
+        b"~3\r\n+orange\r\n#t\r\n_\r\n",
+        b"!21\r\nSYNTAX invalid syntax\r\n",
+        b"=15\r\ntxt:Some string\r\n",
+        b"(3492890328409238509324850943850943825024385\r\n",
+        b">3\r\n+message\r\n+somechannel\r\n+this is the message\r\n",
+        b">0\r\n",
+    ];
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn parse_value_ref_matches_parse_value() {
+        for reply in REPLIES {
+            let expected = parse_redis_value(reply).unwrap();
+            assert_eq!(parse_redis_value_ref(reply).unwrap().to_value(), expected);
+            assert_eq!(reply_len(reply).unwrap(), Some(reply.len()));
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn reply_len_waits_for_complete_reply() {
+        for reply in REPLIES {
+            for len in 0..reply.len() {
+                assert_eq!(reply_len(&reply[..len]).unwrap(), None);
+                let err = parse_redis_value_ref(&reply[..len]).unwrap_err();
+                assert_eq!(err.kind(), ErrorKind::Io);
+            }
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn parse_value_ref_rejects_invalid_replies() {
+        for reply in [
+            &b"$3\r\nfoobar\r\n"[..],
+            b":abc\r\n",
+            b"#x\r\n",
+            b"?\r\n",
+            b"=3\r\ntxt\r\n",
+        ] {
+            assert!(parse_redis_value(reply).is_err());
+            assert_eq!(
+                parse_redis_value_ref(reply).unwrap_err().kind(),
+                ErrorKind::Parse
+            );
+            assert!(reply_len(reply).is_err());
+        }
+    }
+
+    #[cfg(feature = "aio")]
+    #[test]
+    fn reply_codec_splits_replies_without_copying() {
+        use tokio_util::codec::Decoder;
+        let mut codec = ReplyCodec::default();
+
+        let mut bytes = bytes::BytesMut::from(&b"$3\r\nfoo\r\n*2\r\n:1\r\n"[..]);
+        let start = bytes.as_ptr();
+        let reply = codec.decode(&mut bytes).unwrap().unwrap();
+        assert_eq!(reply.as_bytes().as_ptr(), start);
+        assert_eq!(reply.parse::<&str>().unwrap(), "foo");
+
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+        bytes.extend_from_slice(b":2\r\n");
+        let reply = codec.decode(&mut bytes).unwrap().unwrap();
+        assert_eq!(reply.parse::<(i64, i64)>().unwrap(), (1, 2));
+        assert!(bytes.is_empty());
+    }
+
+    #[cfg(feature = "aio")]
+    #[test]
+    fn server_codec_decodes_commands() {
+        use tokio_util::codec::Decoder;
+        let mut codec = ServerCodec::default();
+
+        let mut bytes =
+            bytes::BytesMut::from(&b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n\r\n*0\r\nSET k"[..]);
+        let cmd = codec.decode(&mut bytes).unwrap().unwrap();
+        assert_eq!(
+            cmd.get_packed_command(),
+            crate::cmd("GET").arg("key").get_packed_command()
+        );
+        assert_eq!(codec.decode(&mut bytes).unwrap().map(|_| ()), None);
+        assert_eq!(&bytes[..], b"SET k");
+
+        bytes.extend_from_slice(b"ey \"a value\"\r\n");
+        let cmd = codec.decode(&mut bytes).unwrap().unwrap();
+        assert_eq!(
+            cmd.get_packed_command(),
+            crate::cmd("SET")
+                .arg("key")
+                .arg("a value")
+                .get_packed_command()
+        );
+        assert!(bytes.is_empty());
+
+        // a command that arrives in pieces is decoded once it's complete.
+        let packed = crate::cmd("SET")
+            .arg("key")
+            .arg(vec![b'x'; 100])
+            .get_packed_command();
+        for chunk in packed.chunks(7) {
+            assert_eq!(codec.decode(&mut bytes).unwrap().map(|_| ()), None);
+            bytes.extend_from_slice(chunk);
+        }
+        let cmd = codec.decode(&mut bytes).unwrap().unwrap();
+        assert_eq!(cmd.get_packed_command(), packed);
+        assert!(bytes.is_empty());
+
+        let mut bytes = bytes::BytesMut::from(&b"*1\r\n:1\r\n"[..]);
+        assert!(matches!(
+            codec.decode(&mut bytes),
+            Err(err) if err.kind() == ErrorKind::Parse
+        ));
+    }
+
+    #[cfg(feature = "aio")]
+    #[test]
+    fn server_codec_encodes_replies_for_protocol() {
+        use tokio_util::codec::Encoder;
+        let reply = Value::Push {
+            kind: PushKind::Message,
+            data: vec![
+                Value::BulkString(b"channel".to_vec()),
+                Value::Set(vec![Value::Boolean(true)]),
+            ],
+        };
+
+        let mut codec = ServerCodec::default();
+        let mut bytes = bytes::BytesMut::new();
+        codec.encode(&reply, &mut bytes).unwrap();
+        assert_eq!(
+            &bytes[..],
+            b"*3\r\n$7\r\nmessage\r\n$7\r\nchannel\r\n*1\r\n:1\r\n"
+        );
+
+        codec.set_protocol(ProtocolVersion::RESP3);
+        let mut bytes = bytes::BytesMut::new();
+        codec.encode(reply.clone(), &mut bytes).unwrap();
+        assert_eq!(parse_redis_value(&bytes).unwrap(), reply);
+    }
+
+    #[cfg(feature = "aio")]
+    #[test]
+    fn streaming_codec_streams_bulk_strings_when_requested() {
+        use std::sync::Arc;
+        use tokio_util::codec::Decoder;
+
+        let next_reply = Arc::new(NextReplyMode::default());
+        next_reply.set(NextReply::Streamed);
+        let mut codec = StreamingValueCodec::new(ValueCodec::default(), next_reply.clone());
+
+        let mut bytes = bytes::BytesMut::from(&b"$1"[..]);
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+        bytes.extend_from_slice(b"0\r\n0123");
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::BulkStart(10))
+        );
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::BulkChunk("0123".into()))
+        );
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+        bytes.extend_from_slice(b"456789\r");
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::BulkChunk("456789".into()))
+        );
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+        bytes.extend_from_slice(b"\n$3\r\nfoo\r\n");
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(ReplyFrame::BulkEnd));
+
+        next_reply.set(NextReply::Value);
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::Value(Value::BulkString(b"foo".to_vec())))
+        );
+    }
+
+    #[cfg(feature = "aio")]
+    #[test]
+    fn streaming_codec_splits_off_raw_replies_when_requested() {
+        use std::sync::Arc;
+        use tokio_util::codec::Decoder;
+
+        let next_reply = Arc::new(NextReplyMode::default());
+        next_reply.set(NextReply::Raw);
+        let mut codec = StreamingValueCodec::new(ValueCodec::default(), next_reply.clone());
+
+        let mut bytes = bytes::BytesMut::from(&b"*2\r\n$3\r\nfoo"[..]);
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+        bytes.extend_from_slice(b"\r\n$-1\r\n>2\r\n+message\r\n+hi\r\n:1\r\n");
+        let Some(ReplyFrame::Raw(reply)) = codec.decode(&mut bytes).unwrap() else {
+            panic!("expected a raw reply");
+        };
+        assert_eq!(reply.as_bytes(), b"*2\r\n$3\r\nfoo\r\n$-1\r\n");
+        assert_eq!(
+            reply.parse::<Vec<Option<&str>>>().unwrap(),
+            [Some("foo"), None]
+        );
+
+        // pushes are decoded even when a raw reply was requested.
+        assert!(matches!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::Value(Value::Push { .. }))
+        ));
+        next_reply.set(NextReply::Value);
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::Value(Value::Int(1)))
+        );
+    }
+
+    #[cfg(feature = "aio")]
+    #[test]
+    fn streaming_codec_decodes_other_replies_as_values() {
+        use std::sync::Arc;
+        use tokio_util::codec::Decoder;
+
+        let next_reply = Arc::new(NextReplyMode::default());
+        next_reply.set(NextReply::Streamed);
+        let mut codec = StreamingValueCodec::new(ValueCodec::default(), next_reply);
+        let mut bytes =
+            bytes::BytesMut::from(&b"$-1\r\n-ERR wrong type\r\n>2\r\n+message\r\n+hi\r\n"[..]);
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::Value(Value::Nil))
+        );
+        assert!(matches!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::Value(Value::ServerError(_)))
+        ));
+        assert!(matches!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::Value(Value::Push { .. }))
+        ));
+
+        // a bulk string that follows a partially received value isn't streamed.
+        let mut bytes = bytes::BytesMut::from(&b"*1\r\n"[..]);
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+        bytes.extend_from_slice(b"$3\r\nfoo\r\n");
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(ReplyFrame::Value(Value::Array(vec![Value::BulkString(
+                b"foo".to_vec()
+            )])))
+        );
+    }
+
     #[test]
     fn parse_nested_error_and_handle_more_inputs() {
         // from https://redis.io/docs/interact/transactions/ -
@@ -536,10 +781,10 @@ mod tests {
             result.unwrap(),
             Value::Array(vec![
                 Value::Okay,
-                Value::ServerError(ServerError(Repr::Known {
-                    kind: ServerErrorKind::BusyLoading,
-                    detail: Some(arcstr::literal!("server is loading"))
-                })),
+                Value::ServerError(ServerError::new(
+                    ServerErrorKind::BusyLoading,
+                    Some("server is loading")
+                )),
                 Value::Okay
             ])
         );
@@ -615,10 +860,7 @@ mod tests {
         let val = parse_redis_value(b"!21\r\nSYNTAX invalid syntax\r\n");
         assert_eq!(
             val.unwrap(),
-            Value::ServerError(ServerError(Repr::Extension {
-                code: arcstr::literal!("SYNTAX"),
-                detail: Some(arcstr::literal!("invalid syntax"))
-            }))
+            Value::ServerError(ServerError::extension("SYNTAX", Some("invalid syntax")))
         )
     }
 
@@ -661,48 +903,4 @@ mod tests {
             panic!("Expected Value::Push")
         }
     }
-
-    #[test]
-    fn test_max_recursion_depth_set_and_array() {
-        for test_byte in ["*", "~"] {
-            let initial = format!("{test_byte}1\r\n").as_bytes().to_vec();
-            let end = format!("{test_byte}0\r\n").as_bytes().to_vec();
-
-            let mut ba = initial.repeat(MAX_RECURSE_DEPTH - 1).to_vec();
-            ba.extend(end.clone());
-            match parse_redis_value(&ba) {
-                Ok(Value::Array(a)) => assert_eq!(a.len(), 1),
-                Ok(Value::Set(s)) => assert_eq!(s.len(), 1),
-                _ => panic!("Expected valid array or set"),
-            }
-
-            let mut ba = initial.repeat(MAX_RECURSE_DEPTH).to_vec();
-            ba.extend(end);
-            match parse_redis_value(&ba) {
-                Ok(_) => panic!("Expected ParseError"),
-                Err(e) => assert!(matches!(e.kind(), ErrorKind::Parse)),
-            }
-        }
-    }
-
-    #[test]
-    fn test_max_recursion_depth_map() {
-        let initial = b"%1\r\n+a\r\n";
-        let end = b"%0\r\n";
-
-        let mut ba = initial.repeat(MAX_RECURSE_DEPTH - 1).to_vec();
-        ba.extend(*end);
-        match parse_redis_value(&ba) {
-            Ok(Value::Map(m)) => assert_eq!(m.len(), 1),
-            Ok(Value::Set(s)) => assert_eq!(s.len(), 1),
-            _ => panic!("Expected valid array or set"),
-        }
-
-        let mut ba = initial.repeat(MAX_RECURSE_DEPTH).to_vec();
-        ba.extend(end);
-        match parse_redis_value(&ba) {
-            Ok(_) => panic!("Expected ParseError"),
-            Err(e) => assert!(matches!(e.kind(), ErrorKind::Parse)),
-        }
-    }
 }
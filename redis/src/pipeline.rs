@@ -2,7 +2,9 @@
 
 #[cfg(feature = "cache-aio")]
 use crate::cmd::CommandCacheConfig;
-use crate::cmd::{cmd, cmd_len, Cmd};
+#[cfg(feature = "aio")]
+use crate::cmd::PackedCommand;
+use crate::cmd::{cmd, cmd_len, Cmd, ResponseTimeout};
 use crate::connection::ConnectionLike;
 use crate::errors::ErrorKind;
 use crate::types::{from_redis_value, FromRedisValue, HashSet, RedisResult, ToRedisArgs, Value};
@@ -14,6 +16,7 @@ pub struct Pipeline {
     pub(crate) transaction_mode: bool,
     pub(crate) ignored_commands: HashSet<usize>,
     pub(crate) ignore_errors: bool,
+    pub(crate) response_timeout: Option<ResponseTimeout>,
 }
 
 /// A pipeline allows you to send multiple commands in one go to the
@@ -51,6 +54,7 @@ impl Pipeline {
             transaction_mode: false,
             ignored_commands: HashSet::new(),
             ignore_errors: false,
+            response_timeout: None,
         }
     }
 
@@ -123,6 +127,43 @@ impl Pipeline {
         self
     }
 
+    /// Sets how long async connections wait for the responses to this pipeline, overriding
+    /// the response timeout of the connection. `None` waits indefinitely.
+    ///
+    /// The time that blocking commands in the pipeline may block on the server is added to
+    /// the timeout, and pipelines with commands that may block indefinitely wait indefinitely.
+    /// See [Cmd::set_response_timeout].
+    #[inline]
+    pub fn set_response_timeout(&mut self, timeout: Option<std::time::Duration>) -> &mut Pipeline {
+        self.response_timeout = Some(ResponseTimeout::After(timeout));
+        self
+    }
+
+    /// Sets the instant by which async connections stop waiting for the responses to this
+    /// pipeline, overriding the response timeout of the connection.
+    ///
+    /// The time that blocking commands in the pipeline may block on the server is added to
+    /// the deadline.
+    #[inline]
+    pub fn set_response_deadline(&mut self, deadline: std::time::Instant) -> &mut Pipeline {
+        self.response_timeout = Some(ResponseTimeout::Deadline(deadline));
+        self
+    }
+
+    /// Returns how long to wait for the responses to this pipeline on a connection whose
+    /// response timeout is `connection_timeout`.
+    #[cfg(feature = "aio")]
+    pub(crate) fn response_timeout(
+        &self,
+        connection_timeout: Option<std::time::Duration>,
+    ) -> Option<std::time::Duration> {
+        ResponseTimeout::resolve(
+            self.response_timeout,
+            connection_timeout,
+            self.commands.iter().map(Cmd::block_time).sum(),
+        )
+    }
+
     /// Returns `true` if the pipeline is in transaction mode (aka atomic mode).
     pub fn is_transaction(&self) -> bool {
         self.transaction_mode
@@ -133,6 +174,25 @@ impl Pipeline {
         encode_pipeline(&self.commands, self.transaction_mode)
     }
 
+    /// Returns the encoded pipeline commands, without copying their shared arguments.
+    #[cfg(feature = "aio")]
+    pub(crate) fn get_packed_pipeline_shared(&self) -> PackedCommand {
+        if !self.commands.iter().any(Cmd::has_shared_args) {
+            return self.get_packed_pipeline().into();
+        }
+        let mut packed = PackedCommand::default();
+        if self.transaction_mode {
+            packed.push_cmd(&cmd("MULTI"));
+        }
+        for cmd in &self.commands {
+            packed.push_cmd(cmd);
+        }
+        if self.transaction_mode {
+            packed.push_cmd(&cmd("EXEC"));
+        }
+        packed
+    }
+
     /// Returns the number of commands currently queued by the usr in the pipeline.
     ///
     /// Depending on its configuration (e.g. `atomic`), the pipeline may send more commands to the server than the returned length
@@ -145,6 +205,14 @@ impl Pipeline {
         self.commands.is_empty()
     }
 
+    /// Returns `true` if all of the pipeline's commands can be sent again when it's unknown
+    /// whether the server executed them. See [crate::Cmd::idempotency].
+    pub fn is_safe_to_retry(&self) -> bool {
+        self.commands
+            .iter()
+            .all(|cmd| cmd.idempotency().is_safe_to_retry())
+    }
+
     /// Executes the pipeline and fetches the return values.  Since most
     /// pipelines return different types it's recommended to use tuple
     /// matching to process the results:
@@ -426,7 +494,7 @@ impl Pipeline {
 #[cfg(test)]
 mod tests {
     use crate::{
-        errors::{RedisError, Repr, ServerError},
+        errors::{RedisError, ServerError},
         pipe, ServerErrorKind,
     };
 
@@ -445,10 +513,7 @@ mod tests {
     }
 
     fn server_error() -> Value {
-        Value::ServerError(ServerError(Repr::Known {
-            kind: ServerErrorKind::CrossSlot,
-            detail: None,
-        }))
+        Value::ServerError(ServerError::new(ServerErrorKind::CrossSlot, None))
     }
 
     #[test]
@@ -0,0 +1,585 @@
+//! Read/write splitting over a standalone primary and its replicas, without sentinel.
+//!
+//! A [`ReplicatedConnection`] keeps a [`MultiplexedConnection`] to the primary, which is replaced
+//! by a new connection after it breaks, and discovers the primary's replicas by sending it [`ROLE`][1] - or `INFO replication`, when `ROLE` isn't available.
+//! Commands that don't change any data are sent to one of the replicas, and all other commands are
+//! sent to the primary. A command is considered read-only if its
+//! [idempotency](crate::Cmd::idempotency) is [`ReadOnly`](crate::CommandIdempotency::ReadOnly), so
+//! scripts that only read data can be marked with
+//! [`set_idempotency`](crate::Cmd::set_idempotency) in order to run on the replicas. Pipelines are
+//! sent to a replica only if they aren't atomic and all of their commands are read-only.
+//!
+//! The replica set is refreshed periodically. Replicas that lag behind the primary by more than
+//! [`max_replication_lag`](ReplicatedClientBuilder::max_replication_lag) bytes of the replication
+//! stream, or that don't answer a `PING`, aren't used until the next refresh. If no replica is
+//! usable, or a replica's connection fails, reads are sent to the primary.
+//!
+//! # Example
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use redis::replicated::{ReplicaSelection, ReplicatedClientBuilder};
+//! use redis::AsyncTypedCommands;
+//!
+//! async fn do_something() -> redis::RedisResult<()> {
+//!     let client = ReplicatedClientBuilder::new("redis://127.0.0.1:6379/")?
+//!         .replica_selection(ReplicaSelection::LowestLatency)
+//!         .max_replication_lag(1024)
+//!         .refresh_interval(Duration::from_secs(5))
+//!         .build();
+//!     let mut connection = client.get_async_connection().await?;
+//!     // writes are sent to the primary, and reads to one of its replicas.
+//!     connection.set("key", "value").await?;
+//!     let value = connection.get("key").await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Note that replication is asynchronous - a read that follows a write might not observe it, if it's
+//! served by a replica that didn't receive the write yet.
+//!
+//! [1]: https://redis.io/docs/latest/commands/role/
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+use futures_util::future::{self, FutureExt};
+use tokio::sync::Mutex;
+
+use crate::aio::{ConnectionLike, HandleContainer, MultiplexedConnection, Runtime};
+use crate::{
+    cmd, AsyncConnectionConfig, Client, Cmd, CommandIdempotency, ConnectionAddr, ConnectionInfo,
+    ErrorKind, IntoConnectionInfo, Pipeline, RedisFuture, RedisResult, ReplicaInfo, Role, Value,
+};
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Decides which replica serves a read.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReplicaSelection {
+    /// Reads are spread evenly over the usable replicas.
+    #[default]
+    RoundRobin,
+    /// Reads are sent to the replica that answered the last refresh's `PING` the fastest.
+    LowestLatency,
+}
+
+/// Used to configure and build a [`ReplicatedClient`].
+#[derive(Clone)]
+pub struct ReplicatedClientBuilder {
+    primary: ConnectionInfo,
+    selection: ReplicaSelection,
+    max_replication_lag: Option<u64>,
+    refresh_interval: Duration,
+    connection_config: AsyncConnectionConfig,
+}
+
+impl ReplicatedClientBuilder {
+    /// Creates a new `ReplicatedClientBuilder` for the given primary.
+    pub fn new(primary: impl IntoConnectionInfo) -> RedisResult<ReplicatedClientBuilder> {
+        Ok(ReplicatedClientBuilder {
+            primary: primary.into_connection_info()?,
+            selection: ReplicaSelection::default(),
+            max_replication_lag: None,
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            connection_config: AsyncConnectionConfig::new(),
+        })
+    }
+
+    /// Sets how the replica that serves each read is chosen. Defaults to
+    /// [`ReplicaSelection::RoundRobin`].
+    pub fn replica_selection(mut self, selection: ReplicaSelection) -> ReplicatedClientBuilder {
+        self.selection = selection;
+        self
+    }
+
+    /// Sets the maximal difference, in bytes, between the primary's replication offset and the
+    /// offset a replica acknowledged, for the replica to serve reads. Replicas aren't excluded
+    /// because of their lag by default.
+    pub fn max_replication_lag(mut self, lag: u64) -> ReplicatedClientBuilder {
+        self.max_replication_lag = Some(lag);
+        self
+    }
+
+    /// Sets the interval between refreshes of the replica set. Defaults to 10 seconds.
+    pub fn refresh_interval(mut self, interval: Duration) -> ReplicatedClientBuilder {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Sets the configuration used for the connections to the primary and to each replica.
+    pub fn connection_config(mut self, config: AsyncConnectionConfig) -> ReplicatedClientBuilder {
+        self.connection_config = config;
+        self
+    }
+
+    /// Creates a new [`ReplicatedClient`].
+    pub fn build(self) -> ReplicatedClient {
+        ReplicatedClient {
+            primary: self.primary,
+            selection: self.selection,
+            max_replication_lag: self.max_replication_lag,
+            refresh_interval: self.refresh_interval,
+            connection_config: self.connection_config,
+        }
+    }
+}
+
+/// A client that sends writes to a primary, and reads to the primary's replicas.
+///
+/// See the [module level documentation](self) for details.
+#[derive(Clone)]
+pub struct ReplicatedClient {
+    primary: ConnectionInfo,
+    selection: ReplicaSelection,
+    max_replication_lag: Option<u64>,
+    refresh_interval: Duration,
+    connection_config: AsyncConnectionConfig,
+}
+
+impl ReplicatedClient {
+    /// Creates a replicated client for the given primary, with the default configuration.
+    pub fn new(primary: impl IntoConnectionInfo) -> RedisResult<Self> {
+        Ok(ReplicatedClientBuilder::new(primary)?.build())
+    }
+
+    /// Connects to the primary and to its replicas, and returns a [`ReplicatedConnection`].
+    ///
+    /// Fails if the primary can't be reached, or isn't a primary. Replicas that can't be reached
+    /// are skipped.
+    pub async fn get_async_connection(&self) -> RedisResult<ReplicatedConnection> {
+        let primary = connect(self.primary.clone(), &self.connection_config).await?;
+        let refresh_interval = self.refresh_interval;
+        let inner = Arc::new_cyclic(|weak: &Weak<Inner>| Inner {
+            primary: Mutex::new(Some(Arc::new(primary))),
+            primary_info: self.primary.clone(),
+            selection: self.selection,
+            max_replication_lag: self.max_replication_lag,
+            connection_config: self.connection_config.clone(),
+            replicas: RwLock::new(Arc::new(Vec::new())),
+            next_replica: AtomicUsize::new(0),
+            _refresh_task: HandleContainer::new(
+                Runtime::locate().spawn(refresh_periodically(weak.clone(), refresh_interval)),
+            ),
+        });
+        inner.refresh().await?;
+        Ok(ReplicatedConnection { inner })
+    }
+}
+
+async fn connect(
+    info: ConnectionInfo,
+    config: &AsyncConnectionConfig,
+) -> RedisResult<MultiplexedConnection> {
+    Client::open(info)?
+        .get_multiplexed_async_connection_with_config(config)
+        .await
+}
+
+/// Refreshes the replica set every `interval`, until the connection is dropped.
+async fn refresh_periodically(inner: Weak<Inner>, interval: Duration) {
+    loop {
+        Runtime::locate().sleep(interval).await;
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        // a failed refresh keeps the current replicas, and is retried on the next interval.
+        let _ = inner.refresh().await;
+    }
+}
+
+/// Parses the output of `INFO replication` into the [`Role`] that `ROLE` would have returned.
+fn parse_info_replication(info: &str) -> RedisResult<Role> {
+    let fields: HashMap<&str, &str> = info
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .collect();
+    let field = |name: &str| -> RedisResult<&str> {
+        fields.get(name).copied().ok_or_else(|| {
+            (
+                ErrorKind::Parse,
+                "Missing field in INFO replication response",
+                name.to_string(),
+            )
+                .into()
+        })
+    };
+    let number = |name: &str| -> RedisResult<u64> {
+        field(name)?.parse::<u64>().map_err(|_| {
+            (
+                ErrorKind::Parse,
+                "Invalid number in INFO replication response",
+                name.to_string(),
+            )
+                .into()
+        })
+    };
+
+    match field("role")? {
+        "master" => {
+            let replicas = (0..)
+                .map_while(|index| fields.get(format!("slave{index}").as_str()))
+                .filter_map(|replica| {
+                    let attributes: HashMap<&str, &str> = replica
+                        .split(',')
+                        .filter_map(|attribute| attribute.split_once('='))
+                        .collect();
+                    if attributes
+                        .get("state")
+                        .is_some_and(|state| *state != "online")
+                    {
+                        return None;
+                    }
+                    Some(ReplicaInfo {
+                        ip: attributes.get("ip")?.to_string(),
+                        port: attributes.get("port")?.parse().ok()?,
+                        replication_offset: attributes.get("offset")?.parse().ok()?,
+                    })
+                })
+                .collect();
+            Ok(Role::Primary {
+                replication_offset: number("master_repl_offset")?,
+                replicas,
+            })
+        }
+        "slave" => Ok(Role::Replica {
+            primary_ip: field("master_host")?.to_string(),
+            primary_port: number("master_port")? as u16,
+            replication_state: field("master_link_status")?.to_string(),
+            data_received: number("slave_repl_offset")?,
+        }),
+        role => Err((
+            ErrorKind::Parse,
+            "Unknown role in INFO replication response",
+            role.to_string(),
+        )
+            .into()),
+    }
+}
+
+/// Asks the primary for its replication offset and its replicas.
+async fn discover_replicas(
+    primary: &mut MultiplexedConnection,
+) -> RedisResult<(u64, Vec<ReplicaInfo>)> {
+    let role = match cmd("ROLE").query_async(primary).await {
+        Ok(role) => role,
+        // servers that don't support or allow `ROLE` might still report their replicas in `INFO`.
+        Err(err) if !err.is_io_error() && !err.is_unrecoverable_error() => {
+            let info: String = cmd("INFO").arg("replication").query_async(primary).await?;
+            parse_info_replication(&info)?
+        }
+        Err(err) => return Err(err),
+    };
+    match role {
+        Role::Primary {
+            replication_offset,
+            replicas,
+        } => Ok((replication_offset, replicas)),
+        _ => Err((
+            ErrorKind::Client,
+            "The primary endpoint of a replicated connection isn't a primary",
+        )
+            .into()),
+    }
+}
+
+fn replication_lag(primary_offset: u64, replica: &ReplicaInfo) -> u64 {
+    primary_offset.saturating_sub(replica.replication_offset.max(0) as u64)
+}
+
+/// Returns the connection information of a replica, which shares the primary's settings.
+fn replica_connection_info(primary: &ConnectionInfo, replica: &ReplicaInfo) -> ConnectionInfo {
+    let addr = match primary.addr() {
+        ConnectionAddr::TcpTls {
+            insecure,
+            tls_params,
+            ..
+        } => ConnectionAddr::TcpTls {
+            host: replica.ip.clone(),
+            port: replica.port,
+            insecure: *insecure,
+            tls_params: tls_params.clone(),
+        },
+        _ => ConnectionAddr::Tcp(replica.ip.clone(), replica.port),
+    };
+    primary.clone().set_addr(addr)
+}
+
+struct Replica {
+    info: ReplicaInfo,
+    connection: MultiplexedConnection,
+    latency: Duration,
+}
+
+struct Inner {
+    /// The connection to the primary, or `None` after it broke.
+    primary: Mutex<Option<Arc<MultiplexedConnection>>>,
+    primary_info: ConnectionInfo,
+    selection: ReplicaSelection,
+    max_replication_lag: Option<u64>,
+    connection_config: AsyncConnectionConfig,
+    replicas: RwLock<Arc<Vec<Replica>>>,
+    next_replica: AtomicUsize,
+    _refresh_task: HandleContainer,
+}
+
+impl Inner {
+    /// Returns the connection to the primary, and reconnects first if the previous connection
+    /// broke.
+    async fn primary(&self) -> RedisResult<Arc<MultiplexedConnection>> {
+        let mut primary = self.primary.lock().await;
+        if let Some(connection) = primary.as_ref() {
+            return Ok(connection.clone());
+        }
+        let connection = connect(self.primary_info.clone(), &self.connection_config).await?;
+        Ok(primary.insert(Arc::new(connection)).clone())
+    }
+
+    /// Sends a request to the primary. If the connection broke, it's dropped, so that the next
+    /// request reconnects - unless it was already replaced by a new connection.
+    async fn send_to_primary<T>(
+        &self,
+        send: impl AsyncFnOnce(MultiplexedConnection) -> RedisResult<T>,
+    ) -> RedisResult<T> {
+        let connection = self.primary().await?;
+        let result = send(MultiplexedConnection::clone(&connection)).await;
+        if is_broken(&result) {
+            let mut primary = self.primary.lock().await;
+            if primary
+                .as_ref()
+                .is_some_and(|current| Arc::ptr_eq(current, &connection))
+            {
+                *primary = None;
+            }
+        }
+        result
+    }
+
+    async fn refresh(&self) -> RedisResult<()> {
+        let (primary_offset, infos) = self
+            .send_to_primary(async |mut primary| discover_replicas(&mut primary).await)
+            .await?;
+        let current = self.replicas.read().unwrap().clone();
+        let replicas = future::join_all(
+            infos
+                .into_iter()
+                .filter(|info| {
+                    self.max_replication_lag
+                        .is_none_or(|max| replication_lag(primary_offset, info) <= max)
+                })
+                .map(|info| {
+                    let existing = current
+                        .iter()
+                        .find(|replica| {
+                            replica.info.ip == info.ip && replica.info.port == info.port
+                        })
+                        .map(|replica| replica.connection.clone());
+                    self.connect_replica(info, existing)
+                }),
+        )
+        .await;
+        *self.replicas.write().unwrap() = Arc::new(replicas.into_iter().flatten().collect());
+        Ok(())
+    }
+
+    /// Checks that the replica is responsive, and measures its latency. Connections that fail the
+    /// check aren't reused, so that a broken connection is replaced on the next refresh.
+    async fn connect_replica(
+        &self,
+        info: ReplicaInfo,
+        existing: Option<MultiplexedConnection>,
+    ) -> Option<Replica> {
+        let mut connection = match existing {
+            Some(connection) => connection,
+            None => connect(
+                replica_connection_info(&self.primary_info, &info),
+                &self.connection_config,
+            )
+            .await
+            .ok()?,
+        };
+        let start = Instant::now();
+        cmd("PING").query_async::<()>(&mut connection).await.ok()?;
+        Some(Replica {
+            info,
+            connection,
+            latency: start.elapsed(),
+        })
+    }
+
+    fn replica_connection(&self) -> Option<MultiplexedConnection> {
+        let replicas = self.replicas.read().unwrap();
+        let replica = match self.selection {
+            ReplicaSelection::RoundRobin => {
+                if replicas.is_empty() {
+                    return None;
+                }
+                let index = self.next_replica.fetch_add(1, Ordering::Relaxed) % replicas.len();
+                &replicas[index]
+            }
+            ReplicaSelection::LowestLatency => {
+                replicas.iter().min_by_key(|replica| replica.latency)?
+            }
+        };
+        Some(replica.connection.clone())
+    }
+}
+
+/// Errors after which a connection isn't used anymore. Reads that fail on a replica with these
+/// errors are retried on the primary.
+fn is_broken(result: &RedisResult<impl Sized>) -> bool {
+    result
+        .as_ref()
+        .is_err_and(|err| err.is_io_error() || err.is_unrecoverable_error())
+}
+
+/// A connection to a primary and its replicas, which sends reads to the replicas and writes to
+/// the primary.
+///
+/// The connection can be cloned, and all clones share the same connections and replica set.
+///
+/// See the [module level documentation](self) for details.
+#[derive(Clone)]
+pub struct ReplicatedConnection {
+    inner: Arc<Inner>,
+}
+
+impl ReplicatedConnection {
+    /// Returns the replicas that currently serve reads.
+    pub fn replicas(&self) -> Vec<ReplicaInfo> {
+        self.inner
+            .replicas
+            .read()
+            .unwrap()
+            .iter()
+            .map(|replica| replica.info.clone())
+            .collect()
+    }
+
+    /// Refreshes the replica set immediately, instead of waiting for the next periodic refresh.
+    pub async fn refresh_replicas(&self) -> RedisResult<()> {
+        self.inner.refresh().await
+    }
+
+    /// Sends a read-only command to a replica, and any other command to the primary.
+    pub async fn send_packed_command(&mut self, cmd: &Cmd) -> RedisResult<Value> {
+        if cmd.idempotency() == CommandIdempotency::ReadOnly {
+            if let Some(mut replica) = self.inner.replica_connection() {
+                let result = replica.send_packed_command(cmd).await;
+                if !is_broken(&result) {
+                    return result;
+                }
+            }
+        }
+        self.inner
+            .send_to_primary(async |mut primary| primary.send_packed_command(cmd).await)
+            .await
+    }
+
+    /// Sends a non-atomic pipeline of read-only commands to a replica, and any other pipeline to
+    /// the primary.
+    pub async fn send_packed_commands(
+        &mut self,
+        pipeline: &Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        let read_only = !pipeline.is_transaction()
+            && pipeline
+                .cmd_iter()
+                .all(|cmd| cmd.idempotency() == CommandIdempotency::ReadOnly);
+        if read_only {
+            if let Some(mut replica) = self.inner.replica_connection() {
+                let result = replica.send_packed_commands(pipeline, offset, count).await;
+                if !is_broken(&result) {
+                    return result;
+                }
+            }
+        }
+        self.inner
+            .send_to_primary(async |mut primary| {
+                primary.send_packed_commands(pipeline, offset, count).await
+            })
+            .await
+    }
+}
+
+impl ConnectionLike for ReplicatedConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        (async move { self.send_packed_command(cmd).await }).boxed()
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        (async move { self.send_packed_commands(cmd, offset, count).await }).boxed()
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.primary_info.redis.db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info_replication_of_a_primary_is_parsed_into_its_online_replicas() {
+        let info = "# Replication\r\n\
+            role:master\r\n\
+            connected_slaves:2\r\n\
+            slave0:ip=10.0.0.2,port=6380,state=online,offset=1000,lag=0\r\n\
+            slave1:ip=10.0.0.3,port=6381,state=wait_bgsave,offset=0,lag=0\r\n\
+            master_repl_offset:1200\r\n";
+
+        assert_eq!(
+            parse_info_replication(info).unwrap(),
+            Role::Primary {
+                replication_offset: 1200,
+                replicas: vec![ReplicaInfo {
+                    ip: "10.0.0.2".to_string(),
+                    port: 6380,
+                    replication_offset: 1000,
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn info_replication_of_a_replica_is_parsed() {
+        let info = "role:slave\r\n\
+            master_host:10.0.0.1\r\n\
+            master_port:6379\r\n\
+            master_link_status:up\r\n\
+            slave_repl_offset:500\r\n";
+
+        assert_eq!(
+            parse_info_replication(info).unwrap(),
+            Role::Replica {
+                primary_ip: "10.0.0.1".to_string(),
+                primary_port: 6379,
+                replication_state: "up".to_string(),
+                data_received: 500,
+            }
+        );
+        assert!(parse_info_replication("connected_slaves:0").is_err());
+    }
+
+    #[test]
+    fn replication_lag_ignores_offsets_ahead_of_the_primary() {
+        let replica = |replication_offset| ReplicaInfo {
+            ip: "10.0.0.2".to_string(),
+            port: 6380,
+            replication_offset,
+        };
+
+        assert_eq!(replication_lag(1200, &replica(1000)), 200);
+        assert_eq!(replication_lag(1200, &replica(-1)), 1200);
+        assert_eq!(replication_lag(1200, &replica(1300)), 0);
+    }
+}
@@ -0,0 +1,255 @@
+//! Policies that decide whether, and when, a failed operation is attempted again.
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::{Cmd, Pipeline, RedisError};
+
+/// The request whose failure a [RetryPolicy] is asked about.
+#[derive(Clone, Copy)]
+#[non_exhaustive]
+pub enum RetryRequest<'a> {
+    /// A single command.
+    Cmd(&'a Cmd),
+    /// A pipeline of commands.
+    Pipeline(&'a Pipeline),
+}
+
+/// The information a [RetryPolicy] uses to decide on a failure.
+#[derive(Clone, Copy)]
+pub struct RetryContext<'a> {
+    error: &'a RedisError,
+    attempt: u32,
+    request: Option<RetryRequest<'a>>,
+}
+
+impl<'a> RetryContext<'a> {
+    /// Creates the context of the `attempt`-th consecutive failure, which failed with `error`.
+    pub fn new(error: &'a RedisError, attempt: u32) -> Self {
+        Self {
+            error,
+            attempt,
+            request: None,
+        }
+    }
+
+    /// Sets the request that failed.
+    pub fn with_request(mut self, request: RetryRequest<'a>) -> Self {
+        self.request = Some(request);
+        self
+    }
+
+    /// The error that failed the last attempt. Its [crate::ErrorKind] is available through
+    /// [RedisError::kind].
+    pub fn error(&self) -> &'a RedisError {
+        self.error
+    }
+
+    /// The number of consecutive attempts that failed so far, starting at 1.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// The request that failed, or `None` if the failed operation was establishing a connection.
+    pub fn request(&self) -> Option<RetryRequest<'a>> {
+        self.request
+    }
+}
+
+/// The decision of a [RetryPolicy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RetryDecision {
+    /// Attempt the operation again immediately.
+    Retry,
+    /// Attempt the operation again after the given delay.
+    RetryAfter(Duration),
+    /// Stop attempting the operation, and return the last error.
+    GiveUp,
+}
+
+impl RetryDecision {
+    pub(crate) fn delay(self) -> Option<Duration> {
+        match self {
+            RetryDecision::Retry => Some(Duration::ZERO),
+            RetryDecision::RetryAfter(delay) => Some(delay),
+            RetryDecision::GiveUp => None,
+        }
+    }
+}
+
+/// Decides whether a failed operation should be attempted again, and how long to wait before
+/// doing so.
+///
+/// Policies can be set on [crate::aio::ConnectionManagerConfig], `ClusterClientBuilder` and
+/// `SentinelClientBuilder`. The clients only consult the policy for failures they consider
+/// retryable, so for example requests that might have already been executed by the server are
+/// only sent again if they are safe to retry (see [crate::CommandIdempotency]).
+///
+/// The policy can be implemented by closures:
+///
+/// ```rust
+/// # use std::time::Duration;
+/// use redis::{ErrorKind, RetryContext, RetryDecision, RetryPolicy};
+///
+/// fn retry_io_errors(context: &RetryContext<'_>) -> RetryDecision {
+///     if context.error().kind() == ErrorKind::Io && context.attempt() <= 3 {
+///         RetryDecision::RetryAfter(Duration::from_millis(50))
+///     } else {
+///         RetryDecision::GiveUp
+///     }
+/// }
+///
+/// let error = redis::RedisError::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+/// assert_eq!(
+///     retry_io_errors.decide(&RetryContext::new(&error, 1)),
+///     RetryDecision::RetryAfter(Duration::from_millis(50))
+/// );
+/// ```
+pub trait RetryPolicy: Send + Sync + 'static {
+    /// Decides what to do after the failure described by `context`.
+    fn decide(&self, context: &RetryContext<'_>) -> RetryDecision;
+}
+
+impl<F> RetryPolicy for F
+where
+    F: Fn(&RetryContext<'_>) -> RetryDecision + Send + Sync + 'static,
+{
+    fn decide(&self, context: &RetryContext<'_>) -> RetryDecision {
+        self(context)
+    }
+}
+
+/// A [RetryPolicy] that retries a fixed number of times, with an exponentially increasing delay.
+///
+/// The delay after the `n`-th failure is a random duration in
+/// `min_delay ..= min(max_delay, factor * exponent_base ^ n)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExponentialBackoff {
+    number_of_retries: u32,
+    factor: Duration,
+    exponent_base: f64,
+    min_delay: Duration,
+    max_delay: Option<Duration>,
+}
+
+impl ExponentialBackoff {
+    /// Creates a policy with the default settings: 6 retries, a factor and minimal delay of 100
+    /// milliseconds, an exponent base of 2 and no maximal delay.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of times an operation is retried before giving up.
+    pub fn set_number_of_retries(mut self, number_of_retries: u32) -> Self {
+        self.number_of_retries = number_of_retries;
+        self
+    }
+
+    /// Sets the duration that is multiplied by the exponent.
+    pub fn set_factor(mut self, factor: Duration) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    /// Sets the base that is raised to the number of failures.
+    pub fn set_exponent_base(mut self, exponent_base: f64) -> Self {
+        self.exponent_base = exponent_base;
+        self
+    }
+
+    /// Sets the minimal delay between attempts.
+    pub fn set_min_delay(mut self, min_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self
+    }
+
+    /// Sets the maximal delay between attempts.
+    pub fn set_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Returns the number of times an operation is retried before giving up.
+    pub fn number_of_retries(&self) -> u32 {
+        self.number_of_retries
+    }
+
+    /// Returns the delay after the `attempt`-th failure.
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt).unwrap_or(i32::MAX);
+        let max_delay = self.max_delay.unwrap_or(Duration::MAX);
+        let upper_bound = Duration::try_from_secs_f64(
+            self.factor.as_secs_f64() * self.exponent_base.powi(exponent),
+        )
+        .unwrap_or(Duration::MAX)
+        .min(max_delay)
+        .max(self.min_delay);
+        rand::rng().random_range(self.min_delay..=upper_bound)
+    }
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            number_of_retries: 6,
+            factor: Duration::from_millis(100),
+            exponent_base: 2.0,
+            min_delay: Duration::from_millis(100),
+            max_delay: None,
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn decide(&self, context: &RetryContext<'_>) -> RetryDecision {
+        if context.attempt() > self.number_of_retries {
+            return RetryDecision::GiveUp;
+        }
+        RetryDecision::RetryAfter(self.delay_for_attempt(context.attempt()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn io_error() -> RedisError {
+        std::io::Error::from(std::io::ErrorKind::BrokenPipe).into()
+    }
+
+    #[test]
+    fn exponential_backoff_gives_up_after_number_of_retries() {
+        let error = io_error();
+        let policy = ExponentialBackoff::new().set_number_of_retries(2);
+
+        assert!(matches!(
+            policy.decide(&RetryContext::new(&error, 1)),
+            RetryDecision::RetryAfter(_)
+        ));
+        assert!(matches!(
+            policy.decide(&RetryContext::new(&error, 2)),
+            RetryDecision::RetryAfter(_)
+        ));
+        assert_eq!(
+            policy.decide(&RetryContext::new(&error, 3)),
+            RetryDecision::GiveUp
+        );
+    }
+
+    #[test]
+    fn exponential_backoff_delay_is_bounded() {
+        let policy = ExponentialBackoff::new()
+            .set_factor(Duration::from_millis(10))
+            .set_min_delay(Duration::from_millis(15))
+            .set_max_delay(Duration::from_millis(100));
+
+        for _ in 0..100 {
+            assert_eq!(policy.delay_for_attempt(0), Duration::from_millis(15));
+            let delay = policy.delay_for_attempt(2);
+            assert!(delay >= Duration::from_millis(15) && delay <= Duration::from_millis(40));
+            let delay = policy.delay_for_attempt(u32::MAX);
+            assert!(delay >= Duration::from_millis(15) && delay <= Duration::from_millis(100));
+        }
+    }
+}
@@ -130,7 +130,7 @@ use futures_util::StreamExt;
 use rand::Rng;
 #[cfg(feature = "r2d2")]
 use std::sync::Mutex;
-use std::{collections::HashMap, num::NonZeroUsize};
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc, time::Duration};
 
 #[cfg(feature = "aio")]
 use crate::aio::MultiplexedConnection;
@@ -143,7 +143,7 @@ use crate::TlsCertificates;
 use crate::{
     cmd, connection::ConnectionInfo, errors::ServerErrorKind, types::RedisResult, Client, Cmd,
     Connection, ConnectionAddr, ErrorKind, FromRedisValue, IntoConnectionInfo, ProtocolVersion,
-    RedisConnectionInfo, RedisError, Role, TlsMode,
+    RedisConnectionInfo, RedisError, RetryContext, RetryPolicy, Role, TlsMode,
 };
 
 /// The Sentinel type, serves as a special purpose client which builds other clients on
@@ -1084,6 +1084,7 @@ pub struct SentinelClient {
     service_name: ArcStr,
     node_connection_info: SentinelNodeConnectionInfo,
     server_type: SentinelServerType,
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
 }
 
 impl SentinelClient {
@@ -1132,6 +1133,7 @@ impl SentinelClient {
             service_name: service_name.as_ref().into(),
             node_connection_info: node_connection_info.unwrap_or_default(),
             server_type,
+            retry_policy: None,
         })
     }
 
@@ -1199,9 +1201,29 @@ impl SentinelClient {
     /// Creates a new connection to the desired type of server (based on the
     /// service/master name, and the server type). We use a Sentinel to create a client
     /// for the target type of server, and then create a connection using that client.
+    ///
+    /// If a retry policy was set with [SentinelClientBuilder::set_retry_policy], failures are
+    /// retried according to it.
     pub fn get_connection(&mut self) -> RedisResult<Connection> {
-        let client = self.get_client()?;
-        client.get_connection()
+        let mut failed_attempts = 0;
+        loop {
+            let err = match self.get_client().and_then(|client| client.get_connection()) {
+                Ok(connection) => return Ok(connection),
+                Err(err) => err,
+            };
+            let Some(delay) = self.retry_delay(&err, &mut failed_attempts) else {
+                return Err(err);
+            };
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn retry_delay(&self, err: &RedisError, failed_attempts: &mut u32) -> Option<Duration> {
+        let retry_policy = self.retry_policy.as_ref()?;
+        *failed_attempts += 1;
+        retry_policy
+            .decide(&RetryContext::new(err, *failed_attempts))
+            .delay()
     }
 }
 
@@ -1292,10 +1314,25 @@ impl SentinelClient {
         &mut self,
         config: &AsyncConnectionConfig,
     ) -> RedisResult<AsyncConnection> {
-        self.async_get_client()
-            .await?
-            .get_multiplexed_async_connection_with_config(config)
-            .await
+        let mut failed_attempts = 0;
+        loop {
+            let result = match self.async_get_client().await {
+                Ok(client) => {
+                    client
+                        .get_multiplexed_async_connection_with_config(config)
+                        .await
+                }
+                Err(err) => Err(err),
+            };
+            let err = match result {
+                Ok(connection) => return Ok(connection),
+                Err(err) => err,
+            };
+            let Some(delay) = self.retry_delay(&err, &mut failed_attempts) else {
+                return Err(err);
+            };
+            crate::aio::Runtime::locate().sleep(delay).await;
+        }
     }
 }
 
@@ -1319,6 +1356,7 @@ pub struct SentinelClientBuilder {
     server_type: SentinelServerType,
     client_to_redis_params: BuilderConnectionParams,
     client_to_sentinel_params: BuilderConnectionParams,
+    retry_policy: Option<Arc<dyn RetryPolicy>>,
 }
 
 impl SentinelClientBuilder {
@@ -1353,6 +1391,7 @@ impl SentinelClientBuilder {
                 #[cfg(feature = "tls-rustls")]
                 certificates: None,
             },
+            retry_policy: None,
         })
     }
 
@@ -1469,14 +1508,16 @@ impl SentinelClientBuilder {
             })
             .collect();
 
-        SentinelClient::build_inner(
+        let mut client = SentinelClient::build_inner(
             sentinels,
             self.service_name,
             Some(client_to_redis_connection_info),
             self.server_type,
             #[cfg(feature = "tls-rustls")]
             self.client_to_redis_params.certificates,
-        )
+        )?;
+        client.retry_policy = self.retry_policy;
+        Ok(client)
     }
 
     /// Set tls mode for the connection to redis
@@ -1570,4 +1611,13 @@ impl SentinelClientBuilder {
         self.client_to_sentinel_params.certificates = Some(certificates);
         self
     }
+
+    /// Set the policy that decides whether failures to connect to the redis server are retried.
+    ///
+    /// By default, connection failures are returned without retrying. [crate::ExponentialBackoff]
+    /// can be used to retry with an exponentially increasing delay.
+    pub fn set_retry_policy(mut self, policy: impl RetryPolicy) -> SentinelClientBuilder {
+        self.retry_policy = Some(Arc::new(policy));
+        self
+    }
 }
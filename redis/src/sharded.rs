@@ -0,0 +1,944 @@
+//! Client-side sharding of keys across independent, non-cluster servers.
+//!
+//! A [`ShardedConnection`] keeps a [`MultiplexedConnection`] to every configured node and places the
+//! nodes on a consistent hash ring, ketama-style: each node owns a number of points on the ring that
+//! is proportional to its weight, and a key is served by the first node point that follows the key's
+//! position on the ring. Adding or removing a node only moves the keys that land on that node's points.
+//!
+//! Keys are reduced to their cluster hash slot before being placed on the ring, so hash tags behave
+//! exactly as they do in cluster mode - `{user1}:name` and `{user1}:email` will always be stored on
+//! the same node.
+//!
+//! Multi-key commands such as `MGET`, `MSET` and `DEL` are split into per-node sub-commands, and the
+//! replies are merged back in the order of the original keys. Commands that target all nodes in
+//! cluster mode, such as `FLUSHALL` or `DBSIZE`, are sent to every node and aggregated the same way
+//! the cluster connection aggregates them.
+//!
+//! # Example
+//! ```rust,no_run
+//! use redis::sharded::ShardedClientBuilder;
+//! use redis::AsyncTypedCommands;
+//!
+//! async fn do_something() -> redis::RedisResult<()> {
+//!     let client = ShardedClientBuilder::new(vec!["redis://127.0.0.1:6379/", "redis://127.0.0.1:6380/"])?
+//!         .add_node("redis://127.0.0.1:6381/", 2)?
+//!         .build()?;
+//!     let mut connection = client.get_async_connection().await?;
+//!     connection.mset(&[("key1", "value1"), ("key2", "value2")]).await?;
+//!     let values: Vec<Option<String>> = connection.mget(&["key1", "key2"]).await?;
+//!
+//!     // nodes can be added and removed while the connection is in use.
+//!     connection.add_node("redis://127.0.0.1:6382/", 1).await?;
+//!     connection.remove_node("127.0.0.1:6379");
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Note that moving a node in or out of the ring doesn't migrate any data - keys that are now owned
+//! by a different node will appear missing until they're written again.
+//!
+//! All the nodes must use the same database, which is the database that the connection reports.
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use arcstr::ArcStr;
+use futures_util::future::{self, FutureExt};
+use rand::seq::IteratorRandom;
+
+use crate::aio::{ConnectionLike, MultiplexedConnection};
+use crate::cluster_handling::routing::{
+    aggregate, combine_and_sort_array_results, combine_array_results, combine_map_results,
+    command_for_multi_slot_indices, get_slot, logical_aggregate, MultiSlotArgPattern,
+    MultipleNodeRoutingInfo, ResponsePolicy, Route, RoutingInfo, SingleNodeRoutingInfo,
+};
+use crate::types::extract_error;
+use crate::{
+    AsyncConnectionConfig, Client, Cmd, ConnectionInfo, ErrorKind, IntoConnectionInfo, Pipeline,
+    RedisError, RedisFuture, RedisResult, Value,
+};
+
+/// The number of points each unit of weight adds to the ring. This matches the density used by
+/// libketama, which keeps the key distribution within a few percent of the configured weights.
+const POINTS_PER_WEIGHT: u32 = 160;
+
+/// The largest weight a node can have, which bounds the number of points a single node adds to
+/// the ring.
+const MAX_WEIGHT: u32 = 1000;
+
+fn no_nodes_error() -> RedisError {
+    (ErrorKind::Client, "No nodes in sharded connection").into()
+}
+
+fn ring_hash(data: &[u8]) -> u32 {
+    // FNV-1a, followed by the murmur3 finalizer in order to spread out similar inputs, such as
+    // consecutive slot numbers.
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in data {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^= hash >> 16;
+    hash
+}
+
+/// The argument indices of a multi-key command that are sent to a single node.
+type NodeGroup = (ArcStr, Vec<usize>);
+
+/// A consistent hash ring of node addresses.
+#[derive(Debug, Default, Clone)]
+struct HashRing {
+    points: Vec<(u32, ArcStr)>,
+    weights: HashMap<ArcStr, u32>,
+}
+
+impl HashRing {
+    fn add(&mut self, node: ArcStr, weight: u32) {
+        let points = weight
+            .checked_mul(POINTS_PER_WEIGHT)
+            .expect("node weights are validated before they are added to the ring");
+        for index in 0..points {
+            let point = ring_hash(format!("{node}-{index}").as_bytes());
+            self.points.push((point, node.clone()));
+        }
+        // sorting by the address as well keeps the ring deterministic if two points collide.
+        self.points.sort_unstable();
+        self.weights.insert(node, weight);
+    }
+
+    fn remove(&mut self, node: &str) -> bool {
+        if self.weights.remove(node).is_none() {
+            return false;
+        }
+        self.points.retain(|(_, address)| address != node);
+        true
+    }
+
+    fn node_for_slot(&self, slot: u16) -> Option<&ArcStr> {
+        let position = ring_hash(&slot.to_be_bytes());
+        let index = self.points.partition_point(|(point, _)| *point < position);
+        self.points
+            .get(index)
+            .or_else(|| self.points.first())
+            .map(|(_, node)| node)
+    }
+
+    /// Groups the argument indices of a multi-slot command by the node that owns each slot.
+    fn group_by_node(&self, routes: &[(Route, Vec<usize>)]) -> RedisResult<Vec<NodeGroup>> {
+        let mut groups: Vec<NodeGroup> = Vec::new();
+        for (route, indices) in routes {
+            let address = self
+                .node_for_slot(route.slot())
+                .ok_or_else(no_nodes_error)?;
+            match groups.iter_mut().find(|(existing, _)| existing == address) {
+                Some((_, existing_indices)) => existing_indices.extend(indices),
+                None => groups.push((address.clone(), indices.clone())),
+            }
+        }
+        for (_, indices) in groups.iter_mut() {
+            // Each route contains consecutive groups of arguments, so sorting keeps key-value pairs together.
+            // Patterns with a shared trailing argument add it to every route, so it must be deduplicated.
+            indices.sort_unstable();
+            indices.dedup();
+        }
+        Ok(groups)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ShardNode {
+    info: ConnectionInfo,
+    weight: u32,
+}
+
+fn shard_node(info: impl IntoConnectionInfo, weight: u32) -> RedisResult<ShardNode> {
+    if !(1..=MAX_WEIGHT).contains(&weight) {
+        return Err((
+            ErrorKind::InvalidClientConfig,
+            "Shard node weight must be between 1 and 1000",
+            weight.to_string(),
+        )
+            .into());
+    }
+    Ok(ShardNode {
+        info: info.into_connection_info()?,
+        weight,
+    })
+}
+
+/// Used to configure and build a [`ShardedClient`].
+#[derive(Clone)]
+pub struct ShardedClientBuilder {
+    nodes: Vec<ShardNode>,
+    connection_config: AsyncConnectionConfig,
+}
+
+impl ShardedClientBuilder {
+    /// Creates a new `ShardedClientBuilder` with the provided nodes, each with a weight of 1.
+    pub fn new<T: IntoConnectionInfo>(
+        nodes: impl IntoIterator<Item = T>,
+    ) -> RedisResult<ShardedClientBuilder> {
+        Ok(ShardedClientBuilder {
+            nodes: nodes
+                .into_iter()
+                .map(|info| shard_node(info, 1))
+                .collect::<RedisResult<_>>()?,
+            connection_config: AsyncConnectionConfig::new(),
+        })
+    }
+
+    /// Adds a node with the given weight, which must be between 1 and 1000. A node with weight 2
+    /// will receive roughly twice as many keys as a node with weight 1.
+    pub fn add_node(
+        mut self,
+        info: impl IntoConnectionInfo,
+        weight: u32,
+    ) -> RedisResult<ShardedClientBuilder> {
+        self.nodes.push(shard_node(info, weight)?);
+        Ok(self)
+    }
+
+    /// Sets the configuration used for the connection to each node.
+    pub fn connection_config(mut self, config: AsyncConnectionConfig) -> ShardedClientBuilder {
+        self.connection_config = config;
+        self
+    }
+
+    /// Creates a new [`ShardedClient`].
+    ///
+    /// Returns an error if no nodes were provided, if two nodes share the same address, or if the
+    /// nodes use different databases.
+    pub fn build(self) -> RedisResult<ShardedClient> {
+        let Some(first) = self.nodes.first() else {
+            return Err((
+                ErrorKind::InvalidClientConfig,
+                "At least one node is required for a sharded client",
+            )
+                .into());
+        };
+        let db = first.info.redis_settings().db();
+        check_db(&self.nodes, db)?;
+        let mut addresses = HashSet::new();
+        for node in &self.nodes {
+            let address = node.info.addr().to_string();
+            if !addresses.insert(address.clone()) {
+                return Err((
+                    ErrorKind::InvalidClientConfig,
+                    "Duplicate node address in sharded client",
+                    address,
+                )
+                    .into());
+            }
+        }
+        Ok(ShardedClient {
+            nodes: self.nodes,
+            connection_config: self.connection_config,
+            db,
+        })
+    }
+}
+
+/// Checks that all the nodes use the database `db`.
+fn check_db(nodes: &[ShardNode], db: i64) -> RedisResult<()> {
+    match nodes
+        .iter()
+        .find(|node| node.info.redis_settings().db() != db)
+    {
+        Some(node) => Err((
+            ErrorKind::InvalidClientConfig,
+            "All the nodes of a sharded client must use the same database",
+            format!(
+                "{} uses database {}, instead of {db}",
+                node.info.addr(),
+                node.info.redis_settings().db()
+            ),
+        )
+            .into()),
+        None => Ok(()),
+    }
+}
+
+/// A client that distributes keys across a set of standalone servers using consistent hashing.
+///
+/// See the [module level documentation](self) for details.
+#[derive(Clone)]
+pub struct ShardedClient {
+    nodes: Vec<ShardNode>,
+    connection_config: AsyncConnectionConfig,
+    db: i64,
+}
+
+impl ShardedClient {
+    /// Creates a sharded client from the given nodes, each with a weight of 1.
+    pub fn new<T: IntoConnectionInfo>(nodes: impl IntoIterator<Item = T>) -> RedisResult<Self> {
+        ShardedClientBuilder::new(nodes)?.build()
+    }
+
+    /// Connects to all of the nodes and returns a [`ShardedConnection`].
+    ///
+    /// Fails if any of the nodes can't be reached.
+    pub async fn get_async_connection(&self) -> RedisResult<ShardedConnection> {
+        let connections = future::try_join_all(
+            self.nodes
+                .iter()
+                .map(|node| connect_node(node.info.clone(), &self.connection_config)),
+        )
+        .await?;
+
+        let mut state = ShardState::default();
+        for (node, connection) in self.nodes.iter().zip(connections) {
+            state.insert(node.info.addr().to_string().into(), node.weight, connection);
+        }
+        Ok(ShardedConnection {
+            state: Arc::new(RwLock::new(state)),
+            connection_config: self.connection_config.clone(),
+            db: self.db,
+        })
+    }
+}
+
+async fn connect_node(
+    info: ConnectionInfo,
+    config: &AsyncConnectionConfig,
+) -> RedisResult<MultiplexedConnection> {
+    Client::open(info)?
+        .get_multiplexed_async_connection_with_config(config)
+        .await
+}
+
+#[derive(Default)]
+struct ShardState {
+    ring: HashRing,
+    connections: HashMap<ArcStr, MultiplexedConnection>,
+}
+
+/// Describes how a single command is executed over the shards.
+enum Dispatch {
+    Single(MultiplexedConnection),
+    MultiKey {
+        requests: Vec<(MultiplexedConnection, Cmd)>,
+        groups: Vec<NodeGroup>,
+        pattern: MultiSlotArgPattern,
+        response_policy: Option<ResponsePolicy>,
+    },
+    AllNodes {
+        requests: Vec<(ArcStr, MultiplexedConnection)>,
+        response_policy: Option<ResponsePolicy>,
+    },
+}
+
+impl ShardState {
+    fn insert(&mut self, address: ArcStr, weight: u32, connection: MultiplexedConnection) {
+        self.ring.add(address.clone(), weight);
+        self.connections.insert(address, connection);
+    }
+
+    fn remove(&mut self, address: &str) -> bool {
+        self.connections.remove(address);
+        self.ring.remove(address)
+    }
+
+    fn connection_for_slot(&self, slot: u16) -> RedisResult<(&ArcStr, &MultiplexedConnection)> {
+        self.ring
+            .node_for_slot(slot)
+            .and_then(|address| self.connections.get_key_value(address))
+            .ok_or_else(no_nodes_error)
+    }
+
+    fn random_connection(&self) -> RedisResult<(&ArcStr, &MultiplexedConnection)> {
+        self.connections
+            .iter()
+            .choose(&mut rand::rng())
+            .ok_or_else(no_nodes_error)
+    }
+
+    /// Returns the node that should receive `cmd`, if the command is routed to a single node.
+    fn address_for_single_node(
+        &self,
+        routing: &SingleNodeRoutingInfo,
+    ) -> RedisResult<Option<&ArcStr>> {
+        match routing {
+            SingleNodeRoutingInfo::SpecificNode(route) => {
+                Ok(Some(self.connection_for_slot(route.slot())?.0))
+            }
+            SingleNodeRoutingInfo::ByAddress { host, port } => {
+                let address = format!("{host}:{port}");
+                match self.connections.get_key_value(address.as_str()) {
+                    Some((address, _)) => Ok(Some(address)),
+                    None => {
+                        Err((ErrorKind::Client, "Requested connection not found", address).into())
+                    }
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn dispatch(&self, cmd: &Cmd) -> RedisResult<Dispatch> {
+        let routing = RoutingInfo::for_routable(cmd)
+            .unwrap_or(RoutingInfo::SingleNode(SingleNodeRoutingInfo::Random));
+        match routing {
+            RoutingInfo::SingleNode(routing) => {
+                let connection = match self.address_for_single_node(&routing)? {
+                    Some(address) => self.connections.get(address),
+                    None => Some(self.random_connection()?.1),
+                };
+                connection
+                    .cloned()
+                    .map(Dispatch::Single)
+                    .ok_or_else(no_nodes_error)
+            }
+            RoutingInfo::MultiNode((
+                MultipleNodeRoutingInfo::MultiSlot((routes, pattern)),
+                response_policy,
+            )) => {
+                let mut groups = self.ring.group_by_node(&routes)?;
+                if groups.len() == 1 {
+                    let (address, _) = groups.pop().unwrap();
+                    return Ok(Dispatch::Single(self.connections[&address].clone()));
+                }
+                let requests = groups
+                    .iter()
+                    .map(|(address, indices)| {
+                        (
+                            self.connections[address].clone(),
+                            command_for_multi_slot_indices(cmd, indices.iter()),
+                        )
+                    })
+                    .collect();
+                Ok(Dispatch::MultiKey {
+                    requests,
+                    groups,
+                    pattern,
+                    response_policy,
+                })
+            }
+            RoutingInfo::MultiNode((_, response_policy)) => {
+                if self.connections.is_empty() {
+                    return Err(no_nodes_error());
+                }
+                Ok(Dispatch::AllNodes {
+                    requests: self
+                        .connections
+                        .iter()
+                        .map(|(address, connection)| (address.clone(), connection.clone()))
+                        .collect(),
+                    response_policy,
+                })
+            }
+        }
+    }
+
+    /// Finds the single node that should receive all of the keyed commands in `pipeline`.
+    fn connection_for_pipeline(&self, pipeline: &Pipeline) -> RedisResult<MultiplexedConnection> {
+        let mut chosen: Option<&ArcStr> = None;
+        for cmd in pipeline.cmd_iter() {
+            let address = match RoutingInfo::for_routable(cmd) {
+                Some(RoutingInfo::SingleNode(routing)) => self.address_for_single_node(&routing)?,
+                Some(RoutingInfo::MultiNode((
+                    MultipleNodeRoutingInfo::MultiSlot((routes, _)),
+                    _,
+                ))) => {
+                    let mut groups = self.ring.group_by_node(&routes)?;
+                    if groups.len() > 1 {
+                        return Err((
+                            ErrorKind::Client,
+                            "Received a command with keys mapped to different nodes in pipeline",
+                        )
+                            .into());
+                    }
+                    groups
+                        .pop()
+                        .and_then(|(address, _)| self.connections.get_key_value(&address))
+                        .map(|(address, _)| address)
+                }
+                _ => None,
+            };
+            match (chosen, address) {
+                (None, address) => chosen = address,
+                (Some(chosen), Some(address)) if chosen != address => {
+                    return Err((
+                        ErrorKind::Client,
+                        "Received keys mapped to different nodes in pipeline",
+                    )
+                        .into());
+                }
+                _ => {}
+            }
+        }
+        match chosen {
+            Some(address) => Ok(self.connections[address].clone()),
+            None => Ok(self.random_connection()?.1.clone()),
+        }
+    }
+}
+
+/// Combines the results of a command that was sent to multiple nodes, according to its response policy.
+fn aggregate_results(
+    results: Vec<(ArcStr, RedisResult<Value>)>,
+    response_policy: Option<ResponsePolicy>,
+    multi_key: Option<(&[NodeGroup], &MultiSlotArgPattern)>,
+) -> RedisResult<Value> {
+    let all_succeeded = |results: Vec<(ArcStr, RedisResult<Value>)>| {
+        results
+            .into_iter()
+            .map(|(_, result)| result.and_then(extract_error))
+            .collect::<RedisResult<Vec<_>>>()
+    };
+
+    match response_policy {
+        Some(ResponsePolicy::AllSucceeded) => {
+            all_succeeded(results)?.pop().ok_or_else(no_nodes_error)
+        }
+        Some(ResponsePolicy::OneSucceeded) => {
+            let mut last_error = None;
+            for (_, result) in results {
+                match result.and_then(extract_error) {
+                    Ok(value) => return Ok(value),
+                    Err(err) => last_error = Some(err),
+                }
+            }
+            Err(last_error.unwrap_or_else(no_nodes_error))
+        }
+        Some(ResponsePolicy::FirstSucceededNonEmptyOrAllEmpty) => {
+            let num_results = results.len();
+            let mut nil_counter = 0;
+            let mut last_error = None;
+            for (_, result) in results {
+                match result.and_then(extract_error) {
+                    Ok(Value::Nil) => nil_counter += 1,
+                    Ok(value) => return Ok(value),
+                    Err(err) => last_error = Some(err),
+                }
+            }
+            if nil_counter == num_results {
+                Ok(Value::Nil)
+            } else {
+                Err(last_error.unwrap_or_else(no_nodes_error))
+            }
+        }
+        Some(ResponsePolicy::Aggregate(op)) => aggregate(all_succeeded(results)?, op),
+        Some(ResponsePolicy::AggregateLogical(op)) => {
+            logical_aggregate(all_succeeded(results)?, op)
+        }
+        Some(ResponsePolicy::CombineArrays) => match multi_key {
+            Some((groups, pattern)) => {
+                combine_and_sort_array_results(all_succeeded(results)?, groups, pattern)
+            }
+            None => combine_array_results(all_succeeded(results)?),
+        },
+        Some(ResponsePolicy::CombineMaps) => combine_map_results(all_succeeded(results)?),
+        Some(ResponsePolicy::Special) | None => {
+            // There's no coherent way to aggregate the responses, so each response is mapped to its node.
+            let values = results
+                .into_iter()
+                .map(|(address, result)| {
+                    let value = result.or_else(|err| match err.try_into() {
+                        Ok(server_error) => Ok(Value::ServerError(server_error)),
+                        Err(err) => Err(err),
+                    })?;
+                    Ok((Value::BulkString(address.as_bytes().to_vec()), value))
+                })
+                .collect::<RedisResult<Vec<_>>>()?;
+            Ok(Value::Map(values))
+        }
+    }
+}
+
+/// A connection to a set of standalone servers, which distributes keys between them using a
+/// consistent hash ring.
+///
+/// The connection can be cloned, and all clones share the same set of nodes - nodes that are added
+/// or removed through one clone are visible to all of them. The connection reports the database of
+/// the first configured node as its database.
+///
+/// See the [module level documentation](self) for details.
+#[derive(Clone)]
+pub struct ShardedConnection {
+    state: Arc<RwLock<ShardState>>,
+    connection_config: AsyncConnectionConfig,
+    db: i64,
+}
+
+impl ShardedConnection {
+    /// Connects to a new node and adds it to the hash ring with the given weight.
+    ///
+    /// Keys that are now owned by the new node won't be migrated to it. Returns an error if the
+    /// node uses a different database than the other nodes.
+    pub async fn add_node(&self, info: impl IntoConnectionInfo, weight: u32) -> RedisResult<()> {
+        let node = shard_node(info, weight)?;
+        check_db(std::slice::from_ref(&node), self.db)?;
+        let address: ArcStr = node.info.addr().to_string().into();
+        if self
+            .state
+            .read()
+            .unwrap()
+            .connections
+            .contains_key(&address)
+        {
+            return Err((
+                ErrorKind::InvalidClientConfig,
+                "Node is already part of the sharded connection",
+                address.to_string(),
+            )
+                .into());
+        }
+
+        let connection = connect_node(node.info, &self.connection_config).await?;
+        let mut state = self.state.write().unwrap();
+        // the node might have been added concurrently while connecting.
+        state.remove(&address);
+        state.insert(address, node.weight, connection);
+        Ok(())
+    }
+
+    /// Removes the node with the given address (`host:port`, or the socket path for unix connections) from the hash ring.
+    ///
+    /// Returns `false` if there was no such node.
+    pub fn remove_node(&self, address: &str) -> bool {
+        self.state.write().unwrap().remove(address)
+    }
+
+    /// Returns the addresses of the nodes currently in the hash ring.
+    pub fn nodes(&self) -> Vec<String> {
+        let mut nodes: Vec<_> = self
+            .state
+            .read()
+            .unwrap()
+            .connections
+            .keys()
+            .map(|address| address.to_string())
+            .collect();
+        nodes.sort_unstable();
+        nodes
+    }
+
+    /// Returns the address of the node that stores `key`, if there are any nodes.
+    pub fn node_for_key(&self, key: &[u8]) -> Option<String> {
+        self.state
+            .read()
+            .unwrap()
+            .ring
+            .node_for_slot(get_slot(key))
+            .map(|address| address.to_string())
+    }
+
+    /// Sends a command to the nodes that own its keys, and combines the responses.
+    pub async fn send_packed_command(&mut self, cmd: &Cmd) -> RedisResult<Value> {
+        let dispatch = self.state.read().unwrap().dispatch(cmd)?;
+        match dispatch {
+            Dispatch::Single(mut connection) => connection.send_packed_command(cmd).await,
+            Dispatch::MultiKey {
+                requests,
+                groups,
+                pattern,
+                response_policy,
+            } => {
+                let results =
+                    future::join_all(requests.into_iter().map(
+                        |(mut connection, cmd)| async move {
+                            connection.send_packed_command(&cmd).await
+                        },
+                    ))
+                    .await;
+                let results = groups
+                    .iter()
+                    .map(|(address, _)| address.clone())
+                    .zip(results)
+                    .collect();
+                aggregate_results(results, response_policy, Some((&groups, &pattern)))
+            }
+            Dispatch::AllNodes {
+                requests,
+                response_policy,
+            } => {
+                let results = future::join_all(requests.into_iter().map(
+                    |(address, mut connection)| async move {
+                        (address, connection.send_packed_command(cmd).await)
+                    },
+                ))
+                .await;
+                aggregate_results(results, response_policy, None)
+            }
+        }
+    }
+
+    /// Sends a pipeline to a single node. All of the keys in the pipeline must be owned by the same node.
+    pub async fn send_packed_commands(
+        &mut self,
+        pipeline: &Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        let mut connection = self
+            .state
+            .read()
+            .unwrap()
+            .connection_for_pipeline(pipeline)?;
+        connection
+            .send_packed_commands(pipeline, offset, count)
+            .await
+    }
+}
+
+impl ConnectionLike for ShardedConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        (async move { self.send_packed_command(cmd).await }).boxed()
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        (async move { self.send_packed_commands(cmd, offset, count).await }).boxed()
+    }
+
+    fn get_db(&self) -> i64 {
+        self.db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster_handling::slot_map::SLOT_SIZE;
+
+    fn ring(nodes: &[(&str, u32)]) -> HashRing {
+        let mut ring = HashRing::default();
+        for (node, weight) in nodes {
+            ring.add((*node).into(), *weight);
+        }
+        ring
+    }
+
+    fn slot_owners(ring: &HashRing) -> Vec<ArcStr> {
+        (0..SLOT_SIZE)
+            .map(|slot| ring.node_for_slot(slot).unwrap().clone())
+            .collect()
+    }
+
+    fn count(owners: &[ArcStr], node: &str) -> usize {
+        owners.iter().filter(|owner| *owner == node).count()
+    }
+
+    #[test]
+    fn empty_ring_has_no_owner() {
+        assert_eq!(HashRing::default().node_for_slot(0), None);
+    }
+
+    #[test]
+    fn slots_are_distributed_according_to_weight() {
+        let owners = slot_owners(&ring(&[("a:1", 1), ("b:1", 1), ("c:1", 2)]));
+        let share = |node| count(&owners, node) as f64 / SLOT_SIZE as f64;
+
+        assert!((share("a:1") - 0.25).abs() < 0.05, "{}", share("a:1"));
+        assert!((share("b:1") - 0.25).abs() < 0.05, "{}", share("b:1"));
+        assert!((share("c:1") - 0.5).abs() < 0.05, "{}", share("c:1"));
+    }
+
+    #[test]
+    fn adding_a_node_only_moves_slots_to_that_node() {
+        let mut ring = ring(&[("a:1", 1), ("b:1", 1), ("c:1", 1)]);
+        let before = slot_owners(&ring);
+        ring.add("d:1".into(), 1);
+        let after = slot_owners(&ring);
+
+        for (before, after) in before.iter().zip(after.iter()) {
+            assert!(before == after || after == "d:1");
+        }
+        assert!(count(&after, "d:1") > 0);
+    }
+
+    #[test]
+    fn removing_a_node_only_moves_its_slots() {
+        let mut ring = ring(&[("a:1", 1), ("b:1", 1), ("c:1", 1)]);
+        let before = slot_owners(&ring);
+        assert!(ring.remove("b:1"));
+        assert!(!ring.remove("b:1"));
+        let after = slot_owners(&ring);
+
+        for (before, after) in before.iter().zip(after.iter()) {
+            assert!(before == after || before == "b:1");
+        }
+        assert_eq!(count(&after, "b:1"), 0);
+    }
+
+    #[test]
+    fn hash_tags_are_placed_on_the_same_node() {
+        let ring = ring(&[("a:1", 1), ("b:1", 1), ("c:1", 1), ("d:1", 1)]);
+        let owner = |key: &[u8]| ring.node_for_slot(get_slot(key)).unwrap().clone();
+
+        assert_eq!(owner(b"{user1}:name"), owner(b"{user1}:email"));
+        assert_eq!(owner(b"{user1}:name"), owner(b"user1"));
+    }
+
+    fn node_groups(ring: &HashRing, cmd: &Cmd) -> (Vec<NodeGroup>, MultiSlotArgPattern) {
+        let Some(RoutingInfo::MultiNode((
+            MultipleNodeRoutingInfo::MultiSlot((routes, pattern)),
+            _,
+        ))) = RoutingInfo::for_routable(cmd)
+        else {
+            panic!("expected a multi slot route");
+        };
+        (ring.group_by_node(&routes).unwrap(), pattern)
+    }
+
+    #[test]
+    fn multi_key_results_are_reassembled_in_key_order() {
+        let ring = ring(&[("a:1", 1), ("b:1", 1), ("c:1", 1)]);
+        let keys: Vec<String> = (0..20).map(|i| format!("key{i}")).collect();
+        let mut cmd = crate::cmd("MSET");
+        for key in &keys {
+            cmd.arg(key).arg(format!("value-of-{key}"));
+        }
+        let (groups, pattern) = node_groups(&ring, &cmd);
+        assert!(groups.len() > 1);
+
+        // Every sub command keeps its key-value pairs intact.
+        for (_, indices) in &groups {
+            let sub_cmd = command_for_multi_slot_indices(&cmd, indices.iter());
+            let args: Vec<_> = sub_cmd.args_iter().skip(1).collect();
+            for pair in args.chunks(2) {
+                let [crate::Arg::Simple(key), crate::Arg::Simple(value)] = pair else {
+                    panic!("unexpected cursor arg");
+                };
+                assert_eq!(
+                    [b"value-of-".as_slice(), key].concat(),
+                    value.to_vec(),
+                    "key and value were split"
+                );
+            }
+        }
+
+        // Replying with the key of each pair checks that the results are merged back in order.
+        let replies = groups
+            .iter()
+            .map(|(_, indices)| {
+                Value::Array(
+                    indices
+                        .iter()
+                        .step_by(2)
+                        .map(|index| Value::BulkString(keys[index / 2].as_bytes().to_vec()))
+                        .collect(),
+                )
+            })
+            .collect();
+        let merged = combine_and_sort_array_results(replies, &groups, &pattern).unwrap();
+        assert_eq!(
+            merged,
+            Value::Array(
+                keys.iter()
+                    .map(|key| Value::BulkString(key.as_bytes().to_vec()))
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn shared_trailing_argument_is_not_duplicated() {
+        let ring = ring(&[("a:1", 1), ("b:1", 1)]);
+        let mut cmd = crate::cmd("JSON.MGET");
+        for i in 0..10 {
+            cmd.arg(format!("key{i}"));
+        }
+        cmd.arg("$.path");
+        let (groups, _) = node_groups(&ring, &cmd);
+
+        for (_, indices) in &groups {
+            let sub_cmd = command_for_multi_slot_indices(&cmd, indices.iter());
+            let args: Vec<_> = sub_cmd.args_iter().collect();
+            assert_eq!(args.last(), Some(&crate::Arg::Simple(b"$.path".as_slice())));
+            assert_eq!(
+                args.iter()
+                    .filter(|arg| **arg == crate::Arg::Simple(b"$.path".as_slice()))
+                    .count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn aggregate_sums_deleted_keys() {
+        let results = vec![
+            ("a:1".into(), Ok(Value::Int(2))),
+            ("b:1".into(), Ok(Value::Int(3))),
+        ];
+        assert_eq!(
+            aggregate_results(
+                results,
+                ResponsePolicy::for_command(b"DEL"),
+                Some((&[], &MultiSlotArgPattern::KeysOnly))
+            ),
+            Ok(Value::Int(5))
+        );
+    }
+
+    #[test]
+    fn aggregate_reports_server_errors() {
+        use crate::errors::ServerErrorKind;
+
+        let server_error = crate::ServerError::new(ServerErrorKind::ReadOnly, None);
+        let results = vec![
+            ("a:1".into(), Ok(Value::Okay)),
+            ("b:1".into(), Ok(Value::ServerError(server_error))),
+        ];
+        let err = aggregate_results(
+            results,
+            ResponsePolicy::for_command(b"MSET"),
+            Some((&[], &MultiSlotArgPattern::KeyValuePairs)),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ServerErrorKind::ReadOnly.into());
+    }
+
+    #[test]
+    fn builder_rejects_invalid_configuration() {
+        assert!(ShardedClientBuilder::new(Vec::<String>::new())
+            .unwrap()
+            .build()
+            .is_err());
+        assert!(ShardedClientBuilder::new(vec!["redis://127.0.0.1:6379"])
+            .unwrap()
+            .add_node("redis://127.0.0.1:6379", 2)
+            .unwrap()
+            .build()
+            .is_err());
+        assert!(ShardedClientBuilder::new(vec!["redis://127.0.0.1:6379"])
+            .unwrap()
+            .add_node("redis://127.0.0.1:6380", 0)
+            .is_err());
+        assert!(ShardedClientBuilder::new(vec!["redis://127.0.0.1:6379"])
+            .unwrap()
+            .add_node("redis://127.0.0.1:6380", MAX_WEIGHT + 1)
+            .is_err());
+        assert!(ShardedClientBuilder::new(vec!["redis://127.0.0.1:6379"])
+            .unwrap()
+            .add_node("redis://127.0.0.1:6380", u32::MAX)
+            .is_err());
+    }
+
+    #[test]
+    fn builder_rejects_nodes_with_different_databases() {
+        let err = ShardedClientBuilder::new(vec!["redis://127.0.0.1:6379/1"])
+            .unwrap()
+            .add_node("redis://127.0.0.1:6380/2", 1)
+            .unwrap()
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidClientConfig);
+
+        let client = ShardedClientBuilder::new(vec!["redis://127.0.0.1:6379/1"])
+            .unwrap()
+            .add_node("redis://127.0.0.1:6380/1", 1)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(client.db, 1);
+    }
+}
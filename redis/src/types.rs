@@ -1,8 +1,6 @@
 use crate::errors::ParsingError;
 #[cfg(feature = "ahash")]
 pub(crate) use ahash::{AHashMap as HashMap, AHashSet as HashSet};
-#[cfg(feature = "num-bigint")]
-use num_bigint::BigInt;
 use std::borrow::Cow;
 #[cfg(not(feature = "ahash"))]
 pub(crate) use std::collections::{HashMap, HashSet};
@@ -14,7 +12,7 @@ use std::io;
 use std::ops::Deref;
 use std::str::from_utf8;
 
-use crate::errors::{RedisError, ServerError};
+use crate::errors::RedisError;
 
 /// Helper enum that is used to define expiry time
 #[non_exhaustive]
@@ -80,382 +78,43 @@ pub enum NumericBehavior {
     NumberIsFloat,
 }
 
-/// Internal low-level redis value enum.
-#[derive(PartialEq, Clone, Default)]
-#[non_exhaustive]
-pub enum Value {
-    /// A nil response from the server.
-    #[default]
-    Nil,
-    /// An integer response.  Note that there are a few situations
-    /// in which redis actually returns a string for an integer which
-    /// is why this library generally treats integers and strings
-    /// the same for all numeric responses.
-    Int(i64),
-    /// An arbitrary binary data, usually represents a binary-safe string.
-    BulkString(Vec<u8>),
-    /// A response containing an array with more data. This is generally used by redis
-    /// to express nested structures.
-    Array(Vec<Value>),
-    /// A simple string response, without line breaks and not binary safe.
-    SimpleString(String),
-    /// A status response which represents the string "OK".
-    Okay,
-    /// Unordered key,value list from the server. Use `as_map_iter` function.
-    Map(Vec<(Value, Value)>),
-    /// Attribute value from the server. Client will give data instead of whole Attribute type.
-    Attribute {
-        /// Data that attributes belong to.
-        data: Box<Value>,
-        /// Key,Value list of attributes.
-        attributes: Vec<(Value, Value)>,
-    },
-    /// Unordered set value from the server.
-    Set(Vec<Value>),
-    /// A floating number response from the server.
-    Double(f64),
-    /// A boolean response from the server.
-    Boolean(bool),
-    /// First String is format and other is the string
-    VerbatimString {
-        /// Text's format type
-        format: VerbatimFormat,
-        /// Remaining string check format before using!
-        text: String,
-    },
-    #[cfg(feature = "num-bigint")]
-    /// Very large number that out of the range of the signed 64 bit numbers
-    BigNumber(BigInt),
-    #[cfg(not(feature = "num-bigint"))]
-    /// Very large number that out of the range of the signed 64 bit numbers
-    BigNumber(Vec<u8>),
-    /// Push data from the server.
-    Push {
-        /// Push Kind
-        kind: PushKind,
-        /// Remaining data from push message
-        data: Vec<Value>,
-    },
-    /// Represents an error message from the server
-    ServerError(ServerError),
-}
+pub use redis_resp::{ProtocolVersion, PushKind, Value, VerbatimFormat};
 
-/// `VerbatimString`'s format types defined by spec
-#[derive(PartialEq, Clone, Debug)]
-#[non_exhaustive]
-pub enum VerbatimFormat {
-    /// Unknown type to catch future formats.
-    Unknown(String),
-    /// `mkd` format
-    Markdown,
-    /// `txt` format
-    Text,
-}
-
-/// `Push` type's currently known kinds.
-#[derive(PartialEq, Clone, Debug)]
-#[non_exhaustive]
-pub enum PushKind {
-    /// `Disconnection` is sent from the **library** when connection is closed.
-    Disconnection,
-    /// Other kind to catch future kinds.
-    Other(String),
-    /// `invalidate` is received when a key is changed/deleted.
-    Invalidate,
-    /// `message` is received when pubsub message published by another client.
-    Message,
-    /// `pmessage` is received when pubsub message published by another client and client subscribed to topic via pattern.
-    PMessage,
-    /// `smessage` is received when pubsub message published by another client and client subscribed to it with sharding.
-    SMessage,
-    /// `unsubscribe` is received when client unsubscribed from a channel.
-    Unsubscribe,
-    /// `punsubscribe` is received when client unsubscribed from a pattern.
-    PUnsubscribe,
-    /// `sunsubscribe` is received when client unsubscribed from a shard channel.
-    SUnsubscribe,
-    /// `subscribe` is received when client subscribed to a channel.
-    Subscribe,
-    /// `psubscribe` is received when client subscribed to a pattern.
-    PSubscribe,
-    /// `ssubscribe` is received when client subscribed to a shard channel.
-    SSubscribe,
-}
-
-impl PushKind {
-    #[cfg(feature = "aio")]
-    pub(crate) fn has_reply(&self) -> bool {
-        matches!(
-            self,
-            &PushKind::Unsubscribe
-                | &PushKind::PUnsubscribe
-                | &PushKind::SUnsubscribe
-                | &PushKind::Subscribe
-                | &PushKind::PSubscribe
-                | &PushKind::SSubscribe
-        )
-    }
-}
-
-impl fmt::Display for VerbatimFormat {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            VerbatimFormat::Markdown => write!(f, "mkd"),
-            VerbatimFormat::Unknown(val) => write!(f, "{val}"),
-            VerbatimFormat::Text => write!(f, "txt"),
-        }
+fn is_collection_of_len(value: &Value, len: usize) -> bool {
+    match value {
+        Value::Array(values) => values.len() == len,
+        Value::Map(items) => items.len() * 2 == len,
+        Value::Set(values) => values.len() == len,
+        _ => false,
     }
 }
 
-impl fmt::Display for PushKind {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            PushKind::Other(kind) => write!(f, "{kind}"),
-            PushKind::Invalidate => write!(f, "invalidate"),
-            PushKind::Message => write!(f, "message"),
-            PushKind::PMessage => write!(f, "pmessage"),
-            PushKind::SMessage => write!(f, "smessage"),
-            PushKind::Unsubscribe => write!(f, "unsubscribe"),
-            PushKind::PUnsubscribe => write!(f, "punsubscribe"),
-            PushKind::SUnsubscribe => write!(f, "sunsubscribe"),
-            PushKind::Subscribe => write!(f, "subscribe"),
-            PushKind::PSubscribe => write!(f, "psubscribe"),
-            PushKind::SSubscribe => write!(f, "ssubscribe"),
-            PushKind::Disconnection => write!(f, "disconnection"),
-        }
-    }
-}
-
-#[non_exhaustive]
-pub enum MapIter<'a> {
-    Array(std::slice::Iter<'a, Value>),
-    Map(std::slice::Iter<'a, (Value, Value)>),
-}
-
-impl<'a> Iterator for MapIter<'a> {
-    type Item = (&'a Value, &'a Value);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self {
-            MapIter::Array(iter) => Some((iter.next()?, iter.next()?)),
-            MapIter::Map(iter) => {
-                let (k, v) = iter.next()?;
-                Some((k, v))
-            }
-        }
-    }
-
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        match self {
-            MapIter::Array(iter) => iter.size_hint(),
-            MapIter::Map(iter) => iter.size_hint(),
-        }
-    }
-}
-
-#[non_exhaustive]
-pub enum OwnedMapIter {
-    Array(std::vec::IntoIter<Value>),
-    Map(std::vec::IntoIter<(Value, Value)>),
-}
-
-impl Iterator for OwnedMapIter {
-    type Item = (Value, Value);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self {
-            OwnedMapIter::Array(iter) => Some((iter.next()?, iter.next()?)),
-            OwnedMapIter::Map(iter) => iter.next(),
-        }
-    }
-
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        match self {
-            OwnedMapIter::Array(iter) => {
-                let (low, high) = iter.size_hint();
-                (low / 2, high.map(|h| h / 2))
-            }
-            OwnedMapIter::Map(iter) => iter.size_hint(),
-        }
-    }
-}
-
-/// Values are generally not used directly unless you are using the
-/// more low level functionality in the library.  For the most part
-/// this is hidden with the help of the `FromRedisValue` trait.
-///
-/// While on the redis protocol there is an error type this is already
-/// separated at an early point so the value only holds the remaining
-/// types.
-impl Value {
-    /// Checks if the return value looks like it fulfils the cursor
-    /// protocol.  That means the result is an array item of length
-    /// two with the first one being a cursor and the second an
-    /// array response.
-    pub fn looks_like_cursor(&self) -> bool {
-        match *self {
-            Value::Array(ref items) => {
-                if items.len() != 2 {
-                    return false;
-                }
-                matches!(items[0], Value::BulkString(_)) && matches!(items[1], Value::Array(_))
-            }
-            _ => false,
-        }
-    }
-
-    /// Returns an `&[Value]` if `self` is compatible with a sequence type
-    pub fn as_sequence(&self) -> Option<&[Value]> {
-        match self {
-            Value::Array(items) => Some(&items[..]),
-            Value::Set(items) => Some(&items[..]),
-            Value::Nil => Some(&[]),
-            _ => None,
-        }
-    }
-
-    /// Returns a `Vec<Value>` if `self` is compatible with a sequence type,
-    /// otherwise returns `Err(self)`.
-    pub fn into_sequence(self) -> Result<Vec<Value>, Value> {
-        match self {
-            Value::Array(items) => Ok(items),
-            Value::Set(items) => Ok(items),
-            Value::Nil => Ok(vec![]),
-            _ => Err(self),
-        }
-    }
-
-    /// Returns an iterator of `(&Value, &Value)` if `self` is compatible with a map type
-    pub fn as_map_iter(&self) -> Option<MapIter<'_>> {
-        match self {
-            Value::Array(items) => {
-                if items.len() % 2 == 0 {
-                    Some(MapIter::Array(items.iter()))
-                } else {
-                    None
-                }
-            }
-            Value::Map(items) => Some(MapIter::Map(items.iter())),
-            _ => None,
-        }
-    }
-
-    /// Returns an iterator of `(Value, Value)` if `self` is compatible with a map type.
-    /// If not, returns `Err(self)`.
-    pub fn into_map_iter(self) -> Result<OwnedMapIter, Value> {
-        match self {
-            Value::Array(items) => {
-                if items.len() % 2 == 0 {
-                    Ok(OwnedMapIter::Array(items.into_iter()))
-                } else {
-                    Err(Value::Array(items))
-                }
-            }
-            Value::Map(items) => Ok(OwnedMapIter::Map(items.into_iter())),
-            _ => Err(self),
-        }
-    }
-
-    /// If value contains a server error, return it as an Err. Otherwise wrap the value in Ok.
-    pub fn extract_error(self) -> RedisResult<Self> {
-        match self {
-            Self::Array(val) => Ok(Self::Array(Self::extract_error_vec(val)?)),
-            Self::Map(map) => Ok(Self::Map(Self::extract_error_map(map)?)),
-            Self::Attribute { data, attributes } => {
-                let data = Box::new((*data).extract_error()?);
-                let attributes = Self::extract_error_map(attributes)?;
-                Ok(Value::Attribute { data, attributes })
-            }
-            Self::Set(set) => Ok(Self::Set(Self::extract_error_vec(set)?)),
-            Self::Push { kind, data } => Ok(Self::Push {
-                kind,
-                data: Self::extract_error_vec(data)?,
-            }),
-            Value::ServerError(err) => Err(err.into()),
-            _ => Ok(self),
-        }
-    }
-
-    pub(crate) fn extract_error_vec(vec: Vec<Self>) -> RedisResult<Vec<Self>> {
-        vec.into_iter()
-            .map(Self::extract_error)
-            .collect::<RedisResult<Vec<_>>>()
-    }
-
-    pub(crate) fn extract_error_map(map: Vec<(Self, Self)>) -> RedisResult<Vec<(Self, Self)>> {
-        let mut vec = Vec::with_capacity(map.len());
-        for (key, value) in map.into_iter() {
-            vec.push((key.extract_error()?, value.extract_error()?));
-        }
-        Ok(vec)
-    }
-
-    fn is_collection_of_len(&self, len: usize) -> bool {
-        match self {
-            Value::Array(values) => values.len() == len,
-            Value::Map(items) => items.len() * 2 == len,
-            Value::Set(values) => values.len() == len,
-            _ => false,
-        }
-    }
+/// Library generic result type.
+pub type RedisResult<T> = Result<T, RedisError>;
 
-    #[cfg(feature = "cluster-async")]
-    pub(crate) fn is_error_that_requires_action(&self) -> bool {
-        matches!(self, Self::ServerError(error) if error.requires_action())
-    }
+/// Returns the server errors in the value as errors, see [`Value::extract_error`].
+#[cfg(feature = "cluster")]
+pub(crate) fn extract_error(value: Value) -> RedisResult<Value> {
+    Ok(value.extract_error()?)
 }
 
-impl fmt::Debug for Value {
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            Value::Nil => write!(fmt, "nil"),
-            Value::Int(val) => write!(fmt, "int({val:?})"),
-            Value::BulkString(ref val) => match from_utf8(val) {
-                Ok(x) => write!(fmt, "bulk-string('{x:?}')"),
-                Err(_) => write!(fmt, "binary-data({val:?})"),
-            },
-            Value::Array(ref values) => write!(fmt, "array({values:?})"),
-            Value::Push { ref kind, ref data } => write!(fmt, "push({kind:?}, {data:?})"),
-            Value::Okay => write!(fmt, "ok"),
-            Value::SimpleString(ref s) => write!(fmt, "simple-string({s:?})"),
-            Value::Map(ref values) => write!(fmt, "map({values:?})"),
-            Value::Attribute {
-                ref data,
-                attributes: _,
-            } => write!(fmt, "attribute({data:?})"),
-            Value::Set(ref values) => write!(fmt, "set({values:?})"),
-            Value::Double(ref d) => write!(fmt, "double({d:?})"),
-            Value::Boolean(ref b) => write!(fmt, "boolean({b:?})"),
-            Value::VerbatimString {
-                ref format,
-                ref text,
-            } => {
-                write!(fmt, "verbatim-string({format:?},{text:?})")
-            }
-            Value::BigNumber(ref m) => write!(fmt, "big-number({m:?})"),
-            Value::ServerError(ref err) => match err.details() {
-                Some(details) => write!(fmt, "Server error: `{}: {details}`", err.code()),
-                None => write!(fmt, "Server error: `{}`", err.code()),
-            },
-        }
-    }
+#[cfg(feature = "cluster")]
+pub(crate) fn extract_error_vec(values: Vec<Value>) -> RedisResult<Vec<Value>> {
+    values.into_iter().map(extract_error).collect()
 }
 
-/// Library generic result type.
-pub type RedisResult<T> = Result<T, RedisError>;
-
 impl<T: FromRedisValue> FromRedisValue for RedisResult<T> {
     fn from_redis_value_ref(value: &Value) -> Result<Self, ParsingError> {
-        match value {
+        match get_inner_value(value) {
             Value::ServerError(err) => Ok(Err(err.clone().into())),
             _ => from_redis_value_ref(value).map(|result| Ok(result)),
         }
     }
 
     fn from_redis_value(value: Value) -> Result<Self, ParsingError> {
-        match value {
+        match get_owned_inner_value(value) {
             Value::ServerError(err) => Ok(Err(err.into())),
-            _ => from_redis_value(value).map(|result| Ok(result)),
+            value => from_redis_value(value).map(|result| Ok(result)),
         }
     }
 }
@@ -545,6 +204,116 @@ impl Deref for InfoDict {
     }
 }
 
+/// Information about the server, as returned by the [`HELLO`][1] command.
+///
+/// Connections that use RESP3 keep the information that the server sent while
+/// connecting, see for example [`Connection::server_info`](crate::Connection::server_info).
+///
+/// [1]: https://redis.io/docs/latest/commands/hello/
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
+pub struct ServerInfo {
+    /// The name of the server, such as `redis` or `valkey`.
+    pub server: String,
+    /// The version of the server.
+    pub version: String,
+    /// The protocol version that the connection uses.
+    pub protocol: ProtocolVersion,
+    /// The ID of the connection.
+    pub id: i64,
+    /// The mode that the server runs in, such as `standalone` or `cluster`.
+    pub mode: String,
+    /// The role of the server, such as `master` or `replica`.
+    pub role: String,
+    /// The modules that are loaded into the server.
+    pub modules: Vec<Value>,
+}
+
+impl FromRedisValue for ServerInfo {
+    fn from_redis_value_ref(v: &Value) -> Result<Self, ParsingError> {
+        let v = get_inner_value(v);
+        let Some(fields) = v.as_map_iter() else {
+            crate::errors::invalid_type_error!(v, "HELLO response should be a map")
+        };
+        let mut info = ServerInfo::default();
+        for (key, value) in fields {
+            match from_redis_value_ref::<String>(key)?.as_str() {
+                "server" => info.server = from_redis_value_ref(value)?,
+                "version" => info.version = from_redis_value_ref(value)?,
+                "proto" => {
+                    info.protocol = match from_redis_value_ref::<i64>(value)? {
+                        2 => ProtocolVersion::RESP2,
+                        _ => ProtocolVersion::RESP3,
+                    }
+                }
+                "id" => info.id = from_redis_value_ref(value)?,
+                "mode" => info.mode = from_redis_value_ref(value)?,
+                "role" => info.role = from_redis_value_ref(value)?,
+                "modules" => info.modules = from_redis_value_ref(value)?,
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        Self::from_redis_value_ref(&v)
+    }
+}
+
+/// A reply together with the RESP3 attributes that the server attached to it.
+///
+/// Attributes carry auxiliary information about a reply, such as the
+/// popularity of the keys that were read. Other types ignore attributes and
+/// only convert the reply itself, so they have to be requested explicitly:
+///
+/// ```rust,no_run
+/// # fn do_something() -> redis::RedisResult<()> {
+/// # let client = redis::Client::open("redis://127.0.0.1/?protocol=resp3").unwrap();
+/// # let mut con = client.get_connection().unwrap();
+/// let reply: redis::WithAttributes<Option<String>> = redis::cmd("GET").arg("key").query(&mut con)?;
+/// for (key, value) in &reply.attributes {
+///     println!("{key:?}: {value:?}");
+/// }
+/// let value = reply.data;
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithAttributes<T> {
+    /// The reply.
+    pub data: T,
+    /// The attributes of the reply, which are empty if the server didn't send any.
+    pub attributes: Vec<(Value, Value)>,
+}
+
+impl<T: FromRedisValue> FromRedisValue for WithAttributes<T> {
+    fn from_redis_value_ref(v: &Value) -> Result<Self, ParsingError> {
+        match v {
+            Value::Attribute { data, attributes } => Ok(WithAttributes {
+                data: from_redis_value_ref(data)?,
+                attributes: attributes.clone(),
+            }),
+            _ => Ok(WithAttributes {
+                data: from_redis_value_ref(v)?,
+                attributes: Vec::new(),
+            }),
+        }
+    }
+
+    fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        match v {
+            Value::Attribute { data, attributes } => Ok(WithAttributes {
+                data: from_redis_value(*data)?,
+                attributes,
+            }),
+            _ => Ok(WithAttributes {
+                data: from_redis_value(v)?,
+                attributes: Vec::new(),
+            }),
+        }
+    }
+}
+
 /// High level representation of response to the [`ROLE`][1] command.
 ///
 /// [1]: https://redis.io/docs/latest/commands/role/
@@ -713,6 +482,25 @@ pub trait RedisWrite {
         self.write_arg(arg.to_string().as_bytes())
     }
 
+    /// Accepts a shared argument.
+    ///
+    /// Unlike [`write_arg`](Self::write_arg), implementations may keep a reference to the
+    /// argument instead of copying it. [`Cmd`](crate::Cmd) does so for large arguments, and
+    /// writes them to the socket with vectored I/O.
+    fn write_shared_arg(&mut self, arg: &std::sync::Arc<[u8]>) {
+        self.write_arg(arg)
+    }
+
+    #[cfg(feature = "bytes")]
+    /// Accepts a shared argument.
+    ///
+    /// Unlike [`write_arg`](Self::write_arg), implementations may keep a reference to the
+    /// argument instead of copying it. [`Cmd`](crate::Cmd) does so for large arguments, and
+    /// writes them to the socket with vectored I/O.
+    fn write_bytes_arg(&mut self, arg: &bytes::Bytes) {
+        self.write_arg(arg)
+    }
+
     /// Appends an empty argument to the command, and returns a
     /// [`std::io::Write`] instance that can write to it.
     ///
@@ -915,6 +703,16 @@ pub trait ToRedisArgs: Sized {
         Self::make_arg_iter_ref(items.iter(), out)
     }
 
+    /// This only exists internally as a workaround for the lack of
+    /// specialization.
+    #[doc(hidden)]
+    fn write_args_from_arc<W>(items: &std::sync::Arc<[Self]>, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        Self::write_args_from_slice(items, out)
+    }
+
     /// This only exists internally as a workaround for the lack of
     /// specialization.
     #[doc(hidden)]
@@ -1015,6 +813,13 @@ impl ToRedisArgs for u8 {
         out.write_arg(items);
     }
 
+    fn write_args_from_arc<W>(items: &std::sync::Arc<[u8]>, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        out.write_shared_arg(items);
+    }
+
     fn is_single_vec_arg(_items: &[u8]) -> bool {
         true
     }
@@ -1100,6 +905,18 @@ impl ToRedisArgs for String {
 }
 impl ToSingleRedisArg for String {}
 
+#[cfg(feature = "bytes")]
+impl ToRedisArgs for bytes::Bytes {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        out.write_bytes_arg(self)
+    }
+}
+#[cfg(feature = "bytes")]
+impl ToSingleRedisArg for bytes::Bytes {}
+
 impl ToRedisArgs for &str {
     fn write_redis_args<W>(&self, out: &mut W)
     where
@@ -1163,6 +980,9 @@ impl<T: ToRedisArgs> ToRedisArgs for Option<T> {
 
 macro_rules! impl_write_redis_args_for_collection {
     ($type:ty) => {
+        impl_write_redis_args_for_collection!($type, write_args_from_slice);
+    };
+    ($type:ty, $write_args:ident) => {
         impl<'a, T> ToRedisArgs for $type
         where
             T: ToRedisArgs,
@@ -1172,7 +992,7 @@ macro_rules! impl_write_redis_args_for_collection {
             where
                 W: ?Sized + RedisWrite,
             {
-                ToRedisArgs::write_args_from_slice(self, out)
+                T::$write_args(self, out)
             }
 
             fn num_of_args(&self) -> usize {
@@ -1228,7 +1048,7 @@ deref_to_write_redis_args_impl! {std::rc::Rc<T>}
 impl_write_redis_args_for_collection! {&'a [T]}
 impl_write_redis_args_for_collection! {&'a mut [T]}
 impl_write_redis_args_for_collection! {Box<[T]>}
-impl_write_redis_args_for_collection! {std::sync::Arc<[T]>}
+impl_write_redis_args_for_collection! {std::sync::Arc<[T]>, write_args_from_arc}
 impl_write_redis_args_for_collection! {std::rc::Rc<[T]>}
 impl_write_redis_args_for_collection! {Vec<T>}
 impl ToSingleRedisArg for &[u8] {}
@@ -1414,6 +1234,7 @@ fn vec_to_array<T, const N: usize>(
 
 impl<T: FromRedisValue, const N: usize> FromRedisValue for [T; N] {
     fn from_redis_value_ref(value: &Value) -> Result<[T; N], ParsingError> {
+        let value = get_inner_value(value);
         match *value {
             Value::BulkString(ref bytes) => match FromRedisValue::from_byte_slice(bytes) {
                 Some(items) => vec_to_array(items, value),
@@ -1521,7 +1342,7 @@ fn get_inner_value(v: &Value) -> &Value {
     }
 }
 
-fn get_owned_inner_value(v: Value) -> Value {
+pub(crate) fn get_owned_inner_value(v: Value) -> Value {
     if let Value::Attribute {
         data,
         attributes: _,
@@ -1613,7 +1434,7 @@ from_redis_value_for_num!(usize);
 ))]
 macro_rules! from_redis_value_for_bignum_internal {
     ($t:ty, $v:expr) => {{
-        let v = $v;
+        let v = get_inner_value($v);
         match *v {
             Value::Int(val) => <$t>::try_from(val).map_err(|_| {
                 crate::errors::invalid_type_error_inner!(v, "Could not convert from integer.")
@@ -1781,6 +1602,7 @@ macro_rules! from_vec_from_redis_value {
     (<$T:ident> $Type:ty; $convert:expr) => {
         impl<$T: FromRedisValue> FromRedisValue for $Type {
             fn from_redis_value_ref(v: &Value) -> Result<$Type, ParsingError> {
+                let v = get_inner_value(v);
                 match v {
                     // All binary data except u8 will try to parse into a single element vector.
                     // u8 has its own implementation of from_byte_slice.
@@ -1812,6 +1634,7 @@ macro_rules! from_vec_from_redis_value {
                 }
             }
             fn from_redis_value(v: Value) -> Result<$Type, ParsingError> {
+                let v = get_owned_inner_value(v);
                 match v {
                     // Binary data is parsed into a single-element vector, except
                     // for the element type `u8`, which directly consumes the entire
@@ -1968,7 +1791,7 @@ impl FromRedisValue for Value {
 
 impl FromRedisValue for () {
     fn from_redis_value_ref(v: &Value) -> Result<(), ParsingError> {
-        match v {
+        match get_inner_value(v) {
             Value::ServerError(err) => Err(ParsingError::from(err.to_string())),
             _ => Ok(()),
         }
@@ -2089,7 +1912,7 @@ macro_rules! from_redis_value_for_tuple {
                     return Ok(vec![]);
                 }
 
-                if items.iter().all(|item| item.is_collection_of_len(n)) {
+                if items.iter().all(|item| is_collection_of_len(item, n)) {
                     return items.iter().map(|item| from_redis_value_ref(item)).collect();
                 }
 
@@ -2123,7 +1946,7 @@ macro_rules! from_redis_value_for_tuple {
                 if items.len() == 0 {
                     return vec![];
                 }
-                if items.iter().all(|item| item.is_collection_of_len(n)) {
+                if items.iter().all(|item| is_collection_of_len(item, n)) {
                     return items.into_iter().map(|item| from_redis_value(item).map_err(|err|err.into())).collect();
                 }
 
@@ -2152,7 +1975,7 @@ macro_rules! from_redis_value_for_tuple {
                 if items.len() == 0 {
                     return Ok(vec![])
                 }
-                if items.iter().all(|item| item.is_collection_of_len(n)) {
+                if items.iter().all(|item| is_collection_of_len(item, n)) {
                     return items.into_iter().map(|item| from_redis_value(item)).collect();
                 }
 
@@ -2237,6 +2060,7 @@ impl FromRedisValue for bytes::Bytes {
 #[cfg(feature = "uuid")]
 impl FromRedisValue for uuid::Uuid {
     fn from_redis_value_ref(v: &Value) -> Result<Self, ParsingError> {
+        let v = get_inner_value(v);
         match *v {
             Value::BulkString(ref bytes) => Ok(uuid::Uuid::from_slice(bytes)?),
             _ => crate::errors::invalid_type_error!(v, "Response type not uuid compatible."),
@@ -2273,27 +2097,6 @@ pub fn from_redis_value<T: FromRedisValue>(v: Value) -> Result<T, ParsingError>
     FromRedisValue::from_redis_value(v)
 }
 
-/// Enum representing the communication protocol with the server.
-///
-/// This enum represents the types of data that the server can send to the client,
-/// and the capabilities that the client can use.
-#[derive(Clone, Eq, PartialEq, Default, Debug, Copy)]
-#[non_exhaustive]
-pub enum ProtocolVersion {
-    /// <https://github.com/redis/redis-specifications/blob/master/protocol/RESP2.md>
-    #[default]
-    RESP2,
-    /// <https://github.com/redis/redis-specifications/blob/master/protocol/RESP3.md>
-    RESP3,
-}
-
-impl ProtocolVersion {
-    /// Returns true if the protocol can support RESP3 features.
-    pub fn supports_resp3(&self) -> bool {
-        !matches!(self, ProtocolVersion::RESP2)
-    }
-}
-
 /// Helper enum that is used to define option for the hash expire commands
 #[derive(Clone, Copy)]
 #[non_exhaustive]
@@ -2395,13 +2198,14 @@ impl From<ValueType> for String {
 
 impl FromRedisValue for ValueType {
     fn from_redis_value_ref(v: &Value) -> Result<Self, ParsingError> {
-        match v {
+        match get_inner_value(v) {
             Value::SimpleString(s) => Ok(s.into()),
             _ => crate::errors::invalid_type_error!(v, "Value type should be a simple string"),
         }
     }
 
     fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        let v = get_owned_inner_value(v);
         match v {
             Value::SimpleString(s) => Ok(s.into()),
             _ => crate::errors::invalid_type_error!(v, "Value type should be a simple string"),
@@ -2434,7 +2238,7 @@ impl IntegerReplyOrNoOp {
 
 impl FromRedisValue for IntegerReplyOrNoOp {
     fn from_redis_value_ref(v: &Value) -> Result<Self, ParsingError> {
-        match v {
+        match get_inner_value(v) {
             Value::Int(s) => match s {
                 -2 => Ok(IntegerReplyOrNoOp::NotExists),
                 -1 => Ok(IntegerReplyOrNoOp::ExistsButNotRelevant),
@@ -2445,6 +2249,7 @@ impl FromRedisValue for IntegerReplyOrNoOp {
     }
 
     fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        let v = get_owned_inner_value(v);
         match v {
             Value::Int(s) => match s {
                 -2 => Ok(IntegerReplyOrNoOp::NotExists),
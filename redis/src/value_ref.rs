@@ -0,0 +1,512 @@
+//! Borrowed decoding of redis replies.
+//!
+//! Decoding a reply into a [`Value`] copies every bulk string into its own
+//! `Vec<u8>`. For replies carrying a lot of data, such as large `MGET` or
+//! `HGETALL` responses, these copies dominate the cost of handling the reply.
+//!
+//! A [`Reply`] instead keeps the raw bytes of a single reply in a shared
+//! [`Bytes`] buffer. Parsing it produces a [`ValueRef`] that points into that
+//! buffer, and [`FromRedisValueRef`] extracts `&str`, `&[u8]` or [`Bytes`]
+//! values from it without copying the underlying data.
+//!
+//! ```rust,no_run
+//! # fn example(reply: redis::Reply) -> redis::RedisResult<()> {
+//! let values: Vec<Option<&str>> = reply.parse()?;
+//! let buffers: Vec<bytes::Bytes> = reply.parse()?;
+//! # Ok(()) }
+//! ```
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::str::from_utf8;
+
+use bytes::Bytes;
+
+use crate::errors::{ParsingError, RedisError, ServerError};
+use crate::types::{RedisResult, Value, WithAttributes};
+
+pub use redis_resp::ValueRef;
+
+// Conversions look through attributes to the data.
+fn inner<'v, 'a>(value: &'v ValueRef<'a>) -> &'v ValueRef<'a> {
+    match value {
+        ValueRef::Attribute { data, .. } => data,
+        _ => value,
+    }
+}
+
+fn first_error<'v>(value: &'v ValueRef<'_>) -> Option<&'v ServerError> {
+    fn in_values<'b>(items: &'b [ValueRef<'_>]) -> Option<&'b ServerError> {
+        items.iter().find_map(first_error)
+    }
+    fn in_pairs<'b>(items: &'b [(ValueRef<'_>, ValueRef<'_>)]) -> Option<&'b ServerError> {
+        items
+            .iter()
+            .find_map(|(key, value)| first_error(key).or_else(|| first_error(value)))
+    }
+
+    match value {
+        ValueRef::ServerError(err) => Some(err),
+        ValueRef::Array(items) | ValueRef::Set(items) => in_values(items),
+        ValueRef::Push { data, .. } => in_values(data),
+        ValueRef::Map(items) => in_pairs(items),
+        ValueRef::Attribute { data, attributes } => {
+            first_error(data).or_else(|| in_pairs(attributes))
+        }
+        _ => None,
+    }
+}
+
+/// A single, complete redis reply held in a shared buffer.
+///
+/// Replies are produced by the [`ReplyCodec`](crate::ReplyCodec), which splits
+/// them off the connection's read buffer without copying, and are returned by
+/// [`send_packed_command_reply`](crate::aio::MultiplexedConnection::send_packed_command_reply)
+/// on async connections. They can also be created from raw bytes with
+/// [`Reply::from_bytes`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct Reply {
+    buffer: Bytes,
+}
+
+impl Reply {
+    /// Wraps a buffer holding exactly one encoded reply.
+    pub fn from_bytes(buffer: Bytes) -> RedisResult<Reply> {
+        match crate::parser::reply_len(&buffer)? {
+            Some(len) if len == buffer.len() => Ok(Reply { buffer }),
+            Some(_) => fail!(ParsingError::from(
+                "Buffer contains more than one reply".to_string()
+            )),
+            None => fail!(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)),
+        }
+    }
+
+    /// Wraps a buffer that is known to hold exactly one encoded reply.
+    #[cfg(feature = "aio")]
+    pub(crate) fn new(buffer: Bytes) -> Reply {
+        Reply { buffer }
+    }
+
+    /// Returns the encoded reply.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns the buffer holding the encoded reply.
+    pub fn into_bytes(self) -> Bytes {
+        self.buffer
+    }
+
+    /// Parses the reply into a value borrowing from this reply's buffer.
+    pub fn value(&self) -> RedisResult<ValueRef<'_>> {
+        crate::parser::parse_redis_value_ref(&self.buffer)
+    }
+
+    /// Parses the reply into an owned [`Value`].
+    pub fn to_value(&self) -> RedisResult<Value> {
+        self.value().map(|value| value.to_value())
+    }
+
+    /// Converts the reply into the requested type, borrowing from this
+    /// reply's buffer where the type allows it.
+    ///
+    /// Like queries, server errors anywhere in the reply are returned as errors.
+    pub fn parse<'a, T: FromRedisValueRef<'a>>(&'a self) -> RedisResult<T> {
+        let value = self.value()?;
+        if let Some(err) = first_error(&value) {
+            fail!(err.clone());
+        }
+        Ok(T::from_value_ref(&value, &self.buffer)?)
+    }
+}
+
+impl std::fmt::Debug for Reply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.value() {
+            Ok(value) => f.debug_tuple("Reply").field(&value).finish(),
+            Err(_) => f.debug_tuple("Reply").field(&self.buffer).finish(),
+        }
+    }
+}
+
+impl TryFrom<Reply> for Value {
+    type Error = RedisError;
+
+    fn try_from(reply: Reply) -> RedisResult<Value> {
+        reply.to_value()
+    }
+}
+
+/// This trait is used to convert a borrowed redis value into a more
+/// appropriate type, without copying string data where possible.
+///
+/// `buffer` is the buffer that `v` was parsed from. It allows handing out
+/// [`Bytes`] that share the buffer instead of copying out of it.
+pub trait FromRedisValueRef<'a>: Sized {
+    /// Given a borrowed redis value this attempts to convert it into the
+    /// given destination type.  If that fails because it's not compatible an
+    /// appropriate error is generated.
+    fn from_value_ref(v: &ValueRef<'a>, buffer: &Bytes) -> Result<Self, ParsingError>;
+}
+
+impl<'a> FromRedisValueRef<'a> for ValueRef<'a> {
+    fn from_value_ref(v: &ValueRef<'a>, _buffer: &Bytes) -> Result<Self, ParsingError> {
+        Ok(v.clone())
+    }
+}
+
+impl<'a> FromRedisValueRef<'a> for Value {
+    fn from_value_ref(v: &ValueRef<'a>, _buffer: &Bytes) -> Result<Self, ParsingError> {
+        Ok(v.to_value())
+    }
+}
+
+impl<'a> FromRedisValueRef<'a> for &'a [u8] {
+    fn from_value_ref(v: &ValueRef<'a>, _buffer: &Bytes) -> Result<Self, ParsingError> {
+        let v = inner(v);
+        match *v {
+            ValueRef::BulkString(bytes) => Ok(bytes),
+            ValueRef::SimpleString(s) => Ok(s.as_bytes()),
+            ValueRef::Okay => Ok(b"OK"),
+            ValueRef::VerbatimString {
+                text: Cow::Borrowed(text),
+                ..
+            } => Ok(text.as_bytes()),
+            _ => crate::errors::invalid_type_error!(v, "Response type not borrowable as bytes."),
+        }
+    }
+}
+
+impl<'a> FromRedisValueRef<'a> for &'a str {
+    fn from_value_ref(v: &ValueRef<'a>, _buffer: &Bytes) -> Result<Self, ParsingError> {
+        let v = inner(v);
+        match *v {
+            ValueRef::BulkString(bytes) => Ok(from_utf8(bytes)?),
+            ValueRef::SimpleString(s) => Ok(s),
+            ValueRef::Okay => Ok("OK"),
+            ValueRef::VerbatimString {
+                text: Cow::Borrowed(text),
+                ..
+            } => Ok(text),
+            _ => crate::errors::invalid_type_error!(v, "Response type not borrowable as string."),
+        }
+    }
+}
+
+fn slice_of(buffer: &Bytes, data: &[u8]) -> Bytes {
+    let start = buffer.as_ptr() as usize;
+    let data_start = data.as_ptr() as usize;
+    if data_start >= start && data_start + data.len() <= start + buffer.len() {
+        buffer.slice_ref(data)
+    } else {
+        Bytes::copy_from_slice(data)
+    }
+}
+
+impl<'a> FromRedisValueRef<'a> for Bytes {
+    fn from_value_ref(v: &ValueRef<'a>, buffer: &Bytes) -> Result<Self, ParsingError> {
+        let v = inner(v);
+        match *v {
+            ValueRef::BulkString(bytes) => Ok(slice_of(buffer, bytes)),
+            ValueRef::SimpleString(s) => Ok(slice_of(buffer, s.as_bytes())),
+            ValueRef::Okay => Ok(Bytes::from_static(b"OK")),
+            _ => crate::errors::invalid_type_error!(v, "Not a bulk string"),
+        }
+    }
+}
+
+impl<'a> FromRedisValueRef<'a> for String {
+    fn from_value_ref(v: &ValueRef<'a>, buffer: &Bytes) -> Result<Self, ParsingError> {
+        match inner(v) {
+            ValueRef::Int(val) => Ok(val.to_string()),
+            ValueRef::Double(val) => Ok(val.to_string()),
+            ValueRef::VerbatimString { text, .. } => Ok(text.to_string()),
+            v => <&str>::from_value_ref(v, buffer).map(str::to_string),
+        }
+    }
+}
+
+impl<'a> FromRedisValueRef<'a> for bool {
+    fn from_value_ref(v: &ValueRef<'a>, _buffer: &Bytes) -> Result<Self, ParsingError> {
+        let v = inner(v);
+        match *v {
+            ValueRef::Nil => Ok(false),
+            ValueRef::Int(val) => Ok(val != 0),
+            ValueRef::SimpleString("1") | ValueRef::BulkString(b"1") => Ok(true),
+            ValueRef::SimpleString("0") | ValueRef::BulkString(b"0") => Ok(false),
+            ValueRef::Boolean(b) => Ok(b),
+            ValueRef::Okay => Ok(true),
+            _ => crate::errors::invalid_type_error!(v, "Response type not bool compatible."),
+        }
+    }
+}
+
+macro_rules! from_value_ref_for_num {
+    ($t:ty) => {
+        impl<'a> FromRedisValueRef<'a> for $t {
+            fn from_value_ref(v: &ValueRef<'a>, _buffer: &Bytes) -> Result<Self, ParsingError> {
+                let v = inner(v);
+                match *v {
+                    ValueRef::Int(val) => Ok(val as $t),
+                    ValueRef::SimpleString(s) => match s.parse::<$t>() {
+                        Ok(rv) => Ok(rv),
+                        Err(_) => {
+                            crate::errors::invalid_type_error!(v, "Could not convert from string.")
+                        }
+                    },
+                    ValueRef::BulkString(bytes) => match from_utf8(bytes)?.parse::<$t>() {
+                        Ok(rv) => Ok(rv),
+                        Err(_) => {
+                            crate::errors::invalid_type_error!(v, "Could not convert from string.")
+                        }
+                    },
+                    ValueRef::Double(val) => Ok(val as $t),
+                    _ => crate::errors::invalid_type_error!(
+                        v,
+                        "Response type not convertible to numeric."
+                    ),
+                }
+            }
+        }
+    };
+}
+
+from_value_ref_for_num!(i8);
+from_value_ref_for_num!(u8);
+from_value_ref_for_num!(i16);
+from_value_ref_for_num!(u16);
+from_value_ref_for_num!(i32);
+from_value_ref_for_num!(u32);
+from_value_ref_for_num!(i64);
+from_value_ref_for_num!(u64);
+from_value_ref_for_num!(i128);
+from_value_ref_for_num!(u128);
+from_value_ref_for_num!(isize);
+from_value_ref_for_num!(usize);
+from_value_ref_for_num!(f32);
+from_value_ref_for_num!(f64);
+
+impl<'a, T: FromRedisValueRef<'a>> FromRedisValueRef<'a> for Option<T> {
+    fn from_value_ref(v: &ValueRef<'a>, buffer: &Bytes) -> Result<Self, ParsingError> {
+        match inner(v) {
+            ValueRef::Nil => Ok(None),
+            v => T::from_value_ref(v, buffer).map(Some),
+        }
+    }
+}
+
+impl<'a, T: FromRedisValueRef<'a>> FromRedisValueRef<'a> for WithAttributes<T> {
+    fn from_value_ref(v: &ValueRef<'a>, buffer: &Bytes) -> Result<Self, ParsingError> {
+        match v {
+            ValueRef::Attribute { data, attributes } => Ok(WithAttributes {
+                data: T::from_value_ref(data, buffer)?,
+                attributes: attributes
+                    .iter()
+                    .map(|(key, value)| (key.to_value(), value.to_value()))
+                    .collect(),
+            }),
+            _ => Ok(WithAttributes {
+                data: T::from_value_ref(v, buffer)?,
+                attributes: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl<'a, T: FromRedisValueRef<'a>> FromRedisValueRef<'a> for Vec<T> {
+    fn from_value_ref(v: &ValueRef<'a>, buffer: &Bytes) -> Result<Self, ParsingError> {
+        let v = inner(v);
+        match v {
+            ValueRef::Array(items) | ValueRef::Set(items) => items
+                .iter()
+                .map(|item| T::from_value_ref(item, buffer))
+                .collect(),
+            ValueRef::Map(items) => items
+                .iter()
+                .map(|item| T::from_value_ref(&ValueRef::Map(vec![item.clone()]), buffer))
+                .collect(),
+            ValueRef::Nil => Ok(Vec::new()),
+            _ => crate::errors::invalid_type_error!(v, "Response type not vector compatible."),
+        }
+    }
+}
+
+impl<'a, K, V, S> FromRedisValueRef<'a> for HashMap<K, V, S>
+where
+    K: FromRedisValueRef<'a> + Eq + Hash,
+    V: FromRedisValueRef<'a>,
+    S: BuildHasher + Default,
+{
+    fn from_value_ref(v: &ValueRef<'a>, buffer: &Bytes) -> Result<Self, ParsingError> {
+        let v = inner(v);
+        match v {
+            ValueRef::Nil => Ok(Default::default()),
+            ValueRef::Map(items) => items
+                .iter()
+                .map(|(key, value)| {
+                    Ok((
+                        K::from_value_ref(key, buffer)?,
+                        V::from_value_ref(value, buffer)?,
+                    ))
+                })
+                .collect(),
+            ValueRef::Array(items) if items.len() % 2 == 0 => items
+                .chunks_exact(2)
+                .map(|pair| {
+                    Ok((
+                        K::from_value_ref(&pair[0], buffer)?,
+                        V::from_value_ref(&pair[1], buffer)?,
+                    ))
+                })
+                .collect(),
+            _ => crate::errors::invalid_type_error!(v, "Response type not map compatible"),
+        }
+    }
+}
+
+macro_rules! from_value_ref_for_tuple {
+    ($($name:ident),+) => {
+        impl<'a, $($name: FromRedisValueRef<'a>),+> FromRedisValueRef<'a> for ($($name,)+) {
+            fn from_value_ref(v: &ValueRef<'a>, buffer: &Bytes) -> Result<Self, ParsingError> {
+                let v = inner(v);
+                let len = [$(stringify!($name)),+].len();
+                match v {
+                    ValueRef::Array(items) | ValueRef::Set(items) if items.len() == len => {
+                        let mut items = items.iter();
+                        Ok(($({
+                            let item = items.next().ok_or_else(|| {
+                                crate::errors::invalid_type_error_inner!(v, "Missing tuple item")
+                            })?;
+                            $name::from_value_ref(item, buffer)?
+                        },)+))
+                    }
+                    ValueRef::Map(pairs) if pairs.len() * 2 == len => {
+                        let mut items = pairs.iter().flat_map(|(key, value)| [key, value]);
+                        Ok(($({
+                            let item = items.next().ok_or_else(|| {
+                                crate::errors::invalid_type_error_inner!(v, "Missing tuple item")
+                            })?;
+                            $name::from_value_ref(item, buffer)?
+                        },)+))
+                    }
+                    _ => crate::errors::invalid_type_error!(v, "Response type not tuple compatible."),
+                }
+            }
+        }
+    };
+}
+
+from_value_ref_for_tuple!(T1);
+from_value_ref_for_tuple!(T1, T2);
+from_value_ref_for_tuple!(T1, T2, T3);
+from_value_ref_for_tuple!(T1, T2, T3, T4);
+from_value_ref_for_tuple!(T1, T2, T3, T4, T5);
+from_value_ref_for_tuple!(T1, T2, T3, T4, T5, T6);
+from_value_ref_for_tuple!(T1, T2, T3, T4, T5, T6, T7);
+from_value_ref_for_tuple!(T1, T2, T3, T4, T5, T6, T7, T8);
+from_value_ref_for_tuple!(T1, T2, T3, T4, T5, T6, T7, T8, T9);
+from_value_ref_for_tuple!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+from_value_ref_for_tuple!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+from_value_ref_for_tuple!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::{ErrorKind, ServerErrorKind};
+
+    fn reply(bytes: &'static [u8]) -> Reply {
+        Reply::from_bytes(Bytes::from_static(bytes)).unwrap()
+    }
+
+    #[test]
+    fn borrowed_strings_point_into_the_buffer() {
+        let reply = reply(b"*3\r\n$3\r\nfoo\r\n$-1\r\n+bar\r\n");
+        let values: Vec<Option<&str>> = reply.parse().unwrap();
+        assert_eq!(values, vec![Some("foo"), None, Some("bar")]);
+
+        let range = reply.as_bytes().as_ptr_range();
+        assert!(range.contains(&values[0].unwrap().as_ptr()));
+        assert!(range.contains(&values[2].unwrap().as_ptr()));
+    }
+
+    #[test]
+    fn bytes_share_the_buffer() {
+        let reply = reply(b"*2\r\n$3\r\nfoo\r\n$5\r\nhello\r\n");
+        let values: Vec<Bytes> = reply.parse().unwrap();
+        assert_eq!(values, vec![Bytes::from("foo"), Bytes::from("hello")]);
+        assert_eq!(values[1].as_ptr(), reply.as_bytes()[17..].as_ptr());
+    }
+
+    #[test]
+    fn bytes_outside_the_buffer_are_copied() {
+        let value = ValueRef::BulkString(b"foo");
+        let bytes = Bytes::from_value_ref(&value, &Bytes::new()).unwrap();
+        assert_eq!(bytes, Bytes::from("foo"));
+    }
+
+    #[test]
+    fn map_from_resp2_and_resp3() {
+        let resp2 = reply(b"*4\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n");
+        let resp3 = reply(b"%2\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n:2\r\n");
+        for reply in [resp2, resp3] {
+            let map: HashMap<&str, i64> = reply.parse().unwrap();
+            assert_eq!(map, HashMap::from([("a", 1), ("b", 2)]));
+        }
+    }
+
+    #[test]
+    fn tuples_and_numbers() {
+        let reply = reply(b"*4\r\n:1\r\n$3\r\n2.5\r\n,3.5\r\n#t\r\n");
+        let (a, b, c, d): (i32, f64, f64, bool) = reply.parse().unwrap();
+        assert_eq!((a, b, c, d), (1, 2.5, 3.5, true));
+
+        let result: RedisResult<(i32, i32)> = reply.parse();
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Parse);
+    }
+
+    #[test]
+    fn attributes_are_transparent() {
+        let reply = reply(b"|1\r\n+key-popularity\r\n:1\r\n$3\r\nfoo\r\n");
+        let value: &str = reply.parse().unwrap();
+        assert_eq!(value, "foo");
+    }
+
+    #[test]
+    fn attributes_can_be_requested() {
+        let reply = reply(b"|1\r\n+key-popularity\r\n:1\r\n$3\r\nfoo\r\n");
+        let value: WithAttributes<&str> = reply.parse().unwrap();
+        assert_eq!(value.data, "foo");
+        assert_eq!(
+            value.attributes,
+            vec![(Value::SimpleString("key-popularity".into()), Value::Int(1))]
+        );
+    }
+
+    #[test]
+    fn server_errors_are_returned_as_errors() {
+        let reply =
+            reply(b"*2\r\n+OK\r\n-READONLY You can't write against a read only replica.\r\n");
+        let result: RedisResult<Vec<ValueRef<'_>>> = reply.parse();
+        assert_eq!(
+            result.unwrap_err().kind(),
+            ErrorKind::Server(ServerErrorKind::ReadOnly)
+        );
+
+        assert_eq!(
+            reply.to_value().unwrap(),
+            Value::Array(vec![
+                Value::Okay,
+                Value::ServerError(ServerError::new(
+                    ServerErrorKind::ReadOnly,
+                    Some("You can't write against a read only replica."),
+                )),
+            ])
+        );
+    }
+
+    #[test]
+    fn from_bytes_requires_exactly_one_reply() {
+        assert!(Reply::from_bytes(Bytes::from_static(b"$3\r\nfo")).is_err());
+        assert!(Reply::from_bytes(Bytes::from_static(b"+OK\r\n+OK\r\n")).is_err());
+    }
+}
@@ -187,7 +187,7 @@ quickcheck! {
 
         let mut reader = &encoded_input[..];
         let mut partial_reader = PartialAsyncRead { inner: &mut reader, ops: Box::new(seq.into_iter()) };
-        let mut decoder = combine::stream::Decoder::new();
+        let mut decoder = redis::AsyncValueDecoder::new();
 
         let result = current_thread_runtime().block_on(redis::parse_redis_value_async(&mut decoder, &mut partial_reader));
         assert!(result.as_ref().is_ok(), "{}", result.unwrap_err());
@@ -24,9 +24,23 @@ type Handler = Arc<dyn Fn(&[u8], u16) -> Result<(), RedisResult<Value>> + Send +
 
 static HANDLERS: LazyLock<RwLock<HashMap<String, Handler>>> = LazyLock::new(Default::default);
 
+#[cfg(feature = "cluster-async")]
+static RESPONSE_DELAYS: LazyLock<RwLock<HashMap<(String, u16), Duration>>> =
+    LazyLock::new(Default::default);
+
+/// Delays the async responses of the node `name:port` by `delay`.
+#[cfg(feature = "cluster-async")]
+pub fn delay_responses(name: &str, port: u16, delay: Duration) {
+    RESPONSE_DELAYS
+        .write()
+        .unwrap()
+        .insert((name.to_string(), port), delay);
+}
+
 #[derive(Clone)]
 pub struct MockConnection {
     pub handler: Handler,
+    pub name: String,
     pub port: u16,
 }
 
@@ -52,6 +66,7 @@ impl cluster_async::Connect for MockConnection {
                 .get(name)
                 .unwrap_or_else(|| panic!("Handler `{name}` were not installed"))
                 .clone(),
+            name: name.clone(),
             port,
         }))
     }
@@ -75,6 +90,7 @@ impl cluster::Connect for MockConnection {
                 .get(name)
                 .unwrap_or_else(|| panic!("Handler `{name}` were not installed"))
                 .clone(),
+            name: name.clone(),
             port,
         })
     }
@@ -209,10 +225,20 @@ pub fn respond_startup_with_replica_using_config(
 #[cfg(feature = "cluster-async")]
 impl aio::ConnectionLike for MockConnection {
     fn req_packed_command<'a>(&'a mut self, cmd: &'a redis::Cmd) -> RedisFuture<'a, Value> {
-        Box::pin(future::ready(
-            (self.handler)(&cmd.get_packed_command(), self.port)
-                .expect_err("Handler did not specify a response"),
-        ))
+        let response = (self.handler)(&cmd.get_packed_command(), self.port)
+            .expect_err("Handler did not specify a response");
+        let delay = RESPONSE_DELAYS
+            .read()
+            .unwrap()
+            .get(&(self.name.clone(), self.port))
+            .copied();
+        match delay {
+            Some(delay) => Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                response
+            }),
+            None => Box::pin(future::ready(response)),
+        }
     }
 
     fn req_packed_commands<'a>(
@@ -301,6 +327,11 @@ impl Drop for RemoveHandler {
     fn drop(&mut self) {
         for id in &self.0 {
             HANDLERS.write().unwrap().remove(id);
+            #[cfg(feature = "cluster-async")]
+            RESPONSE_DELAYS
+                .write()
+                .unwrap()
+                .retain(|(name, _), _| name != id);
         }
     }
 }
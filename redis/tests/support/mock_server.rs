@@ -0,0 +1,135 @@
+use std::{
+    io::{BufReader, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use redis::Value;
+
+/// A command that was received by a [`MockServer`].
+pub struct MockRequest<'a> {
+    /// The port of the server.
+    pub port: u16,
+    /// The index of the connection, in the order the connections were accepted.
+    pub connection: usize,
+    /// The name of the command, in upper case.
+    pub name: &'a str,
+    /// The arguments that follow the name.
+    pub args: &'a [Value],
+}
+
+/// How a [`MockServer`] answers a command.
+pub enum MockReply {
+    /// Writes the reply.
+    Reply(Vec<u8>),
+    /// Writes the reply and closes the connection.
+    ReplyAndClose(Vec<u8>),
+    /// Leaves the command unanswered.
+    NoReply,
+    /// Closes the connection without replying.
+    Close,
+}
+
+impl MockReply {
+    pub fn ok() -> Self {
+        MockReply::Reply(b"+OK\r\n".to_vec())
+    }
+}
+
+impl From<&str> for MockReply {
+    fn from(reply: &str) -> Self {
+        MockReply::Reply(reply.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for MockReply {
+    fn from(reply: String) -> Self {
+        MockReply::Reply(reply.into_bytes())
+    }
+}
+
+/// A fake server that answers commands according to a script, and logs the
+/// commands of each connection.
+///
+/// Every connection is served by its own thread, which calls the script, so
+/// the script can sleep to delay a reply.
+pub struct MockServer {
+    port: u16,
+    commands: Arc<Mutex<Vec<(usize, String)>>>,
+}
+
+impl MockServer {
+    pub fn start<F>(script: F) -> Self
+    where
+        F: Fn(&MockRequest) -> MockReply + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let log = commands.clone();
+        let script = Arc::new(script);
+        std::thread::spawn(move || {
+            for (connection, stream) in listener.incoming().enumerate() {
+                let Ok(mut stream) = stream else {
+                    return;
+                };
+                let log = log.clone();
+                let script = script.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut parser = redis::Parser::new();
+                    while let Ok(Value::Array(args)) = parser.parse_value(&mut reader) {
+                        let Some((Value::BulkString(name), args)) = args.split_first() else {
+                            return;
+                        };
+                        let name = String::from_utf8_lossy(name).to_uppercase();
+                        log.lock().unwrap().push((connection, name.clone()));
+                        let request = MockRequest {
+                            port,
+                            connection,
+                            name: &name,
+                            args,
+                        };
+                        let (reply, close) = match script(&request) {
+                            MockReply::Reply(reply) => (reply, false),
+                            MockReply::ReplyAndClose(reply) => (reply, true),
+                            MockReply::NoReply => continue,
+                            MockReply::Close => return,
+                        };
+                        if stream.write_all(&reply).is_err() || close {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        MockServer { port, commands }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn url(&self) -> String {
+        format!("redis://127.0.0.1:{}", self.port)
+    }
+
+    pub fn client(&self) -> redis::Client {
+        redis::Client::open(self.url()).unwrap()
+    }
+
+    /// The names of the received commands, with the index of their connection.
+    pub fn commands(&self) -> Vec<(usize, String)> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    /// How often the command was received, on any connection.
+    pub fn count(&self, name: &str) -> usize {
+        self.commands
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, command)| command == name)
+            .count()
+    }
+}
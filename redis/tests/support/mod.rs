@@ -115,8 +115,12 @@ mod cluster;
 #[cfg(any(feature = "cluster", feature = "cluster-async"))]
 mod mock_cluster;
 
+mod mock_server;
 mod util;
 
+#[allow(unused_imports)]
+pub use self::mock_server::*;
+
 #[cfg(any(feature = "cluster", feature = "cluster-async"))]
 #[allow(unused_imports)]
 pub use self::cluster::*;
@@ -87,6 +87,36 @@ mod basic_async {
         Ok(())
     }
 
+    #[async_test]
+    async fn shared_args(mut con: impl ConnectionLike) -> RedisResult<()> {
+        let value = bytes::Bytes::from(
+            (0..10 * 1024 * 1024)
+                .map(|i| (i % 251) as u8)
+                .collect::<Vec<_>>(),
+        );
+        let (_, _, result, length): ((), (), Vec<u8>, usize) = redis::pipe()
+            .set("key", &value)
+            .set("other_key", &value)
+            .get("key")
+            .strlen("other_key")
+            .query_async(&mut con)
+            .await?;
+        assert!(result == value);
+        assert_eq!(length, value.len());
+
+        // other requests on the connection are unaffected.
+        redis::cmd("SET")
+            .arg("key")
+            .arg(&value)
+            .exec_async(&mut con)
+            .await?;
+        let result: bytes::Bytes = redis::cmd("GET").arg("key").query_async(&mut con).await?;
+        assert!(result == value);
+        let result: String = redis::cmd("ECHO").arg("foo").query_async(&mut con).await?;
+        assert_eq!(result, "foo");
+        Ok(())
+    }
+
     #[async_test]
     async fn no_response_skips_response_even_on_error(
         mut con: impl ConnectionLike,
@@ -666,6 +696,684 @@ mod basic_async {
         Ok(())
     }
 
+    #[async_test]
+    async fn response_timeout_of_request_overrides_connection_timeout() -> RedisResult<()> {
+        // a server that replies to SLOW and BLPOP after 200 milliseconds.
+        let server = MockServer::start(|request| {
+            if matches!(request.name, "SLOW" | "BLPOP") {
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            MockReply::ok()
+        });
+        let client = server.client();
+        let config = redis::AsyncConnectionConfig::new()
+            .set_response_timeout(Some(Duration::from_millis(50)));
+        let mut con = client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+
+        let err = cmd("SLOW").exec_async(&mut con).await.unwrap_err();
+        assert!(err.is_timeout());
+
+        cmd("SLOW")
+            .set_response_timeout(Some(Duration::from_secs(5)))
+            .exec_async(&mut con)
+            .await?;
+        redis::pipe()
+            .cmd("SLOW")
+            .set_response_deadline(std::time::Instant::now() + Duration::from_secs(5))
+            .exec_async(&mut con)
+            .await?;
+
+        // blocking commands get their block time added to the timeout.
+        cmd("BLPOP").arg("list").arg(1).exec_async(&mut con).await?;
+        Ok(())
+    }
+
+    #[async_test]
+    async fn multiplexed_pool_spreads_requests_and_replaces_broken_connections() -> RedisResult<()>
+    {
+        // a server that replies to ID with the index of the connection, replies to SLOW after
+        // 200 milliseconds, and closes the connection on CLOSE.
+        let server = MockServer::start(|request| match request.name {
+            "ID" => format!(":{}\r\n", request.connection).into(),
+            "SLOW" => {
+                std::thread::sleep(Duration::from_millis(200));
+                MockReply::ok()
+            }
+            "CLOSE" => MockReply::Close,
+            _ => MockReply::ok(),
+        });
+        let client = server.client();
+        let config = redis::aio::MultiplexedPoolConfig::new().set_size(2);
+        let mut pool = client.get_multiplexed_pool_with_config(config).await?;
+        assert_eq!(pool.available_connections(), 2);
+
+        // requests avoid the connection that has a request in flight.
+        let (slow, id) = futures::future::join(
+            cmd("SLOW").exec_async(&mut pool.clone()),
+            cmd("ID").query_async::<i64>(&mut pool.clone()),
+        )
+        .await;
+        slow?;
+        let slow_connections: Vec<_> = server
+            .commands()
+            .into_iter()
+            .filter(|(_, name)| name == "SLOW")
+            .map(|(connection, _)| connection as i64)
+            .collect();
+        assert_ne!(slow_connections, [id?]);
+
+        // a broken connection is replaced.
+        let err = cmd("CLOSE").exec_async(&mut pool).await.unwrap_err();
+        assert!(err.is_unrecoverable_error());
+        for _ in 0..100 {
+            if pool.available_connections() == 2 {
+                break;
+            }
+            sleep(Duration::from_millis(10).into()).await;
+        }
+        assert_eq!(pool.available_connections(), 2);
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            ids.push(cmd("ID").query_async::<i64>(&mut pool).await?);
+        }
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&2));
+        Ok(())
+    }
+
+    #[async_test]
+    async fn in_flight_limits_wait_or_fail_fast() -> RedisResult<()> {
+        // a server that replies to SLOW after 100 milliseconds.
+        let server = MockServer::start(|request| {
+            if request.name == "SLOW" {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            MockReply::ok()
+        });
+        let client = server.client();
+
+        let config = redis::AsyncConnectionConfig::new()
+            .set_max_in_flight_requests(Some(1))
+            .set_backpressure_policy(redis::aio::BackpressurePolicy::FailFast);
+        let con = client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+        let (slow, fast) = futures::future::join(cmd("SLOW").exec_async(&mut con.clone()), async {
+            sleep(Duration::from_millis(20).into()).await;
+            assert_eq!(con.queue_depth(), 1);
+            assert!(con.queued_bytes() > 0);
+            cmd("FAST").exec_async(&mut con.clone()).await
+        })
+        .await;
+        slow?;
+        assert_eq!(fast.unwrap_err().kind(), ErrorKind::RequestLimitReached);
+        assert_eq!(con.queue_depth(), 0);
+        assert_eq!(con.queued_bytes(), 0);
+
+        // requests wait for capacity by default, and a request that is larger than the byte
+        // limit is sent when it's alone.
+        let config = redis::AsyncConnectionConfig::new().set_max_queued_bytes(Some(10));
+        let con = client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+        let start = std::time::Instant::now();
+        let (slow, fast) = futures::future::join(cmd("SLOW").exec_async(&mut con.clone()), async {
+            sleep(Duration::from_millis(20).into()).await;
+            cmd("FAST").exec_async(&mut con.clone()).await?;
+            Ok::<_, RedisError>(start.elapsed())
+        })
+        .await;
+        slow?;
+        assert!(fast? >= Duration::from_millis(100));
+        assert_eq!(con.queue_depth(), 0);
+        Ok(())
+    }
+
+    /// Starts a server that replies to SLOW after 100 milliseconds, and closes the connection
+    /// after replying to QUIT.
+    fn start_closing_server() -> MockServer {
+        MockServer::start(|request| match request.name {
+            "SLOW" => {
+                std::thread::sleep(Duration::from_millis(100));
+                MockReply::ok()
+            }
+            "QUIT" => MockReply::ReplyAndClose(b"+OK\r\n".to_vec()),
+            _ => MockReply::ok(),
+        })
+    }
+
+    #[async_test]
+    async fn close_drains_requests_and_sends_quit() -> RedisResult<()> {
+        let server = start_closing_server();
+        let client = server.client();
+        let con = client.get_multiplexed_async_connection().await?;
+
+        let (slow, closed) =
+            futures::future::join(cmd("SLOW").exec_async(&mut con.clone()), async {
+                sleep(Duration::from_millis(20).into()).await;
+                con.close(Duration::from_secs(1)).await
+            })
+            .await;
+        slow?;
+        closed?;
+        assert_eq!(server.commands().last(), Some(&(0, "QUIT".to_string())));
+        assert!(cmd("PING")
+            .exec_async(&mut con.clone())
+            .await
+            .unwrap_err()
+            .is_unrecoverable_error());
+
+        // requests that aren't answered in time fail, and QUIT isn't sent.
+        let con = client.get_multiplexed_async_connection().await?;
+        let (slow, closed) =
+            futures::future::join(cmd("SLOW").exec_async(&mut con.clone()), async {
+                sleep(Duration::from_millis(20).into()).await;
+                con.close(Duration::from_millis(10)).await
+            })
+            .await;
+        assert!(slow.unwrap_err().is_unrecoverable_error());
+        assert!(closed.unwrap_err().is_timeout());
+        assert!(!server.commands().contains(&(1, "QUIT".to_string())));
+        Ok(())
+    }
+
+    #[async_test]
+    #[cfg(feature = "connection-manager")]
+    async fn connection_manager_close_stops_reconnecting() -> RedisResult<()> {
+        let server = start_closing_server();
+        let client = server.client();
+        let mut manager = client.get_connection_manager().await?;
+        cmd("PING").exec_async(&mut manager).await?;
+
+        manager.close(Duration::from_secs(1)).await?;
+        assert_eq!(manager.state(), redis::aio::ConnectionState::Closed);
+        assert!(cmd("PING").exec_async(&mut manager).await.is_err());
+        sleep(Duration::from_millis(100).into()).await;
+        assert_eq!(manager.state(), redis::aio::ConnectionState::Closed);
+        assert!(server
+            .commands()
+            .iter()
+            .all(|(connection, _)| *connection == 0));
+        Ok(())
+    }
+
+    #[async_test]
+    async fn idle_connections_are_pinged_until_they_expire() -> RedisResult<()> {
+        let server = start_closing_server();
+        let client = server.client();
+        let config = redis::AsyncConnectionConfig::new()
+            .set_idle_ping_interval(Some(Duration::from_millis(30)))
+            .set_max_lifetime(Some(Duration::from_millis(300)));
+        let mut con = client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+
+        sleep(Duration::from_millis(100).into()).await;
+        assert!(server.commands().contains(&(0, "PING".to_string())));
+        cmd("GET").arg("key").exec_async(&mut con).await?;
+
+        sleep(Duration::from_millis(300).into()).await;
+        assert!(cmd("GET")
+            .arg("key")
+            .exec_async(&mut con)
+            .await
+            .unwrap_err()
+            .is_unrecoverable_error());
+        Ok(())
+    }
+
+    #[async_test]
+    async fn expired_connections_send_quit_and_report_the_disconnect() -> RedisResult<()> {
+        // a server that speaks RESP3, and closes the connection after replying to QUIT.
+        let server = MockServer::start(|request| match request.name {
+            "HELLO" => "%1\r\n$5\r\nproto\r\n:3\r\n".into(),
+            "QUIT" => MockReply::ReplyAndClose(b"+OK\r\n".to_vec()),
+            _ => MockReply::ok(),
+        });
+        let redis = RedisConnectionInfo::default().set_protocol(ProtocolVersion::RESP3);
+        let connection_info = server
+            .url()
+            .into_connection_info()?
+            .set_redis_settings(redis);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let config = redis::AsyncConnectionConfig::new()
+            .set_push_sender(tx)
+            .set_max_lifetime(Some(Duration::from_millis(100)));
+        let _con = redis::Client::open(connection_info)?
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+
+        sleep(Duration::from_millis(200).into()).await;
+        assert_eq!(server.commands().last(), Some(&(0, "QUIT".to_string())));
+        assert_eq!(rx.try_recv().unwrap().kind, PushKind::Disconnection);
+        assert!(rx.try_recv().is_err());
+        Ok(())
+    }
+
+    #[async_test]
+    #[cfg(feature = "connection-manager")]
+    async fn connection_manager_replaces_expired_connections() -> RedisResult<()> {
+        let server = start_closing_server();
+        let client = server.client();
+        let config =
+            redis::aio::ConnectionManagerConfig::new().set_max_lifetime(Duration::from_millis(100));
+        let mut manager = client.get_connection_manager_with_config(config).await?;
+
+        // requests keep succeeding while the connection is replaced.
+        for _ in 0..8 {
+            cmd("SLOW").exec_async(&mut manager).await?;
+        }
+        assert_eq!(manager.state(), redis::aio::ConnectionState::Connected);
+        let commands = server.commands();
+        assert!(commands.contains(&(0, "QUIT".to_string())));
+        assert!(commands.contains(&(1, "QUIT".to_string())));
+        assert!(commands.contains(&(2, "SLOW".to_string())));
+        Ok(())
+    }
+
+    #[async_test]
+    #[cfg(feature = "connection-manager")]
+    async fn connection_manager_heartbeat_replaces_unresponsive_connections() -> RedisResult<()> {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        // a server whose first connection stops replying after receiving HANG.
+        let hanging = AtomicBool::new(false);
+        let server = MockServer::start(move |request| {
+            if request.connection != 0 {
+                return MockReply::ok();
+            }
+            if request.name == "HANG" {
+                hanging.store(true, Ordering::SeqCst);
+            }
+            if hanging.load(Ordering::SeqCst) {
+                MockReply::NoReply
+            } else {
+                MockReply::ok()
+            }
+        });
+        let client = server.client();
+        let config = redis::aio::ConnectionManagerConfig::new()
+            .set_response_timeout(None)
+            .set_heartbeat(redis::aio::HeartbeatConfig::new(
+                Duration::from_millis(50),
+                Duration::from_millis(50),
+            ));
+        let mut manager = client.get_connection_manager_with_config(config).await?;
+
+        // the request fails once the heartbeat detects the unresponsive connection.
+        let start = std::time::Instant::now();
+        assert!(cmd("HANG").exec_async(&mut manager).await.is_err());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        cmd("PING").exec_async(&mut manager).await?;
+        Ok(())
+    }
+
+    #[async_test]
+    async fn idle_pings_skip_connections_waiting_for_blocking_commands() -> RedisResult<()> {
+        // a server that answers BLPOP after 300 milliseconds, and doesn't answer other commands
+        // on the connection meanwhile.
+        let server = MockServer::start(|request| match request.name {
+            "BLPOP" => {
+                std::thread::sleep(Duration::from_millis(300));
+                "*2\r\n$4\r\nlist\r\n$5\r\nvalue\r\n".into()
+            }
+            _ => MockReply::ok(),
+        });
+        let config = redis::AsyncConnectionConfig::new()
+            .set_idle_ping_interval(Some(Duration::from_millis(100)));
+        let mut con = server
+            .client()
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+
+        let popped: (String, String) = cmd("BLPOP")
+            .arg("list")
+            .arg(0)
+            .query_async(&mut con)
+            .await?;
+        assert_eq!(popped, ("list".to_string(), "value".to_string()));
+        // a PING that was sent behind BLPOP would be read right after its reply.
+        sleep(Duration::from_millis(50).into()).await;
+        assert_eq!(server.count("PING"), 0);
+        Ok(())
+    }
+
+    #[async_test]
+    #[cfg(feature = "connection-manager")]
+    async fn connection_manager_heartbeat_skips_connections_waiting_for_blocking_commands(
+    ) -> RedisResult<()> {
+        // a server that answers BLPOP after 300 milliseconds, and doesn't answer other commands
+        // on the connection meanwhile.
+        let server = MockServer::start(|request| match request.name {
+            "BLPOP" => {
+                std::thread::sleep(Duration::from_millis(300));
+                "*2\r\n$4\r\nlist\r\n$5\r\nvalue\r\n".into()
+            }
+            _ => MockReply::ok(),
+        });
+        let config = redis::aio::ConnectionManagerConfig::new().set_heartbeat(
+            redis::aio::HeartbeatConfig::new(Duration::from_millis(50), Duration::from_millis(50)),
+        );
+        let mut manager = server
+            .client()
+            .get_connection_manager_with_config(config)
+            .await?;
+
+        let popped: (String, String) = cmd("BLPOP")
+            .arg("list")
+            .arg(1)
+            .query_async(&mut manager)
+            .await?;
+        assert_eq!(popped, ("list".to_string(), "value".to_string()));
+        // the connection wasn't pinged while BLPOP was in flight, nor replaced.
+        assert!(server
+            .commands()
+            .iter()
+            .all(|(connection, name)| *connection == 0 && name != "PING"));
+        Ok(())
+    }
+
+    #[async_test]
+    #[cfg(feature = "connection-manager")]
+    async fn connection_manager_fails_over_to_the_new_master() -> RedisResult<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // servers that reply to ROLE and reject writes according to which of them is the master.
+        let master = Arc::new(AtomicUsize::new(1));
+        let servers: Vec<_> = (0..2)
+            .map(|server| {
+                let master = master.clone();
+                MockServer::start(move |request| {
+                    let is_master = master.load(Ordering::SeqCst) == server;
+                    match (request.name, is_master) {
+                        ("ROLE", true) => "*3\r\n$6\r\nmaster\r\n:0\r\n*0\r\n".into(),
+                        ("ROLE", false) => {
+                            "*5\r\n$5\r\nslave\r\n$9\r\n127.0.0.1\r\n:6379\r\n$9\r\nconnected\r\n:0\r\n".into()
+                        }
+                        ("SET", false) => {
+                            "-READONLY You can't write against a read only replica.\r\n".into()
+                        }
+                        _ => MockReply::ok(),
+                    }
+                })
+            })
+            .collect();
+        let ports: Vec<_> = servers.iter().map(MockServer::port).collect();
+
+        let endpoints: Vec<_> = servers.iter().map(MockServer::url).collect();
+        let mut manager = redis::aio::ConnectionManager::new_with_endpoints(
+            endpoints,
+            redis::aio::ConnectionManagerConfig::new(),
+        )
+        .await?;
+        assert_eq!(
+            manager.active_endpoint().addr(),
+            &redis::ConnectionAddr::Tcp("127.0.0.1".to_string(), ports[1])
+        );
+        cmd("SET")
+            .arg("key")
+            .arg(1)
+            .exec_async(&mut manager)
+            .await?;
+
+        // the demoted master rejects the write, and the manager reconnects to the new master.
+        master.store(0, Ordering::SeqCst);
+        let err = cmd("SET")
+            .arg("key")
+            .arg(1)
+            .exec_async(&mut manager)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ServerErrorKind::ReadOnly.into());
+        cmd("SET")
+            .arg("key")
+            .arg(1)
+            .exec_async(&mut manager)
+            .await?;
+        assert_eq!(
+            manager.active_endpoint().addr(),
+            &redis::ConnectionAddr::Tcp("127.0.0.1".to_string(), ports[0])
+        );
+        Ok(())
+    }
+
+    #[async_test]
+    #[cfg(feature = "connection-manager")]
+    async fn connection_manager_fails_over_when_the_circuit_opens() -> RedisResult<()> {
+        use redis::{CircuitBreakerConfig, CircuitState};
+
+        // masters, of which the first never replies to HANG.
+        let servers: Vec<_> = (0..2)
+            .map(|_| {
+                MockServer::start(|request| match request.name {
+                    "ROLE" => "*3\r\n$6\r\nmaster\r\n:0\r\n*0\r\n".into(),
+                    "HANG" => MockReply::NoReply,
+                    _ => MockReply::ok(),
+                })
+            })
+            .collect();
+        let endpoints: Vec<_> = servers.iter().map(MockServer::url).collect();
+        let config = redis::aio::ConnectionManagerConfig::new()
+            .set_response_timeout(Some(Duration::from_millis(50)))
+            .set_circuit_breaker(CircuitBreakerConfig::new(2, Duration::from_secs(60)));
+        let mut manager =
+            redis::aio::ConnectionManager::new_with_endpoints(endpoints, config).await?;
+        assert_eq!(
+            manager.active_endpoint().addr(),
+            &redis::ConnectionAddr::Tcp("127.0.0.1".to_string(), servers[0].port())
+        );
+
+        for _ in 0..2 {
+            let err = cmd("HANG").exec_async(&mut manager).await.unwrap_err();
+            assert!(err.is_timeout());
+        }
+        assert_eq!(manager.circuit_state(), Some(CircuitState::Open));
+
+        // the request that finds the circuit open fails, and the manager fails over to the
+        // endpoint whose circuit is closed.
+        let err = cmd("PING").exec_async(&mut manager).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::CircuitOpen);
+        for _ in 0..100 {
+            if manager.state() == redis::aio::ConnectionState::Connected {
+                break;
+            }
+            sleep(Duration::from_millis(10).into()).await;
+        }
+        cmd("PING").exec_async(&mut manager).await?;
+        assert_eq!(
+            manager.active_endpoint().addr(),
+            &redis::ConnectionAddr::Tcp("127.0.0.1".to_string(), servers[1].port())
+        );
+        assert_eq!(manager.circuit_state(), Some(CircuitState::Closed));
+        Ok(())
+    }
+
+    #[async_test]
+    #[cfg(feature = "connection-manager")]
+    async fn connection_manager_does_not_fail_over_after_authentication_failures() -> RedisResult<()>
+    {
+        let servers: Vec<_> = (0..2)
+            .map(|server| {
+                MockServer::start(move |request| match (request.name, server) {
+                    ("AUTH", 0) => {
+                        "-WRONGPASS invalid username-password pair or user is disabled.\r\n".into()
+                    }
+                    ("ROLE", _) => "*3\r\n$6\r\nmaster\r\n:0\r\n*0\r\n".into(),
+                    _ => MockReply::ok(),
+                })
+            })
+            .collect();
+        let endpoints: Vec<_> = servers
+            .iter()
+            .map(|server| format!("redis://:password@127.0.0.1:{}", server.port()))
+            .collect();
+
+        let err = redis::aio::ConnectionManager::new_with_endpoints(
+            endpoints,
+            redis::aio::ConnectionManagerConfig::new().set_number_of_retries(0),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AuthenticationFailed);
+        assert!(servers[1].commands().is_empty());
+        Ok(())
+    }
+
+    #[async_test]
+    async fn auto_protocol_rejects_resp3_features_after_falling_back_to_resp2() -> RedisResult<()> {
+        // a server that doesn't know HELLO.
+        let server = MockServer::start(|request| match request.name {
+            "HELLO" => "-ERR unknown command `HELLO`, with args beginning with: `3`, \r\n".into(),
+            _ => MockReply::ok(),
+        });
+        let client = redis::Client::open(format!("{}/?protocol=auto", server.url())).unwrap();
+
+        let mut con = client.get_multiplexed_async_connection().await?;
+        assert_eq!(con.protocol(), ProtocolVersion::RESP2);
+        let err = con.subscribe("channel").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidClientConfig);
+        let err = con.psubscribe("channel*").await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidClientConfig);
+        assert_eq!(server.count("SUBSCRIBE") + server.count("PSUBSCRIBE"), 0);
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let config = redis::AsyncConnectionConfig::new().set_push_sender(tx);
+        let err = client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidClientConfig);
+
+        #[cfg(feature = "connection-manager")]
+        {
+            let mut manager = client.get_connection_manager().await?;
+            cmd("PING").exec_async(&mut manager).await?;
+            let err = manager.subscribe("channel").await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidClientConfig);
+            let err = manager.psubscribe("channel*").await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidClientConfig);
+
+            let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+            let config = redis::aio::ConnectionManagerConfig::new().set_push_sender(tx);
+            let err = client
+                .get_connection_manager_with_config(config)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidClientConfig);
+        }
+        Ok(())
+    }
+
+    #[async_test]
+    async fn leased_connections_are_reset_and_reused() -> RedisResult<()> {
+        // a server that replies to ID with the index of the connection, and replies to SLOW
+        // after 200 milliseconds.
+        let server = MockServer::start(|request| match request.name {
+            "ID" => format!(":{}\r\n", request.connection).into(),
+            "SLOW" => {
+                std::thread::sleep(Duration::from_millis(200));
+                MockReply::ok()
+            }
+            _ => MockReply::ok(),
+        });
+        let sent = |index: usize, name: &str| {
+            server
+                .commands()
+                .iter()
+                .filter(|command| **command == (index, name.to_string()))
+                .count()
+        };
+        let wait_for = async |index: usize, name: &str, count: usize| {
+            for _ in 0..100 {
+                if sent(index, name) >= count {
+                    break;
+                }
+                sleep(Duration::from_millis(10).into()).await;
+            }
+            assert_eq!(sent(index, name), count);
+            // the connection returns to the pool once the reply was read.
+            sleep(Duration::from_millis(50).into()).await;
+        };
+        let pool = server.client().get_lease_pool();
+
+        // watched keys are unwatched, and the connection is reused.
+        let mut lease = pool.lease().await?;
+        assert_eq!(cmd("ID").query_async::<i64>(&mut lease).await?, 0);
+        cmd("WATCH").arg("key").exec_async(&mut lease).await?;
+        drop(lease);
+        wait_for(0, "UNWATCH", 1).await;
+
+        // transactions are discarded.
+        let mut lease = pool.clone().lease().await?;
+        assert_eq!(cmd("ID").query_async::<i64>(&mut lease).await?, 0);
+        cmd("MULTI").exec_async(&mut lease).await?;
+        drop(lease);
+        wait_for(0, "DISCARD", 1).await;
+
+        // idle connections are checked before they're leased again.
+        let mut lease = pool.lease().await?;
+        assert_eq!(cmd("ID").query_async::<i64>(&mut lease).await?, 0);
+        assert_eq!(sent(0, "PING"), 2);
+        drop(lease);
+        sleep(Duration::from_millis(50).into()).await;
+
+        // other pools of the client have their own connections.
+        let mut lease = server.client().get_lease_pool().lease().await?;
+        assert_eq!(cmd("ID").query_async::<i64>(&mut lease).await?, 1);
+        drop(lease);
+
+        // a connection that was dropped while waiting for a response is closed.
+        let mut lease = pool.lease().await?;
+        {
+            let slow = cmd("SLOW");
+            let request = slow.exec_async(&mut lease);
+            let timeout = sleep(Duration::from_millis(20).into());
+            futures::pin_mut!(request, timeout);
+            assert!(matches!(
+                futures::future::select(request, timeout).await,
+                futures::future::Either::Right(_)
+            ));
+        }
+        drop(lease);
+        let mut lease = pool.lease().await?;
+        assert_eq!(cmd("ID").query_async::<i64>(&mut lease).await?, 2);
+        assert_eq!(sent(0, "UNWATCH") + sent(0, "DISCARD"), 2);
+        Ok(())
+    }
+
+    #[async_test]
+    async fn leased_connections_that_broke_while_idle_are_replaced() -> RedisResult<()> {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        // a server that replies to ID with the index of the connection, and closes the first
+        // connection once it's killed.
+        let killed = Arc::new(AtomicBool::new(false));
+        let kill = killed.clone();
+        let server = MockServer::start(move |request| match request.name {
+            _ if request.connection == 0 && killed.load(Ordering::SeqCst) => MockReply::Close,
+            "ID" => format!(":{}\r\n", request.connection).into(),
+            _ => MockReply::ok(),
+        });
+        let pool = server.client().get_lease_pool();
+
+        let mut lease = pool.lease().await?;
+        assert_eq!(cmd("ID").query_async::<i64>(&mut lease).await?, 0);
+        drop(lease);
+        // the connection returns to the pool, and breaks while it's idle.
+        sleep(Duration::from_millis(50).into()).await;
+        kill.store(true, Ordering::SeqCst);
+
+        let mut lease = pool.lease().await?;
+        assert_eq!(cmd("ID").query_async::<i64>(&mut lease).await?, 1);
+        Ok(())
+    }
+
     #[async_test]
     #[cfg(feature = "script")]
     async fn script(mut con: impl ConnectionLike) -> RedisResult<()> {
@@ -836,6 +1544,106 @@ mod basic_async {
         Ok(())
     }
 
+    #[async_test]
+    async fn send_packed_command_streamed_streams_bulk_strings() -> RedisResult<()> {
+        use tokio::io::AsyncRead;
+
+        let ctx = TestContext::new();
+        let mut con = ctx.async_connection().await?;
+        let value: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        con.set::<_, _, ()>("blob", &value).await?;
+
+        let mut stream = con
+            .send_packed_command_streamed(redis::cmd("GET").arg("blob"))
+            .await?
+            .unwrap();
+        assert_eq!(stream.len(), value.len());
+
+        // other requests are answered once the stream was consumed.
+        let mut other_con = con.clone();
+        let (received, other) = futures::join!(
+            async {
+                let mut received = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    received.extend_from_slice(&chunk?);
+                }
+                RedisResult::Ok(received)
+            },
+            other_con.get::<_, Option<String>>("missing")
+        );
+        assert_eq!(received?, value);
+        assert_eq!(other?, None);
+
+        let mut stream = con
+            .send_packed_command_streamed(redis::cmd("GET").arg("blob"))
+            .await?
+            .unwrap();
+        let mut received = Vec::new();
+        let mut buffer = [0; 1000];
+        loop {
+            let read = std::future::poll_fn(|cx| {
+                let mut buffer = tokio::io::ReadBuf::new(&mut buffer);
+                std::pin::Pin::new(&mut stream)
+                    .poll_read(cx, &mut buffer)
+                    .map_ok(|()| buffer.filled().len())
+            })
+            .await
+            .unwrap();
+            if read == 0 {
+                break;
+            }
+            received.extend_from_slice(&buffer[..read]);
+        }
+        assert_eq!(received, value);
+
+        let missing = con
+            .send_packed_command_streamed(redis::cmd("GET").arg("missing"))
+            .await?;
+        assert!(missing.is_none());
+
+        con.hset::<_, _, _, ()>("hash", "field", "value").await?;
+        let result = con
+            .send_packed_command_streamed(redis::cmd("GET").arg("hash"))
+            .await;
+        assert_eq!(result.unwrap_err().code(), Some("WRONGTYPE"));
+
+        // dropping a stream before it ends doesn't affect later requests.
+        let stream = con
+            .send_packed_command_streamed(redis::cmd("GET").arg("blob"))
+            .await?;
+        drop(stream);
+        let value: Option<String> = con.get("missing").await?;
+        assert_eq!(value, None);
+        Ok(())
+    }
+
+    #[async_test]
+    async fn send_packed_command_reply_returns_undecoded_replies() -> RedisResult<()> {
+        let ctx = TestContext::new();
+        let mut con = ctx.async_connection().await?;
+        con.mset::<_, _, ()>(&[("a", "first"), ("b", "second")])
+            .await?;
+
+        let reply = con
+            .send_packed_command_reply(redis::cmd("MGET").arg(&["a", "missing", "b"]))
+            .await?;
+        let values: Vec<Option<&str>> = reply.parse()?;
+        assert_eq!(values, [Some("first"), None, Some("second")]);
+
+        // server errors are returned when the reply is parsed, and later requests are unaffected.
+        con.hset::<_, _, _, ()>("hash", "field", "value").await?;
+        let reply = con
+            .send_packed_command_reply(redis::cmd("GET").arg("hash"))
+            .await?;
+        assert_eq!(
+            reply.parse::<Option<&str>>().unwrap_err().code(),
+            Some("WRONGTYPE")
+        );
+        let value: Option<String> = con.get("a").await?;
+        assert_eq!(value.as_deref(), Some("first"));
+        Ok(())
+    }
+
     mod pub_sub {
         use std::time::Duration;
 
@@ -1558,6 +2366,275 @@ mod basic_async {
         panic!("failed to reconnect");
     }
 
+    #[async_test]
+    async fn setup_hooks_run_on_new_connection() -> RedisResult<()> {
+        use redis::aio::{MultiplexedConnection, SetupFailurePolicy};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let ctx = TestContext::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let config = redis::AsyncConnectionConfig::new()
+            .add_setup_hook(
+                cmd("CLIENT").arg("SETNAME").arg("hooked").clone(),
+                SetupFailurePolicy::Abort,
+            )
+            .add_setup_hook(
+                move |_: MultiplexedConnection| {
+                    calls_clone.fetch_add(1, Ordering::Relaxed);
+                    async { Ok(()) }
+                },
+                SetupFailurePolicy::Abort,
+            );
+        let mut con = ctx
+            .client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+
+        let name: String = cmd("CLIENT").arg("GETNAME").query_async(&mut con).await?;
+        assert_eq!(name, "hooked");
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        Ok(())
+    }
+
+    #[async_test]
+    async fn failing_setup_hook_follows_failure_policy() -> RedisResult<()> {
+        use redis::aio::SetupFailurePolicy;
+
+        let ctx = TestContext::new();
+        let config = redis::AsyncConnectionConfig::new()
+            .add_setup_hook(cmd("NOT_A_COMMAND"), SetupFailurePolicy::Abort);
+        let err = ctx
+            .client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ServerErrorKind::ResponseError.into());
+
+        let config = redis::AsyncConnectionConfig::new()
+            .add_setup_hook(cmd("NOT_A_COMMAND"), SetupFailurePolicy::Warn);
+        let mut con = ctx
+            .client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+        cmd("PING").exec_async(&mut con).await?;
+
+        Ok(())
+    }
+
+    #[async_test]
+    async fn failing_setup_hook_with_warn_policy_is_logged() -> RedisResult<()> {
+        use redis::aio::SetupFailurePolicy;
+        use std::sync::Mutex;
+
+        struct RecordingLogger(Mutex<Vec<String>>);
+
+        impl log::Log for RecordingLogger {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                if record.level() == log::Level::Warn {
+                    self.0.lock().unwrap().push(record.args().to_string());
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: RecordingLogger = RecordingLogger(Mutex::new(Vec::new()));
+        // the logger stays installed for the rest of the process, so the cases of both runtimes
+        // share it.
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Warn);
+
+        let server = MockServer::start(|request| match request.name {
+            "BROKEN_HOOK" => "-ERR the hook is broken\r\n".into(),
+            _ => MockReply::ok(),
+        });
+        let config = redis::AsyncConnectionConfig::new()
+            .add_setup_hook(cmd("BROKEN_HOOK"), SetupFailurePolicy::Warn);
+        let mut con = server
+            .client()
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+        cmd("PING").exec_async(&mut con).await?;
+
+        assert!(LOGGER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|message| message.contains("the hook is broken")));
+        Ok(())
+    }
+
+    #[cfg(feature = "connection-manager")]
+    #[async_test]
+    async fn manager_should_run_setup_hooks_after_reconnect() -> RedisResult<()> {
+        use redis::aio::{MultiplexedConnection, SetupFailurePolicy};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let ctx = TestContext::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = calls.clone();
+        let config = redis::aio::ConnectionManagerConfig::new()
+            .set_max_delay(Duration::from_millis(2))
+            .add_setup_hook(
+                cmd("CLIENT").arg("SETNAME").arg("hooked").clone(),
+                SetupFailurePolicy::Abort,
+            )
+            .add_setup_hook(
+                move |_: MultiplexedConnection| {
+                    calls_clone.fetch_add(1, Ordering::Relaxed);
+                    async { Ok(()) }
+                },
+                SetupFailurePolicy::Abort,
+            );
+        let mut manager = ctx
+            .client
+            .get_connection_manager_with_config(config)
+            .await?;
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        kill_client_async(&mut manager, &ctx.client).await?;
+
+        for _ in 0..10 {
+            let Ok(name) = cmd("CLIENT")
+                .arg("GETNAME")
+                .query_async::<String>(&mut manager)
+                .await
+            else {
+                sleep(Duration::from_millis(3).into()).await;
+                continue;
+            };
+            assert_eq!(name, "hooked");
+            assert_eq!(calls.load(Ordering::Relaxed), 2);
+            return Ok(());
+        }
+        panic!("failed to reconnect");
+    }
+
+    #[cfg(feature = "connection-manager")]
+    #[async_test]
+    async fn manager_should_report_state_changes() -> RedisResult<()> {
+        use redis::aio::ConnectionState;
+
+        let ctx = TestContext::new();
+        let config =
+            redis::aio::ConnectionManagerConfig::new().set_max_delay(Duration::from_millis(2));
+        let mut manager = ctx
+            .client
+            .get_connection_manager_with_config(config)
+            .await?;
+        assert_eq!(manager.state(), ConnectionState::Connected);
+        let mut events = Box::pin(manager.events());
+
+        kill_client_async(&mut manager, &ctx.client).await?;
+        // without RESP3 pushes, the manager notices the disconnection on the next request.
+        let _ = cmd("PING").exec_async(&mut manager).await;
+
+        let event = events.next().await.unwrap();
+        assert_eq!(event.state, ConnectionState::Reconnecting { attempt: 1 });
+        let event = events.next().await.unwrap();
+        assert_eq!(event.state, ConnectionState::Connected);
+        assert!(event.error.is_none());
+        assert_eq!(manager.state(), ConnectionState::Connected);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "connection-manager")]
+    #[async_test]
+    async fn manager_should_follow_retry_policy() -> RedisResult<()> {
+        use redis::{RetryContext, RetryDecision};
+        use std::sync::{Arc, Mutex};
+
+        let port = redis_test::utils::get_random_available_port();
+        let client = redis::Client::open(format!("redis://127.0.0.1:{port}")).unwrap();
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let attempts_clone = attempts.clone();
+        let config = redis::aio::ConnectionManagerConfig::new().set_retry_policy(
+            move |context: &RetryContext<'_>| {
+                attempts_clone
+                    .lock()
+                    .unwrap()
+                    .push((context.attempt(), context.error().kind()));
+                if context.attempt() < 3 {
+                    RetryDecision::Retry
+                } else {
+                    RetryDecision::GiveUp
+                }
+            },
+        );
+
+        let err = client
+            .get_connection_manager_with_config(config)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert_eq!(
+            *attempts.lock().unwrap(),
+            vec![(1, ErrorKind::Io), (2, ErrorKind::Io), (3, ErrorKind::Io)]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "connection-manager")]
+    #[async_test]
+    async fn manager_should_open_circuit_after_consecutive_failures() -> RedisResult<()> {
+        use redis::{CircuitBreakerConfig, CircuitState};
+
+        // a server that never replies to HANG.
+        let server = MockServer::start(|request| match request.name {
+            "HANG" => MockReply::NoReply,
+            _ => MockReply::ok(),
+        });
+        let client = server.client();
+        let config = redis::aio::ConnectionManagerConfig::new()
+            .set_response_timeout(Some(Duration::from_millis(50)))
+            .set_circuit_breaker(CircuitBreakerConfig::new(2, Duration::from_secs(60)));
+        let mut manager = client.get_connection_manager_with_config(config).await?;
+        assert_eq!(manager.circuit_state(), Some(CircuitState::Closed));
+
+        for _ in 0..2 {
+            let err = cmd("HANG").exec_async(&mut manager).await.unwrap_err();
+            assert!(err.is_timeout());
+        }
+        assert_eq!(manager.circuit_state(), Some(CircuitState::Open));
+
+        let err = cmd("PING").exec_async(&mut manager).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::CircuitOpen);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "connection-manager")]
+    #[async_test]
+    async fn manager_should_send_buffered_commands_after_reconnect() -> RedisResult<()> {
+        let ctx = TestContext::new();
+        let config = redis::aio::ConnectionManagerConfig::new()
+            .set_max_delay(Duration::from_millis(2))
+            .set_reconnect_buffer(10, Duration::from_secs(1));
+        let mut manager = ctx
+            .client
+            .get_connection_manager_with_config(config)
+            .await?;
+        let _: () = manager.set("foo", "bar").await?;
+
+        kill_client_async(&mut manager, &ctx.client).await?;
+
+        let value: String = manager.get("foo").await?;
+        assert_eq!(value, "bar");
+
+        Ok(())
+    }
+
     #[cfg(feature = "connection-manager")]
     #[async_test]
     async fn manager_should_reconnect_without_actions_if_resp3_is_set() -> RedisResult<()> {
@@ -102,6 +102,38 @@ mod basic {
         );
     }
 
+    #[test]
+    fn test_shared_args() {
+        let ctx = TestContext::new();
+        let mut con = ctx.connection();
+        let value: std::sync::Arc<[u8]> = (0..10 * 1024 * 1024)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>()
+            .into();
+
+        redis::cmd("SET")
+            .arg("key")
+            .arg(&value)
+            .exec(&mut con)
+            .unwrap();
+        redis::cmd("RPUSH")
+            .arg("list")
+            .arg(&[value.clone(), value.clone()])
+            .exec(&mut con)
+            .unwrap();
+
+        let result: Vec<u8> = redis::cmd("GET").arg("key").query(&mut con).unwrap();
+        assert!(result == *value);
+        let result: Vec<Vec<u8>> = redis::cmd("LRANGE")
+            .arg("list")
+            .arg(0)
+            .arg(-1)
+            .query(&mut con)
+            .unwrap();
+        assert!(result.iter().all(|item| *item == *value));
+        assert_eq!(result.len(), 2);
+    }
+
     #[test]
     fn test_can_authenticate_with_username_and_password() {
         let ctx = TestContext::new();
@@ -2857,6 +2889,77 @@ mod basic {
         assert_eq!(value, "key2");
     }
 
+    /// Starts a fake server that replies to `HELLO` with `hello_reply` and to
+    /// every other command with `+OK`.
+    fn fake_redis_with_hello_reply(hello_reply: &'static [u8]) -> u16 {
+        use std::io::Write;
+
+        let listener = get_listener_on_free_port();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut stream = listener.incoming().next().unwrap().unwrap();
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut parser = redis::Parser::new();
+            while let Ok(Value::Array(args)) = parser.parse_value(&mut reader) {
+                let reply = match args.first() {
+                    Some(Value::BulkString(name)) if name.eq_ignore_ascii_case(b"HELLO") => {
+                        hello_reply
+                    }
+                    _ => b"+OK\r\n",
+                };
+                stream.write_all(reply).unwrap();
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_auto_protocol_uses_resp3_when_supported() {
+        let port = fake_redis_with_hello_reply(
+            b"%7\r\n+server\r\n+redis\r\n+version\r\n+7.4.0\r\n+proto\r\n:3\r\n+id\r\n:5\r\n\
+              +mode\r\n+standalone\r\n+role\r\n+master\r\n+modules\r\n*0\r\n",
+        );
+        let client = Client::open(format!("redis://127.0.0.1:{port}/?protocol=auto")).unwrap();
+        let mut con = client.get_connection().unwrap();
+
+        assert_eq!(con.protocol(), ProtocolVersion::RESP3);
+        let info = con.server_info().unwrap();
+        assert_eq!(info.server, "redis");
+        assert_eq!(info.version, "7.4.0");
+        assert_eq!(info.protocol, ProtocolVersion::RESP3);
+        assert_eq!(info.id, 5);
+        assert_eq!(info.mode, "standalone");
+        assert_eq!(info.role, "master");
+        assert!(info.modules.is_empty());
+
+        redis::cmd("PING").exec(&mut con).unwrap();
+    }
+
+    #[test]
+    fn test_auto_protocol_falls_back_to_resp2() {
+        for hello_reply in [
+            &b"-ERR unknown command `HELLO`, with args beginning with: `3`, \r\n"[..],
+            &b"-NOPROTO unsupported protocol version\r\n"[..],
+        ] {
+            let port = fake_redis_with_hello_reply(hello_reply);
+            let client = Client::open(format!("redis://127.0.0.1:{port}/?protocol=auto")).unwrap();
+            let mut con = client.get_connection().unwrap();
+
+            assert_eq!(con.protocol(), ProtocolVersion::RESP2);
+            assert!(con.server_info().is_none());
+            redis::cmd("PING").exec(&mut con).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_resp3_protocol_does_not_fall_back_to_resp2() {
+        let port = fake_redis_with_hello_reply(b"-NOPROTO unsupported protocol version\r\n");
+        let client = Client::open(format!("redis://127.0.0.1:{port}/?protocol=resp3")).unwrap();
+
+        let err = client.get_connection().err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::RESP3NotSupported);
+    }
+
     #[test]
     fn test_blocking_sorted_set_api() {
         let ctx = TestContext::new();
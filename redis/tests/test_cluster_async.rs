@@ -754,6 +754,312 @@ mod cluster_async {
         assert_eq!(requests.load(atomic::Ordering::SeqCst), 3);
     }
 
+    #[test]
+    fn test_async_cluster_circuit_breaker_reroutes_reads_from_replicas() {
+        let name = "circuit_breaker_reroutes_reads_from_replicas";
+
+        let MockEnv {
+            runtime,
+            async_connection: mut connection,
+            handler: _handler,
+            ..
+        } = MockEnv::with_client_builder(
+            ClusterClient::builder(vec![&*format!("redis://{name}")])
+                .retries(0)
+                .read_from_replicas()
+                .circuit_breaker(redis::CircuitBreakerConfig::new(2, Duration::from_secs(60))),
+            name,
+            move |cmd: &[u8], port| {
+                respond_startup_with_replica(name, cmd)?;
+                match port {
+                    6379 => Err(Ok(Value::BulkString(b"123".to_vec()))),
+                    6380 => Err(Err(RedisError::from(std::io::Error::from(
+                        std::io::ErrorKind::TimedOut,
+                    )))),
+                    _ => panic!("Wrong node"),
+                }
+            },
+        );
+
+        for _ in 0..2 {
+            let result = runtime.block_on(
+                cmd("GET")
+                    .arg("test")
+                    .query_async::<Option<i32>>(&mut connection),
+            );
+            assert!(matches!(result, Err(err) if err.is_timeout()));
+        }
+        let replica = format!("{name}:6380");
+        assert_eq!(
+            connection.circuit_states().unwrap().get(&replica),
+            Some(&redis::CircuitState::Open)
+        );
+
+        let value = runtime.block_on(
+            cmd("GET")
+                .arg("test")
+                .query_async::<Option<i32>>(&mut connection),
+        );
+        assert_eq!(value, Ok(Some(123)));
+    }
+
+    #[test]
+    fn test_async_cluster_circuit_breaker_fails_requests_to_open_primaries() {
+        let name = "circuit_breaker_fails_requests_to_open_primaries";
+
+        let MockEnv {
+            runtime,
+            async_connection: mut connection,
+            handler: _handler,
+            ..
+        } = MockEnv::with_client_builder(
+            ClusterClient::builder(vec![&*format!("redis://{name}")])
+                .retries(0)
+                .circuit_breaker(redis::CircuitBreakerConfig::new(2, Duration::from_secs(60))),
+            name,
+            move |cmd: &[u8], port| {
+                respond_startup_with_replica(name, cmd)?;
+                match port {
+                    6379 => Err(Err(RedisError::from(std::io::Error::from(
+                        std::io::ErrorKind::TimedOut,
+                    )))),
+                    // without `read_from_replicas`, replicas don't serve reads.
+                    _ => panic!("Wrong node"),
+                }
+            },
+        );
+
+        for _ in 0..2 {
+            let result = runtime.block_on(
+                cmd("GET")
+                    .arg("test")
+                    .query_async::<Option<i32>>(&mut connection),
+            );
+            assert!(matches!(result, Err(err) if err.is_timeout()));
+        }
+        let primary = format!("{name}:6379");
+        assert_eq!(
+            connection.circuit_states().unwrap().get(&primary),
+            Some(&redis::CircuitState::Open)
+        );
+
+        let result = runtime.block_on(
+            cmd("GET")
+                .arg("test")
+                .query_async::<Option<i32>>(&mut connection),
+        );
+        assert!(
+            matches!(&result, Err(err) if err.kind() == redis::ErrorKind::CircuitOpen),
+            "{result:?}"
+        );
+        let result = runtime.block_on(
+            cmd("SET")
+                .arg("test")
+                .arg("123")
+                .exec_async(&mut connection),
+        );
+        assert!(
+            matches!(&result, Err(err) if err.kind() == redis::ErrorKind::CircuitOpen),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_async_cluster_circuit_breaker_drops_nodes_that_left_the_cluster() {
+        let name = "circuit_breaker_drops_nodes_that_left_the_cluster";
+
+        let refreshed = atomic::AtomicBool::new(false);
+
+        let MockEnv {
+            runtime,
+            async_connection: mut connection,
+            handler: _handler,
+            ..
+        } = MockEnv::with_client_builder(
+            ClusterClient::builder(vec![&*format!("redis://{name}")])
+                .retries(0)
+                .circuit_breaker(redis::CircuitBreakerConfig::new(2, Duration::from_secs(60))),
+            name,
+            move |cmd: &[u8], port| {
+                if refreshed.load(Ordering::SeqCst) {
+                    // the first primary left the cluster, and its slots moved to the second one.
+                    respond_startup_with_replica_using_config(
+                        name,
+                        cmd,
+                        Some(vec![MockSlotRange {
+                            primary_port: 6381,
+                            replica_ports: vec![6382],
+                            slot_range: (0..16383),
+                        }]),
+                    )?;
+                } else {
+                    respond_startup_with_replica(name, cmd)?;
+                }
+                match port {
+                    6379 => Err(Err(RedisError::from(std::io::Error::from(
+                        std::io::ErrorKind::TimedOut,
+                    )))),
+                    6381 if !refreshed.swap(true, Ordering::SeqCst) => Err(parse_redis_value(
+                        format!("-MOVED 123 {name}:6381\r\n").as_bytes(),
+                    )),
+                    6381 => Err(Ok(Value::BulkString(b"123".to_vec()))),
+                    _ => panic!("Wrong node"),
+                }
+            },
+        );
+
+        for _ in 0..2 {
+            let result = runtime.block_on(
+                cmd("GET")
+                    .arg("test")
+                    .query_async::<Option<i32>>(&mut connection),
+            );
+            assert!(matches!(result, Err(err) if err.is_timeout()));
+        }
+        let primary = format!("{name}:6379");
+        assert_eq!(
+            connection.circuit_states().unwrap().get(&primary),
+            Some(&redis::CircuitState::Open)
+        );
+
+        // the redirection refreshes the slots, and the next request waits for the refresh.
+        let result = runtime.block_on(
+            cmd("GET")
+                .arg("foo")
+                .query_async::<Option<i32>>(&mut connection),
+        );
+        assert!(result.is_err(), "{result:?}");
+        let value = runtime.block_on(
+            cmd("GET")
+                .arg("test")
+                .query_async::<Option<i32>>(&mut connection),
+        );
+        assert_eq!(value, Ok(Some(123)));
+
+        let states = connection.circuit_states().unwrap();
+        assert!(!states.contains_key(&primary), "{states:?}");
+    }
+
+    #[test]
+    fn test_async_cluster_hedged_reads_use_first_successful_reply() {
+        let name = "hedged_reads_use_first_successful_reply";
+
+        let MockEnv {
+            runtime,
+            async_connection: mut connection,
+            handler: _handler,
+            ..
+        } = MockEnv::with_client_builder(
+            ClusterClient::builder(vec![&*format!("redis://{name}")])
+                .read_from_replicas()
+                .hedged_reads(
+                    redis::cluster_async::HedgedReadsConfig::new(90.0)
+                        .set_max_delay(Duration::from_millis(10)),
+                ),
+            name,
+            move |cmd: &[u8], port| {
+                respond_startup_with_replica(name, cmd)?;
+                match port {
+                    6379 => Err(Ok(Value::Int(1))),
+                    6380 => Err(Ok(Value::Int(2))),
+                    _ => panic!("Wrong node"),
+                }
+            },
+        );
+        delay_responses(name, 6380, Duration::from_secs(10));
+
+        let value = runtime.block_on(
+            cmd("GET")
+                .arg("test")
+                .query_async::<Option<i32>>(&mut connection),
+        );
+        assert_eq!(value, Ok(Some(1)));
+    }
+
+    #[test]
+    fn test_async_cluster_close_drains_requests() {
+        let name = "close_drains_requests";
+
+        let MockEnv {
+            runtime,
+            async_connection: connection,
+            handler: _handler,
+            ..
+        } = MockEnv::new(name, move |cmd: &[u8], _| {
+            respond_startup(name, cmd)?;
+            Err(Ok(Value::Int(1)))
+        });
+        delay_responses(name, 6379, Duration::from_millis(100));
+
+        let (value, closed) = runtime.block_on(futures::future::join(
+            cmd("GET")
+                .arg("test")
+                .query_async::<i32>(&mut connection.clone()),
+            async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                connection.close(Duration::from_secs(1)).await
+            },
+        ));
+        assert_eq!(value, Ok(1));
+        assert_eq!(closed, Ok(()));
+
+        let result = runtime.block_on(
+            cmd("GET")
+                .arg("test")
+                .query_async::<i32>(&mut connection.clone()),
+        );
+        assert!(result.unwrap_err().is_unrecoverable_error());
+    }
+
+    #[test]
+    fn test_async_cluster_heartbeat_replaces_broken_connections() {
+        let name = "heartbeat_replaces_broken_connections";
+        let broken = Arc::new(atomic::AtomicBool::new(false));
+        let pings = Arc::new(atomic::AtomicUsize::new(0));
+        let (broken_clone, pings_clone) = (broken.clone(), pings.clone());
+
+        let MockEnv {
+            runtime,
+            async_connection: mut connection,
+            handler: _handler,
+            ..
+        } = MockEnv::with_client_builder(
+            ClusterClient::builder(vec![&*format!("redis://{name}")])
+                .retries(0)
+                .heartbeat(redis::aio::HeartbeatConfig::new(
+                    Duration::from_millis(20),
+                    Duration::from_millis(20),
+                )),
+            name,
+            move |cmd: &[u8], _| {
+                if contains_slice(cmd, b"PING") {
+                    pings_clone.fetch_add(1, atomic::Ordering::SeqCst);
+                    if broken_clone.load(atomic::Ordering::SeqCst) {
+                        return Err(Err(RedisError::from(std::io::Error::from(
+                            std::io::ErrorKind::BrokenPipe,
+                        ))));
+                    }
+                }
+                respond_startup(name, cmd)?;
+                Err(Ok(Value::Int(1)))
+            },
+        );
+
+        runtime.block_on(sleep(Duration::from_millis(100).into()));
+        assert!(pings.load(atomic::Ordering::SeqCst) > 2);
+
+        // the broken connection is dropped after its replacement fails, and isn't pinged again.
+        broken.store(true, atomic::Ordering::SeqCst);
+        runtime.block_on(sleep(Duration::from_millis(100).into()));
+        let pings_after_failure = pings.load(atomic::Ordering::SeqCst);
+        runtime.block_on(sleep(Duration::from_millis(100).into()));
+        assert_eq!(pings.load(atomic::Ordering::SeqCst), pings_after_failure);
+
+        broken.store(false, atomic::Ordering::SeqCst);
+        let value = runtime.block_on(cmd("GET").arg("test").query_async::<i32>(&mut connection));
+        assert_eq!(value, Ok(1));
+    }
+
     #[test]
     fn test_async_cluster_move_error_when_new_node_is_added() {
         let name = "rebuild_with_extra_nodes";
@@ -899,6 +1205,7 @@ mod cluster_async {
             )
             .unwrap()
             .extract_error()
+            .map_err(RedisError::from)
         );
 
         let value = runtime.block_on(
@@ -0,0 +1,198 @@
+#![cfg(feature = "replicated-aio")]
+mod support;
+
+#[cfg(test)]
+mod replicated {
+    use redis::{
+        cmd, pipe,
+        replicated::{ReplicatedClientBuilder, ReplicatedConnection},
+        AsyncTypedCommands, RedisResult,
+    };
+    use rstest::rstest;
+
+    use crate::support::*;
+
+    /// Starts a server that answers commands from `replies`, and otherwise answers `GET` with the
+    /// server's port.
+    fn start_server(replies: Vec<(&'static str, String)>) -> MockServer {
+        MockServer::start(move |request| {
+            match replies.iter().find(|(command, _)| *command == request.name) {
+                Some((_, reply)) => reply.as_str().into(),
+                None if request.name == "GET" => bulk_string(&request.port.to_string()).into(),
+                None => MockReply::ok(),
+            }
+        })
+    }
+
+    fn bulk_string(value: &str) -> String {
+        format!("${}\r\n{value}\r\n", value.len())
+    }
+
+    /// A `ROLE` reply of a primary with the given replication offset and replicas.
+    fn primary_role(offset: u64, replicas: &[(u16, u64)]) -> (&'static str, String) {
+        let mut reply = format!("*3\r\n$6\r\nmaster\r\n:{offset}\r\n*{}\r\n", replicas.len());
+        for (port, offset) in replicas {
+            reply += "*3\r\n";
+            reply += &bulk_string("127.0.0.1");
+            reply += &bulk_string(&port.to_string());
+            reply += &bulk_string(&offset.to_string());
+        }
+        ("ROLE", reply)
+    }
+
+    async fn connect(
+        primary: &MockServer,
+        max_replication_lag: u64,
+    ) -> RedisResult<ReplicatedConnection> {
+        ReplicatedClientBuilder::new(primary.url())?
+            .max_replication_lag(max_replication_lag)
+            .build()
+            .get_async_connection()
+            .await
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "tokio-comp", case::tokio(RuntimeType::Tokio))]
+    #[cfg_attr(feature = "smol-comp", case::smol(RuntimeType::Smol))]
+    fn test_replicated_reads_go_to_replicas_and_writes_to_the_primary(
+        #[case] runtime: RuntimeType,
+    ) {
+        let in_sync = start_server(vec![]);
+        let other_in_sync = start_server(vec![]);
+        let lagging = start_server(vec![]);
+        let primary = start_server(vec![
+            primary_role(
+                1000,
+                &[
+                    (in_sync.port(), 990),
+                    (other_in_sync.port(), 1000),
+                    (lagging.port(), 10),
+                ],
+            ),
+            ("EXEC", format!("*1\r\n{}", bulk_string("value"))),
+        ]);
+
+        block_on_all(
+            async move {
+                let mut con = connect(&primary, 100).await?;
+                let mut replicas: Vec<_> = con.replicas().iter().map(|info| info.port).collect();
+                replicas.sort_unstable();
+                let mut expected = vec![in_sync.port(), other_in_sync.port()];
+                expected.sort_unstable();
+                assert_eq!(replicas, expected);
+
+                con.set("key", "value").await?;
+                for _ in 0..4 {
+                    con.get("key").await?;
+                }
+                let _: Vec<String> = pipe().get("a").get("b").query_async(&mut con).await?;
+                let _: (String,) = pipe().atomic().get("a").query_async(&mut con).await?;
+
+                let servers = [&primary, &in_sync, &other_in_sync, &lagging];
+                assert_eq!(servers.map(|server| server.count("SET")), [1, 0, 0, 0]);
+                // the single reads alternate between the replicas, the pipeline is sent to one of
+                // them, and only the transaction reads from the primary.
+                let reads = servers.map(|server| server.count("GET"));
+                assert_eq!(reads[0], 1);
+                let mut replica_reads = [reads[1], reads[2]];
+                replica_reads.sort_unstable();
+                assert_eq!(replica_reads, [2, 4]);
+                assert_eq!(reads[3], 0);
+                Ok(())
+            },
+            runtime,
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "tokio-comp", case::tokio(RuntimeType::Tokio))]
+    #[cfg_attr(feature = "smol-comp", case::smol(RuntimeType::Smol))]
+    fn test_replicated_reads_fall_back_to_the_primary(#[case] runtime: RuntimeType) {
+        let unreachable = redis_test::utils::get_random_available_port();
+        let primary = start_server(vec![primary_role(0, &[(unreachable, 0)])]);
+
+        block_on_all(
+            async move {
+                let mut con = connect(&primary, 0).await?;
+                assert!(con.replicas().is_empty());
+                assert_eq!(con.get("key").await?, Some(primary.port().to_string()));
+                Ok(())
+            },
+            runtime,
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "tokio-comp", case::tokio(RuntimeType::Tokio))]
+    #[cfg_attr(feature = "smol-comp", case::smol(RuntimeType::Smol))]
+    fn test_replicated_falls_back_to_info_replication(#[case] runtime: RuntimeType) {
+        let replica = start_server(vec![]);
+        let info = format!(
+            "role:master\r\nslave0:ip=127.0.0.1,port={},state=online,offset=5,lag=0\r\nmaster_repl_offset:5\r\n",
+            replica.port()
+        );
+        // a primary that doesn't allow ROLE, but reports its replicas in INFO.
+        let primary = start_server(vec![
+            (
+                "ROLE",
+                "-NOPERM this user has no permissions to run the 'role' command\r\n".to_string(),
+            ),
+            ("INFO", bulk_string(&info)),
+        ]);
+
+        block_on_all(
+            async move {
+                let mut con = connect(&primary, 0).await?;
+                let replicas: Vec<_> = con.replicas().iter().map(|info| info.port).collect();
+                assert_eq!(replicas, vec![replica.port()]);
+                assert_eq!(con.get("key").await?, Some(replica.port().to_string()));
+                cmd("DEL").arg("key").exec_async(&mut con).await?;
+                assert_eq!((primary.count("DEL"), replica.count("DEL")), (1, 0));
+                Ok(())
+            },
+            runtime,
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "tokio-comp", case::tokio(RuntimeType::Tokio))]
+    #[cfg_attr(feature = "smol-comp", case::smol(RuntimeType::Smol))]
+    fn test_replicated_reconnects_to_the_primary(#[case] runtime: RuntimeType) {
+        let (_, role) = primary_role(0, &[]);
+        // the first connection to the primary closes on the first write.
+        let primary = MockServer::start(move |request| match request.name {
+            "ROLE" => role.as_str().into(),
+            "SET" if request.connection == 0 => MockReply::Close,
+            _ => MockReply::ok(),
+        });
+
+        block_on_all(
+            async move {
+                let mut con = connect(&primary, 0).await?;
+                assert!(con.set("key", "value").await.is_err());
+                con.set("key", "value").await?;
+                con.refresh_replicas().await?;
+                let commands: Vec<_> = primary
+                    .commands()
+                    .into_iter()
+                    .filter(|(_, name)| name != "CLIENT")
+                    .collect();
+                assert_eq!(
+                    commands,
+                    [
+                        (0, "ROLE".to_string()),
+                        (0, "SET".to_string()),
+                        (1, "SET".to_string()),
+                        (1, "ROLE".to_string()),
+                    ]
+                );
+                Ok(())
+            },
+            runtime,
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+#![cfg(feature = "sharded-aio")]
+mod support;
+
+#[cfg(test)]
+mod sharded {
+    use redis::{
+        sharded::{ShardedClient, ShardedClientBuilder, ShardedConnection},
+        AsyncCommands, RedisResult,
+    };
+    use rstest::rstest;
+
+    use crate::support::*;
+
+    fn contexts(count: usize) -> Vec<TestContext> {
+        (0..count).map(|_| TestContext::new()).collect()
+    }
+
+    fn address(ctx: &TestContext) -> String {
+        ctx.server.connection_info().addr().to_string()
+    }
+
+    async fn connect(contexts: &[TestContext]) -> RedisResult<ShardedConnection> {
+        ShardedClient::new(contexts.iter().map(|ctx| ctx.server.connection_info()))?
+            .get_async_connection()
+            .await
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "tokio-comp", case::tokio(RuntimeType::Tokio))]
+    #[cfg_attr(feature = "smol-comp", case::smol(RuntimeType::Smol))]
+    fn test_sharded_keys_are_stored_on_their_node(#[case] runtime: RuntimeType) {
+        let contexts = contexts(3);
+        block_on_all(
+            async move {
+                let mut con = connect(&contexts).await?;
+                let keys: Vec<String> = (0..30).map(|i| format!("key{i}")).collect();
+                for key in &keys {
+                    con.set::<_, _, ()>(key, key).await?;
+                }
+
+                for key in &keys {
+                    let owner = con.node_for_key(key.as_bytes()).unwrap();
+                    for ctx in &contexts {
+                        let mut node_con = ctx.async_connection().await?;
+                        let exists: bool = node_con.exists(key).await?;
+                        assert_eq!(exists, owner == address(ctx), "{key}");
+                    }
+                }
+                Ok(())
+            },
+            runtime,
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "tokio-comp", case::tokio(RuntimeType::Tokio))]
+    #[cfg_attr(feature = "smol-comp", case::smol(RuntimeType::Smol))]
+    fn test_sharded_multi_key_commands(#[case] runtime: RuntimeType) {
+        let contexts = contexts(3);
+        block_on_all(
+            async move {
+                let mut con = connect(&contexts).await?;
+                let pairs: Vec<(String, String)> = (0..30)
+                    .map(|i| (format!("key{i}"), format!("value{i}")))
+                    .collect();
+                con.mset::<_, _, ()>(&pairs).await?;
+
+                let keys: Vec<&String> = pairs.iter().map(|(key, _)| key).collect();
+                let values: Vec<String> = con.mget(&keys).await?;
+                assert_eq!(
+                    values,
+                    pairs
+                        .iter()
+                        .map(|(_, value)| value.clone())
+                        .collect::<Vec<_>>()
+                );
+
+                let deleted: usize = con.del(&keys[..10]).await?;
+                assert_eq!(deleted, 10);
+                let dbsize: usize = redis::cmd("DBSIZE").query_async(&mut con).await?;
+                assert_eq!(dbsize, 20);
+                Ok(())
+            },
+            runtime,
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "tokio-comp", case::tokio(RuntimeType::Tokio))]
+    #[cfg_attr(feature = "smol-comp", case::smol(RuntimeType::Smol))]
+    fn test_sharded_hash_tagged_pipeline(#[case] runtime: RuntimeType) {
+        let contexts = contexts(3);
+        block_on_all(
+            async move {
+                let mut con = connect(&contexts).await?;
+                let (first, second): (String, String) = redis::pipe()
+                    .set("{user}:name", "foo")
+                    .ignore()
+                    .set("{user}:email", "bar")
+                    .ignore()
+                    .get("{user}:name")
+                    .get("{user}:email")
+                    .query_async(&mut con)
+                    .await?;
+                assert_eq!((first.as_str(), second.as_str()), ("foo", "bar"));
+
+                // with enough different hash tags, some keys are bound to be on different nodes.
+                let mut pipeline = redis::pipe();
+                for i in 0..30 {
+                    pipeline.set(format!("{{tag{i}}}"), "foo");
+                }
+                let result: RedisResult<()> = pipeline.query_async(&mut con).await;
+                assert!(result.is_err());
+                Ok(())
+            },
+            runtime,
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    #[cfg_attr(feature = "tokio-comp", case::tokio(RuntimeType::Tokio))]
+    #[cfg_attr(feature = "smol-comp", case::smol(RuntimeType::Smol))]
+    fn test_sharded_add_and_remove_nodes(#[case] runtime: RuntimeType) {
+        let contexts = contexts(3);
+        block_on_all(
+            async move {
+                let client = ShardedClientBuilder::new(vec![contexts[0].server.connection_info()])?
+                    .build()?;
+                let mut con = client.get_async_connection().await?;
+                con.set::<_, _, ()>("key", "value").await?;
+                assert_eq!(con.nodes(), vec![address(&contexts[0])]);
+
+                con.add_node(contexts[1].server.connection_info(), 1)
+                    .await?;
+                con.add_node(contexts[2].server.connection_info(), 2)
+                    .await?;
+                assert!(con
+                    .add_node(contexts[2].server.connection_info(), 1)
+                    .await
+                    .is_err());
+                assert_eq!(con.nodes().len(), 3);
+
+                assert!(con.remove_node(&address(&contexts[1])));
+                assert!(!con.remove_node(&address(&contexts[1])));
+                assert_eq!(con.nodes().len(), 2);
+                for i in 0..20 {
+                    let key = format!("key{i}");
+                    assert_ne!(
+                        con.node_for_key(key.as_bytes()),
+                        Some(address(&contexts[1]))
+                    );
+                }
+                Ok(())
+            },
+            runtime,
+        )
+        .unwrap();
+    }
+}
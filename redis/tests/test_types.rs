@@ -903,6 +903,74 @@ mod types {
         }
     }
 
+    #[test]
+    fn test_attributes_are_unwrapped() {
+        let attributed = |data: Value| Value::Attribute {
+            data: Box::new(data),
+            attributes: vec![(Value::SimpleString("ttl".to_string()), Value::Int(3600))],
+        };
+        for parse_mode in [RedisParseMode::Owned, RedisParseMode::Ref] {
+            let value = attributed(Value::Array(vec![Value::Int(1), attributed(Value::Int(2))]));
+            let vec: Vec<i32> = parse_mode.parse_redis_value(value.clone()).unwrap();
+            assert_eq!(vec, vec![1, 2]);
+            let array: [i32; 2] = parse_mode.parse_redis_value(value.clone()).unwrap();
+            assert_eq!(array, [1, 2]);
+            let tuple: (i32, i32) = parse_mode.parse_redis_value(value).unwrap();
+            assert_eq!(tuple, (1, 2));
+
+            let value = attributed(Value::ServerError(redis::ServerError::new(
+                redis::ServerErrorKind::ResponseError,
+                None,
+            )));
+            let result: redis::RedisResult<i32> =
+                parse_mode.parse_redis_value(value.clone()).unwrap();
+            assert!(result.is_err());
+            assert!(parse_mode.parse_redis_value::<()>(value).is_err());
+
+            let value = attributed(Value::SimpleString("string".to_string()));
+            let value_type: redis::ValueType = parse_mode.parse_redis_value(value).unwrap();
+            assert_eq!(value_type, redis::ValueType::String);
+        }
+    }
+
+    #[test]
+    fn test_with_attributes() {
+        use redis::{parse_redis_value, WithAttributes};
+        let bytes: &[u8] = b"|1\r\n+key-popularity\r\n%1\r\n$3\r\nkey\r\n,0.5\r\n$3\r\nfoo\r\n";
+        let attributes = vec![(
+            Value::SimpleString("key-popularity".to_string()),
+            Value::Map(vec![(
+                Value::BulkString(b"key".to_vec()),
+                Value::Double(0.5),
+            )]),
+        )];
+        for parse_mode in [RedisParseMode::Owned, RedisParseMode::Ref] {
+            let value: WithAttributes<String> = parse_mode
+                .parse_redis_value(parse_redis_value(bytes).unwrap())
+                .unwrap();
+            assert_eq!(
+                value,
+                WithAttributes {
+                    data: "foo".to_string(),
+                    attributes: attributes.clone(),
+                }
+            );
+
+            let values: Vec<WithAttributes<i32>> = parse_mode
+                .parse_redis_value(
+                    parse_redis_value(b"*2\r\n:1\r\n|1\r\n+ttl\r\n:3600\r\n:2\r\n").unwrap(),
+                )
+                .unwrap();
+            assert_eq!(values[0].data, 1);
+            assert!(values[0].attributes.is_empty());
+            assert_eq!(values[1].data, 2);
+            assert_eq!(
+                values[1].attributes,
+                vec![(Value::SimpleString("ttl".to_string()), Value::Int(3600))]
+            );
+        }
+    }
+
     #[test]
     fn arrays_to_tuples() {
         for parse_mode in [RedisParseMode::Owned, RedisParseMode::Ref] {